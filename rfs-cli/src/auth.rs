@@ -48,7 +48,8 @@ fn submit_auth(
 ) -> error::Result<Option<rfs_lib::schema::auth::VerifyMethod>> {
     match auth_method {
         rfs_lib::schema::auth::AuthMethod::None => Ok(None),
-        rfs_lib::schema::auth::AuthMethod::Password => {
+        rfs_lib::schema::auth::AuthMethod::Password |
+        rfs_lib::schema::auth::AuthMethod::Ldap => {
             let prompt = "password: ";
 
             loop {
@@ -79,6 +80,13 @@ fn submit_auth(
 
                 return Ok(json.into_payload());
             }
+        },
+        rfs_lib::schema::auth::AuthMethod::Oidc => {
+            // the provider redirects back to the server so the session
+            // cookie would end up in the browser and not here
+            Err(error::Error::new()
+                .kind("OidcLoginRequired")
+                .message("the user must log in with the identity provider from a browser"))
         }
     }
 }
@@ -144,6 +152,22 @@ pub fn connect(state: &mut AppState) -> error::Result<()> {
     Ok(())
 }
 
-pub fn disconnect(_state: &mut AppState) -> error::Result<()> {
+pub fn disconnect(state: &mut AppState) -> error::Result<()> {
+    let res = state.client.post(state.server.url.join("/auth/logout")?)
+        .send()?;
+
+    let status = res.status();
+
+    if status != reqwest::StatusCode::OK {
+        let json = res.json::<rfs_lib::json::Error>()?;
+
+        return Err(error::Error::new()
+            .kind("FailedLogout")
+            .message("failed to logout of the current session")
+            .source(format!("{:?}", json)));
+    }
+
+    state.clear()?;
+
     Ok(())
 }
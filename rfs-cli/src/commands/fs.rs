@@ -109,6 +109,26 @@ pub fn command() -> Command {
                 .conflicts_with("comment")
             )
         )
+        .subcommand(Command::new("sign")
+            .about("creates a signed url for an fs item that can be used without a session")
+            .arg(util::default_help_arg())
+            .arg(Arg::new("id")
+                .long("id")
+                .value_parser(value_parser!(i64))
+                .required(true)
+                .help("the id of the fs item to sign")
+            )
+            .arg(Arg::new("upload")
+                .long("upload")
+                .action(ArgAction::SetTrue)
+                .help("creates an upload url for a directory instead of a download url for a file")
+            )
+            .arg(Arg::new("expires-in")
+                .long("expires-in")
+                .value_parser(value_parser!(u64))
+                .help("the number of seconds the url will be valid for")
+            )
+        )
 }
 
 pub fn create(state: &mut AppState, args: &ArgMatches) -> error::Result<()> {
@@ -409,3 +429,43 @@ pub fn update(state: &mut AppState, args: &ArgMatches) -> error::Result<()> {
 
     Ok(())
 }
+
+pub fn sign(state: &mut AppState, args: &ArgMatches) -> error::Result<()> {
+    let id = args.get_one::<i64>("id").cloned().unwrap();
+    let path = format!("/fs/{}/sign", id);
+
+    let operation = if args.get_flag("upload") {
+        rfs_lib::schema::fs::SignedOperation::Upload
+    } else {
+        rfs_lib::schema::fs::SignedOperation::Download
+    };
+
+    let action = rfs_lib::actions::fs::SignUrl {
+        operation,
+        expires_in: args.get_one::<u64>("expires-in").cloned(),
+    };
+
+    let url = state.server.url.join(&path)?;
+    let res = state.client.post(url)
+        .json(&action)
+        .send()?;
+
+    let status = res.status();
+
+    if status != reqwest::StatusCode::OK {
+        let json = res.json::<rfs_lib::json::Error>()?;
+
+        return Err(error::Error::new()
+            .kind("FailedSignUrl")
+            .message("failed to create the signed url")
+            .source(format!("{:?}", json)));
+    }
+
+    let result = res.json::<rfs_lib::json::Wrapper<rfs_lib::schema::fs::SignedUrl>>()?
+        .into_payload();
+
+    println!("{}", state.server.url.join(&result.url)?);
+    println!("expires: {}", result.expires);
+
+    Ok(())
+}
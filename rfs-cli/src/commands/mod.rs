@@ -9,11 +9,13 @@ use crate::state::AppState;
 
 mod storage;
 mod fs;
+mod totp;
 
 fn append_subcommands(command: Command) -> Command {
     command
         .subcommand(storage::command())
         .subcommand(fs::command())
+        .subcommand(totp::command())
         .subcommand(Command::new("connect")
             .alias("login")
             .about("logs in into the desired server")
@@ -44,6 +46,10 @@ pub fn cli() -> Command {
             .value_parser(value_parser!(PathBuf))
             .help("specifies a specific file to save any cookie data to")
         )
+        .arg(Arg::new("token")
+            .long("token")
+            .help("bot token to authenticate requests with instead of a session")
+        )
         .arg(Arg::new("host")
             .long("host")
             .short('h')
@@ -92,6 +98,19 @@ pub fn connect(state: &mut AppState, _args: &ArgMatches) -> error::Result<()> {
 pub fn disconnect(state: &mut AppState, _args: &ArgMatches) -> error::Result<()> {
     auth::disconnect(state)?;
 
+    println!("session disconnected");
+
+    Ok(())
+}
+
+pub fn totp(state: &mut AppState, args: &ArgMatches) -> error::Result<()> {
+    match args.subcommand() {
+        Some(("setup", setup_args)) => totp::setup(state, setup_args)?,
+        Some(("recovery", recovery_args)) => totp::recovery(state, recovery_args)?,
+        Some(("recovery-status", status_args)) => totp::recovery_status(state, status_args)?,
+        _ => unreachable!()
+    }
+
     Ok(())
 }
 
@@ -109,6 +128,7 @@ pub fn fs(state: &mut AppState, args: &ArgMatches) -> error::Result<()> {
     match args.subcommand() {
         Some(("create", create_args)) => fs::create(state, create_args)?,
         Some(("update", update_args)) => fs::update(state, update_args)?,
+        Some(("sign", sign_args)) => fs::sign(state, sign_args)?,
         _ => unreachable!()
     }
 
@@ -0,0 +1,195 @@
+use clap::{Command, Arg, ArgAction, ArgMatches, value_parser};
+
+use crate::error;
+use crate::input;
+use crate::util;
+use crate::state::AppState;
+
+pub fn command() -> Command {
+    Command::new("totp")
+        .subcommand_required(true)
+        .about("manages totp verification for the current user")
+        .arg(util::default_help_arg())
+        .subcommand(Command::new("setup")
+            .about("creates a new totp and confirms it with an authenticator app")
+            .arg(util::default_help_arg())
+            .arg(Arg::new("algo")
+                .long("algo")
+                .value_parser(["SHA1", "SHA256", "SHA512"])
+                .help("the hashing algorithm of the totp")
+            )
+            .arg(Arg::new("digits")
+                .long("digits")
+                .value_parser(value_parser!(u32))
+                .help("the number of digits in a totp code")
+            )
+            .arg(Arg::new("step")
+                .long("step")
+                .value_parser(value_parser!(u64))
+                .help("the number of seconds that a totp code is valid for")
+            )
+        )
+        .subcommand(Command::new("recovery")
+            .about("creates recovery codes for when an authenticator app is not available")
+            .arg(util::default_help_arg())
+            .arg(Arg::new("count")
+                .long("count")
+                .value_parser(value_parser!(u32))
+                .help("the number of recovery codes to create")
+            )
+            .arg(Arg::new("regen")
+                .long("regen")
+                .action(ArgAction::SetTrue)
+                .help("replaces any existing recovery codes")
+            )
+        )
+        .subcommand(Command::new("recovery-status")
+            .about("shows how many recovery codes are left")
+            .arg(util::default_help_arg())
+        )
+}
+
+/// renders a qr code that can be scanned from the terminal
+fn print_qr(data: &str) -> error::Result<()> {
+    use qrcode::render::unicode::Dense1x2;
+
+    let code = qrcode::QrCode::new(data.as_bytes())
+        .map_err(|e| error::Error::new()
+            .kind("FailedQrCode")
+            .message("failed to create qr code for the totp")
+            .source(e))?;
+
+    let rendered = code.render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .build();
+
+    println!("{}", rendered);
+
+    Ok(())
+}
+
+pub fn setup(state: &mut AppState, args: &ArgMatches) -> error::Result<()> {
+    let action = rfs_lib::actions::auth::CreateTotp {
+        algo: args.get_one::<String>("algo").cloned(),
+        digits: args.get_one::<u32>("digits").cloned(),
+        step: args.get_one::<u64>("step").cloned(),
+    };
+
+    let res = state.client.post(state.server.url.join("/auth/totp")?)
+        .json(&action)
+        .send()?;
+
+    let status = res.status();
+
+    if status != reqwest::StatusCode::CREATED {
+        let json = res.json::<rfs_lib::json::Error>()?;
+
+        return Err(error::Error::new()
+            .kind("FailedTotpCreate")
+            .message("failed to create totp")
+            .source(format!("{:?}", json)));
+    }
+
+    let enrollment = res.json::<rfs_lib::json::Wrapper<rfs_lib::schema::auth::TotpEnrollment>>()?
+        .into_payload();
+
+    print_qr(&enrollment.uri)?;
+
+    println!("uri: {}", enrollment.uri);
+    println!("secret: {}", enrollment.secret);
+    println!("algo: {} digits: {} step: {}", enrollment.algo, enrollment.digits, enrollment.step);
+
+    loop {
+        let code = input::read_stdin_trimmed("totp code: ")?;
+
+        let body = rfs_lib::actions::auth::ConfirmTotp { code };
+
+        let res = state.client.post(state.server.url.join("/auth/totp/confirm")?)
+            .json(&body)
+            .send()?;
+
+        let status = res.status();
+
+        if status != reqwest::StatusCode::OK {
+            let json = res.json::<rfs_lib::json::Error>()?;
+
+            if json.kind() == "InvalidCode" {
+                println!("invalid totp code provided");
+                continue;
+            }
+
+            return Err(error::Error::new()
+                .kind("FailedTotpConfirm")
+                .message("failed to confirm totp")
+                .source(format!("{:?}", json)));
+        }
+
+        break;
+    }
+
+    println!("totp enabled");
+
+    Ok(())
+}
+
+pub fn recovery(state: &mut AppState, args: &ArgMatches) -> error::Result<()> {
+    let action = rfs_lib::actions::auth::CreateRecoveryCodes {
+        count: args.get_one::<u32>("count").cloned(),
+        regen: args.get_flag("regen"),
+    };
+
+    let res = state.client.post(state.server.url.join("/auth/recovery")?)
+        .json(&action)
+        .send()?;
+
+    let status = res.status();
+
+    if status != reqwest::StatusCode::CREATED {
+        let json = res.json::<rfs_lib::json::Error>()?;
+
+        return Err(error::Error::new()
+            .kind("FailedRecoveryCreate")
+            .message("failed to create recovery codes")
+            .source(format!("{:?}", json)));
+    }
+
+    let codes = res.json::<rfs_lib::json::ListWrapper<Vec<rfs_lib::schema::auth::TotpHashCode>>>()?
+        .into_payload();
+
+    for code in codes {
+        println!("{}", code.code);
+    }
+
+    println!("store these codes somewhere safe. they will not be shown again");
+
+    Ok(())
+}
+
+pub fn recovery_status(state: &mut AppState, _args: &ArgMatches) -> error::Result<()> {
+    let res = state.client.get(state.server.url.join("/auth/recovery")?)
+        .send()?;
+
+    let status = res.status();
+
+    if status != reqwest::StatusCode::OK {
+        let json = res.json::<rfs_lib::json::Error>()?;
+
+        return Err(error::Error::new()
+            .kind("FailedRecoveryStatus")
+            .message("failed to retrieve recovery code status")
+            .source(format!("{:?}", json)));
+    }
+
+    let wrapper = res.json::<rfs_lib::json::Wrapper<rfs_lib::schema::auth::RecoveryStatus>>()?;
+
+    if let Some(msg) = wrapper.message() {
+        println!("{}", msg);
+    }
+
+    let recovery = wrapper.into_payload();
+
+    println!("remaining: {} total: {}", recovery.remaining, recovery.total);
+
+    Ok(())
+}
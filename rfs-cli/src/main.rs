@@ -54,6 +54,10 @@ fn run() -> error::Result<()> {
 
     let mut state = state::AppState::load(session_file)?;
 
+    if let Some(token) = app_matches.get_one::<String>("token") {
+        state.set_bearer_token(token);
+    }
+
     let host = app_matches.get_one::<String>("host").unwrap();
     let port = app_matches.get_one("port")
         .map(|v: &u16| v.clone())
@@ -75,6 +79,8 @@ fn run() -> error::Result<()> {
         return Ok(());
     }
 
+    state.refresh_client();
+
     match app_matches.subcommand() {
         None => {
             loop {
@@ -120,6 +126,7 @@ fn run_subcommand(state: &mut state::AppState, command: &str, matches: &ArgMatch
         "hash" => commands::hash(state, matches),
         "storage" => commands::storage(state, matches),
         "fs" => commands::fs(state, matches),
+        "totp" => commands::totp(state, matches),
         _ => {
             println!("uknown command");
 
@@ -26,6 +26,7 @@ pub struct AppState {
     pub store: Arc<CookieStoreSync>,
     pub client: reqwest::blocking::Client,
     pub server: ServerInfo,
+    bearer_token: Option<String>,
 }
 
 impl AppState {
@@ -51,20 +52,84 @@ impl AppState {
         };
 
         let store = Arc::new(CookieStoreSync::new(store));
-        let client = reqwest::blocking::Client::builder()
-            .cookie_provider(store.clone())
-            .user_agent("rfs-client-0.1.0")
-            .build()
-            .expect("failed to create client");
+        let server = ServerInfo::new();
+        let client = Self::build_client(&store, None, &server.url);
 
         Ok(AppState {
             cookie_file: given_file_ref.to_owned(),
             store,
             client,
-            server: ServerInfo::new(),
+            server,
+            bearer_token: None,
         })
     }
 
+    fn build_client(
+        store: &Arc<CookieStoreSync>,
+        token: Option<&str>,
+        url: &Url,
+    ) -> reqwest::blocking::Client {
+        let mut headers = reqwest::header::HeaderMap::new();
+
+        // the server rejects state changing requests made with the session
+        // cookie unless they come from an allowed origin
+        if let Ok(value) = reqwest::header::HeaderValue::from_str(&url.origin().ascii_serialization()) {
+            headers.insert(reqwest::header::ORIGIN, value);
+        }
+
+        if let Some(token) = token {
+            let mut value = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token))
+                .expect("invalid characters in bearer token");
+            value.set_sensitive(true);
+
+            headers.insert(reqwest::header::AUTHORIZATION, value);
+        }
+
+        reqwest::blocking::Client::builder()
+            .cookie_provider(store.clone())
+            .user_agent("rfs-client-0.1.0")
+            .default_headers(headers)
+            .build()
+            .expect("failed to create client")
+    }
+
+    /// sends the given bot token with every request instead of relying on
+    /// the session cookie
+    pub fn set_bearer_token(&mut self, token: &str) {
+        self.bearer_token = Some(token.to_owned());
+        self.refresh_client();
+    }
+
+    /// recreates the client so that it uses the current server url and
+    /// bearer token
+    pub fn refresh_client(&mut self) {
+        self.client = Self::build_client(
+            &self.store,
+            self.bearer_token.as_deref(),
+            &self.server.url
+        );
+    }
+
+    /// removes all stored cookies along with the cookies file
+    pub fn clear(&mut self) -> error::Result<()> {
+        let mut store = self.store.write()
+            .map_err(|_e| error::Error::new()
+                .kind("RwLockPoisoned")
+                .message("something has caused the RwLock to be poisoned"))?;
+
+        store.clear();
+
+        if self.cookie_file.try_exists()? {
+            std::fs::remove_file(&self.cookie_file)
+                .map_err(|e| error::Error::new()
+                    .kind("FailedRemovingRFSCookies")
+                    .message("failed to remove the rfs cookies file")
+                    .source(e))?;
+        }
+
+        Ok(())
+    }
+
     pub fn save(&self) -> error::Result<()> {
         let store = self.store.read()
             .map_err(|_e| error::Error::new()
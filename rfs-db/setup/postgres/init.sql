@@ -4,7 +4,93 @@ create table users (
     username varchar not null unique,
 
     email varchar unique,
-    email_verified bool not null default false
+    email_verified bool not null default false,
+
+    deleted timestamp with time zone
+);
+
+create table user_invites (
+    code varchar not null primary key,
+    user_id bigint not null references users(id),
+
+    max_uses int,
+    uses int not null default 0,
+
+    expires timestamp with time zone,
+    created timestamp with time zone not null
+);
+
+create table user_email_tokens (
+    hash bytea not null primary key,
+    user_id bigint not null references users(id),
+
+    kind smallint not null,
+    email varchar not null,
+
+    expires timestamp with time zone not null,
+    created timestamp with time zone not null
+);
+
+create table authz_roles (
+    id bigint not null primary key,
+
+    name varchar not null unique
+);
+
+create table authz_permissions (
+    role_id bigint not null references authz_roles(id),
+    permission smallint not null,
+
+    constraint unique_role_id_permission primary key (role_id, permission)
+);
+
+create table authz_user_roles (
+    user_id bigint not null references users(id),
+    role_id bigint not null references authz_roles(id),
+
+    constraint unique_user_id_role_id primary key (user_id, role_id)
+);
+
+create table groups (
+    id bigint not null primary key,
+
+    name varchar not null unique,
+
+    created timestamp with time zone not null
+);
+
+create table group_users (
+    group_id bigint not null references groups(id),
+    user_id bigint not null references users(id),
+
+    admin bool not null default false,
+
+    added timestamp with time zone not null,
+
+    constraint unique_group_id_user_id primary key (group_id, user_id)
+);
+
+create table bots (
+    id bigint not null primary key,
+    user_id bigint not null references users(id),
+
+    name varchar not null,
+
+    created timestamp with time zone not null,
+
+    constraint unique_user_id_name unique (user_id, name)
+);
+
+create table bot_tokens (
+    id varchar not null primary key,
+    bot_id bigint not null references bots(id),
+
+    hash bytea not null,
+    read_only bool not null default false,
+
+    expires timestamp with time zone,
+    last_used timestamp with time zone,
+    created timestamp with time zone not null
 );
 
 create table auth_password (
@@ -19,9 +105,25 @@ create table auth_totp (
     algo smallint not null,
     step int not null,
     digits int not null,
-    secret bytea not null
+    secret bytea not null,
+
+    verified bool not null default false,
+
+    last_step bigint
 );
 
+create table auth_totp_attempts (
+    user_id bigint not null references users(id),
+
+    step bigint,
+    result smallint not null,
+    ip inet,
+
+    created timestamp with time zone not null
+);
+
+create index auth_totp_attempts_created on auth_totp_attempts (created);
+
 create table auth_totp_hash (
     key varchar not null,
 
@@ -33,6 +135,51 @@ create table auth_totp_hash (
     primary key (key, user_id)
 );
 
+create table auth_ldap (
+    user_id bigint not null primary key references users(id),
+
+    dn varchar not null,
+
+    synced timestamp with time zone not null
+);
+
+create table auth_ldap_pending (
+    token varchar not null primary key,
+
+    username varchar not null,
+
+    expires timestamp with time zone not null,
+    created timestamp with time zone not null
+);
+
+create table auth_ldap_groups (
+    user_id bigint not null references users(id),
+    group_id bigint not null references groups(id),
+
+    primary key (user_id, group_id)
+);
+
+create table auth_oidc_link (
+    issuer varchar not null,
+    subject varchar not null,
+
+    user_id bigint not null references users(id),
+
+    created timestamp with time zone not null,
+
+    primary key (issuer, subject)
+);
+
+create table auth_oidc_pending (
+    state varchar not null primary key,
+
+    nonce varchar not null,
+    verifier varchar not null,
+
+    expires timestamp with time zone not null,
+    created timestamp with time zone not null
+);
+
 create table auth_session (
     token bytea not null primary key,
 
@@ -47,9 +194,97 @@ create table auth_session (
     verified bool not null default false,
 
     auth_method smallint not null,
-    verify_method smallint not null
+    verify_method smallint not null,
+
+    user_agent varchar,
+    ip inet
+);
+
+create table auth_access_tokens (
+    id varchar not null primary key,
+
+    user_id bigint not null references users(id),
+
+    hash bytea not null,
+    session_token bytea not null,
+
+    expires timestamp with time zone not null,
+    created timestamp with time zone not null
+);
+
+create table auth_lockout (
+    subject varchar not null primary key,
+
+    failures int not null,
+    last_failure timestamp with time zone not null,
+
+    locked_until timestamp with time zone
+);
+
+-- user_id is not a reference so that entries are kept after a user is
+-- deleted
+create table audit_log (
+    id bigserial not null primary key,
+
+    event varchar not null,
+
+    user_id bigint,
+    mechanism varchar,
+
+    request_id bigint,
+    ip inet,
+
+    details jsonb,
+
+    created timestamp with time zone not null
 );
 
+create index audit_log_created on audit_log (created);
+create index audit_log_user_id on audit_log (user_id);
+
+-- the audit log is owned by rfs_audit which nothing logs in as. the role
+-- running the setup is only able to add and read entries and removes old
+-- ones with audit_log_sweep. it must be able to create roles and should not
+-- be a superuser when the server connects with it
+do $$
+begin
+    if not exists (select from pg_roles where rolname = 'rfs_audit') then
+        create role rfs_audit nologin;
+    end if;
+end
+$$;
+
+-- entries older than the retention are removed. retentions of less than a
+-- day are raised to a day so the log cannot be emptied with the function
+create function audit_log_sweep(retention_days integer) returns bigint
+    language sql
+    security definer
+    set search_path = public
+as $$
+    with deleted as (
+        delete from audit_log
+        where created < now() - make_interval(days => greatest(retention_days, 1))
+        returning 1
+    )
+    select count(*) from deleted
+$$;
+
+grant rfs_audit to current_user;
+grant create on schema public to rfs_audit;
+
+alter table audit_log owner to rfs_audit;
+alter function audit_log_sweep(integer) owner to rfs_audit;
+
+revoke all on audit_log from public;
+revoke all on function audit_log_sweep(integer) from public;
+
+grant select, insert on audit_log to current_user;
+grant usage on sequence audit_log_id_seq to current_user;
+grant execute on function audit_log_sweep(integer) to current_user;
+
+revoke create on schema public from rfs_audit;
+revoke rfs_audit from current_user;
+
 create table storage (
     id bigint not null primary key,
 
@@ -74,6 +309,37 @@ create table storage_tags (
     constraint unique_storage_id_tag primary key (storage_id, tag)
 );
 
+create table storage_access (
+    storage_id bigint not null references storage(id),
+    user_id bigint not null references users(id),
+
+    ability smallint not null,
+
+    created timestamp with time zone not null,
+    updated timestamp with time zone,
+
+    constraint unique_storage_id_user_id primary key (storage_id, user_id)
+);
+
+create table bot_token_storage (
+    token_id varchar not null references bot_tokens(id),
+    storage_id bigint not null references storage(id),
+
+    constraint unique_token_id_storage_id primary key (token_id, storage_id)
+);
+
+create table storage_group_access (
+    storage_id bigint not null references storage(id),
+    group_id bigint not null references groups(id),
+
+    ability smallint not null,
+
+    created timestamp with time zone not null,
+    updated timestamp with time zone,
+
+    constraint unique_storage_id_group_id primary key (storage_id, group_id)
+);
+
 create table fs (
     id bigint not null primary key,
 
@@ -108,6 +374,49 @@ create table fs_tags (
     constraint unique_fs_id_tag primary key (fs_id, tag)
 );
 
+create table fs_access (
+    fs_id bigint not null references fs(id),
+    user_id bigint not null references users(id),
+
+    ability smallint not null,
+
+    created timestamp with time zone not null,
+    updated timestamp with time zone,
+
+    constraint unique_fs_id_user_id primary key (fs_id, user_id)
+);
+
+create table fs_group_access (
+    fs_id bigint not null references fs(id),
+    group_id bigint not null references groups(id),
+
+    ability smallint not null,
+
+    created timestamp with time zone not null,
+    updated timestamp with time zone,
+
+    constraint unique_fs_id_group_id primary key (fs_id, group_id)
+);
+
+create table fs_share (
+    token varchar not null primary key,
+
+    fs_id bigint not null references fs(id),
+    user_id bigint not null references users(id),
+
+    mode smallint not null,
+
+    password varchar,
+    password_version integer,
+
+    expires timestamp with time zone,
+
+    max_downloads integer,
+    downloads integer not null default 0,
+
+    created timestamp with time zone not null
+);
+
 create table fs_checksums (
     fs_id bigint not null references fs(id),
     algo varchar not null,
@@ -1,4 +1,6 @@
 pub mod db {
+    use std::path::PathBuf;
+
     use clap::{Arg, ArgAction, value_parser};
 
     pub fn connect() -> Arg {
@@ -79,4 +81,34 @@ pub mod db {
             .conflicts_with("connect")
     }
 
+    pub fn tls() -> Arg {
+        Arg::new("tls")
+            .long("tls")
+            .action(ArgAction::SetTrue)
+            .help("connect with tls. the certificate and hostname of the server are verified")
+    }
+
+    pub fn ca_file() -> Arg {
+        Arg::new("ca_file")
+            .long("ca-file")
+            .action(ArgAction::Set)
+            .value_parser(value_parser!(PathBuf))
+            .help("pem file of the certificate authorities used to verify the server. defaults to the webpki roots")
+    }
+
+    pub fn connect_timeout() -> Arg {
+        Arg::new("connect_timeout")
+            .long("connect-timeout")
+            .action(ArgAction::Set)
+            .value_parser(value_parser!(u64))
+            .help("seconds to wait for the connection to be created")
+    }
+
+    pub fn statement_timeout() -> Arg {
+        Arg::new("statement_timeout")
+            .long("statement-timeout")
+            .action(ArgAction::Set)
+            .value_parser(value_parser!(u64))
+            .help("milliseconds a statement can run before postgres cancels it")
+    }
 }
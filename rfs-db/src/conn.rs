@@ -1,15 +1,83 @@
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
 use clap::ArgMatches;
-use tokio_postgres::{Client, Config, NoTls};
+use tokio_postgres::{Client, Config, Connection, NoTls};
+use tokio_postgres::config::SslMode;
+use tokio_postgres::tls::TlsStream;
+use tokio_postgres_rustls::MakeRustlsConnect;
+use rustls::{ClientConfig, RootCertStore};
 
 use crate::error;
 
+/// creates the tls connector for postgres. the certificate and hostname of
+/// the server are always verified
+fn make_tls(ca_file: Option<&PathBuf>) -> error::Result<MakeRustlsConnect> {
+    let mut roots = RootCertStore::empty();
+
+    if let Some(path) = ca_file {
+        let file = std::fs::File::open(path)
+            .map_err(|err| error::Error::new()
+                .kind("InvalidTls")
+                .message(format!("failed to open ca file. path: {}", path.display()))
+                .source(err))?;
+        let mut reader = std::io::BufReader::new(file);
+
+        for cert in rustls_pemfile::certs(&mut reader) {
+            let cert = cert.map_err(|err| error::Error::new()
+                .kind("InvalidTls")
+                .message(format!("failed to read ca file. path: {}", path.display()))
+                .source(err))?;
+
+            roots.add(cert).map_err(|err| error::Error::new()
+                .kind("InvalidTls")
+                .message(format!("invalid certificate in ca file. path: {}", path.display()))
+                .source(err))?;
+        }
+
+        if roots.is_empty() {
+            return Err(error::Error::new()
+                .kind("InvalidTls")
+                .message(format!("no certificates found in ca file. path: {}", path.display())));
+        }
+    } else {
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    }
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|err| error::Error::new()
+            .kind("InvalidTls")
+            .message("failed to create tls config")
+            .source(err))?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    Ok(MakeRustlsConnect::new(config))
+}
+
+fn spawn_connection<S, T>(conn: Connection<S, T>)
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+    T: TlsStream + Unpin + Send + 'static,
+{
+    tokio::task::spawn(async move {
+        if let Err(e) = conn.await {
+            tracing::event!(
+                tracing::Level::ERROR,
+                "postgres connection error: {}",
+                e
+            );
+        }
+    });
+}
+
 pub async fn postgres(args: &ArgMatches) -> error::Result<Client> {
-    let (client, conn) = if let Some(connect) = args.get_one::<String>("connect") {
+    let mut config = if let Some(connect) = args.get_one::<String>("connect") {
         Config::from_str(connect.as_str())?
-            .connect(NoTls)
-            .await?
     } else {
         let user = args.get_one::<String>("user")
             .unwrap();
@@ -41,18 +109,42 @@ pub async fn postgres(args: &ArgMatches) -> error::Result<Client> {
             config.password(pass.as_str());
         }
 
-        config.connect(NoTls).await?
+        config
     };
 
-    tokio::task::spawn(async move {
-        if let Err(e) = conn.await {
-            tracing::event!(
-                tracing::Level::ERROR,
-                "postgres connection error: {}",
-                e
-            );
-        }
-    });
+    if let Some(seconds) = args.get_one::<u64>("connect_timeout") {
+        config.connect_timeout(Duration::from_secs(*seconds));
+    }
+
+    if let Some(milliseconds) = args.get_one::<u64>("statement_timeout") {
+        let mut options = config.get_options()
+            .map(|options| format!("{} ", options))
+            .unwrap_or_default();
+        options.push_str(&format!("-c statement_timeout={}", milliseconds));
+
+        config.options(options.as_str());
+    }
+
+    // a connection string that requires tls will not connect without it
+    let with_tls = args.get_flag("tls") ||
+        matches!(config.get_ssl_mode(), SslMode::Require);
+
+    let client = if with_tls {
+        config.ssl_mode(SslMode::Require);
+
+        let tls = make_tls(args.get_one::<PathBuf>("ca_file"))?;
+        let (client, conn) = config.connect(tls).await?;
+
+        spawn_connection(conn);
+
+        client
+    } else {
+        let (client, conn) = config.connect(NoTls).await?;
+
+        spawn_connection(conn);
+
+        client
+    };
 
     Ok(client)
 }
@@ -102,3 +102,5 @@ macro_rules! generic_catch {
 generic_catch!("std::io::Error", std::io::Error);
 generic_catch!("tokio_postgres::Error", tokio_postgres::Error);
 generic_catch!("snowcloud_cloud::error::Error", snowcloud_cloud::error::Error);
+generic_catch!("argon2::Error", argon2::Error);
+generic_catch!("rand::Error", rand::Error);
@@ -17,6 +17,8 @@ fn commands() -> clap::Command {
                 .subcommand_required(true)
                 .about("generates an for the desired namespace")
                 .subcommand(Command::new("user"))
+                .subcommand(Command::new("role"))
+                .subcommand(Command::new("group"))
         )
         .subcommand(
             Command::new("setup")
@@ -34,6 +36,38 @@ fn commands() -> clap::Command {
                 .arg(args::db::host())
                 .arg(args::db::port())
                 .arg(args::db::dbname())
+                .arg(args::db::tls())
+                .arg(args::db::ca_file())
+                .arg(args::db::connect_timeout())
+                .arg(args::db::statement_timeout())
+        )
+        .subcommand(
+            Command::new("bootstrap")
+                .about("creates the default roles and the first admin user")
+                .arg(
+                    Arg::new("username")
+                        .long("username")
+                        .action(ArgAction::Set)
+                        .required(true)
+                        .help("username of the admin user to create")
+                )
+                .arg(
+                    Arg::new("email")
+                        .long("email")
+                        .action(ArgAction::Set)
+                        .help("optional email of the admin user")
+                )
+                .arg(args::db::connect())
+                .arg(args::db::user())
+                .arg(args::db::password())
+                .arg(args::db::req_password())
+                .arg(args::db::host())
+                .arg(args::db::port())
+                .arg(args::db::dbname())
+                .arg(args::db::tls())
+                .arg(args::db::ca_file())
+                .arg(args::db::connect_timeout())
+                .arg(args::db::statement_timeout())
         )
         .subcommand(
             Command::new("migrate")
@@ -45,6 +79,10 @@ fn commands() -> clap::Command {
                 .arg(args::db::host())
                 .arg(args::db::port())
                 .arg(args::db::dbname())
+                .arg(args::db::tls())
+                .arg(args::db::ca_file())
+                .arg(args::db::connect_timeout())
+                .arg(args::db::statement_timeout())
                 .subcommand(
                     Command::new("run")
                         .about("runs migrates")
@@ -129,6 +167,7 @@ async fn exec(matches: &clap::ArgMatches) -> error::Result<()> {
     match matches.subcommand() {
         Some(("setup", setup_matches)) => run::setup(&setup_matches).await?,
         Some(("ids", ids_matches)) => run::ids(&ids_matches)?,
+        Some(("bootstrap", bootstrap_matches)) => run::bootstrap(bootstrap_matches).await?,
         Some(("migrate", migrate_matches)) => {},
         _ => unreachable!()
     };
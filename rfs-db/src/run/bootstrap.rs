@@ -0,0 +1,151 @@
+use clap::ArgMatches;
+use rfs_lib::ids;
+use snowcloud_cloud::Generator;
+use tokio_postgres::GenericClient;
+
+use crate::error;
+use crate::conn;
+
+/// permission values as they are stored by the server in authz_permissions
+const STORAGE_CREATE: i16 = 0;
+const STORAGE_LOCAL_ANY: i16 = 1;
+const USER_MANAGE: i16 = 2;
+const ROLE_MANAGE: i16 = 3;
+const AUDIT_READ: i16 = 4;
+
+const ADMIN_PERMISSIONS: [i16; 5] = [
+    STORAGE_CREATE,
+    STORAGE_LOCAL_ANY,
+    USER_MANAGE,
+    ROLE_MANAGE,
+    AUDIT_READ,
+];
+
+const USER_PERMISSIONS: [i16; 1] = [
+    STORAGE_CREATE,
+];
+
+/// matches the argon2id configuration used by the server with the empty
+/// secret at version 0
+fn hash_password(password: &str) -> error::Result<String> {
+    use argon2::Variant;
+    use rand::RngCore;
+
+    let config = argon2::Config {
+        mem_cost: 19456,
+        variant: Variant::Argon2id,
+        ..argon2::Config::default()
+    };
+
+    let mut salt = [0u8; 32];
+
+    rand::thread_rng()
+        .try_fill_bytes(&mut salt)?;
+
+    Ok(argon2::hash_encoded(password.as_bytes(), &salt, &config)?)
+}
+
+fn prompt_password(username: &str) -> error::Result<String> {
+    let password = rpassword::prompt_password(format!("{} password: ", username))?;
+    let confirm = rpassword::prompt_password("confirm password: ")?;
+
+    if password != confirm {
+        return Err(error::Error::new()
+            .kind("InvalidPassword")
+            .message("passwords do not match"));
+    }
+
+    if password.is_empty() {
+        return Err(error::Error::new()
+            .kind("InvalidPassword")
+            .message("password cannot be empty"));
+    }
+
+    Ok(password)
+}
+
+/// retrieves the id of the role with the given name or creates it with the
+/// provided permissions
+async fn ensure_role(
+    conn: &impl GenericClient,
+    generator: &mut Generator<ids::RoleId>,
+    name: &str,
+    permissions: &[i16],
+) -> error::Result<ids::RoleId> {
+    if let Some(row) = conn.query_opt(
+        "select id from authz_roles where name = $1",
+        &[&name]
+    ).await? {
+        return Ok(ids::role_id_from_pg(row.get(0)));
+    }
+
+    let role_id = generator.next_id()?;
+
+    let _ = conn.execute(
+        "insert into authz_roles (id, name) values ($1, $2)",
+        &[&role_id, &name]
+    ).await?;
+
+    for permission in permissions {
+        let _ = conn.execute(
+            "insert into authz_permissions (role_id, permission) values ($1, $2)",
+            &[&role_id, permission]
+        ).await?;
+    }
+
+    println!("created role \"{}\" id {}", name, role_id.id());
+
+    Ok(role_id)
+}
+
+pub async fn run(args: &ArgMatches) -> error::Result<()> {
+    let username = args.get_one::<String>("username").unwrap();
+    let email = args.get_one::<String>("email");
+
+    let mut conn = conn::postgres(args).await?;
+
+    let existing = conn.query_opt(
+        "select id from users limit 1",
+        &[]
+    ).await?;
+
+    if existing.is_some() {
+        return Err(error::Error::new()
+            .kind("UsersExist")
+            .message("users already exist in the database. bootstrap is only available for a new database"));
+    }
+
+    let password = prompt_password(username)?;
+    let hash = hash_password(&password)?;
+
+    let mut role_generator: Generator<ids::RoleId> = Generator::new(ids::START_TIME, 1)?;
+    let mut user_generator: Generator<ids::UserId> = Generator::new(ids::START_TIME, 1)?;
+
+    let transaction = conn.transaction().await?;
+
+    let admin_id = ensure_role(&transaction, &mut role_generator, "admin", &ADMIN_PERMISSIONS).await?;
+    let _user_role_id = ensure_role(&transaction, &mut role_generator, "user", &USER_PERMISSIONS).await?;
+
+    let user_id = user_generator.next_id()?;
+
+    let _ = transaction.execute(
+        "insert into users (id, username, email) values ($1, $2, $3)",
+        &[&user_id, username, &email]
+    ).await?;
+
+    let _ = transaction.execute(
+        "insert into auth_password (user_id, version, hash) values ($1, 0, $2)",
+        &[&user_id, &hash]
+    ).await?;
+
+    let _ = transaction.execute(
+        "insert into authz_user_roles (user_id, role_id) values ($1, $2)",
+        &[&user_id, &admin_id]
+    ).await?;
+
+    transaction.commit().await?;
+
+    println!("created admin user \"{}\" id {}", username, user_id.id());
+
+    Ok(())
+}
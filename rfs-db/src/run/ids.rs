@@ -20,10 +20,14 @@ macro_rules! gen_id {
 }
 
 gen_id!(gen_user_id, ids::UserId, "user");
+gen_id!(gen_role_id, ids::RoleId, "role");
+gen_id!(gen_group_id, ids::GroupId, "group");
 
 pub fn run(args: &ArgMatches) -> error::Result<()> {
     match args.subcommand() {
         Some(("user", _)) => gen_user_id()?,
+        Some(("role", _)) => gen_role_id()?,
+        Some(("group", _)) => gen_group_id()?,
         _ => {}
     };
 
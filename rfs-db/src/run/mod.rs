@@ -1,6 +1,8 @@
 mod setup;
 mod ids;
+mod bootstrap;
 //mod migrate;
 
 pub use setup::run as setup;
 pub use ids::run as ids;
+pub use bootstrap::run as bootstrap;
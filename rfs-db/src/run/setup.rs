@@ -29,21 +29,17 @@ pub async fn run(args: &ArgMatches) -> error::Result<()> {
                 .message(format!("failed to read file. {}", path.display()))
                 .source(err))?;
 
-        for sql in file_sql.split(';') {
-            let trim = sql.trim();
+        // the file is sent as is since function bodies can contain ";"
+        if let Err(err) = transaction.batch_execute(&file_sql).await {
+            failed = true;
 
-            if let Err(err) = transaction.execute(trim, &[]).await {
-                failed = true;
+            println!(
+                "error running queries from {}. {}",
+                path.strip_prefix(&current_dir).unwrap().display(),
+                err
+            );
 
-                println!(
-                    "error running query from {}. {}\n{}", 
-                    path.strip_prefix(&current_dir).unwrap().display(),
-                    err,
-                    trim
-                );
-
-                break 'read_dir;
-            }
+            break 'read_dir;
         }
     }
 
@@ -0,0 +1,19 @@
+use serde::{Serialize, Deserialize};
+use snowcloud_flake::serde_ext::string_id;
+
+use crate::ids;
+use crate::schema::access::Ability;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetAccess {
+    #[serde(with = "string_id")]
+    pub user_id: ids::UserId,
+    pub ability: Ability,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetGroupAccess {
+    #[serde(with = "string_id")]
+    pub group_id: ids::GroupId,
+    pub ability: Ability,
+}
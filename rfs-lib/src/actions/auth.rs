@@ -37,6 +37,11 @@ pub struct CreateTotp {
     pub step: Option<u64>
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct ConfirmTotp {
+    pub code: String
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct CreateTotpHash {
     pub key: String
@@ -47,3 +52,27 @@ pub struct UpdateTotpHash {
     pub key: Option<String>,
     pub regen: bool
 }
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateRecoveryCodes {
+    pub count: Option<u32>,
+    /// replaces any existing codes
+    pub regen: bool
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ConfirmEmail {
+    pub token: String
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RequestReset {
+    pub username: String
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CompleteReset {
+    pub token: String,
+    pub updated: String,
+    pub confirm: String,
+}
@@ -0,0 +1,28 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+
+use crate::ids;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateBot {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateBot {
+    pub name: Option<String>,
+}
+
+impl UpdateBot {
+    pub fn has_work(&self) -> bool {
+        self.name.is_some()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateToken {
+    #[serde(default)]
+    pub read_only: bool,
+    pub storages: Option<Vec<ids::StorageId>>,
+    pub expires: Option<DateTime<Utc>>,
+}
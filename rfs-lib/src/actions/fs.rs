@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 use serde::{Serialize, Deserialize};
 
+use crate::schema::fs::SignedOperation;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateMetadata {
     pub tags: Option<HashMap<String, Option<String>>>,
@@ -14,6 +16,13 @@ pub struct CreateDir {
     pub comment: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignUrl {
+    pub operation: SignedOperation,
+    /// number of seconds the url will be valid for
+    pub expires_in: Option<u64>,
+}
+
 impl UpdateMetadata {
     pub fn has_work(&self) -> bool {
         self.tags.is_some() ||
@@ -0,0 +1,29 @@
+use serde::{Serialize, Deserialize};
+use snowcloud_flake::serde_ext::string_id;
+
+use crate::ids;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateGroup {
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateGroup {
+    pub name: Option<String>,
+}
+
+impl UpdateGroup {
+    pub fn has_work(&self) -> bool {
+        self.name.is_some()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetMember {
+    #[serde(with = "string_id")]
+    pub user_id: ids::UserId,
+    /// only users with user management are able to change this
+    #[serde(default)]
+    pub admin: bool,
+}
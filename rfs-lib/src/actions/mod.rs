@@ -1,3 +1,9 @@
 pub mod storage;
 pub mod fs;
 pub mod auth;
+pub mod access;
+pub mod share;
+pub mod user;
+pub mod role;
+pub mod bot;
+pub mod group;
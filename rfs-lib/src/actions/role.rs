@@ -0,0 +1,22 @@
+use serde::{Serialize, Deserialize};
+
+use crate::schema::role::Permission;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateRole {
+    pub name: String,
+    pub permissions: Vec<Permission>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateRole {
+    pub name: Option<String>,
+    pub permissions: Option<Vec<Permission>>,
+}
+
+impl UpdateRole {
+    pub fn has_work(&self) -> bool {
+        self.name.is_some() ||
+            self.permissions.is_some()
+    }
+}
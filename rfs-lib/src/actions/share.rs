@@ -0,0 +1,12 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+
+use crate::schema::share::ShareMode;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateShare {
+    pub mode: ShareMode,
+    pub expires: Option<DateTime<Utc>>,
+    pub password: Option<String>,
+    pub max_downloads: Option<u32>,
+}
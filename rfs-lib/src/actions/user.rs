@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+
+use crate::ids;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateUser {
+    pub username: String,
+    pub email: Option<String>,
+    pub password: Option<String>,
+    pub roles: Option<Vec<ids::RoleId>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateUser {
+    pub username: Option<String>,
+    /// an empty string will remove the current email
+    pub email: Option<String>,
+}
+
+impl UpdateUser {
+    pub fn has_work(&self) -> bool {
+        self.username.is_some() ||
+            self.email.is_some()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetRoles {
+    pub roles: Vec<ids::RoleId>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Register {
+    pub username: String,
+    pub email: Option<String>,
+    pub password: String,
+    /// required when the server only allows registration with an invite
+    pub invite: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateInvite {
+    /// the number of times the invite can be used. unlimited if not set
+    pub max_uses: Option<u32>,
+    pub expires: Option<DateTime<Utc>>,
+}
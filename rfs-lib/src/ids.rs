@@ -9,6 +9,8 @@ pub type FSId = SingleIdFlake<43, 8, 12>;
 pub type StorageId = SingleIdFlake<43, 8, 12>;
 pub type BotId = SingleIdFlake<43, 8, 12>;
 pub type ListenerId = SingleIdFlake<43, 8, 12>;
+pub type RoleId = SingleIdFlake<43, 8, 12>;
+pub type GroupId = SingleIdFlake<43, 8, 12>;
 
 pub fn from_pg<V, T>(value: V) -> T
 where
@@ -43,3 +45,13 @@ pub fn bot_id_from_pg(value: i64) -> BotId {
 pub fn listener_id_from_pg(value: i64) -> ListenerId {
     from_pg(value)
 }
+
+#[inline]
+pub fn role_id_from_pg(value: i64) -> RoleId {
+    from_pg(value)
+}
+
+#[inline]
+pub fn group_id_from_pg(value: i64) -> GroupId {
+    from_pg(value)
+}
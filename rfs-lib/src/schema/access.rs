@@ -0,0 +1,39 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use snowcloud_flake::serde_ext::string_id;
+
+use crate::ids;
+
+use super::fs::ListItem;
+use super::storage::StorageListItem;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Ability {
+    Read,
+    Write,
+    Manage,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccessItem {
+    #[serde(with = "string_id")]
+    pub user_id: ids::UserId,
+    pub ability: Ability,
+    pub created: DateTime<Utc>,
+    pub updated: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GroupAccessItem {
+    #[serde(with = "string_id")]
+    pub group_id: ids::GroupId,
+    pub ability: Ability,
+    pub created: DateTime<Utc>,
+    pub updated: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SharedItems {
+    pub storage: Vec<StorageListItem>,
+    pub fs: Vec<ListItem>,
+}
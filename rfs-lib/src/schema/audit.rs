@@ -0,0 +1,17 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+
+use crate::ids;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Entry {
+    pub id: i64,
+    pub event: String,
+    pub user_id: Option<ids::UserId>,
+    /// how the user was authenticated when the event happened
+    pub mechanism: Option<String>,
+    pub request_id: Option<u64>,
+    pub ip: Option<String>,
+    pub details: Option<serde_json::Value>,
+    pub created: DateTime<Utc>,
+}
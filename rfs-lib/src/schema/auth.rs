@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -11,5 +12,74 @@ pub enum VerifyMethod {
 #[derive(Debug, Serialize, Deserialize)]
 pub enum AuthMethod {
     None,
-    Password
+    Password,
+    /// login must continue with the identity provider
+    Oidc,
+    /// the password is checked against the ldap server
+    Ldap,
+}
+
+/// where to send the user to log in with the identity provider
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OidcLogin {
+    pub url: String,
+}
+
+/// information needed to add a totp to an authenticator app
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TotpEnrollment {
+    pub algo: String,
+    pub digits: u32,
+    pub step: u64,
+    /// base32 encoded secret
+    pub secret: String,
+    /// otpauth uri of the totp
+    pub uri: String,
+    /// svg image of a qr code for the uri
+    pub qr_svg: String,
+}
+
+/// a totp hash code. only available when it is created
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TotpHashCode {
+    pub key: String,
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecoveryStatus {
+    pub total: u64,
+    /// codes that have not been used
+    pub remaining: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SecretVersion {
+    pub version: u32,
+    /// if the secret is available to the server
+    pub loaded: bool,
+    /// if this is the secret used for new passwords
+    pub latest: bool,
+    /// the number of passwords hashed with this secret
+    pub passwords: u64,
+}
+
+/// a short lived bearer token. only available when it is created
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccessToken {
+    pub token: String,
+    pub expires: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Session {
+    pub id: String,
+    /// true if this is the session making the request
+    pub current: bool,
+    pub issued_on: DateTime<Utc>,
+    pub expires: DateTime<Utc>,
+    pub authenticated: bool,
+    pub verified: bool,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
 }
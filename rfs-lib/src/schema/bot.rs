@@ -0,0 +1,44 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use snowcloud_flake::serde_ext::string_id;
+
+use crate::ids;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Bot {
+    #[serde(with = "string_id")]
+    pub id: ids::BotId,
+    #[serde(with = "string_id")]
+    pub user_id: ids::UserId,
+    pub name: String,
+    pub created: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListItem {
+    #[serde(with = "string_id")]
+    pub id: ids::BotId,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Token {
+    pub id: String,
+    #[serde(with = "string_id")]
+    pub bot_id: ids::BotId,
+    /// limits the token to read operations
+    pub read_only: bool,
+    /// limits the token to the given storage mediums. an empty list allows
+    /// for all storage mediums available to the owner
+    pub storages: Vec<ids::StorageId>,
+    pub expires: Option<DateTime<Utc>>,
+    pub last_used: Option<DateTime<Utc>>,
+    pub created: DateTime<Utc>,
+}
+
+/// only returned when a token is created since the full token is not stored
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatedToken {
+    pub token: String,
+    pub info: Token,
+}
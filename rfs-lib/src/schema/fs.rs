@@ -109,3 +109,16 @@ pub enum Item {
     File(File),
     Directory(Directory),
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SignedOperation {
+    Download,
+    Upload,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignedUrl {
+    pub operation: SignedOperation,
+    pub url: String,
+    pub expires: DateTime<Utc>,
+}
@@ -0,0 +1,30 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use snowcloud_flake::serde_ext::string_id;
+
+use crate::ids;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Group {
+    #[serde(with = "string_id")]
+    pub id: ids::GroupId,
+    pub name: String,
+    pub created: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListItem {
+    #[serde(with = "string_id")]
+    pub id: ids::GroupId,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Member {
+    #[serde(with = "string_id")]
+    pub user_id: ids::UserId,
+    pub username: String,
+    /// group admins are able to add and remove members of the group
+    pub admin: bool,
+    pub added: DateTime<Utc>,
+}
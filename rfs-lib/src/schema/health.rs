@@ -0,0 +1,19 @@
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Pool {
+    /// the most connections the pool will open
+    pub max_size: usize,
+    /// the connections currently open
+    pub size: usize,
+    /// idle connections minus requests waiting for one. negative if
+    /// requests are waiting
+    pub available: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Health {
+    /// if a query could be run against the database
+    pub database: bool,
+    pub pool: Pool,
+}
@@ -3,3 +3,11 @@ pub mod tags;
 pub mod fs;
 pub mod storage;
 pub mod auth;
+pub mod access;
+pub mod share;
+pub mod user;
+pub mod role;
+pub mod bot;
+pub mod group;
+pub mod audit;
+pub mod health;
@@ -0,0 +1,34 @@
+use serde::{Serialize, Deserialize};
+use snowcloud_flake::serde_ext::string_id;
+
+use crate::ids;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Permission {
+    /// allows for creating new storage mediums
+    StorageCreate,
+    /// allows for creating local storage mediums outside of the configured
+    /// storage root
+    StorageLocalAny,
+    /// allows for creating, updating and deleting users
+    UserManage,
+    /// allows for creating, updating and deleting roles
+    RoleManage,
+    /// allows for reading audit logs
+    AuditRead,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Role {
+    #[serde(with = "string_id")]
+    pub id: ids::RoleId,
+    pub name: String,
+    pub permissions: Vec<Permission>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListItem {
+    #[serde(with = "string_id")]
+    pub id: ids::RoleId,
+    pub name: String,
+}
@@ -0,0 +1,35 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use snowcloud_flake::serde_ext::string_id;
+
+use crate::ids;
+
+use super::fs::ListItem;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShareMode {
+    ReadOnly,
+    UploadOnly,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShareItem {
+    pub token: String,
+    #[serde(with = "string_id")]
+    pub fs_id: ids::FSId,
+    #[serde(with = "string_id")]
+    pub user_id: ids::UserId,
+    pub mode: ShareMode,
+    pub has_password: bool,
+    pub expires: Option<DateTime<Utc>>,
+    pub max_downloads: Option<u32>,
+    pub downloads: u32,
+    pub created: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PublicShare {
+    pub mode: ShareMode,
+    pub expires: Option<DateTime<Utc>>,
+    pub item: ListItem,
+}
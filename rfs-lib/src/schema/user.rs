@@ -0,0 +1,45 @@
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use snowcloud_flake::serde_ext::string_id;
+
+use crate::ids;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserEmail {
+    pub email: String,
+    pub verified: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct User {
+    #[serde(with = "string_id")]
+    pub id: ids::UserId,
+    pub username: String,
+    pub email: Option<UserEmail>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListItem {
+    #[serde(with = "string_id")]
+    pub id: ids::UserId,
+    pub username: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RegistrationMode {
+    Disabled,
+    Open,
+    Invite,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Invite {
+    pub code: String,
+    #[serde(with = "string_id")]
+    pub user_id: ids::UserId,
+    /// the number of times the invite can be used. unlimited if not set
+    pub max_uses: Option<u32>,
+    pub uses: u32,
+    pub expires: Option<DateTime<Utc>>,
+    pub created: DateTime<Utc>,
+}
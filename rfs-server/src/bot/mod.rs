@@ -0,0 +1,132 @@
+use chrono::{DateTime, Utc};
+use rfs_lib::ids;
+use rfs_lib::schema;
+use tokio_postgres::Error as PgError;
+use deadpool_postgres::GenericClient;
+
+pub mod token;
+
+pub async fn name_check(
+    conn: &impl GenericClient,
+    user_id: &ids::UserId,
+    name: &str,
+) -> Result<Option<ids::BotId>, PgError> {
+    let check = conn.query_opt(
+        "select id from bots where user_id = $1 and name = $2",
+        &[user_id, &name]
+    ).await?;
+
+    Ok(check.map(|row| ids::bot_id_from_pg(row.get(0))))
+}
+
+pub struct BotBuilder {
+    id: ids::BotId,
+    user_id: ids::UserId,
+    name: String,
+}
+
+impl BotBuilder {
+    pub async fn build(self, conn: &impl GenericClient) -> Result<Bot, PgError> {
+        let created = Utc::now();
+
+        let _ = conn.execute(
+            "\
+            insert into bots (id, user_id, name, created) values \
+            ($1, $2, $3, $4)",
+            &[&self.id, &self.user_id, &self.name, &created]
+        ).await?;
+
+        Ok(Bot {
+            id: self.id,
+            user_id: self.user_id,
+            name: self.name,
+            created,
+        })
+    }
+}
+
+pub struct Bot {
+    pub id: ids::BotId,
+    pub user_id: ids::UserId,
+    pub name: String,
+    pub created: DateTime<Utc>,
+}
+
+impl Bot {
+    pub fn builder(id: ids::BotId, user_id: ids::UserId, name: String) -> BotBuilder {
+        BotBuilder {
+            id,
+            user_id,
+            name,
+        }
+    }
+
+    fn from_row(row: tokio_postgres::Row) -> Self {
+        Bot {
+            id: ids::bot_id_from_pg(row.get(0)),
+            user_id: ids::user_id_from_pg(row.get(1)),
+            name: row.get(2),
+            created: row.get(3),
+        }
+    }
+
+    pub async fn retrieve(
+        conn: &impl GenericClient,
+        id: &ids::BotId,
+    ) -> Result<Option<Self>, PgError> {
+        let result = conn.query_opt(
+            "\
+            select bots.id, \
+                   bots.user_id, \
+                   bots.name, \
+                   bots.created \
+            from bots \
+            where bots.id = $1",
+            &[id]
+        ).await?;
+
+        Ok(result.map(Self::from_row))
+    }
+
+    pub async fn retrieve_user(
+        conn: &impl GenericClient,
+        user_id: &ids::UserId,
+    ) -> Result<Vec<Self>, PgError> {
+        let rows = conn.query(
+            "\
+            select bots.id, \
+                   bots.user_id, \
+                   bots.name, \
+                   bots.created \
+            from bots \
+            where bots.user_id = $1 \
+            order by bots.id",
+            &[user_id]
+        ).await?;
+
+        Ok(rows.into_iter()
+            .map(Self::from_row)
+            .collect())
+    }
+
+    /// removes the bot along with all of its tokens
+    pub async fn delete(&self, conn: &impl GenericClient) -> Result<bool, PgError> {
+        token::delete_bot_tokens(conn, &self.id).await?;
+
+        let deleted = conn.execute(
+            "delete from bots where id = $1",
+            &[&self.id]
+        ).await?;
+
+        Ok(deleted == 1)
+    }
+
+    pub fn into_schema(self) -> schema::bot::Bot {
+        schema::bot::Bot {
+            id: self.id,
+            user_id: self.user_id,
+            name: self.name,
+            created: self.created,
+        }
+    }
+}
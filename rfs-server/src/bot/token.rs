@@ -0,0 +1,351 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use rfs_lib::ids;
+use rfs_lib::schema;
+use tokio_postgres::Error as PgError;
+use deadpool_postgres::GenericClient;
+
+use crate::net;
+use crate::sec::authz::Ability;
+
+pub const TOKEN_ID_BYTES: usize = 16;
+pub const TOKEN_SECRET_BYTES: usize = 32;
+
+pub enum TokenError {
+    Rand(rand::Error),
+    Pg(PgError),
+}
+
+impl From<rand::Error> for TokenError {
+    fn from(err: rand::Error) -> Self {
+        TokenError::Rand(err)
+    }
+}
+
+impl From<PgError> for TokenError {
+    fn from(err: PgError) -> Self {
+        TokenError::Pg(err)
+    }
+}
+
+impl From<TokenError> for net::error::Error {
+    fn from(err: TokenError) -> net::error::Error {
+        match err {
+            TokenError::Rand(err) => err.into(),
+            TokenError::Pg(err) => err.into(),
+        }
+    }
+}
+
+fn gen_bytes<const N: usize>() -> Result<[u8; N], rand::Error> {
+    let mut bytes = [0u8; N];
+
+    rand::thread_rng().try_fill_bytes(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+/// splits a bearer token into its id and secret
+///
+/// tokens are formatted as "{id}.{secret}" with both parts being url safe
+/// base64 without padding
+pub fn split_token(given: &str) -> Option<(&str, Vec<u8>)> {
+    let (id, secret) = given.split_once('.')?;
+
+    let decoded = URL_SAFE_NO_PAD.decode(secret).ok()?;
+
+    if decoded.len() != TOKEN_SECRET_BYTES {
+        return None;
+    }
+
+    Some((id, decoded))
+}
+
+/// removes all tokens for the given bot
+pub async fn delete_bot_tokens(
+    conn: &impl GenericClient,
+    bot_id: &ids::BotId,
+) -> Result<(), PgError> {
+    let _ = conn.execute(
+        "\
+        delete from bot_token_storage \
+        where token_id in (\
+            select bot_tokens.id \
+            from bot_tokens \
+            where bot_tokens.bot_id = $1\
+        )",
+        &[bot_id]
+    ).await?;
+    let _ = conn.execute(
+        "delete from bot_tokens where bot_id = $1",
+        &[bot_id]
+    ).await?;
+
+    Ok(())
+}
+
+pub struct TokenBuilder {
+    bot_id: ids::BotId,
+    read_only: bool,
+    storages: Vec<ids::StorageId>,
+    expires: Option<DateTime<Utc>>,
+}
+
+impl TokenBuilder {
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    pub fn set_storages(&mut self, storages: Vec<ids::StorageId>) {
+        self.storages = storages;
+    }
+
+    pub fn set_expires(&mut self, expires: DateTime<Utc>) {
+        self.expires = Some(expires);
+    }
+
+    /// creates the token and returns it along with the full bearer value.
+    /// only the hash of the secret is stored so the bearer value cannot be
+    /// retrieved later
+    pub async fn build(self, conn: &impl GenericClient) -> Result<(Token, String), TokenError> {
+        let created = Utc::now();
+        let id = URL_SAFE_NO_PAD.encode(gen_bytes::<TOKEN_ID_BYTES>()?);
+        let secret = gen_bytes::<TOKEN_SECRET_BYTES>()?;
+        let hash = blake3::hash(&secret);
+
+        let _ = conn.execute(
+            "\
+            insert into bot_tokens (\
+                id, \
+                bot_id, \
+                hash, \
+                read_only, \
+                expires, \
+                created\
+            ) values \
+            ($1, $2, $3, $4, $5, $6)",
+            &[
+                &id,
+                &self.bot_id,
+                &hash.as_bytes().as_slice(),
+                &self.read_only,
+                &self.expires,
+                &created
+            ]
+        ).await?;
+
+        for storage_id in &self.storages {
+            let _ = conn.execute(
+                "\
+                insert into bot_token_storage (token_id, storage_id) values \
+                ($1, $2) \
+                on conflict do nothing",
+                &[&id, storage_id]
+            ).await?;
+        }
+
+        let bearer = format!("{}.{}", id, URL_SAFE_NO_PAD.encode(secret));
+
+        Ok((Token {
+            id,
+            bot_id: self.bot_id,
+            hash,
+            read_only: self.read_only,
+            storages: self.storages,
+            expires: self.expires,
+            last_used: None,
+            created,
+        }, bearer))
+    }
+}
+
+pub struct Token {
+    pub id: String,
+    pub bot_id: ids::BotId,
+    pub hash: blake3::Hash,
+    pub read_only: bool,
+    pub storages: Vec<ids::StorageId>,
+    pub expires: Option<DateTime<Utc>>,
+    pub last_used: Option<DateTime<Utc>>,
+    pub created: DateTime<Utc>,
+}
+
+impl Token {
+    pub fn builder(bot_id: ids::BotId) -> TokenBuilder {
+        TokenBuilder {
+            bot_id,
+            read_only: false,
+            storages: Vec::new(),
+            expires: None,
+        }
+    }
+
+    fn from_row(row: tokio_postgres::Row, storages: Vec<ids::StorageId>) -> Self {
+        let hash_bytes: Vec<u8> = row.get(2);
+        let mut hash = [0u8; blake3::OUT_LEN];
+
+        if hash_bytes.len() == blake3::OUT_LEN {
+            hash.copy_from_slice(&hash_bytes);
+        }
+
+        Token {
+            id: row.get(0),
+            bot_id: ids::bot_id_from_pg(row.get(1)),
+            hash: blake3::Hash::from(hash),
+            read_only: row.get(3),
+            storages,
+            expires: row.get(4),
+            last_used: row.get(5),
+            created: row.get(6),
+        }
+    }
+
+    async fn retrieve_storages(
+        conn: &impl GenericClient,
+        id: &str,
+    ) -> Result<Vec<ids::StorageId>, PgError> {
+        let rows = conn.query(
+            "\
+            select bot_token_storage.storage_id \
+            from bot_token_storage \
+            where bot_token_storage.token_id = $1",
+            &[&id]
+        ).await?;
+
+        Ok(rows.into_iter()
+            .map(|row| ids::storage_id_from_pg(row.get(0)))
+            .collect())
+    }
+
+    pub async fn retrieve(
+        conn: &impl GenericClient,
+        id: &str,
+    ) -> Result<Option<Self>, PgError> {
+        let Some(row) = conn.query_opt(
+            "\
+            select bot_tokens.id, \
+                   bot_tokens.bot_id, \
+                   bot_tokens.hash, \
+                   bot_tokens.read_only, \
+                   bot_tokens.expires, \
+                   bot_tokens.last_used, \
+                   bot_tokens.created \
+            from bot_tokens \
+            where bot_tokens.id = $1",
+            &[&id]
+        ).await? else {
+            return Ok(None);
+        };
+
+        let storages = Self::retrieve_storages(conn, id).await?;
+
+        Ok(Some(Self::from_row(row, storages)))
+    }
+
+    pub async fn retrieve_bot(
+        conn: &impl GenericClient,
+        bot_id: &ids::BotId,
+    ) -> Result<Vec<Self>, PgError> {
+        let rows = conn.query(
+            "\
+            select bot_tokens.id, \
+                   bot_tokens.bot_id, \
+                   bot_tokens.hash, \
+                   bot_tokens.read_only, \
+                   bot_tokens.expires, \
+                   bot_tokens.last_used, \
+                   bot_tokens.created \
+            from bot_tokens \
+            where bot_tokens.bot_id = $1 \
+            order by bot_tokens.created",
+            &[bot_id]
+        ).await?;
+
+        let mut rtn = Vec::with_capacity(rows.len());
+
+        for row in rows {
+            let id: String = row.get(0);
+            let storages = Self::retrieve_storages(conn, &id).await?;
+
+            rtn.push(Self::from_row(row, storages));
+        }
+
+        Ok(rtn)
+    }
+
+    /// compares the given secret against the stored hash in constant time
+    pub fn verify(&self, secret: &[u8]) -> bool {
+        blake3::hash(secret) == self.hash
+    }
+
+    pub fn is_expired(&self) -> bool {
+        if let Some(expires) = &self.expires {
+            *expires < Utc::now()
+        } else {
+            false
+        }
+    }
+
+    /// limits the given ability to what the token is allowed to do
+    ///
+    /// tokens are never allowed to manage an item. read only tokens are
+    /// limited to read and tokens with storages are limited to those storage
+    /// mediums
+    pub fn restrict(
+        &self,
+        storage_id: &ids::StorageId,
+        ability: Option<Ability>,
+    ) -> Option<Ability> {
+        if !self.storages.is_empty() && !self.storages.contains(storage_id) {
+            return None;
+        }
+
+        let max = if self.read_only {
+            Ability::Read
+        } else {
+            Ability::Write
+        };
+
+        ability.map(|given| given.min(max))
+    }
+
+    pub async fn update_last_used(&mut self, conn: &impl GenericClient) -> Result<(), PgError> {
+        let now = Utc::now();
+
+        let _ = conn.execute(
+            "update bot_tokens set last_used = $2 where id = $1",
+            &[&self.id, &now]
+        ).await?;
+
+        self.last_used = Some(now);
+
+        Ok(())
+    }
+
+    pub async fn delete(&self, conn: &impl GenericClient) -> Result<bool, PgError> {
+        let _ = conn.execute(
+            "delete from bot_token_storage where token_id = $1",
+            &[&self.id]
+        ).await?;
+
+        let deleted = conn.execute(
+            "delete from bot_tokens where id = $1",
+            &[&self.id]
+        ).await?;
+
+        Ok(deleted == 1)
+    }
+
+    pub fn into_schema(self) -> schema::bot::Token {
+        schema::bot::Token {
+            id: self.id,
+            bot_id: self.bot_id,
+            read_only: self.read_only,
+            storages: self.storages,
+            expires: self.expires,
+            last_used: self.last_used,
+            created: self.created,
+        }
+    }
+}
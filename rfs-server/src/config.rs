@@ -0,0 +1,807 @@
+use std::path::{Path, PathBuf};
+use std::net::IpAddr;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use serde::{Deserialize, Deserializer};
+use serde::de::Error as _;
+
+use crate::error;
+use crate::net;
+use crate::sec;
+use crate::email;
+use crate::state;
+
+/// parses an enum with the same names that are accepted on the command line
+fn value_enum<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: clap::ValueEnum,
+{
+    let Some(given) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+
+    T::from_str(&given, true)
+        .map(Some)
+        .map_err(D::Error::custom)
+}
+
+fn base64_bytes<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>
+{
+    let given = String::deserialize(deserializer)?;
+
+    STANDARD.decode(given.trim())
+        .map_err(|err| D::Error::custom(format!("secret is not valid base64. {}", err)))
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Listen {
+    pub ip: Option<IpAddr>,
+    pub port: Option<u16>,
+    /// origins that are allowed to make state changing requests with the
+    /// session cookie
+    pub allowed_origins: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Tls {
+    /// pem file of the certificate chain
+    pub cert: Option<PathBuf>,
+    /// pem file of the private key
+    pub key: Option<PathBuf>,
+    /// port of a plain http listener that redirects to https
+    pub redirect_port: Option<u16>,
+    /// max-age in seconds of the strict transport security header
+    pub hsts_max_age: Option<u64>,
+    /// seconds between checking the cert and key for changes
+    pub reload_interval: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Templates {
+    pub directory: Option<PathBuf>,
+    pub dev_mode: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Pg {
+    /// connection string or url
+    pub url: Option<String>,
+    pub user: Option<String>,
+    pub password: Option<String>,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub dbname: Option<String>,
+    pub tls: Option<bool>,
+    /// pem file of the certificate authorities used to verify the server
+    pub ca_file: Option<PathBuf>,
+    pub pool_size: Option<usize>,
+    /// seconds to wait for a connection to be available
+    pub wait_timeout: Option<u64>,
+    /// seconds to wait for a new connection to be created
+    pub create_timeout: Option<u64>,
+    /// seconds to wait for a connection to be checked before it is reused
+    pub recycle_timeout: Option<u64>,
+    /// milliseconds a statement can run before it is cancelled
+    pub statement_timeout: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Session {
+    #[serde(deserialize_with = "value_enum")]
+    pub hash: Option<sec::state::SessionHash>,
+    pub secret: Option<String>,
+    pub domain: Option<String>,
+    pub secure: Option<bool>,
+    #[serde(deserialize_with = "value_enum")]
+    pub same_site: Option<sec::state::SessionSameSite>,
+    pub lifetime: Option<u32>,
+    pub idle: Option<u32>,
+    pub pending: Option<u32>,
+    pub renew: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Lockout {
+    pub user_threshold: Option<u32>,
+    pub ip_threshold: Option<u32>,
+    pub basic_threshold: Option<u32>,
+    pub delay: Option<u32>,
+    pub max: Option<u32>,
+    pub reset: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Totp {
+    pub window_before: Option<u64>,
+    pub window_after: Option<u64>,
+}
+
+/// a password secret provided directly in the config file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Secret {
+    pub version: u32,
+    /// standard base64
+    #[serde(deserialize_with = "base64_bytes")]
+    pub secret: Vec<u8>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Oidc {
+    pub issuer: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub scopes: Vec<String>,
+    pub username_claim: Option<String>,
+    pub provision: Option<bool>,
+    pub link_email: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Ldap {
+    pub url: Option<String>,
+    pub base_dn: Option<String>,
+    pub user_filter: Option<String>,
+    pub bind_dn: Option<String>,
+    pub bind_password: Option<String>,
+    pub starttls: Option<bool>,
+    pub no_tls_verify: Option<bool>,
+    pub email_attr: Option<String>,
+    pub group_attr: Option<String>,
+    pub sync_groups: Option<bool>,
+    pub provision: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sec {
+    pub session: Session,
+    pub lockout: Lockout,
+    pub totp: Totp,
+    pub secrets_file: Option<PathBuf>,
+    pub secrets_dir: Option<PathBuf>,
+    pub secrets: Vec<Secret>,
+    /// secret for signing urls. signed urls are disabled if not provided
+    pub signing_secret: Option<String>,
+    pub basic_auth: Option<bool>,
+    pub access_token_lifetime: Option<u32>,
+    pub audit_retention: Option<u32>,
+    pub oidc: Oidc,
+    pub ldap: Ldap,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Email {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: Option<String>,
+    #[serde(deserialize_with = "value_enum")]
+    pub security: Option<email::SmtpSecurity>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Ids {
+    pub primary_id: Option<i64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Timeouts {
+    /// seconds a request has to finish before it is cancelled
+    pub request: Option<u64>,
+    /// seconds between removing expired sessions and lockouts
+    pub session_sweep: Option<u64>,
+    /// seconds that requests have to finish when shutting down
+    pub shutdown_grace: Option<u64>,
+}
+
+/// settings loaded from a toml file
+///
+/// the file is applied first so that environment variables and command
+/// line arguments are able to override anything it provides. relative
+/// paths are resolved from the directory of the file
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    #[serde(skip)]
+    path: Option<PathBuf>,
+
+    pub listen: Listen,
+    pub tls: Tls,
+    pub assets: Option<PathBuf>,
+    pub pages: Option<PathBuf>,
+    pub storage_root: Option<PathBuf>,
+    #[serde(deserialize_with = "value_enum")]
+    pub registration: Option<state::Registration>,
+    pub templates: Templates,
+    pub pg: Pg,
+    pub sec: Sec,
+    pub email: Email,
+    pub ids: Ids,
+    pub timeouts: Timeouts,
+}
+
+impl Config {
+    /// reads and validates the given file
+    pub fn load<P>(path: P) -> error::Result<Self>
+    where
+        P: AsRef<Path>
+    {
+        let path = path.as_ref();
+
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| error::Error::new()
+                .kind("InvalidConfig")
+                .message(format!("failed to read config file. path: {}", path.display()))
+                .source(err))?;
+
+        let mut config: Config = toml::from_str(&contents)
+            .map_err(|err| error::Error::new()
+                .kind("InvalidConfig")
+                .message(format!("failed to parse config file. path: {}", path.display()))
+                .source(err))?;
+
+        config.path = Some(path.to_owned());
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn invalid(&self, key: &str, msg: &str) -> error::Error {
+        let path = self.path.as_deref()
+            .unwrap_or(Path::new(""));
+
+        error::Error::new()
+            .kind("InvalidConfig")
+            .message(format!("{}: \"{}\" {}", path.display(), key, msg))
+    }
+
+    /// checks values that can be given to the builders but would fail when
+    /// built so that the key can be reported
+    fn validate(&self) -> error::Result<()> {
+        let non_zero = [
+            ("sec.session.lifetime", self.sec.session.lifetime),
+            ("sec.session.idle", self.sec.session.idle),
+            ("sec.session.pending", self.sec.session.pending),
+            ("sec.session.renew", self.sec.session.renew),
+            ("sec.lockout.user_threshold", self.sec.lockout.user_threshold),
+            ("sec.lockout.ip_threshold", self.sec.lockout.ip_threshold),
+            ("sec.lockout.basic_threshold", self.sec.lockout.basic_threshold),
+            ("sec.access_token_lifetime", self.sec.access_token_lifetime),
+            ("sec.audit_retention", self.sec.audit_retention),
+        ];
+
+        for (key, value) in non_zero {
+            if value == Some(0) {
+                return Err(self.invalid(key, "must be greater than 0"));
+            }
+        }
+
+        let non_zero_u64 = [
+            ("timeouts.request", self.timeouts.request),
+            ("timeouts.session_sweep", self.timeouts.session_sweep),
+            ("timeouts.shutdown_grace", self.timeouts.shutdown_grace),
+            ("tls.reload_interval", self.tls.reload_interval),
+            ("pg.pool_size", self.pg.pool_size.map(|size| size as u64)),
+        ];
+
+        for (key, value) in non_zero_u64 {
+            if value == Some(0) {
+                return Err(self.invalid(key, "must be greater than 0"));
+            }
+        }
+
+        let windows = [
+            ("sec.totp.window_before", self.sec.totp.window_before),
+            ("sec.totp.window_after", self.sec.totp.window_after),
+        ];
+
+        for (key, value) in windows {
+            if value.map(|steps| steps > 10).unwrap_or(false) {
+                return Err(self.invalid(key, "cannot be more than 10 steps"));
+            }
+        }
+
+        match (&self.tls.cert, &self.tls.key) {
+            (Some(_), None) => {
+                return Err(self.invalid("tls.key", "is required when a cert is provided"));
+            },
+            (None, Some(_)) => {
+                return Err(self.invalid("tls.cert", "is required when a key is provided"));
+            },
+            _ => {}
+        }
+
+        for (index, secret) in self.sec.secrets.iter().enumerate() {
+            let duplicate = self.sec.secrets[..index].iter()
+                .any(|prev| prev.version == secret.version);
+
+            if duplicate {
+                return Err(self.invalid(
+                    &format!("sec.secrets[{}].version", index),
+                    &format!("version {} was provided more than once", secret.version)
+                ));
+            }
+        }
+
+        for (index, origin) in self.listen.allowed_origins.iter().enumerate() {
+            if net::layer::csrf::AllowedOrigins::new([origin]).is_err() {
+                return Err(self.invalid(
+                    &format!("listen.allowed_origins[{}]", index),
+                    "is not a valid origin"
+                ));
+            }
+        }
+
+        if let Some(primary_id) = self.ids.primary_id {
+            if state::ids::Ids::new(primary_id).is_err() {
+                return Err(self.invalid("ids.primary_id", "is not a valid primary id"));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// resolves a path from the file against the directory of the file
+    pub fn resolve(&self, path: &Path) -> PathBuf {
+        match self.path.as_deref().and_then(Path::parent) {
+            Some(parent) if path.is_relative() => parent.join(path),
+            _ => path.to_owned(),
+        }
+    }
+
+    /// assigns everything that was provided to the shared state builder
+    pub fn apply(&self, builder: &mut state::SharedBuilder) {
+        if let Some(path) = &self.assets {
+            builder.set_assets(self.resolve(path));
+        }
+
+        if let Some(path) = &self.pages {
+            builder.set_pages(self.resolve(path));
+        }
+
+        if let Some(path) = &self.storage_root {
+            builder.set_storage_root(self.resolve(path));
+        }
+
+        if let Some(registration) = &self.registration {
+            builder.set_registration(*registration);
+        }
+
+        if let Some(primary_id) = &self.ids.primary_id {
+            builder.set_primary_id(*primary_id);
+        }
+
+        {
+            let templates = builder.templates();
+
+            if let Some(path) = &self.templates.directory {
+                templates.set_templates(self.resolve(path));
+            }
+
+            if let Some(dev_mode) = &self.templates.dev_mode {
+                templates.set_dev_mode(*dev_mode);
+            }
+        }
+
+        {
+            let pg_options = builder.pg_options();
+
+            if let Some(url) = &self.pg.url {
+                pg_options.set_url(url);
+            }
+
+            if let Some(user) = &self.pg.user {
+                pg_options.set_user(user);
+            }
+
+            if let Some(password) = &self.pg.password {
+                pg_options.set_password(password);
+            }
+
+            if let Some(host) = &self.pg.host {
+                pg_options.set_host(host);
+            }
+
+            if let Some(port) = &self.pg.port {
+                pg_options.set_port(*port);
+            }
+
+            if let Some(dbname) = &self.pg.dbname {
+                pg_options.set_dbname(dbname);
+            }
+
+            if let Some(with_tls) = &self.pg.tls {
+                pg_options.set_with_tls(*with_tls);
+            }
+
+            if let Some(path) = &self.pg.ca_file {
+                pg_options.set_ca_file(self.resolve(path));
+            }
+
+            if let Some(size) = &self.pg.pool_size {
+                pg_options.set_max_size(*size);
+            }
+
+            if let Some(seconds) = &self.pg.wait_timeout {
+                pg_options.set_wait_timeout(*seconds);
+            }
+
+            if let Some(seconds) = &self.pg.create_timeout {
+                pg_options.set_create_timeout(*seconds);
+            }
+
+            if let Some(seconds) = &self.pg.recycle_timeout {
+                pg_options.set_recycle_timeout(*seconds);
+            }
+
+            if let Some(milliseconds) = &self.pg.statement_timeout {
+                pg_options.set_statement_timeout(*milliseconds);
+            }
+        }
+
+        self.apply_sec(builder.sec());
+
+        {
+            let email = builder.email();
+
+            if let Some(host) = &self.email.host {
+                email.set_host(host.clone());
+            }
+
+            if let Some(port) = &self.email.port {
+                email.set_port(*port);
+            }
+
+            if let Some(username) = &self.email.username {
+                email.set_username(username.clone());
+            }
+
+            if let Some(password) = &self.email.password {
+                email.set_password(password.clone());
+            }
+
+            if let Some(from) = &self.email.from {
+                email.set_from(from.clone());
+            }
+
+            if let Some(security) = &self.email.security {
+                email.set_security(*security);
+            }
+        }
+    }
+
+    fn apply_sec(&self, sec: &mut sec::state::Builder) {
+        let session = &self.sec.session;
+
+        if let Some(hash) = &session.hash {
+            sec.set_session_hash(hash.clone());
+        }
+
+        if let Some(secret) = &session.secret {
+            sec.set_session_secret(secret.clone());
+        }
+
+        if let Some(domain) = &session.domain {
+            sec.set_session_domain(domain.clone());
+        }
+
+        if let Some(secure) = &session.secure {
+            sec.set_session_secure(*secure);
+        }
+
+        if let Some(same_site) = &session.same_site {
+            sec.set_session_same_site(*same_site);
+        }
+
+        if let Some(lifetime) = &session.lifetime {
+            sec.set_session_lifetime(*lifetime);
+        }
+
+        if let Some(idle) = &session.idle {
+            sec.set_session_idle(*idle);
+        }
+
+        if let Some(pending) = &session.pending {
+            sec.set_session_pending(*pending);
+        }
+
+        if let Some(renew) = &session.renew {
+            sec.set_session_renew(*renew);
+        }
+
+        let lockout = &self.sec.lockout;
+
+        if let Some(threshold) = &lockout.user_threshold {
+            sec.set_lockout_user_threshold(*threshold);
+        }
+
+        if let Some(threshold) = &lockout.ip_threshold {
+            sec.set_lockout_ip_threshold(*threshold);
+        }
+
+        if let Some(threshold) = &lockout.basic_threshold {
+            sec.set_lockout_basic_threshold(*threshold);
+        }
+
+        if let Some(delay) = &lockout.delay {
+            sec.set_lockout_delay(*delay);
+        }
+
+        if let Some(max) = &lockout.max {
+            sec.set_lockout_max(*max);
+        }
+
+        if let Some(reset) = &lockout.reset {
+            sec.set_lockout_reset(*reset);
+        }
+
+        if let Some(steps) = &self.sec.totp.window_before {
+            sec.set_totp_window_before(*steps);
+        }
+
+        if let Some(steps) = &self.sec.totp.window_after {
+            sec.set_totp_window_after(*steps);
+        }
+
+        if let Some(path) = &self.sec.secrets_file {
+            sec.set_secrets_file(self.resolve(path));
+        }
+
+        if let Some(path) = &self.sec.secrets_dir {
+            sec.set_secrets_dir(self.resolve(path));
+        }
+
+        for secret in &self.sec.secrets {
+            sec.add_secret(secret.version, secret.secret.clone());
+        }
+
+        if let Some(signing_secret) = &self.sec.signing_secret {
+            sec.set_signing_secret(signing_secret.clone());
+        }
+
+        if let Some(basic_auth) = &self.sec.basic_auth {
+            sec.set_basic_auth(*basic_auth);
+        }
+
+        if let Some(lifetime) = &self.sec.access_token_lifetime {
+            sec.set_access_token_lifetime(*lifetime);
+        }
+
+        if let Some(days) = &self.sec.audit_retention {
+            sec.set_audit_retention(*days);
+        }
+
+        let oidc_config = &self.sec.oidc;
+        let oidc = sec.oidc();
+
+        if let Some(issuer) = &oidc_config.issuer {
+            oidc.set_issuer(issuer.clone());
+        }
+
+        if let Some(client_id) = &oidc_config.client_id {
+            oidc.set_client_id(client_id.clone());
+        }
+
+        if let Some(client_secret) = &oidc_config.client_secret {
+            oidc.set_client_secret(client_secret.clone());
+        }
+
+        if let Some(redirect_uri) = &oidc_config.redirect_uri {
+            oidc.set_redirect_uri(redirect_uri.clone());
+        }
+
+        if !oidc_config.scopes.is_empty() {
+            oidc.set_scopes(oidc_config.scopes.clone());
+        }
+
+        if let Some(claim) = &oidc_config.username_claim {
+            oidc.set_username_claim(claim.clone());
+        }
+
+        if let Some(provision) = &oidc_config.provision {
+            oidc.set_provision(*provision);
+        }
+
+        if let Some(link_email) = &oidc_config.link_email {
+            oidc.set_link_email(*link_email);
+        }
+
+        let ldap_config = &self.sec.ldap;
+        let ldap = sec.ldap();
+
+        if let Some(url) = &ldap_config.url {
+            ldap.set_url(url.clone());
+        }
+
+        if let Some(base_dn) = &ldap_config.base_dn {
+            ldap.set_base_dn(base_dn.clone());
+        }
+
+        if let Some(filter) = &ldap_config.user_filter {
+            ldap.set_user_filter(filter.clone());
+        }
+
+        if let Some(bind_dn) = &ldap_config.bind_dn {
+            ldap.set_bind_dn(bind_dn.clone());
+        }
+
+        if let Some(bind_password) = &ldap_config.bind_password {
+            ldap.set_bind_password(bind_password.clone());
+        }
+
+        if let Some(starttls) = &ldap_config.starttls {
+            ldap.set_starttls(*starttls);
+        }
+
+        if let Some(no_tls_verify) = &ldap_config.no_tls_verify {
+            ldap.set_no_tls_verify(*no_tls_verify);
+        }
+
+        if let Some(attr) = &ldap_config.email_attr {
+            ldap.set_email_attr(attr.clone());
+        }
+
+        if let Some(attr) = &ldap_config.group_attr {
+            ldap.set_group_attr(attr.clone());
+        }
+
+        if let Some(sync_groups) = &ldap_config.sync_groups {
+            ldap.set_sync_groups(*sync_groups);
+        }
+
+        if let Some(provision) = &ldap_config.provision {
+            ldap.set_provision(*provision);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// a config file in its own directory that is removed once dropped
+    struct ConfigFile {
+        dir: PathBuf,
+    }
+
+    impl ConfigFile {
+        fn new(contents: &str) -> Self {
+            let dir = std::env::temp_dir()
+                .join(format!("rfs_test_{:016x}", rand::random::<u64>()));
+
+            std::fs::create_dir(&dir).unwrap();
+            std::fs::write(dir.join("rfs.toml"), contents).unwrap();
+
+            ConfigFile { dir }
+        }
+
+        fn load(&self) -> error::Result<Config> {
+            Config::load(self.dir.join("rfs.toml"))
+        }
+    }
+
+    impl Drop for ConfigFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn load_err(contents: &str) -> String {
+        let Err(err) = ConfigFile::new(contents).load() else {
+            panic!("invalid config was loaded:\n{}", contents);
+        };
+
+        err.to_string()
+    }
+
+    #[test]
+    fn relative_paths_resolve_from_file() {
+        let file = ConfigFile::new("\
+            storage_root = \"data\"\n\
+            [tls]\n\
+            cert = \"/etc/rfs/cert.pem\"\n\
+            key = \"key.pem\"\n");
+        let config = file.load().unwrap();
+
+        assert_eq!(
+            config.resolve(config.storage_root.as_deref().unwrap()),
+            file.dir.join("data")
+        );
+        assert_eq!(
+            config.resolve(config.tls.key.as_deref().unwrap()),
+            file.dir.join("key.pem")
+        );
+        assert_eq!(
+            config.resolve(config.tls.cert.as_deref().unwrap()),
+            PathBuf::from("/etc/rfs/cert.pem"),
+            "absolute path was changed"
+        );
+        assert_eq!(
+            Config::default().resolve(Path::new("data")),
+            PathBuf::from("data"),
+            "path was changed without a file"
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        load_err("[sec.session]\nlifetimes = 10\n");
+        load_err("[listen]\naddress = \"127.0.0.1\"\n");
+        load_err("registration = \"closed\"\n");
+    }
+
+    #[test]
+    fn invalid_values_name_the_key() {
+        let cases = [
+            ("[sec.lockout]\nuser_threshold = 0\n", "\"sec.lockout.user_threshold\""),
+            ("[timeouts]\nrequest = 0\n", "\"timeouts.request\""),
+            ("[sec.totp]\nwindow_after = 11\n", "\"sec.totp.window_after\""),
+            ("[tls]\ncert = \"cert.pem\"\n", "\"tls.key\""),
+            ("[listen]\nallowed_origins = [\"not an origin\"]\n", "\"listen.allowed_origins[0]\""),
+            (
+                "[[sec.secrets]]\nversion = 1\nsecret = \"c2VjcmV0\"\n\
+                 [[sec.secrets]]\nversion = 1\nsecret = \"b3RoZXI=\"\n",
+                "\"sec.secrets[1].version\""
+            ),
+        ];
+
+        for (contents, key) in cases {
+            let msg = load_err(contents);
+
+            assert!(msg.contains(key), "\"{}\" does not mention {}", msg, key);
+        }
+    }
+
+    #[test]
+    fn apply_assigns_provided_values() {
+        let file = ConfigFile::new("\
+            registration = \"invite\"\n\
+            [sec.lockout]\n\
+            user_threshold = 7\n\
+            [sec.totp]\n\
+            window_before = 2\n");
+        let config = file.load().unwrap();
+
+        let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let tmp = std::env::temp_dir();
+
+        let mut builder = state::Shared::builder();
+        builder.set_assets(&tmp)
+            .set_pages(&tmp);
+        builder.templates()
+            .set_templates(manifest.join("../templates"));
+
+        config.apply(&mut builder);
+
+        let shared = builder.build().expect("failed to create shared state");
+        let defaults = sec::state::LockoutConfig::default();
+
+        assert_eq!(*shared.registration(), state::Registration::Invite);
+        assert_eq!(shared.auth().lockout().user_threshold, 7);
+        assert_eq!(
+            shared.auth().lockout().ip_threshold,
+            defaults.ip_threshold,
+            "value missing from the file was changed"
+        );
+        assert_eq!(shared.auth().totp_window().before, 2);
+        assert_eq!(shared.auth().totp_window().after, 1);
+    }
+}
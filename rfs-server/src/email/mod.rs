@@ -0,0 +1,192 @@
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+
+use crate::error;
+
+pub mod token;
+
+pub type Transport = AsyncSmtpTransport<Tokio1Executor>;
+
+/// how the connection to the smtp server is secured
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SmtpSecurity {
+    /// no encryption. only intended for local smtp sinks
+    None,
+    /// upgrade the connection with STARTTLS
+    StartTls,
+    /// connect with implicit TLS
+    Tls,
+}
+
+pub enum SendError {
+    Address(lettre::address::AddressError),
+    Message(lettre::error::Error),
+    Smtp(lettre::transport::smtp::Error),
+}
+
+impl From<lettre::address::AddressError> for SendError {
+    fn from(err: lettre::address::AddressError) -> Self {
+        SendError::Address(err)
+    }
+}
+
+impl From<lettre::error::Error> for SendError {
+    fn from(err: lettre::error::Error) -> Self {
+        SendError::Message(err)
+    }
+}
+
+impl From<lettre::transport::smtp::Error> for SendError {
+    fn from(err: lettre::transport::smtp::Error) -> Self {
+        SendError::Smtp(err)
+    }
+}
+
+impl From<SendError> for crate::net::error::Error {
+    fn from(err: SendError) -> Self {
+        match err {
+            SendError::Address(err) => crate::net::error::Error::new()
+                .kind("InvalidEmail")
+                .message("the email address is invalid")
+                .source(err),
+            SendError::Message(err) => crate::net::error::Error::new()
+                .source(err),
+            SendError::Smtp(err) => crate::net::error::Error::new()
+                .kind("EmailFailed")
+                .message("failed to send email")
+                .source(err),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Builder {
+    host: Option<String>,
+    port: Option<u16>,
+    username: Option<String>,
+    password: Option<String>,
+    from: Option<String>,
+    security: SmtpSecurity,
+}
+
+impl Builder {
+    pub fn set_host(&mut self, host: String) -> &mut Self {
+        self.host = Some(host);
+        self
+    }
+
+    pub fn set_port(&mut self, port: u16) -> &mut Self {
+        self.port = Some(port);
+        self
+    }
+
+    pub fn set_username(&mut self, username: String) -> &mut Self {
+        self.username = Some(username);
+        self
+    }
+
+    pub fn set_password(&mut self, password: String) -> &mut Self {
+        self.password = Some(password);
+        self
+    }
+
+    pub fn set_from(&mut self, from: String) -> &mut Self {
+        self.from = Some(from);
+        self
+    }
+
+    pub fn set_security(&mut self, security: SmtpSecurity) -> &mut Self {
+        self.security = security;
+        self
+    }
+
+    /// creates the mailer if an smtp host was provided
+    pub fn build(self) -> error::Result<Option<Mailer>> {
+        let Some(host) = self.host else {
+            return Ok(None);
+        };
+
+        let Some(from) = self.from else {
+            return Err(error::Error::new()
+                .kind("InvalidEmailConfig")
+                .message("a from address is required when an smtp host is provided"));
+        };
+
+        let from: Mailbox = from.parse().map_err(|err| error::Error::new()
+            .kind("InvalidEmailConfig")
+            .message("the from address is invalid")
+            .source(err))?;
+
+        let mut builder = match self.security {
+            SmtpSecurity::None => Transport::builder_dangerous(host.as_str()),
+            SmtpSecurity::StartTls => Transport::starttls_relay(host.as_str())
+                .map_err(|err| error::Error::new()
+                    .kind("InvalidEmailConfig")
+                    .message("failed to create smtp transport")
+                    .source(err))?,
+            SmtpSecurity::Tls => Transport::relay(host.as_str())
+                .map_err(|err| error::Error::new()
+                    .kind("InvalidEmailConfig")
+                    .message("failed to create smtp transport")
+                    .source(err))?,
+        };
+
+        if let Some(port) = self.port {
+            builder = builder.port(port);
+        }
+
+        if let Some(username) = self.username {
+            builder = builder.credentials(Credentials::new(
+                username,
+                self.password.unwrap_or_default()
+            ));
+        }
+
+        Ok(Some(Mailer {
+            transport: builder.build(),
+            from,
+        }))
+    }
+}
+
+pub struct Mailer {
+    transport: Transport,
+    from: Mailbox,
+}
+
+impl Mailer {
+    pub fn builder() -> Builder {
+        Builder {
+            host: None,
+            port: None,
+            username: None,
+            password: None,
+            from: None,
+            security: SmtpSecurity::StartTls,
+        }
+    }
+
+    /// sends a plain text email to the given address
+    pub async fn send(&self, to: &str, subject: &str, body: String) -> Result<(), SendError> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to.parse()?)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body)?;
+
+        self.transport.send(message).await?;
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for Mailer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Mailer")
+            .field("from", &self.from)
+            .finish()
+    }
+}
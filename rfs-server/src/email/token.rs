@@ -0,0 +1,158 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc, Duration};
+use rand::RngCore;
+use rfs_lib::ids;
+use tokio_postgres::Error as PgError;
+use deadpool_postgres::GenericClient;
+
+use crate::net;
+
+pub const TOKEN_BYTES: usize = 32;
+
+pub enum TokenError {
+    Rand(rand::Error),
+    Pg(PgError),
+}
+
+impl From<rand::Error> for TokenError {
+    fn from(err: rand::Error) -> Self {
+        TokenError::Rand(err)
+    }
+}
+
+impl From<PgError> for TokenError {
+    fn from(err: PgError) -> Self {
+        TokenError::Pg(err)
+    }
+}
+
+impl From<TokenError> for net::error::Error {
+    fn from(err: TokenError) -> net::error::Error {
+        match err {
+            TokenError::Rand(err) => err.into(),
+            TokenError::Pg(err) => err.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Verify,
+    Reset,
+}
+
+impl TokenKind {
+    pub fn as_i16(&self) -> i16 {
+        match self {
+            TokenKind::Verify => 0,
+            TokenKind::Reset => 1,
+        }
+    }
+
+    /// how long a token of this kind is valid for after it is created
+    pub fn lifetime(&self) -> Duration {
+        match self {
+            TokenKind::Verify => Duration::hours(24),
+            TokenKind::Reset => Duration::hours(1),
+        }
+    }
+}
+
+fn hash_token(token: &str) -> Vec<u8> {
+    blake3::hash(token.as_bytes()).as_bytes().to_vec()
+}
+
+/// removes all tokens of the given kind for a user
+pub async fn delete_user_tokens(
+    conn: &impl GenericClient,
+    user_id: &ids::UserId,
+    kind: TokenKind,
+) -> Result<(), PgError> {
+    let _ = conn.execute(
+        "delete from user_email_tokens where user_id = $1 and kind = $2",
+        &[user_id, &kind.as_i16()]
+    ).await?;
+
+    Ok(())
+}
+
+pub struct EmailToken {
+    pub user_id: ids::UserId,
+    pub email: String,
+    pub expires: DateTime<Utc>,
+}
+
+impl EmailToken {
+    /// creates a new token for the user and email
+    ///
+    /// only one token of a kind is kept for a user so any previous tokens
+    /// are removed. the token is only returned here as the database only
+    /// stores a hash of it
+    pub async fn create(
+        conn: &impl GenericClient,
+        user_id: ids::UserId,
+        kind: TokenKind,
+        email: String,
+    ) -> Result<(Self, String), TokenError> {
+        let created = Utc::now();
+        let expires = created + kind.lifetime();
+
+        let mut bytes = [0u8; TOKEN_BYTES];
+
+        rand::thread_rng().try_fill_bytes(&mut bytes)?;
+
+        let token = URL_SAFE_NO_PAD.encode(bytes);
+        let hash = hash_token(&token);
+
+        delete_user_tokens(conn, &user_id, kind).await?;
+
+        let _ = conn.execute(
+            "\
+            insert into user_email_tokens (hash, user_id, kind, email, expires, created) values \
+            ($1, $2, $3, $4, $5, $6)",
+            &[&hash, &user_id, &kind.as_i16(), &email, &expires, &created]
+        ).await?;
+
+        Ok((EmailToken {
+            user_id,
+            email,
+            expires,
+        }, token))
+    }
+
+    /// consumes the given token
+    ///
+    /// the token is removed regardless of it being expired so it can only be
+    /// used once. returns none if the token was not found or is expired
+    pub async fn take(
+        conn: &impl GenericClient,
+        token: &str,
+        kind: TokenKind,
+    ) -> Result<Option<Self>, PgError> {
+        let hash = hash_token(token);
+
+        let Some(row) = conn.query_opt(
+            "\
+            delete from user_email_tokens \
+            where hash = $1 and kind = $2 \
+            returning user_id, \
+                      email, \
+                      expires",
+            &[&hash, &kind.as_i16()]
+        ).await? else {
+            return Ok(None);
+        };
+
+        let found = EmailToken {
+            user_id: ids::user_id_from_pg(row.get(0)),
+            email: row.get(1),
+            expires: row.get(2),
+        };
+
+        if found.expires <= Utc::now() {
+            return Ok(None);
+        }
+
+        Ok(Some(found))
+    }
+}
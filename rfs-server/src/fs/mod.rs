@@ -19,6 +19,8 @@ pub mod traits;
 pub mod error;
 pub mod checksum;
 pub mod stream;
+pub mod share;
+pub mod upload;
 
 pub mod root;
 pub use root::Root;
@@ -68,6 +70,17 @@ pub async fn name_gen(
     Ok(None)
 }
 
+/// checks that a basename is a single path component
+///
+/// rejects empty names, path separators, nul bytes, and the "." / ".."
+/// entries so that the name cannot escape its parent directory on disk
+pub fn basename_valid(name: &str) -> bool {
+    !(name.is_empty() ||
+        name == "." ||
+        name == ".." ||
+        name.contains(['/', '\\', '\0']))
+}
+
 pub fn validate_dir<P>(name: &str, cwd: &PathBuf, path: P) -> std::io::Result<PathBuf>
 where
     P: AsRef<Path>
@@ -116,6 +129,115 @@ where
     Ok(rtn)
 }
 
+/// creates a list item from a row of the given columns
+///
+/// expects the following columns: id, user_id, parent, basename, fs_type,
+/// fs_path, fs_size, mime_type, mime_subtype
+pub fn list_item_from_row(row: &tokio_postgres::Row) -> schema::fs::ListItem {
+    let fs_type: i16 = row.get(4);
+    let type_ = match fs_type {
+        consts::ROOT_TYPE => schema::fs::Type::Root,
+        consts::FILE_TYPE => schema::fs::Type::File,
+        consts::DIR_TYPE => schema::fs::Type::Directory,
+        _ => panic!("unexpected fs_type when retrieving fs ListItem. type: {}", fs_type)
+    };
+    let mime = row.get::<usize, Option<&str>>(7)
+        .map(|mime_type| sql::mime_from_sql(mime_type, row.get(8)));
+
+    schema::fs::ListItem {
+        id: row.get(0),
+        user_id: row.get(1),
+        parent: row.get(2),
+        basename: row.get(3),
+        type_,
+        path: sql::pathbuf_from_sql(row.get(5)),
+        size: sql::u64_from_sql(row.get(6)),
+        mime,
+    }
+}
+
+pub async fn list_item_retrieve(
+    conn: &impl GenericClient,
+    id: &ids::FSId,
+) -> Result<Option<schema::fs::ListItem>, PgError> {
+    let result = conn.query_opt(
+        "\
+        select fs.id, \
+               fs.user_id, \
+               fs.parent, \
+               fs.basename, \
+               fs.fs_type, \
+               fs.fs_path, \
+               fs.fs_size, \
+               fs.mime_type, \
+               fs.mime_subtype \
+        from fs \
+        where fs.id = $1 and \
+              fs.deleted is null",
+        &[id]
+    ).await?;
+
+    Ok(result.as_ref().map(list_item_from_row))
+}
+
+/// retrieves the immediate children of the given fs item
+pub async fn list_contents(
+    conn: &impl GenericClient,
+    parent: &ids::FSId,
+) -> Result<Vec<schema::fs::ListItem>, PgError> {
+    let rows = conn.query(
+        "\
+        select fs.id, \
+               fs.user_id, \
+               fs.parent, \
+               fs.basename, \
+               fs.fs_type, \
+               fs.fs_path, \
+               fs.fs_size, \
+               fs.mime_type, \
+               fs.mime_subtype \
+        from fs \
+        where fs.parent = $1 and \
+              fs.deleted is null \
+        order by fs.fs_type desc, \
+                 fs.basename",
+        &[parent]
+    ).await?;
+
+    Ok(rows.iter()
+        .map(list_item_from_row)
+        .collect())
+}
+
+/// checks if the given fs item is the ancestor or is below the ancestor
+pub async fn within_check(
+    conn: &impl GenericClient,
+    ancestor: &ids::FSId,
+    id: &ids::FSId,
+) -> Result<bool, PgError> {
+    let result = conn.query_opt(
+        "\
+        with recursive fs_tree as (\
+            select fs.id, \
+                   fs.parent \
+            from fs \
+            where fs.id = $2 \
+            union \
+            select fs.id, \
+                   fs.parent \
+            from fs \
+                join fs_tree on \
+                    fs_tree.parent = fs.id\
+        ) \
+        select fs_tree.id \
+        from fs_tree \
+        where fs_tree.id = $1",
+        &[ancestor, id]
+    ).await?;
+
+    Ok(result.is_some())
+}
+
 pub enum Item {
     Root(Root),
     Directory(Directory),
@@ -227,6 +349,14 @@ impl Item {
         }
     }
 
+    pub fn is_deleted(&self) -> bool {
+        match self {
+            Self::Root(root) => root.deleted.is_some(),
+            Self::Directory(dir) => dir.deleted.is_some(),
+            Self::File(file) => file.deleted.is_some(),
+        }
+    }
+
     pub fn is_file(&self) -> bool {
         match self {
             Self::File(_) => true,
@@ -309,3 +439,19 @@ impl From<File> for Item {
         Item::File(file)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn basename_rejects_traversal() {
+        for name in ["", ".", "..", "../etc", "a/b", "/abs", "..\\win", "a\0b"] {
+            assert!(!basename_valid(name), "basename accepted: {:?}", name);
+        }
+
+        for name in ["file.txt", ".hidden", "..dots", "a b"] {
+            assert!(basename_valid(name), "basename rejected: {:?}", name);
+        }
+    }
+}
@@ -0,0 +1,386 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use rfs_lib::ids;
+use rfs_lib::schema;
+use tokio_postgres::Error as PgError;
+use deadpool_postgres::GenericClient;
+
+use crate::net;
+use crate::sec::authn::password::{self, PasswordError};
+use crate::sec::secret::Secret;
+
+pub const SHARE_TOKEN_BYTES: usize = 32;
+
+pub enum ShareError {
+    Rand(rand::Error),
+    Password(PasswordError),
+    Pg(PgError),
+}
+
+impl From<rand::Error> for ShareError {
+    fn from(err: rand::Error) -> Self {
+        ShareError::Rand(err)
+    }
+}
+
+impl From<PasswordError> for ShareError {
+    fn from(err: PasswordError) -> Self {
+        ShareError::Password(err)
+    }
+}
+
+impl From<PgError> for ShareError {
+    fn from(err: PgError) -> Self {
+        ShareError::Pg(err)
+    }
+}
+
+impl From<ShareError> for net::error::Error {
+    fn from(err: ShareError) -> net::error::Error {
+        match err {
+            ShareError::Rand(err) => err.into(),
+            ShareError::Password(err) => err.into(),
+            ShareError::Pg(err) => err.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    ReadOnly,
+    UploadOnly,
+}
+
+impl Mode {
+    pub fn from_i16(v: i16) -> Option<Self> {
+        match v {
+            0 => Some(Mode::ReadOnly),
+            1 => Some(Mode::UploadOnly),
+            _ => None
+        }
+    }
+
+    pub fn as_i16(&self) -> i16 {
+        match self {
+            Mode::ReadOnly => 0,
+            Mode::UploadOnly => 1,
+        }
+    }
+
+    pub fn into_schema(self) -> schema::share::ShareMode {
+        match self {
+            Mode::ReadOnly => schema::share::ShareMode::ReadOnly,
+            Mode::UploadOnly => schema::share::ShareMode::UploadOnly,
+        }
+    }
+}
+
+impl From<schema::share::ShareMode> for Mode {
+    fn from(v: schema::share::ShareMode) -> Self {
+        match v {
+            schema::share::ShareMode::ReadOnly => Mode::ReadOnly,
+            schema::share::ShareMode::UploadOnly => Mode::UploadOnly,
+        }
+    }
+}
+
+/// generates a new url safe token for a share link
+pub fn gen_token() -> Result<String, rand::Error> {
+    let mut bytes = [0u8; SHARE_TOKEN_BYTES];
+
+    rand::thread_rng().try_fill_bytes(&mut bytes)?;
+
+    Ok(URL_SAFE.encode(bytes))
+}
+
+pub struct ShareBuilder {
+    fs_id: ids::FSId,
+    user_id: ids::UserId,
+    mode: Mode,
+    password: Option<String>,
+    expires: Option<DateTime<Utc>>,
+    max_downloads: Option<u32>,
+}
+
+impl ShareBuilder {
+    pub fn set_password(&mut self, password: String) {
+        self.password = Some(password);
+    }
+
+    pub fn set_expires(&mut self, expires: DateTime<Utc>) {
+        self.expires = Some(expires);
+    }
+
+    pub fn set_max_downloads(&mut self, max: u32) {
+        self.max_downloads = Some(max);
+    }
+
+    pub async fn build(
+        self,
+        conn: &impl GenericClient,
+        secret: &Secret
+    ) -> Result<Share, ShareError> {
+        let created = Utc::now();
+        let token = gen_token()?;
+        let mode = self.mode.as_i16();
+        let max_downloads = self.max_downloads.map(|v| v as i32);
+
+        let (password, password_version) = if let Some(given) = &self.password {
+            (
+                Some(password::hash_with_secret(given, 32, secret)?),
+                Some(*secret.version() as i32)
+            )
+        } else {
+            (None, None)
+        };
+
+        let _ = conn.execute(
+            "\
+            insert into fs_share (\
+                token, \
+                fs_id, \
+                user_id, \
+                mode, \
+                password, \
+                password_version, \
+                expires, \
+                max_downloads, \
+                created\
+            ) values \
+            ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+            &[
+                &token,
+                &self.fs_id,
+                &self.user_id,
+                &mode,
+                &password,
+                &password_version,
+                &self.expires,
+                &max_downloads,
+                &created
+            ]
+        ).await?;
+
+        Ok(Share {
+            token,
+            fs_id: self.fs_id,
+            user_id: self.user_id,
+            mode: self.mode,
+            password,
+            password_version: password_version.map(|v| v as u32),
+            expires: self.expires,
+            max_downloads: self.max_downloads,
+            downloads: 0,
+            created,
+        })
+    }
+}
+
+pub struct Share {
+    pub token: String,
+    pub fs_id: ids::FSId,
+    pub user_id: ids::UserId,
+    pub mode: Mode,
+    pub password: Option<String>,
+    pub password_version: Option<u32>,
+    pub expires: Option<DateTime<Utc>>,
+    pub max_downloads: Option<u32>,
+    pub downloads: u32,
+    pub created: DateTime<Utc>,
+}
+
+impl Share {
+    pub fn builder(fs_id: ids::FSId, user_id: ids::UserId, mode: Mode) -> ShareBuilder {
+        ShareBuilder {
+            fs_id,
+            user_id,
+            mode,
+            password: None,
+            expires: None,
+            max_downloads: None,
+        }
+    }
+
+    fn from_row(row: tokio_postgres::Row) -> Share {
+        Share {
+            token: row.get(0),
+            fs_id: row.get(1),
+            user_id: row.get(2),
+            mode: Mode::from_i16(row.get(3))
+                .expect("invalid mode returned from database for share"),
+            password: row.get(4),
+            password_version: row.get::<usize, Option<i32>>(5).map(|v| v as u32),
+            expires: row.get(6),
+            max_downloads: row.get::<usize, Option<i32>>(7).map(|v| v as u32),
+            downloads: row.get::<usize, i32>(8) as u32,
+            created: row.get(9),
+        }
+    }
+
+    pub async fn retrieve(
+        conn: &impl GenericClient,
+        token: &str,
+    ) -> Result<Option<Self>, PgError> {
+        let result = conn.query_opt(
+            "\
+            select fs_share.token, \
+                   fs_share.fs_id, \
+                   fs_share.user_id, \
+                   fs_share.mode, \
+                   fs_share.password, \
+                   fs_share.password_version, \
+                   fs_share.expires, \
+                   fs_share.max_downloads, \
+                   fs_share.downloads, \
+                   fs_share.created \
+            from fs_share \
+            where fs_share.token = $1",
+            &[&token]
+        ).await?;
+
+        Ok(result.map(Self::from_row))
+    }
+
+    pub async fn retrieve_fs(
+        conn: &impl GenericClient,
+        fs_id: &ids::FSId,
+    ) -> Result<Vec<Self>, PgError> {
+        let rows = conn.query(
+            "\
+            select fs_share.token, \
+                   fs_share.fs_id, \
+                   fs_share.user_id, \
+                   fs_share.mode, \
+                   fs_share.password, \
+                   fs_share.password_version, \
+                   fs_share.expires, \
+                   fs_share.max_downloads, \
+                   fs_share.downloads, \
+                   fs_share.created \
+            from fs_share \
+            where fs_share.fs_id = $1 \
+            order by fs_share.created",
+            &[fs_id]
+        ).await?;
+
+        Ok(rows.into_iter()
+            .map(Self::from_row)
+            .collect())
+    }
+
+    pub async fn retrieve_user(
+        conn: &impl GenericClient,
+        user_id: &ids::UserId,
+    ) -> Result<Vec<Self>, PgError> {
+        let rows = conn.query(
+            "\
+            select fs_share.token, \
+                   fs_share.fs_id, \
+                   fs_share.user_id, \
+                   fs_share.mode, \
+                   fs_share.password, \
+                   fs_share.password_version, \
+                   fs_share.expires, \
+                   fs_share.max_downloads, \
+                   fs_share.downloads, \
+                   fs_share.created \
+            from fs_share \
+            where fs_share.user_id = $1 \
+            order by fs_share.created",
+            &[user_id]
+        ).await?;
+
+        Ok(rows.into_iter()
+            .map(Self::from_row)
+            .collect())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        if let Some(expires) = &self.expires {
+            *expires <= Utc::now()
+        } else {
+            false
+        }
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        if let Some(max) = &self.max_downloads {
+            self.downloads >= *max
+        } else {
+            false
+        }
+    }
+
+    /// verifies the given password for the share
+    ///
+    /// if the share does not have a password then this will always be true
+    pub fn verify_password<C>(
+        &self,
+        check: C,
+        secrets: &crate::sec::secret::Manager
+    ) -> Result<Option<bool>, PasswordError>
+    where
+        C: AsRef<[u8]>
+    {
+        let Some(hash) = &self.password else {
+            return Ok(Some(true));
+        };
+
+        let version = self.password_version.unwrap_or(0);
+
+        let Some(secret) = secrets.get(&version) else {
+            return Ok(None);
+        };
+
+        Ok(Some(password::verify_with_secret(hash.as_str(), check, secret)?))
+    }
+
+    /// attempts to record a download for the share
+    ///
+    /// the update will only happen if the share has not reached its max
+    /// number of downloads. returns false if the max has been reached
+    pub async fn record_download(&mut self, conn: &impl GenericClient) -> Result<bool, PgError> {
+        let result = conn.query_opt(
+            "\
+            update fs_share \
+            set downloads = downloads + 1 \
+            where fs_share.token = $1 and \
+                  (fs_share.max_downloads is null or \
+                   fs_share.downloads < fs_share.max_downloads) \
+            returning downloads",
+            &[&self.token]
+        ).await?;
+
+        if let Some(row) = result {
+            self.downloads = row.get::<usize, i32>(0) as u32;
+
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    pub async fn delete(&self, conn: &impl GenericClient) -> Result<bool, PgError> {
+        let deleted = conn.execute(
+            "delete from fs_share where token = $1",
+            &[&self.token]
+        ).await?;
+
+        Ok(deleted == 1)
+    }
+
+    pub fn into_schema(self) -> schema::share::ShareItem {
+        schema::share::ShareItem {
+            token: self.token,
+            fs_id: self.fs_id,
+            user_id: self.user_id,
+            mode: self.mode.into_schema(),
+            has_password: self.password.is_some(),
+            expires: self.expires,
+            max_downloads: self.max_downloads,
+            downloads: self.downloads,
+            created: self.created,
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// tracks the temporary files of uploads that are in progress so that they
+/// can be removed if the server shuts down before they finish
+#[derive(Debug, Clone, Default)]
+pub struct Uploads(Arc<Mutex<HashSet<PathBuf>>>);
+
+impl Uploads {
+    pub fn new() -> Self {
+        Uploads::default()
+    }
+
+    fn insert(&self, path: PathBuf) {
+        self.0.lock().unwrap().insert(path);
+    }
+
+    fn remove(&self, path: &Path) {
+        self.0.lock().unwrap().remove(path);
+    }
+
+    /// creates a temporary file next to the destination. the file is only
+    /// moved to the destination when persisted
+    pub async fn create(&self, dest: PathBuf) -> std::io::Result<Upload> {
+        let Some(basename) = dest.file_name() else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "upload destination has no file name"
+            ));
+        };
+
+        let tmp = dest.with_file_name(format!(
+            ".{}.{:016x}.upload",
+            basename.to_string_lossy(),
+            rand::random::<u64>()
+        ));
+
+        let file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp)
+            .await?;
+
+        self.insert(tmp.clone());
+
+        Ok(Upload {
+            uploads: self.clone(),
+            tmp,
+            dest,
+            file,
+            persisted: false,
+        })
+    }
+
+    /// removes the temporary files of any uploads that have not finished
+    pub async fn cleanup(&self) -> usize {
+        let paths: Vec<PathBuf> = self.0.lock().unwrap().drain().collect();
+        let mut removed = 0;
+
+        for path in paths {
+            match tokio::fs::remove_file(&path).await {
+                Ok(()) => removed += 1,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {},
+                Err(err) => tracing::error!(
+                    "failed to remove upload temp file. path: {} {}",
+                    path.display(),
+                    err
+                ),
+            }
+        }
+
+        removed
+    }
+}
+
+/// a file that is being uploaded. the temporary file is removed if this is
+/// dropped before it is persisted
+#[derive(Debug)]
+pub struct Upload {
+    uploads: Uploads,
+    tmp: PathBuf,
+    dest: PathBuf,
+    file: tokio::fs::File,
+    persisted: bool,
+}
+
+impl Upload {
+    pub fn file_mut(&mut self) -> &mut tokio::fs::File {
+        &mut self.file
+    }
+
+    /// syncs the temporary file and moves it to the destination, replacing
+    /// anything that is already there
+    ///
+    /// this should only be called once the records for the upload have been
+    /// committed. the destination is replaced with a single rename so if
+    /// the commit or this fails the previous contents are left in place
+    pub async fn persist(mut self) -> std::io::Result<()> {
+        self.file.sync_all().await?;
+
+        tokio::fs::rename(&self.tmp, &self.dest).await?;
+
+        self.persisted = true;
+        self.uploads.remove(&self.tmp);
+
+        Ok(())
+    }
+}
+
+impl Drop for Upload {
+    fn drop(&mut self) {
+        if self.persisted {
+            return;
+        }
+
+        if let Err(err) = std::fs::remove_file(&self.tmp) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                tracing::error!(
+                    "failed to remove upload temp file. path: {} {}",
+                    self.tmp.display(),
+                    err
+                );
+            }
+        }
+
+        self.uploads.remove(&self.tmp);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+
+    /// a directory for uploads that is removed once dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let path = std::env::temp_dir()
+                .join(format!("rfs_test_{:016x}", rand::random::<u64>()));
+
+            std::fs::create_dir(&path).unwrap();
+
+            TempDir(path)
+        }
+
+        /// the names of everything in the directory
+        fn entries(&self) -> Vec<String> {
+            let mut rtn: Vec<String> = std::fs::read_dir(&self.0)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+                .collect();
+
+            rtn.sort();
+            rtn
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn tracked(uploads: &Uploads) -> usize {
+        uploads.0.lock().unwrap().len()
+    }
+
+    #[tokio::test]
+    async fn persist_replaces_destination() {
+        let dir = TempDir::new();
+        let dest = dir.0.join("file.txt");
+        let uploads = Uploads::new();
+
+        std::fs::write(&dest, b"previous").unwrap();
+
+        let mut upload = uploads.create(dest.clone()).await.unwrap();
+        upload.file_mut().write_all(b"updated").await.unwrap();
+
+        assert_eq!(dir.entries().len(), 2, "temp file was not created");
+        assert_eq!(std::fs::read(&dest).unwrap(), b"previous", "destination changed early");
+
+        upload.persist().await.unwrap();
+
+        assert_eq!(dir.entries(), ["file.txt"]);
+        assert_eq!(std::fs::read(&dest).unwrap(), b"updated");
+        assert_eq!(tracked(&uploads), 0, "persisted upload is still tracked");
+    }
+
+    #[tokio::test]
+    async fn dropped_upload_is_removed() {
+        let dir = TempDir::new();
+        let uploads = Uploads::new();
+
+        let mut upload = uploads.create(dir.0.join("file.txt")).await.unwrap();
+        upload.file_mut().write_all(b"partial").await.unwrap();
+
+        assert_eq!(tracked(&uploads), 1);
+
+        drop(upload);
+
+        assert!(dir.entries().is_empty(), "temp file was left behind");
+        assert_eq!(tracked(&uploads), 0, "dropped upload is still tracked");
+    }
+
+    #[tokio::test]
+    async fn cleanup_removes_unfinished_uploads() {
+        let dir = TempDir::new();
+        let uploads = Uploads::new();
+
+        let mut first = uploads.create(dir.0.join("first.txt")).await.unwrap();
+        let second = uploads.create(dir.0.join("second.txt")).await.unwrap();
+        first.file_mut().write_all(b"partial").await.unwrap();
+
+        assert_eq!(uploads.cleanup().await, 2);
+        assert!(dir.entries().is_empty(), "temp files were left behind");
+        assert_eq!(tracked(&uploads), 0);
+
+        // the requests finishing after the cleanup should not fail
+        drop(first);
+        drop(second);
+
+        assert_eq!(uploads.cleanup().await, 0);
+    }
+
+    #[tokio::test]
+    async fn destination_requires_file_name() {
+        let uploads = Uploads::new();
+
+        let Err(err) = uploads.create(PathBuf::from("/")).await else {
+            panic!("upload was created without a file name");
+        };
+
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
@@ -4,79 +4,378 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
 
-use clap::Parser;
+use clap::{Parser, ArgAction};
 use axum::Router;
+use axum::http::header;
 use axum::error_handling::HandleErrorLayer;
 use tower::ServiceBuilder;
 use tower_http::trace::TraceLayer;
+use tower_http::set_header::SetResponseHeaderLayer;
+use tokio_util::sync::CancellationToken;
 use tracing_subscriber::{FmtSubscriber, EnvFilter};
 
 mod error;
+mod config;
 mod util;
 mod net;
 mod fs;
 mod template;
 mod user;
+mod bot;
+mod email;
 mod sec;
 mod state;
 mod tags;
 mod storage;
 mod routing;
 
+#[cfg(test)]
+mod testing;
+
 #[derive(clap::Parser, Debug)]
 #[command(author, version, version, about, long_about = None)]
 struct CommandArgs {
+    /// toml file to load settings from. environment variables and command
+    /// line arguments take precedence over the file
+    #[arg(short, long, env = "RFS_CONFIG")]
+    config: Option<PathBuf>,
+
     /// ip address to bind the server to
-    #[arg(short, long)]
+    #[arg(short, long, env = "RFS_IP")]
     ip: Option<String>,
 
     /// port for the server to listen on
-    #[arg(short, long)]
+    #[arg(short, long, env = "RFS_PORT")]
     port: Option<u16>,
 
+    /// pem file of the certificate chain to serve https with. requires
+    /// --tls-key
+    #[arg(long, env = "RFS_TLS_CERT")]
+    tls_cert: Option<PathBuf>,
+
+    /// pem file of the private key for --tls-cert
+    #[arg(long, env = "RFS_TLS_KEY")]
+    tls_key: Option<PathBuf>,
+
+    /// port of a plain http listener that redirects requests to https
+    #[arg(long, env = "RFS_TLS_REDIRECT_PORT")]
+    tls_redirect_port: Option<u16>,
+
+    /// max-age in seconds of the strict transport security header. the
+    /// header is only sent if provided
+    #[arg(long, env = "RFS_TLS_HSTS_MAX_AGE")]
+    tls_hsts_max_age: Option<u64>,
+
+    /// seconds between checking the cert and key for changes. defaults to
+    /// 60. the files are also reloaded on SIGHUP
+    #[arg(long, env = "RFS_TLS_RELOAD_INTERVAL")]
+    tls_reload_interval: Option<u64>,
+
     /// specified the directory to load assets from
-    #[arg(long)]
+    #[arg(long, env = "RFS_ASSETS")]
     assets: Option<PathBuf>,
 
     /// specifies the directory to load html pages from
-    #[arg(long)]
+    #[arg(long, env = "RFS_PAGES")]
     pages: Option<PathBuf>,
 
     /// specified the directory to load handlebars templates from
-    #[arg(long)]
+    #[arg(long, env = "RFS_TEMPLATES")]
     templates: Option<PathBuf>,
 
+    /// directory that local storage mediums are created in
+    #[arg(long, env = "RFS_STORAGE_ROOT")]
+    storage_root: Option<PathBuf>,
+
+    /// how new users are able to create accounts
+    #[arg(long, env = "RFS_REGISTRATION")]
+    registration: Option<state::Registration>,
+
+    /// primary id of the server when generating ids. defaults to 1
+    #[arg(long, env = "RFS_PRIMARY_ID")]
+    primary_id: Option<i64>,
+
     /// enabled dev mode for handlebars templates
-    #[arg(long)]
-    hbs_dev_mode: bool,
+    #[arg(long, env = "RFS_HBS_DEV_MODE", action = ArgAction::Set, num_args = 0..=1, default_missing_value = "true")]
+    hbs_dev_mode: Option<bool>,
+
+    /// postgres connection string or url. the user, password and dbname
+    /// options take precedence over the string
+    #[arg(long, env = "RFS_PG_URL", hide_env_values = true)]
+    pg_url: Option<String>,
 
     /// postgres username for connecting to database
-    #[arg(long)]
+    #[arg(long, env = "RFS_PG_USER")]
     pg_user: Option<String>,
 
     /// postgres user password for connecting to database
-    #[arg(long)]
+    #[arg(long, env = "RFS_PG_PASSWORD", hide_env_values = true)]
     pg_password: Option<String>,
 
     /// postgres host address for database
-    #[arg(long)]
+    #[arg(long, env = "RFS_PG_HOST")]
     pg_host: Option<String>,
 
     /// postgres port for connecting to host
-    #[arg(long)]
+    #[arg(long, env = "RFS_PG_PORT")]
     pg_port: Option<u16>,
 
     /// postgres database name
-    #[arg(long)]
+    #[arg(long, env = "RFS_PG_DBNAME")]
     pg_dbname: Option<String>,
 
+    /// connect to postgres with tls. the certificate and hostname of the
+    /// server are verified
+    #[arg(long, env = "RFS_PG_TLS", action = ArgAction::Set, num_args = 0..=1, default_missing_value = "true")]
+    pg_tls: Option<bool>,
+
+    /// pem file of the certificate authorities used to verify postgres.
+    /// defaults to the webpki roots
+    #[arg(long, env = "RFS_PG_CA_FILE")]
+    pg_ca_file: Option<PathBuf>,
+
+    /// most connections to open to postgres. defaults to 4
+    #[arg(long, env = "RFS_PG_POOL_SIZE")]
+    pg_pool_size: Option<usize>,
+
+    /// seconds to wait for a postgres connection to be available
+    #[arg(long, env = "RFS_PG_WAIT_TIMEOUT")]
+    pg_wait_timeout: Option<u64>,
+
+    /// seconds to wait for a new postgres connection to be created
+    #[arg(long, env = "RFS_PG_CREATE_TIMEOUT")]
+    pg_create_timeout: Option<u64>,
+
+    /// seconds to wait for a postgres connection to be checked before it
+    /// is reused
+    #[arg(long, env = "RFS_PG_RECYCLE_TIMEOUT")]
+    pg_recycle_timeout: Option<u64>,
+
+    /// milliseconds a statement can run before postgres cancels it
+    #[arg(long, env = "RFS_PG_STATEMENT_TIMEOUT")]
+    pg_statement_timeout: Option<u64>,
+
     /// hashing algorithm to use for session key
-    #[arg(long)]
+    #[arg(long, env = "RFS_SESSION_HASH")]
     session_hash: Option<sec::state::SessionHash>,
 
     /// session secret for hashing session ids
-    #[arg(long)]
-    session_secret: Option<String>
+    #[arg(long, env = "RFS_SESSION_SECRET", hide_env_values = true)]
+    session_secret: Option<String>,
+
+    /// secret for signing urls. must be at least 32 bytes and different
+    /// from the session secret. signed urls are disabled if not provided
+    #[arg(long, env = "RFS_SIGNING_SECRET", hide_env_values = true)]
+    signing_secret: Option<String>,
+
+    /// domain attribute of the session cookie
+    #[arg(long, env = "RFS_SESSION_DOMAIN")]
+    session_domain: Option<String>,
+
+    /// only send the session cookie over https
+    #[arg(long, env = "RFS_SESSION_SECURE", action = ArgAction::Set, num_args = 0..=1, default_missing_value = "true")]
+    session_secure: Option<bool>,
+
+    /// same site policy of the session cookie. defaults to strict
+    #[arg(long, env = "RFS_SESSION_SAME_SITE")]
+    session_same_site: Option<sec::state::SessionSameSite>,
+
+    /// origins that are allowed to make state changing requests with the
+    /// session cookie. can be provided multiple times. defaults to the host
+    /// of the request
+    #[arg(long, value_delimiter = ',', env = "RFS_ALLOWED_ORIGIN")]
+    allowed_origin: Vec<String>,
+
+    /// absolute lifetime of a session in seconds
+    #[arg(long, env = "RFS_SESSION_LIFETIME")]
+    session_lifetime: Option<u32>,
+
+    /// seconds a session can be idle before it expires
+    #[arg(long, env = "RFS_SESSION_IDLE")]
+    session_idle: Option<u32>,
+
+    /// seconds a session has to finish authentication and verification
+    #[arg(long, env = "RFS_SESSION_PENDING")]
+    session_pending: Option<u32>,
+
+    /// minimum seconds between updates to the expiration of a session
+    #[arg(long, env = "RFS_SESSION_RENEW")]
+    session_renew: Option<u32>,
+
+    /// file to load password secrets from. each line is "{version} {base64}"
+    #[arg(long, env = "RFS_SECRETS_FILE")]
+    secrets_file: Option<PathBuf>,
+
+    /// directory to load password secrets from. each file is named after
+    /// the version of the secret
+    #[arg(long, env = "RFS_SECRETS_DIR")]
+    secrets_dir: Option<PathBuf>,
+
+    /// seconds a request has to finish before it is cancelled. defaults to
+    /// 90
+    #[arg(long, env = "RFS_REQUEST_TIMEOUT")]
+    request_timeout: Option<u64>,
+
+    /// seconds between removing expired sessions and lockouts. defaults to
+    /// 3600
+    #[arg(long, env = "RFS_SESSION_SWEEP")]
+    session_sweep: Option<u64>,
+
+    /// seconds that requests have to finish after SIGTERM or SIGINT before
+    /// their connections are closed. defaults to 30
+    #[arg(long, env = "RFS_SHUTDOWN_GRACE")]
+    shutdown_grace: Option<u64>,
+
+    /// failed login attempts for a user before they are locked out
+    #[arg(long, env = "RFS_LOCKOUT_USER_THRESHOLD")]
+    lockout_user_threshold: Option<u32>,
+
+    /// failed login attempts from an ip before it is locked out
+    #[arg(long, env = "RFS_LOCKOUT_IP_THRESHOLD")]
+    lockout_ip_threshold: Option<u32>,
+
+    /// failed basic authorization attempts from an ip before it is locked
+    /// out
+    #[arg(long, env = "RFS_LOCKOUT_BASIC_THRESHOLD")]
+    lockout_basic_threshold: Option<u32>,
+
+    /// seconds of the first lockout. doubles with each failed attempt after
+    #[arg(long, env = "RFS_LOCKOUT_DELAY")]
+    lockout_delay: Option<u32>,
+
+    /// maximum seconds of a lockout
+    #[arg(long, env = "RFS_LOCKOUT_MAX")]
+    lockout_max: Option<u32>,
+
+    /// seconds without a failed attempt before the count is reset
+    #[arg(long, env = "RFS_LOCKOUT_RESET")]
+    lockout_reset: Option<u32>,
+
+    /// time steps before the current one that totp codes are accepted for
+    #[arg(long, env = "RFS_TOTP_WINDOW_BEFORE")]
+    totp_window_before: Option<u64>,
+
+    /// time steps after the current one that totp codes are accepted for
+    #[arg(long, env = "RFS_TOTP_WINDOW_AFTER")]
+    totp_window_after: Option<u64>,
+
+    /// allow users without totp to authenticate requests with basic
+    /// authorization
+    #[arg(long, env = "RFS_BASIC_AUTH", action = ArgAction::Set, num_args = 0..=1, default_missing_value = "true")]
+    basic_auth: Option<bool>,
+
+    /// seconds that access tokens created from a session are valid for
+    #[arg(long, env = "RFS_ACCESS_TOKEN_LIFETIME")]
+    access_token_lifetime: Option<u32>,
+
+    /// days to keep audit log entries for. entries are kept forever if not
+    /// provided
+    #[arg(long, env = "RFS_AUDIT_RETENTION")]
+    audit_retention: Option<u32>,
+
+    /// issuer url of the oidc provider. oidc login is disabled if not
+    /// provided
+    #[arg(long, env = "RFS_OIDC_ISSUER")]
+    oidc_issuer: Option<String>,
+
+    /// client id registered with the oidc provider
+    #[arg(long, env = "RFS_OIDC_CLIENT_ID")]
+    oidc_client_id: Option<String>,
+
+    /// client secret registered with the oidc provider
+    #[arg(long, env = "RFS_OIDC_CLIENT_SECRET", hide_env_values = true)]
+    oidc_client_secret: Option<String>,
+
+    /// callback url registered with the oidc provider. should point to
+    /// /auth/oidc/callback
+    #[arg(long, env = "RFS_OIDC_REDIRECT_URI")]
+    oidc_redirect_uri: Option<String>,
+
+    /// scopes to request from the oidc provider. "openid" is always included
+    #[arg(long, value_delimiter = ',', env = "RFS_OIDC_SCOPES")]
+    oidc_scopes: Vec<String>,
+
+    /// claim used as the username of provisioned users
+    #[arg(long, env = "RFS_OIDC_USERNAME_CLAIM")]
+    oidc_username_claim: Option<String>,
+
+    /// creates local users for oidc accounts that are not linked
+    #[arg(long, env = "RFS_OIDC_PROVISION", action = ArgAction::Set, num_args = 0..=1, default_missing_value = "true")]
+    oidc_provision: Option<bool>,
+
+    /// links oidc accounts to local users with the same verified email
+    #[arg(long, env = "RFS_OIDC_LINK_EMAIL", action = ArgAction::Set, num_args = 0..=1, default_missing_value = "true")]
+    oidc_link_email: Option<bool>,
+
+    /// url of the ldap server. ldap login is disabled if not provided
+    #[arg(long, env = "RFS_LDAP_URL")]
+    ldap_url: Option<String>,
+
+    /// dn to search for users under
+    #[arg(long, env = "RFS_LDAP_BASE_DN")]
+    ldap_base_dn: Option<String>,
+
+    /// filter used to find a user. "{username}" is replaced with the
+    /// escaped username
+    #[arg(long, env = "RFS_LDAP_USER_FILTER")]
+    ldap_user_filter: Option<String>,
+
+    /// dn to bind with when searching. searches are anonymous if not
+    /// provided
+    #[arg(long, env = "RFS_LDAP_BIND_DN")]
+    ldap_bind_dn: Option<String>,
+
+    /// password of the ldap bind dn
+    #[arg(long, env = "RFS_LDAP_BIND_PASSWORD", hide_env_values = true)]
+    ldap_bind_password: Option<String>,
+
+    /// upgrade plain ldap connections with STARTTLS
+    #[arg(long, env = "RFS_LDAP_STARTTLS", action = ArgAction::Set, num_args = 0..=1, default_missing_value = "true")]
+    ldap_starttls: Option<bool>,
+
+    /// skip verifying the certificate of the ldap server
+    #[arg(long, env = "RFS_LDAP_NO_TLS_VERIFY", action = ArgAction::Set, num_args = 0..=1, default_missing_value = "true")]
+    ldap_no_tls_verify: Option<bool>,
+
+    /// attribute that holds the email of a user
+    #[arg(long, env = "RFS_LDAP_EMAIL_ATTR")]
+    ldap_email_attr: Option<String>,
+
+    /// attribute that holds the group dns of a user
+    #[arg(long, env = "RFS_LDAP_GROUP_ATTR")]
+    ldap_group_attr: Option<String>,
+
+    /// updates local group membership to match ldap groups on login
+    #[arg(long, env = "RFS_LDAP_SYNC_GROUPS", action = ArgAction::Set, num_args = 0..=1, default_missing_value = "true")]
+    ldap_sync_groups: Option<bool>,
+
+    /// creates local users for ldap accounts on their first login
+    #[arg(long, env = "RFS_LDAP_PROVISION", action = ArgAction::Set, num_args = 0..=1, default_missing_value = "true")]
+    ldap_provision: Option<bool>,
+
+    /// smtp host for sending emails. emails are disabled if not provided
+    #[arg(long, env = "RFS_SMTP_HOST")]
+    smtp_host: Option<String>,
+
+    /// smtp port to connect to
+    #[arg(long, env = "RFS_SMTP_PORT")]
+    smtp_port: Option<u16>,
+
+    /// smtp username for authenticating with the host
+    #[arg(long, env = "RFS_SMTP_USERNAME")]
+    smtp_username: Option<String>,
+
+    /// smtp password for authenticating with the host
+    #[arg(long, env = "RFS_SMTP_PASSWORD", hide_env_values = true)]
+    smtp_password: Option<String>,
+
+    /// address that emails will be sent from
+    #[arg(long, env = "RFS_SMTP_FROM")]
+    smtp_from: Option<String>,
+
+    /// how the connection to the smtp host is secured
+    #[arg(long, env = "RFS_SMTP_SECURITY")]
+    smtp_security: Option<email::SmtpSecurity>,
 }
 
 fn main() {
@@ -143,7 +442,7 @@ fn main() {
     }
 }
 
-fn get_sock_addr(arg: &CommandArgs) -> error::Result<SocketAddr> {
+fn get_sock_addr(arg: &CommandArgs, config: &config::Config) -> error::Result<SocketAddr> {
     use std::net::IpAddr;
 
     let ip_addr = if let Some(ip) = &arg.ip {
@@ -151,16 +450,59 @@ fn get_sock_addr(arg: &CommandArgs) -> error::Result<SocketAddr> {
             error::Error::new()
                 .message("invalid ip address provided")
         )?
+    } else if let Some(ip) = &config.listen.ip {
+        *ip
     } else {
         IpAddr::from([0,0,0,0])
     };
 
-    Ok(SocketAddr::new(ip_addr, arg.port.unwrap_or(0)))
+    let port = arg.port
+        .or(config.listen.port)
+        .unwrap_or(0);
+
+    Ok(SocketAddr::new(ip_addr, port))
 }
 
-fn get_shared_state(arg: &CommandArgs) -> error::Result<state::Shared> {
+fn get_tls_options(arg: &CommandArgs, config: &config::Config) -> error::Result<Option<net::tls::Options>> {
+    let cert = arg.tls_cert.clone()
+        .or_else(|| config.tls.cert.as_deref().map(|path| config.resolve(path)));
+    let key = arg.tls_key.clone()
+        .or_else(|| config.tls.key.as_deref().map(|path| config.resolve(path)));
+
+    let (cert, key) = match (cert, key) {
+        (Some(cert), Some(key)) => (cert, key),
+        (None, None) => {
+            return Ok(None);
+        },
+        _ => {
+            return Err(error::Error::new()
+                .kind("InvalidTls")
+                .message("a tls cert and key must both be provided"));
+        }
+    };
+
+    let reload_interval = arg.tls_reload_interval
+        .or(config.tls.reload_interval)
+        .unwrap_or(60);
+
+    Ok(Some(net::tls::Options {
+        cert,
+        key,
+        redirect_port: arg.tls_redirect_port.or(config.tls.redirect_port),
+        hsts_max_age: arg.tls_hsts_max_age.or(config.tls.hsts_max_age),
+        reload_interval: Duration::from_secs(reload_interval.max(1)),
+    }))
+}
+
+fn get_shared_state(
+    arg: &CommandArgs,
+    config: &config::Config,
+    with_tls: bool,
+) -> error::Result<state::Shared> {
     let mut state_builder = state::Shared::builder();
 
+    config.apply(&mut state_builder);
+
     if let Some(path) = &arg.assets {
         state_builder.set_assets(path);
     }
@@ -169,6 +511,18 @@ fn get_shared_state(arg: &CommandArgs) -> error::Result<state::Shared> {
         state_builder.set_pages(path.clone());
     }
 
+    if let Some(path) = &arg.storage_root {
+        state_builder.set_storage_root(path.clone());
+    }
+
+    if let Some(registration) = &arg.registration {
+        state_builder.set_registration(*registration);
+    }
+
+    if let Some(primary_id) = &arg.primary_id {
+        state_builder.set_primary_id(*primary_id);
+    }
+
     {
         let templates = state_builder.templates();
 
@@ -176,12 +530,18 @@ fn get_shared_state(arg: &CommandArgs) -> error::Result<state::Shared> {
             templates.set_templates(path.clone());
         }
 
-        templates.set_dev_mode(arg.hbs_dev_mode);
+        if let Some(value) = arg.hbs_dev_mode {
+            templates.set_dev_mode(value);
+        }
     }
 
     {
         let pg_options = state_builder.pg_options();
 
+        if let Some(url) = &arg.pg_url {
+            pg_options.set_url(url);
+        }
+
         if let Some(user) = &arg.pg_user {
             pg_options.set_user(user);
         }
@@ -201,6 +561,34 @@ fn get_shared_state(arg: &CommandArgs) -> error::Result<state::Shared> {
         if let Some(dbname) = &arg.pg_dbname {
             pg_options.set_dbname(dbname);
         }
+
+        if let Some(value) = arg.pg_tls {
+            pg_options.set_with_tls(value);
+        }
+
+        if let Some(path) = &arg.pg_ca_file {
+            pg_options.set_ca_file(path.clone());
+        }
+
+        if let Some(size) = &arg.pg_pool_size {
+            pg_options.set_max_size(*size);
+        }
+
+        if let Some(seconds) = &arg.pg_wait_timeout {
+            pg_options.set_wait_timeout(*seconds);
+        }
+
+        if let Some(seconds) = &arg.pg_create_timeout {
+            pg_options.set_create_timeout(*seconds);
+        }
+
+        if let Some(seconds) = &arg.pg_recycle_timeout {
+            pg_options.set_recycle_timeout(*seconds);
+        }
+
+        if let Some(milliseconds) = &arg.pg_statement_timeout {
+            pg_options.set_statement_timeout(*milliseconds);
+        }
     }
 
     {
@@ -210,9 +598,206 @@ fn get_shared_state(arg: &CommandArgs) -> error::Result<state::Shared> {
             sec.set_session_secret(session_secret.clone());
         }
 
+        if let Some(signing_secret) = &arg.signing_secret {
+            sec.set_signing_secret(signing_secret.clone());
+        }
+
         if let Some(session_hash) = &arg.session_hash { 
             sec.set_session_hash(session_hash.clone());
         }
+
+        if let Some(domain) = &arg.session_domain {
+            sec.set_session_domain(domain.clone());
+        }
+
+        if let Some(secure) = arg.session_secure {
+            sec.set_session_secure(secure);
+        }
+
+        // the cookie is only ever sent over https when tls is active
+        if with_tls {
+            sec.set_session_secure(true);
+        }
+
+        if let Some(same_site) = &arg.session_same_site {
+            sec.set_session_same_site(*same_site);
+        }
+
+        if let Some(lifetime) = &arg.session_lifetime {
+            sec.set_session_lifetime(*lifetime);
+        }
+
+        if let Some(idle) = &arg.session_idle {
+            sec.set_session_idle(*idle);
+        }
+
+        if let Some(pending) = &arg.session_pending {
+            sec.set_session_pending(*pending);
+        }
+
+        if let Some(renew) = &arg.session_renew {
+            sec.set_session_renew(*renew);
+        }
+
+        if let Some(path) = &arg.secrets_file {
+            sec.set_secrets_file(path.clone());
+        }
+
+        if let Some(path) = &arg.secrets_dir {
+            sec.set_secrets_dir(path.clone());
+        }
+
+        if let Some(threshold) = &arg.lockout_user_threshold {
+            sec.set_lockout_user_threshold(*threshold);
+        }
+
+        if let Some(threshold) = &arg.lockout_ip_threshold {
+            sec.set_lockout_ip_threshold(*threshold);
+        }
+
+        if let Some(threshold) = &arg.lockout_basic_threshold {
+            sec.set_lockout_basic_threshold(*threshold);
+        }
+
+        if let Some(delay) = &arg.lockout_delay {
+            sec.set_lockout_delay(*delay);
+        }
+
+        if let Some(max) = &arg.lockout_max {
+            sec.set_lockout_max(*max);
+        }
+
+        if let Some(reset) = &arg.lockout_reset {
+            sec.set_lockout_reset(*reset);
+        }
+
+        if let Some(steps) = &arg.totp_window_before {
+            sec.set_totp_window_before(*steps);
+        }
+
+        if let Some(steps) = &arg.totp_window_after {
+            sec.set_totp_window_after(*steps);
+        }
+
+        if let Some(value) = arg.basic_auth {
+            sec.set_basic_auth(value);
+        }
+
+        if let Some(lifetime) = &arg.access_token_lifetime {
+            sec.set_access_token_lifetime(*lifetime);
+        }
+
+        if let Some(days) = &arg.audit_retention {
+            sec.set_audit_retention(*days);
+        }
+
+        let oidc = sec.oidc();
+
+        if let Some(issuer) = &arg.oidc_issuer {
+            oidc.set_issuer(issuer.clone());
+        }
+
+        if let Some(client_id) = &arg.oidc_client_id {
+            oidc.set_client_id(client_id.clone());
+        }
+
+        if let Some(client_secret) = &arg.oidc_client_secret {
+            oidc.set_client_secret(client_secret.clone());
+        }
+
+        if let Some(redirect_uri) = &arg.oidc_redirect_uri {
+            oidc.set_redirect_uri(redirect_uri.clone());
+        }
+
+        if !arg.oidc_scopes.is_empty() {
+            oidc.set_scopes(arg.oidc_scopes.clone());
+        }
+
+        if let Some(claim) = &arg.oidc_username_claim {
+            oidc.set_username_claim(claim.clone());
+        }
+
+        if let Some(value) = arg.oidc_provision {
+            oidc.set_provision(value);
+        }
+
+        if let Some(value) = arg.oidc_link_email {
+            oidc.set_link_email(value);
+        }
+
+        let ldap = sec.ldap();
+
+        if let Some(url) = &arg.ldap_url {
+            ldap.set_url(url.clone());
+        }
+
+        if let Some(base_dn) = &arg.ldap_base_dn {
+            ldap.set_base_dn(base_dn.clone());
+        }
+
+        if let Some(filter) = &arg.ldap_user_filter {
+            ldap.set_user_filter(filter.clone());
+        }
+
+        if let Some(bind_dn) = &arg.ldap_bind_dn {
+            ldap.set_bind_dn(bind_dn.clone());
+        }
+
+        if let Some(bind_password) = &arg.ldap_bind_password {
+            ldap.set_bind_password(bind_password.clone());
+        }
+
+        if let Some(attr) = &arg.ldap_email_attr {
+            ldap.set_email_attr(attr.clone());
+        }
+
+        if let Some(attr) = &arg.ldap_group_attr {
+            ldap.set_group_attr(attr.clone());
+        }
+
+        if let Some(value) = arg.ldap_starttls {
+            ldap.set_starttls(value);
+        }
+
+        if let Some(value) = arg.ldap_no_tls_verify {
+            ldap.set_no_tls_verify(value);
+        }
+
+        if let Some(value) = arg.ldap_sync_groups {
+            ldap.set_sync_groups(value);
+        }
+
+        if let Some(value) = arg.ldap_provision {
+            ldap.set_provision(value);
+        }
+    }
+
+    {
+        let email = state_builder.email();
+
+        if let Some(host) = &arg.smtp_host {
+            email.set_host(host.clone());
+        }
+
+        if let Some(port) = &arg.smtp_port {
+            email.set_port(*port);
+        }
+
+        if let Some(username) = &arg.smtp_username {
+            email.set_username(username.clone());
+        }
+
+        if let Some(password) = &arg.smtp_password {
+            email.set_password(password.clone());
+        }
+
+        if let Some(from) = &arg.smtp_from {
+            email.set_from(from.clone());
+        }
+
+        if let Some(security) = &arg.smtp_security {
+            email.set_security(*security);
+        }
     }
 
     tracing::event!(
@@ -224,12 +809,53 @@ fn get_shared_state(arg: &CommandArgs) -> error::Result<state::Shared> {
     Ok(state_builder.build()?)
 }
 
+/// stops accepting connections on SIGTERM or SIGINT and gives the requests
+/// that are in progress the grace period to finish
+fn start_shutdown_listener(
+    handle: axum_server::Handle,
+    grace: Duration,
+) -> error::Result<tokio::task::JoinHandle<()>> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate())
+        .map_err(|err| error::Error::new()
+            .message("failed to listen for SIGTERM")
+            .source(err))?;
+    let mut interrupt = signal(SignalKind::interrupt())
+        .map_err(|err| error::Error::new()
+            .message("failed to listen for SIGINT")
+            .source(err))?;
+
+    Ok(tokio::spawn(async move {
+        tokio::select! {
+            _ = terminate.recv() => tracing::info!("received SIGTERM"),
+            _ = interrupt.recv() => tracing::info!("received SIGINT"),
+        }
+
+        tracing::event!(
+            tracing::Level::INFO,
+            connections = handle.connection_count(),
+            "shutting down. waiting {}s for requests to finish",
+            grace.as_secs()
+        );
+
+        handle.graceful_shutdown(Some(grace));
+    }))
+}
+
 async fn init(arg: CommandArgs) -> error::Result<()> {
     use axum::routing::{get, post, put, patch, delete};
     use axum::error_handling::HandleError;
 
-    let sock_addr = get_sock_addr(&arg)?;
-    let state = get_shared_state(&arg)?;
+    let config = if let Some(path) = &arg.config {
+        config::Config::load(path)?
+    } else {
+        config::Config::default()
+    };
+
+    let sock_addr = get_sock_addr(&arg, &config)?;
+    let tls = get_tls_options(&arg, &config)?;
+    let state = Arc::new(get_shared_state(&arg, &config, tls.is_some())?);
 
     tracing::event!(
         tracing::Level::DEBUG,
@@ -237,6 +863,22 @@ async fn init(arg: CommandArgs) -> error::Result<()> {
         state
     );
 
+    let allowed_origins = if !arg.allowed_origin.is_empty() {
+        net::layer::csrf::AllowedOrigins::new(&arg.allowed_origin)?
+    } else {
+        net::layer::csrf::AllowedOrigins::new(&config.listen.allowed_origins)?
+    };
+
+    let request_timeout = arg.request_timeout
+        .or(config.timeouts.request)
+        .unwrap_or(90);
+    let session_sweep = arg.session_sweep
+        .or(config.timeouts.session_sweep)
+        .unwrap_or(3600);
+    let shutdown_grace = arg.shutdown_grace
+        .or(config.timeouts.shutdown_grace)
+        .unwrap_or(30);
+
     let router = Router::new()
         .route(
             "/",
@@ -246,6 +888,11 @@ async fn init(arg: CommandArgs) -> error::Result<()> {
             "/auth",
             get(routing::handle::auth::get)
         )
+        .route(
+            "/register",
+            get(routing::handle::register::get)
+                .post(routing::handle::register::post)
+        )
         .route(
             "/auth/request",
             post(routing::handle::auth::request::post)
@@ -263,22 +910,76 @@ async fn init(arg: CommandArgs) -> error::Result<()> {
             post(routing::handle::auth::password::post)
                 .delete(routing::handle::auth::password::delete)
         )
+        .route(
+            "/auth/oidc",
+            get(routing::handle::auth::oidc::get)
+        )
+        .route(
+            "/auth/oidc/callback",
+            get(routing::handle::auth::oidc::callback::get)
+        )
         .route(
             "/auth/totp",
             get(routing::okay)
                 .post(routing::handle::auth::totp::post)
                 .delete(routing::handle::auth::totp::delete)
         )
+        .route(
+            "/auth/totp/confirm",
+            post(routing::handle::auth::totp::confirm::post)
+        )
         .route(
             "/auth/totp_hash",
             get(routing::okay)
                 .post(routing::handle::auth::totp_hash::post)
         )
+        .route(
+            "/auth/recovery",
+            get(routing::handle::auth::recovery::get)
+                .post(routing::handle::auth::recovery::post)
+        )
         .route(
             "/auth/totp_hash/:key_id",
             put(routing::handle::auth::totp_hash::key_id::put)
                 .delete(routing::handle::auth::totp_hash::key_id::delete)
         )
+        .route(
+            "/auth/secrets",
+            get(routing::handle::auth::secrets::get)
+        )
+        .route(
+            "/auth/logout",
+            post(routing::handle::auth::logout::post)
+        )
+        .route(
+            "/auth/token",
+            post(routing::handle::auth::token::post)
+        )
+        .route(
+            "/auth/session",
+            get(routing::handle::auth::session::get)
+                .delete(routing::handle::auth::session::delete)
+        )
+        .route(
+            "/auth/session/:session_id",
+            delete(routing::handle::auth::session::session_id::delete)
+        )
+        .route(
+            "/auth/email/verify",
+            post(routing::handle::auth::email::verify::post)
+        )
+        .route(
+            "/auth/email/confirm",
+            post(routing::handle::auth::email::confirm::post)
+        )
+        .route(
+            "/auth/reset/request",
+            post(routing::handle::auth::reset::request::post)
+        )
+        .route(
+            "/auth/reset/complete",
+            post(routing::handle::auth::reset::complete::post)
+        )
         .route(
             "/storage",
             get(routing::handle::storage::get)
@@ -294,6 +995,24 @@ async fn init(arg: CommandArgs) -> error::Result<()> {
             "/storage/:storage_id/root",
             get(routing::handle::storage::storage_id::root::get)
         )
+        .route(
+            "/storage/:storage_id/access",
+            get(routing::handle::storage::storage_id::access::get)
+                .put(routing::handle::storage::storage_id::access::put)
+        )
+        .route(
+            "/storage/:storage_id/access/:user_id",
+            delete(routing::handle::storage::storage_id::access::user_id::delete)
+        )
+        .route(
+            "/storage/:storage_id/access/group",
+            get(routing::handle::storage::storage_id::access::group::get)
+                .put(routing::handle::storage::storage_id::access::group::put)
+        )
+        .route(
+            "/storage/:storage_id/access/group/:group_id",
+            delete(routing::handle::storage::storage_id::access::group::group_id::delete)
+        )
         .route(
             "/fs/:fs_id",
             get(routing::handle::fs::fs_id::get)
@@ -304,35 +1023,152 @@ async fn init(arg: CommandArgs) -> error::Result<()> {
         )
         .route(
             "/fs/:fs_id/contents",
-            get(routing::okay)
+            get(routing::handle::fs::fs_id::contents::get)
+        )
+        .route(
+            "/fs/:fs_id/access",
+            get(routing::handle::fs::fs_id::access::get)
+                .put(routing::handle::fs::fs_id::access::put)
+        )
+        .route(
+            "/fs/:fs_id/access/:user_id",
+            delete(routing::handle::fs::fs_id::access::user_id::delete)
+        )
+        .route(
+            "/fs/:fs_id/access/group",
+            get(routing::handle::fs::fs_id::access::group::get)
+                .put(routing::handle::fs::fs_id::access::group::put)
+        )
+        .route(
+            "/fs/:fs_id/access/group/:group_id",
+            delete(routing::handle::fs::fs_id::access::group::group_id::delete)
+        )
+        .route(
+            "/shared",
+            get(routing::handle::shared::get)
         )
         .route(
             "/fs/:fs_id/data",
-            get(routing::okay)
+            get(routing::handle::fs::fs_id::data::get)
+        )
+        .route(
+            "/fs/:fs_id/share",
+            get(routing::handle::fs::fs_id::share::get)
+                .post(routing::handle::fs::fs_id::share::post)
+        )
+        .route(
+            "/fs/:fs_id/sign",
+            post(routing::handle::fs::fs_id::sign::post)
+        )
+        .route(
+            "/signed/:fs_id",
+            get(routing::handle::signed::fs_id::get)
+                .put(routing::handle::signed::fs_id::put)
+        )
+        .route(
+            "/share",
+            get(routing::handle::share::get)
+        )
+        .route(
+            "/share/:token",
+            get(routing::handle::share::token::get)
+                .delete(routing::handle::share::token::delete)
+        )
+        .route(
+            "/share/:token/contents",
+            get(routing::handle::share::token::contents::get)
+        )
+        .route(
+            "/share/:token/data",
+            get(routing::handle::share::token::data::get)
+                .put(routing::handle::share::token::data::put)
         )
         .route(
             "/user",
-            get(routing::okay)
-                .post(routing::okay)
+            get(routing::handle::user::get)
+                .post(routing::handle::user::post)
         )
         .route(
             "/user/:user_id",
-            get(routing::okay)
-                .put(routing::okay)
-                .delete(routing::okay)
+            get(routing::handle::user::user_id::get)
+                .put(routing::handle::user::user_id::put)
+                .delete(routing::handle::user::user_id::delete)
+        )
+        .route(
+            "/user/:user_id/lockout",
+            delete(routing::handle::user::user_id::lockout::delete)
+        )
+        .route(
+            "/user/:user_id/roles",
+            get(routing::handle::user::user_id::roles::get)
+                .put(routing::handle::user::user_id::roles::put)
         )
         .route(
             "/user/:user_id/bot",
-            get(routing::okay)
-                .post(routing::okay)
+            get(routing::handle::user::user_id::bot::get)
+                .post(routing::handle::user::user_id::bot::post)
         )
         .route(
             "/user/:user_id/bot/:bot_id",
-            get(routing::okay)
-                .put(routing::okay)
-                .delete(routing::okay)
+            get(routing::handle::user::user_id::bot::bot_id::get)
+                .put(routing::handle::user::user_id::bot::bot_id::put)
+                .delete(routing::handle::user::user_id::bot::bot_id::delete)
+        )
+        .route(
+            "/user/:user_id/bot/:bot_id/token",
+            get(routing::handle::user::user_id::bot::bot_id::token::get)
+                .post(routing::handle::user::user_id::bot::bot_id::token::post)
+        )
+        .route(
+            "/user/:user_id/bot/:bot_id/token/:token_id",
+            delete(routing::handle::user::user_id::bot::bot_id::token::token_id::delete)
+        )
+        .route(
+            "/invite",
+            get(routing::handle::invite::get)
+                .post(routing::handle::invite::post)
+        )
+        .route(
+            "/invite/:code",
+            delete(routing::handle::invite::code::delete)
+        )
+        .route(
+            "/role",
+            get(routing::handle::role::get)
+                .post(routing::handle::role::post)
+        )
+        .route(
+            "/role/:role_id",
+            get(routing::handle::role::role_id::get)
+                .put(routing::handle::role::role_id::put)
+                .delete(routing::handle::role::role_id::delete)
+        )
+        .route(
+            "/group",
+            get(routing::handle::group::get)
+                .post(routing::handle::group::post)
+        )
+        .route(
+            "/group/:group_id",
+            get(routing::handle::group::group_id::get)
+                .put(routing::handle::group::group_id::put)
+                .delete(routing::handle::group::group_id::delete)
+        )
+        .route(
+            "/group/:group_id/member",
+            get(routing::handle::group::group_id::member::get)
+                .put(routing::handle::group::group_id::member::put)
+        )
+        .route(
+            "/group/:group_id/member/:user_id",
+            delete(routing::handle::group::group_id::member::user_id::delete)
+        )
+        .route(
+            "/audit",
+            get(routing::handle::audit::get)
         )
         .route("/ping", get(routing::handle::ping::get))
+        .route("/health", get(routing::handle::health::get))
         .fallback(routing::serve_file::handle)
         .layer(ServiceBuilder::new()
             .layer(net::layer::request_id::RIDLayer::new())
@@ -341,25 +1177,123 @@ async fn init(arg: CommandArgs) -> error::Result<()> {
                 .on_request(net::layer::trace::on_request)
                 .on_response(net::layer::trace::on_response)
                 .on_failure(net::layer::trace::on_failure))
+            .layer(SetResponseHeaderLayer::if_not_present(
+                header::STRICT_TRANSPORT_SECURITY,
+                tls.as_ref().and_then(|tls| tls.hsts_header())
+            ))
+            .layer(net::layer::csrf::CsrfLayer::new(allowed_origins))
             .layer(HandleErrorLayer::new(net::error::handle_error))
-            .layer(net::layer::timeout::TimeoutLayer::new(Duration::new(90, 0)))
+            .layer(net::layer::timeout::TimeoutLayer::new(Duration::from_secs(request_timeout.max(1))))
         )
-        .with_state(Arc::new(state));
+        .with_state(state.clone());
+
+    // cancelled once the server stops so that background jobs can finish
+    // what they are doing and exit
+    let shutdown = CancellationToken::new();
+    let handle = axum_server::Handle::new();
 
-    let server = hyper::Server::try_bind(&sock_addr)
+    start_shutdown_listener(handle.clone(), Duration::from_secs(shutdown_grace.max(1)))?;
+
+    let sweeper = sec::authn::start_sweeper(
+        state.pool().clone(),
+        *state.auth().lockout(),
+        state.auth().audit_retention().copied(),
+        Duration::from_secs(session_sweep.max(1)),
+        shutdown.clone(),
+    );
+
+    let listener = std::net::TcpListener::bind(sock_addr)
+        .and_then(|listener| {
+            listener.set_nonblocking(true)?;
+
+            Ok(listener)
+        })
         .map_err(|error| error::Error::new()
             .message(format!("failed to bind to socket address: {:#?}", sock_addr))
             .source(error)
-        )?
-        .serve(router.into_make_service());
+        )?;
+    let local_addr = listener.local_addr()?;
+    let make_service = router.into_make_service_with_connect_info::<SocketAddr>();
 
-    tracing::event!(
-        tracing::Level::INFO,
-        addr = %server.local_addr(),
-        "server listening",
-    );
+    let result = if let Some(tls) = tls {
+        let rustls_config = net::tls::load(&tls).await?;
+
+        net::tls::start_reloader(rustls_config.clone(), tls.clone(), shutdown.clone())?;
+
+        if let Some(port) = tls.redirect_port {
+            let redirect_addr = SocketAddr::new(local_addr.ip(), port);
+            let https_port = local_addr.port();
+            let redirect = Router::new()
+                .fallback(move |request| net::tls::redirect(https_port, request));
+
+            let redirect_server = hyper::Server::try_bind(&redirect_addr)
+                .map_err(|error| error::Error::new()
+                    .message(format!("failed to bind to socket address: {:#?}", redirect_addr))
+                    .source(error)
+                )?
+                .serve(redirect.into_make_service());
+
+            tracing::event!(
+                tracing::Level::INFO,
+                addr = %redirect_server.local_addr(),
+                "redirecting to https",
+            );
+
+            let redirect_server = redirect_server.with_graceful_shutdown({
+                let shutdown = shutdown.clone();
+
+                async move { shutdown.cancelled().await }
+            });
+
+            tokio::spawn(async move {
+                if let Err(err) = redirect_server.await {
+                    tracing::error!("https redirect server error: {}", err);
+                }
+            });
+        }
+
+        tracing::event!(
+            tracing::Level::INFO,
+            addr = %local_addr,
+            "server listening with tls",
+        );
+
+        axum_server::from_tcp_rustls(listener, rustls_config)
+            .handle(handle)
+            .serve(make_service)
+            .await
+    } else {
+        tracing::event!(
+            tracing::Level::INFO,
+            addr = %local_addr,
+            "server listening",
+        );
+
+        axum_server::from_tcp(listener)
+            .handle(handle)
+            .serve(make_service)
+            .await
+    };
+
+    shutdown.cancel();
+
+    if let Err(err) = sweeper.await {
+        tracing::error!("session sweeper did not stop cleanly: {}", err);
+    }
 
-    if let Err(err) = server.await {
+    let removed = state.uploads().cleanup().await;
+
+    if removed > 0 {
+        tracing::event!(
+            tracing::Level::INFO,
+            count = removed,
+            "removed unfinished uploads",
+        );
+    }
+
+    tracing::info!("server stopped");
+
+    if let Err(err) = result {
         Err(error::Error::new()
             .message("server error")
             .source(err))
@@ -368,3 +1302,60 @@ async fn init(arg: CommandArgs) -> error::Result<()> {
     }
 }
 
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(given: &[&str]) -> CommandArgs {
+        CommandArgs::try_parse_from(std::iter::once("rfs-server").chain(given.iter().copied()))
+            .expect("invalid test arguments")
+    }
+
+    fn config(contents: &str) -> config::Config {
+        toml::from_str(contents).expect("invalid test config")
+    }
+
+    #[test]
+    fn arguments_override_config() {
+        let config = config("\
+            [listen]\n\
+            ip = \"127.0.0.1\"\n\
+            port = 8000\n\
+            [tls]\n\
+            cert = \"file_cert.pem\"\n\
+            key = \"file_key.pem\"\n\
+            reload_interval = 30\n");
+
+        let addr = get_sock_addr(&args(&[]), &config).unwrap();
+
+        assert_eq!(addr, SocketAddr::from(([127, 0, 0, 1], 8000)));
+
+        let addr = get_sock_addr(&args(&["--port", "9000"]), &config).unwrap();
+
+        assert_eq!(addr, SocketAddr::from(([127, 0, 0, 1], 9000)), "port was not overridden");
+
+        let tls = get_tls_options(&args(&["--tls-cert", "arg_cert.pem"]), &config)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(tls.cert, PathBuf::from("arg_cert.pem"));
+        assert_eq!(tls.key, PathBuf::from("file_key.pem"), "key from the file was not kept");
+        assert_eq!(tls.reload_interval, Duration::from_secs(30));
+    }
+
+    #[test]
+    fn defaults_without_config() {
+        let config = config::Config::default();
+
+        assert_eq!(
+            get_sock_addr(&args(&[]), &config).unwrap(),
+            SocketAddr::from(([0, 0, 0, 0], 0))
+        );
+        assert!(get_tls_options(&args(&[]), &config).unwrap().is_none());
+        assert!(
+            get_tls_options(&args(&["--tls-cert", "cert.pem"]), &config).is_err(),
+            "tls cert was accepted without a key"
+        );
+    }
+}
@@ -1,8 +1,23 @@
 use std::time::Duration;
 
-use axum::http::{HeaderValue, header::InvalidHeaderValue};
+use axum::http::{HeaderMap, HeaderValue, header::{InvalidHeaderValue, ToStrError}};
 use chrono::{DateTime, Utc};
 
+/// finds the value of a cookie sent with the request
+pub fn find<'a>(headers: &'a HeaderMap, key: &str) -> Result<Option<&'a str>, ToStrError> {
+    for value in headers.get_all("cookie") {
+        for pair in value.to_str()?.split(';') {
+            if let Some((name, value)) = pair.trim().split_once('=') {
+                if name == key {
+                    return Ok(Some(value));
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
+
 pub enum SameSite {
     Strict,
     Lax,
@@ -316,4 +316,6 @@ simple_from!(argon2::Error);
 
 simple_from!(rust_otp::error::Error);
 
+simple_from!(qrcode::types::QrError);
+
 simple_from!(snowcloud_cloud::error::Error);
@@ -8,6 +8,8 @@ use axum::response::Response;
 
 use crate::net;
 use crate::net::error;
+use crate::storage;
+use crate::fs;
 
 pub async fn stream_file<P>(path: P) -> error::Result<Response<StreamBody<ReaderStream<File>>>>
 where
@@ -47,3 +49,41 @@ where
         Ok(stream_file(path_ref).await?)
     }
 }
+
+/// streams the contents of a file from its storage medium
+pub async fn stream_fs_file(
+    medium: &storage::Medium,
+    file: &fs::File,
+) -> error::Result<Response<StreamBody<ReaderStream<File>>>> {
+    match &medium.type_ {
+        storage::types::Type::Local(local) => {
+            let mut full = local.path.join(&file.path);
+            full.push(&file.basename);
+
+            if !full.try_exists()? {
+                return Err(error::Error::new()
+                    .status(StatusCode::NOT_FOUND)
+                    .kind("FileNotFound")
+                    .message("the requested file does not exist on the system"));
+            }
+
+            let handle = OpenOptions::new()
+                .read(true)
+                .open(full)
+                .await?;
+
+            let stream = ReaderStream::new(handle);
+            let disposition = format!(
+                "attachment; filename=\"{}\"",
+                file.basename.replace('"', "\\\"")
+            );
+
+            Ok(Response::builder()
+                .status(StatusCode::OK)
+                .header("content-type", file.mime.to_string())
+                .header("content-length", file.size)
+                .header("content-disposition", disposition)
+                .body(StreamBody::new(stream))?)
+        }
+    }
+}
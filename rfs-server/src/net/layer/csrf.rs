@@ -0,0 +1,356 @@
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::pin::Pin;
+use std::future::Future;
+
+use tower::{Layer, Service};
+use pin_project::pin_project;
+use axum::http::{Request, Response, Method, HeaderMap, StatusCode};
+use axum::body::BoxBody;
+use axum::response::IntoResponse;
+
+use crate::error;
+use crate::net;
+use crate::sec::authn::initiator;
+
+/// the name of the cookie that makes a request carry ambient credentials
+const SESSION_COOKIE: &str = "session_id";
+
+/// methods that are not expected to change anything on the server
+fn is_safe_method(method: &Method) -> bool {
+    method == Method::GET ||
+        method == Method::HEAD ||
+        method == Method::OPTIONS ||
+        method == Method::TRACE
+}
+
+fn has_session_cookie(headers: &HeaderMap) -> bool {
+    for value in headers.get_all("cookie") {
+        let Ok(value_str) = value.to_str() else {
+            continue;
+        };
+
+        for pair in value_str.split(';') {
+            if let Some((name, _)) = pair.split_once('=') {
+                if name.trim() == SESSION_COOKIE {
+                    return true;
+                }
+            }
+        }
+    }
+
+    false
+}
+
+/// the serialized origin of a url. "https://example.com:8443/path" would
+/// give "https://example.com:8443"
+fn url_origin(given: &str) -> Option<String> {
+    let url = url::Url::parse(given).ok()?;
+    let origin = url.origin();
+
+    if origin.is_tuple() {
+        Some(origin.ascii_serialization())
+    } else {
+        None
+    }
+}
+
+/// the host and port of a url in the same form as the host header
+fn url_authority(given: &str) -> Option<String> {
+    let url = url::Url::parse(given).ok()?;
+    let host = url.host_str()?;
+
+    if let Some(port) = url.port() {
+        Some(format!("{}:{}", host, port))
+    } else {
+        Some(host.to_owned())
+    }
+}
+
+/// the origin of the request from the origin header or the referer if the
+/// origin was not sent
+fn request_origin(headers: &HeaderMap) -> Option<&str> {
+    if let Some(value) = headers.get("origin") {
+        // browsers send "null" for opaque origins which will not match
+        return value.to_str().ok();
+    }
+
+    headers.get("referer")?.to_str().ok()
+}
+
+/// the list of origins that are allowed to make state changing requests
+/// with the session cookie
+#[derive(Debug, Clone)]
+pub struct AllowedOrigins(Arc<Vec<String>>);
+
+impl AllowedOrigins {
+    /// normalizes the given origins. if none are provided then requests
+    /// must come from the host they were sent to
+    pub fn new<I>(origins: I) -> error::Result<Self>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let mut list = Vec::new();
+
+        for given in origins {
+            let Some(origin) = url_origin(given.as_ref()) else {
+                return Err(error::Error::new()
+                    .kind("InvalidOrigin")
+                    .message(format!("\"{}\" is not a valid origin", given.as_ref())));
+            };
+
+            list.push(origin);
+        }
+
+        Ok(AllowedOrigins(Arc::new(list)))
+    }
+
+    fn check(&self, headers: &HeaderMap) -> bool {
+        let Some(given) = request_origin(headers) else {
+            return false;
+        };
+
+        if !self.0.is_empty() {
+            let Some(origin) = url_origin(given) else {
+                return false;
+            };
+
+            return self.0.contains(&origin);
+        }
+
+        let (Some(authority), Some(host)) = (url_authority(given), headers.get("host")) else {
+            return false;
+        };
+
+        let Ok(host_str) = host.to_str() else {
+            return false;
+        };
+
+        authority.eq_ignore_ascii_case(host_str)
+    }
+}
+
+fn rejected() -> Response<BoxBody> {
+    net::error::Error::new()
+        .status(StatusCode::FORBIDDEN)
+        .kind("CsrfRejected")
+        .message("request origin is not allowed")
+        .into_response()
+}
+
+#[pin_project(project = CsrfFutureProj)]
+pub enum CsrfFuture<F> {
+    Inner {
+        #[pin]
+        response: F,
+    },
+    Rejected {
+        response: Option<Response<BoxBody>>,
+    },
+}
+
+impl<F, Error> Future for CsrfFuture<F>
+where
+    F: Future<Output = Result<Response<BoxBody>, Error>>,
+{
+    type Output = Result<Response<BoxBody>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            CsrfFutureProj::Inner { response } => response.poll(cx),
+            CsrfFutureProj::Rejected { response } => Poll::Ready(Ok(
+                response.take().expect("csrf future polled after completion")
+            )),
+        }
+    }
+}
+
+/// rejects state changing requests that rely on the session cookie and
+/// do not come from an allowed origin
+///
+/// requests with a bearer token are exempt since browsers will not attach
+/// one to a cross site request on their own. basic credentials are not
+/// exempt as browsers will resend them once a user has entered them
+#[derive(Debug, Clone)]
+pub struct Csrf<S> {
+    inner: S,
+    origins: AllowedOrigins,
+}
+
+impl<S> Csrf<S> {
+    pub fn new(inner: S, origins: AllowedOrigins) -> Self {
+        Csrf { inner, origins }
+    }
+}
+
+impl<S, B> Service<Request<B>> for Csrf<S>
+where
+    S: Service<Request<B>, Response = Response<BoxBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = CsrfFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let headers = request.headers();
+
+        let exempt = is_safe_method(request.method()) ||
+            initiator::has_bearer_authorization(headers) ||
+            !has_session_cookie(headers);
+
+        if exempt || self.origins.check(headers) {
+            CsrfFuture::Inner {
+                response: self.inner.call(request)
+            }
+        } else {
+            CsrfFuture::Rejected {
+                response: Some(rejected())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CsrfLayer {
+    origins: AllowedOrigins,
+}
+
+impl CsrfLayer {
+    pub fn new(origins: AllowedOrigins) -> Self {
+        CsrfLayer { origins }
+    }
+}
+
+impl<S> Layer<S> for CsrfLayer {
+    type Service = Csrf<S>;
+
+    fn layer(&self, service: S) -> Self::Service {
+        Csrf::new(service, self.origins.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::Infallible;
+
+    use axum::body::{boxed, Empty};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn send(origins: &[&str], method: Method, headers: &[(&str, &str)]) -> StatusCode {
+        let origins = AllowedOrigins::new(origins).unwrap();
+        let service = Csrf::new(
+            tower::service_fn(|_req: Request<()>| async {
+                Ok::<_, Infallible>(Response::new(boxed(Empty::new())))
+            }),
+            origins
+        );
+
+        let mut builder = Request::builder()
+            .method(method)
+            .uri("/fs");
+
+        for (key, value) in headers {
+            builder = builder.header(*key, *value);
+        }
+
+        let response = service.oneshot(builder.body(()).unwrap())
+            .await
+            .unwrap();
+
+        response.status()
+    }
+
+    #[tokio::test]
+    async fn cross_site_session_rejected() {
+        let status = send(&[], Method::POST, &[
+            ("host", "files.example.com"),
+            ("origin", "https://evil.example.com"),
+            ("cookie", "session_id=abc"),
+        ]).await;
+
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let status = send(&[], Method::POST, &[
+            ("host", "files.example.com"),
+            ("cookie", "session_id=abc"),
+        ]).await;
+
+        assert_eq!(status, StatusCode::FORBIDDEN, "missing origin was allowed");
+    }
+
+    #[tokio::test]
+    async fn same_site_session_allowed() {
+        let status = send(&[], Method::POST, &[
+            ("host", "files.example.com"),
+            ("origin", "https://files.example.com"),
+            ("cookie", "session_id=abc"),
+        ]).await;
+
+        assert_eq!(status, StatusCode::OK);
+
+        let status = send(&["https://app.example.com"], Method::POST, &[
+            ("host", "files.example.com"),
+            ("referer", "https://app.example.com/page"),
+            ("cookie", "session_id=abc"),
+        ]).await;
+
+        assert_eq!(status, StatusCode::OK, "allowed origin was rejected");
+    }
+
+    #[tokio::test]
+    async fn safe_methods_and_no_session_allowed() {
+        let status = send(&[], Method::GET, &[
+            ("host", "files.example.com"),
+            ("origin", "https://evil.example.com"),
+            ("cookie", "session_id=abc"),
+        ]).await;
+
+        assert_eq!(status, StatusCode::OK);
+
+        let status = send(&[], Method::POST, &[
+            ("host", "files.example.com"),
+            ("origin", "https://evil.example.com"),
+            ("cookie", "theme=dark"),
+        ]).await;
+
+        assert_eq!(status, StatusCode::OK, "request without a session was rejected");
+    }
+
+    #[tokio::test]
+    async fn bearer_token_exempt() {
+        let status = send(&[], Method::POST, &[
+            ("host", "files.example.com"),
+            ("origin", "https://evil.example.com"),
+            ("authorization", "Bearer token"),
+            ("cookie", "session_id=abc"),
+        ]).await;
+
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn other_authorization_not_exempt() {
+        for authorization in ["Custom token", "Bearer", "Bearer ", "Basic dXNlcjpwYXNz"] {
+            let status = send(&[], Method::POST, &[
+                ("host", "files.example.com"),
+                ("origin", "https://evil.example.com"),
+                ("authorization", authorization),
+                ("cookie", "session_id=abc"),
+            ]).await;
+
+            assert_eq!(
+                status,
+                StatusCode::FORBIDDEN,
+                "\"{}\" with a session cookie was exempt",
+                authorization
+            );
+        }
+    }
+}
@@ -1,5 +1,6 @@
 pub mod timeout;
 pub mod request_id;
+pub mod csrf;
 
 pub mod trace {
     use std::time::Duration;
@@ -16,6 +16,7 @@ pub mod error;
 pub mod mime;
 pub mod cookie;
 pub mod layer;
+pub mod tls;
 
 pub mod fs;
 pub mod html;
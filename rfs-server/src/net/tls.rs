@@ -0,0 +1,141 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use axum::http::{Request, Response, StatusCode, HeaderValue};
+use axum::http::uri::Authority;
+use axum::body::{Body, BoxBody};
+use axum::response::{IntoResponse, Redirect};
+use axum_server::tls_rustls::RustlsConfig;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio_util::sync::CancellationToken;
+
+use crate::error;
+use crate::net;
+
+/// the certificate and key files used to terminate tls
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    /// port of a plain http listener that redirects to https
+    pub redirect_port: Option<u16>,
+    /// max-age of the strict transport security header. the header is not
+    /// sent if not provided
+    pub hsts_max_age: Option<u64>,
+    /// how often the files are checked for changes
+    pub reload_interval: Duration,
+}
+
+impl Options {
+    pub fn hsts_header(&self) -> Option<HeaderValue> {
+        self.hsts_max_age.map(|max_age| {
+            HeaderValue::from_str(&format!("max-age={}", max_age))
+                .expect("hsts header value should be valid")
+        })
+    }
+}
+
+/// loads the certificate chain and private key from pem files
+pub async fn load(options: &Options) -> error::Result<RustlsConfig> {
+    RustlsConfig::from_pem_file(&options.cert, &options.key)
+        .await
+        .map_err(|err| error::Error::new()
+            .kind("InvalidTls")
+            .message(format!(
+                "failed to load tls certificate and key. cert: {} key: {}",
+                options.cert.display(),
+                options.key.display()
+            ))
+            .source(err))
+}
+
+fn modified(cert: &Path, key: &Path) -> Option<(SystemTime, SystemTime)> {
+    let cert_modified = std::fs::metadata(cert).and_then(|meta| meta.modified()).ok()?;
+    let key_modified = std::fs::metadata(key).and_then(|meta| meta.modified()).ok()?;
+
+    Some((cert_modified, key_modified))
+}
+
+/// reloads the certificate and key on SIGHUP or when either file changes
+///
+/// connections that are already established keep the certificate they
+/// were created with. if the new files fail to load then the current
+/// certificate is kept
+pub fn start_reloader(
+    config: RustlsConfig,
+    options: Options,
+    shutdown: CancellationToken,
+) -> error::Result<tokio::task::JoinHandle<()>> {
+    let mut hangup = signal(SignalKind::hangup())
+        .map_err(|err| error::Error::new()
+            .message("failed to listen for SIGHUP")
+            .source(err))?;
+
+    Ok(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(options.reload_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+        let mut last_modified = modified(&options.cert, &options.key);
+
+        loop {
+            tokio::select! {
+                _ = hangup.recv() => {
+                    tracing::info!("received SIGHUP. reloading tls certificate");
+                },
+                _ = interval.tick() => {
+                    let current = modified(&options.cert, &options.key);
+
+                    if current.is_none() || current == last_modified {
+                        continue;
+                    }
+
+                    tracing::info!("tls certificate changed. reloading");
+                },
+                _ = shutdown.cancelled() => break,
+            }
+
+            last_modified = modified(&options.cert, &options.key);
+
+            match config.reload_from_pem_file(&options.cert, &options.key).await {
+                Ok(()) => tracing::info!("reloaded tls certificate"),
+                Err(err) => tracing::error!("failed to reload tls certificate. keeping current: {}", err),
+            }
+        }
+    }))
+}
+
+/// the https url for the request. the port is left off if it is the
+/// default for https
+fn redirect_uri(request: &Request<Body>, https_port: u16) -> Option<String> {
+    let authority: Authority = request.headers()
+        .get("host")?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()?;
+    let hostname = authority.host();
+
+    let path = request.uri()
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or("/");
+
+    if https_port == 443 {
+        Some(format!("https://{}{}", hostname, path))
+    } else {
+        Some(format!("https://{}:{}{}", hostname, https_port, path))
+    }
+}
+
+/// sends any request to the same host and path over https
+pub async fn redirect(https_port: u16, request: Request<Body>) -> Response<BoxBody> {
+    let Some(location) = redirect_uri(&request, https_port) else {
+        return net::error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidHost")
+            .message("a valid host header is required")
+            .into_response();
+    };
+
+    Redirect::permanent(&location).into_response()
+}
@@ -0,0 +1,153 @@
+use std::fmt::Write;
+
+use axum::http::StatusCode;
+use axum::extract::{State, Query};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use rfs_lib::ids;
+
+use crate::net::{self, error};
+use crate::state::ArcShared;
+use crate::sec::authn::initiator::Initiator;
+use crate::sec::authz;
+use crate::sec::audit::Entry;
+use crate::util::sql;
+
+/// the number of entries returned when no limit is given
+const DEFAULT_LIMIT: i64 = 100;
+/// the most entries that can be returned in a single json response
+const MAX_LIMIT: i64 = 1000;
+
+#[derive(Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Json,
+    /// one entry per line. intended for exporting the log
+    Jsonl,
+}
+
+#[derive(Deserialize)]
+pub struct AuditQuery {
+    event: Option<String>,
+    user_id: Option<ids::UserId>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    /// only entries with an id less than this. used to page through json
+    /// results
+    before: Option<i64>,
+    limit: Option<i64>,
+    #[serde(default)]
+    format: Format,
+}
+
+/// searches the audit log
+///
+/// json results are newest first and limited. jsonl exports are oldest
+/// first and only limited if a limit is provided
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: Initiator,
+    Query(query): Query<AuditQuery>,
+) -> error::Result<Response> {
+    authz::require_permission(&initiator, authz::Permission::AuditRead)?;
+
+    let limit = match (&query.format, query.limit) {
+        (_, Some(limit)) if limit <= 0 => {
+            return Err(error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("InvalidLimit")
+                .message("limit must be greater than 0"));
+        },
+        (Format::Json, Some(limit)) => Some(std::cmp::min(limit, MAX_LIMIT)),
+        (Format::Json, None) => Some(DEFAULT_LIMIT),
+        (Format::Jsonl, limit) => limit,
+    };
+
+    let conn = state.pool().get().await?;
+
+    let mut search_query = format!("select {} from audit_log where true", Entry::COLUMNS);
+    let mut search_params = sql::ParamsVec::new();
+
+    if let Some(event) = &query.event {
+        write!(
+            &mut search_query,
+            " and audit_log.event = ${}",
+            sql::push_param(&mut search_params, event)
+        ).unwrap();
+    }
+
+    if let Some(user_id) = &query.user_id {
+        write!(
+            &mut search_query,
+            " and audit_log.user_id = ${}",
+            sql::push_param(&mut search_params, user_id)
+        ).unwrap();
+    }
+
+    if let Some(since) = &query.since {
+        write!(
+            &mut search_query,
+            " and audit_log.created >= ${}",
+            sql::push_param(&mut search_params, since)
+        ).unwrap();
+    }
+
+    if let Some(until) = &query.until {
+        write!(
+            &mut search_query,
+            " and audit_log.created < ${}",
+            sql::push_param(&mut search_params, until)
+        ).unwrap();
+    }
+
+    if let Some(before) = &query.before {
+        write!(
+            &mut search_query,
+            " and audit_log.id < ${}",
+            sql::push_param(&mut search_params, before)
+        ).unwrap();
+    }
+
+    if query.format == Format::Jsonl {
+        search_query.push_str(" order by audit_log.id");
+    } else {
+        search_query.push_str(" order by audit_log.id desc");
+    }
+
+    if let Some(limit) = &limit {
+        write!(
+            &mut search_query,
+            " limit ${}",
+            sql::push_param(&mut search_params, limit)
+        ).unwrap();
+    }
+
+    let entries = conn.query(search_query.as_str(), search_params.as_slice())
+        .await?
+        .into_iter()
+        .map(|row| Entry::from_row(row).into_schema());
+
+    match query.format {
+        Format::Json => {
+            let wrapper = rfs_lib::json::ListWrapper::with_vec(entries.collect());
+
+            Ok(net::Json::new(wrapper).into_response())
+        },
+        Format::Jsonl => {
+            let mut body = String::new();
+
+            for entry in entries {
+                body.push_str(&serde_json::to_string(&entry)?);
+                body.push('\n');
+            }
+
+            Ok(Response::builder()
+                .status(StatusCode::OK)
+                .header("content-type", "application/x-ndjson")
+                .header("content-length", body.len())
+                .body(axum::body::boxed(body))?)
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use rfs_lib::actions;
+use axum::http::StatusCode;
+use axum::extract::State;
+use axum::response::IntoResponse;
+
+use crate::net::{self, error};
+use crate::state::ArcShared;
+use crate::email::token::{EmailToken, TokenKind};
+
+pub async fn post(
+    State(state): State<ArcShared>,
+    axum::Json(json): axum::Json<actions::auth::ConfirmEmail>,
+) -> error::Result<impl IntoResponse> {
+    let mut conn = state.pool().get().await?;
+    let transaction = conn.transaction().await?;
+
+    let Some(token) = EmailToken::take(&transaction, &json.token, TokenKind::Verify).await? else {
+        // commit so that an expired token is still removed
+        transaction.commit().await?;
+
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidToken")
+            .message("the provided token is invalid or expired"));
+    };
+
+    // the email could have changed since the token was sent
+    let updated = transaction.execute(
+        "\
+        update users \
+        set email_verified = true \
+        where id = $1 and \
+              email = $2 and \
+              deleted is null",
+        &[&token.user_id, &token.email]
+    ).await?;
+
+    transaction.commit().await?;
+
+    if updated != 1 {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidToken")
+            .message("the provided token is invalid or expired"));
+    }
+
+    Ok(net::Json::empty()
+        .with_message("email verified"))
+}
@@ -0,0 +1,108 @@
+pub mod verify;
+pub mod confirm;
+
+#[cfg(test)]
+mod test {
+    use axum::Json;
+    use axum::extract::State;
+    use axum::http::StatusCode;
+    use rfs_lib::actions::auth::ConfirmEmail;
+    use rfs_lib::ids;
+
+    use crate::sec::authn::initiator::{Initiator, Mechanism};
+    use crate::state::ArcShared;
+    use crate::testing::{database_with, status, SmtpSink};
+    use crate::user;
+
+    const EMAIL: &str = "tester@example.com";
+
+    async fn setup() -> Option<(ArcShared, SmtpSink, ids::UserId)> {
+        let sink = SmtpSink::start().await;
+        let state = database_with(|builder| sink.configure(builder)).await?;
+
+        let conn = state.pool().get().await.unwrap();
+        let mut builder = user::User::builder(state.ids().wait_user_id().unwrap(), "tester".into());
+        builder.set_email(EMAIL.into());
+
+        let user = builder.build(&conn).await.unwrap();
+
+        Some((state, sink, user.id().clone()))
+    }
+
+    async fn verify(state: &ArcShared, user_id: &ids::UserId) -> StatusCode {
+        let conn = state.pool().get().await.unwrap();
+        let user = user::User::query_with_id(&conn, user_id).await.unwrap().unwrap();
+
+        status(super::verify::post(
+            State(state.clone()),
+            Initiator::unchecked(user, None, Mechanism::Basic),
+        ).await)
+    }
+
+    async fn confirm(state: &ArcShared, token: &str) -> StatusCode {
+        status(super::confirm::post(
+            State(state.clone()),
+            Json(ConfirmEmail { token: token.into() }),
+        ).await)
+    }
+
+    async fn verified(state: &ArcShared, user_id: &ids::UserId) -> bool {
+        let conn = state.pool().get().await.unwrap();
+
+        conn.query_one("select email_verified from users where id = $1", &[user_id])
+            .await
+            .unwrap()
+            .get(0)
+    }
+
+    #[tokio::test]
+    async fn token_is_delivered_and_single_use() {
+        let Some((state, sink, user_id)) = setup().await else {
+            return;
+        };
+
+        assert_eq!(verify(&state, &user_id).await, StatusCode::OK);
+
+        let messages = sink.messages();
+
+        assert_eq!(messages.len(), 1, "verification email was not delivered");
+        assert!(messages[0].contains(EMAIL), "email was not sent to the user");
+
+        let token = sink.last_token().expect("token was not in the email");
+
+        assert_eq!(confirm(&state, &token).await, StatusCode::OK);
+        assert!(verified(&state, &user_id).await, "email was not verified");
+        assert_eq!(
+            confirm(&state, &token).await,
+            StatusCode::BAD_REQUEST,
+            "token was used twice"
+        );
+    }
+
+    #[tokio::test]
+    async fn expired_token_is_rejected() {
+        let Some((state, sink, user_id)) = setup().await else {
+            return;
+        };
+
+        assert_eq!(verify(&state, &user_id).await, StatusCode::OK);
+
+        let token = sink.last_token().expect("token was not in the email");
+
+        {
+            let conn = state.pool().get().await.unwrap();
+
+            conn.execute(
+                "update user_email_tokens set expires = now() - interval '1 minute'",
+                &[]
+            ).await.unwrap();
+        }
+
+        assert_eq!(
+            confirm(&state, &token).await,
+            StatusCode::BAD_REQUEST,
+            "expired token was accepted"
+        );
+        assert!(!verified(&state, &user_id).await, "email was verified");
+    }
+}
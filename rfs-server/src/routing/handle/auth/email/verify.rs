@@ -0,0 +1,62 @@
+use axum::http::StatusCode;
+use axum::extract::State;
+use axum::response::IntoResponse;
+
+use crate::net::{self, error};
+use crate::state::ArcShared;
+use crate::sec::authn::initiator::Initiator;
+use crate::email::token::{EmailToken, TokenKind};
+
+pub async fn post(
+    State(state): State<ArcShared>,
+    initiator: Initiator,
+) -> error::Result<impl IntoResponse> {
+    let Some(mailer) = state.email() else {
+        return Err(error::Error::new()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .kind("EmailDisabled")
+            .message("the server is not able to send emails"));
+    };
+
+    let Some(email) = initiator.user().email() else {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("EmailNotFound")
+            .message("no email is assigned to the current user"));
+    };
+
+    if *email.verified() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("EmailVerified")
+            .message("the current email is already verified"));
+    }
+
+    let mut conn = state.pool().get().await?;
+    let transaction = conn.transaction().await?;
+
+    let (_, token) = EmailToken::create(
+        &transaction,
+        initiator.user().id().clone(),
+        TokenKind::Verify,
+        email.email().clone(),
+    ).await?;
+
+    // sent before committing so that a failed email does not leave an
+    // unusable token behind
+    mailer.send(
+        email.email(),
+        "Verify your email",
+        format!(
+            "Use the following token to verify the email for \"{}\".\n\n{}\n\nThe token will expire in {} hours.",
+            initiator.user().username(),
+            token,
+            TokenKind::Verify.lifetime().num_hours(),
+        )
+    ).await?;
+
+    transaction.commit().await?;
+
+    Ok(net::Json::empty()
+        .with_message("verification email sent"))
+}
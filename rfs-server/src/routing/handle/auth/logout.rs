@@ -0,0 +1,61 @@
+use axum::http::{HeaderMap, StatusCode};
+use axum::extract::State;
+use axum::response::IntoResponse;
+
+use crate::net::{self, error};
+use crate::state::ArcShared;
+use crate::sec::audit;
+use crate::sec::authn::{session, access};
+use crate::sec::authn::initiator::{self, LookupError, Mechanism};
+
+pub async fn post(
+    State(state): State<ArcShared>,
+    audit: audit::Context,
+    headers: HeaderMap,
+) -> error::Result<impl IntoResponse> {
+    let mut conn = state.pool().get().await?;
+
+    // sessions that are still pending authentication or verification are
+    // also able to log out
+    let session = match initiator::lookup_header_map(state.auth(), &conn, &headers, &audit).await {
+        Ok(initiator) => match initiator.into_mechanism() {
+            Mechanism::Session(session) => Some(session),
+            _ => {
+                return Err(error::Error::new()
+                    .status(StatusCode::BAD_REQUEST)
+                    .kind("InvalidMechanism")
+                    .message("only sessions are able to log out"));
+            }
+        },
+        Err(err) => match err {
+            LookupError::SessionExpired(session) |
+            LookupError::SessionUnauthenticated(session) |
+            LookupError::SessionUnverified(session) => Some(*session),
+            LookupError::MechanismNotFound |
+            LookupError::SessionNotFound => None,
+            _ => {
+                return Err(err.into());
+            }
+        }
+    };
+
+    if let Some(session) = session {
+        let transaction = conn.transaction().await?;
+
+        access::delete_session_tokens(&transaction, &session.token).await?;
+        session.delete(&transaction).await?;
+
+        audit.entry(audit::Event::Logout)
+            .with_session(&session)
+            .record(&transaction)
+            .await?;
+
+        transaction.commit().await?;
+    }
+
+    let session_cookie = session::expire_session_cookie(state.auth());
+
+    Ok(net::Json::empty()
+        .with_message("logged out")
+        .with_header("set-cookie", session_cookie))
+}
@@ -12,6 +12,14 @@ pub mod verify;
 pub mod password;
 pub mod totp;
 pub mod totp_hash;
+pub mod recovery;
+pub mod email;
+pub mod reset;
+pub mod session;
+pub mod logout;
+pub mod secrets;
+pub mod oidc;
+pub mod token;
 
 #[derive(Serialize)]
 pub struct AuthContext {}
@@ -39,3 +47,157 @@ pub async fn get(
         .with_message("no-op")
         .into_response())
 }
+
+#[cfg(test)]
+mod test {
+    use axum::Json;
+    use axum::extract::{ConnectInfo, Path, State};
+    use axum::http::{Request, StatusCode};
+    use axum::response::IntoResponse;
+    use chrono::Utc;
+    use serde::de::DeserializeOwned;
+    use serde_json::json;
+    use rfs_lib::ids;
+
+    use crate::bot;
+    use crate::net::error;
+    use crate::sec::audit;
+    use crate::sec::authn::initiator::{Initiator, Mechanism};
+    use crate::testing::shared;
+    use crate::user;
+
+    fn read_only_bot() -> Initiator {
+        let user_id = ids::user_id_from_pg(1);
+        let bot_id = ids::bot_id_from_pg(2);
+        let now = Utc::now();
+
+        Initiator::unchecked(
+            user::User::unsaved(user_id.clone(), "owner".into()),
+            Some(bot::Bot {
+                id: bot_id.clone(),
+                user_id,
+                name: "reader".into(),
+                created: now,
+            }),
+            Mechanism::Bot(bot::token::Token {
+                id: "token".into(),
+                bot_id,
+                hash: blake3::hash(b"secret"),
+                read_only: true,
+                storages: Vec::new(),
+                expires: None,
+                last_used: None,
+                created: now,
+            })
+        )
+    }
+
+    fn audit() -> audit::Context {
+        let (parts, _) = Request::new(()).into_parts();
+
+        audit::Context::from_parts(&parts)
+    }
+
+    fn value<T>(value: serde_json::Value) -> T
+    where
+        T: DeserializeOwned
+    {
+        serde_json::from_value(value).expect("invalid test value")
+    }
+
+    fn assert_forbidden<T>(route: &str, result: error::Result<T>)
+    where
+        T: IntoResponse
+    {
+        match result {
+            Ok(_) => panic!("{} accepted a bot token", route),
+            Err(err) => assert_eq!(
+                err.into_response().status(),
+                StatusCode::FORBIDDEN,
+                "{} did not reject a bot token",
+                route
+            ),
+        }
+    }
+
+    #[tokio::test]
+    async fn bot_tokens_cannot_manage_credentials() {
+        let state = shared();
+        let addr = ConnectInfo(([127, 0, 0, 1], 0).into());
+
+        assert_forbidden("POST /auth/totp", super::totp::post(
+            State(state.clone()),
+            read_only_bot(),
+            Json(value(json!({"algo": null, "digits": null, "step": null}))),
+        ).await);
+        assert_forbidden("DELETE /auth/totp", super::totp::delete(
+            State(state.clone()),
+            read_only_bot(),
+            audit(),
+        ).await);
+        assert_forbidden("POST /auth/totp/confirm", super::totp::confirm::post(
+            State(state.clone()),
+            addr,
+            read_only_bot(),
+            audit(),
+            Json(value(json!({"code": "000000"}))),
+        ).await);
+        assert_forbidden("POST /auth/totp_hash", super::totp_hash::post(
+            State(state.clone()),
+            read_only_bot(),
+            Json(value(json!({"key": "backup"}))),
+        ).await);
+        assert_forbidden("PUT /auth/totp_hash/:key_id", super::totp_hash::key_id::put(
+            State(state.clone()),
+            read_only_bot(),
+            Path(value(json!({"key_id": "backup"}))),
+            Json(value(json!({"key": null, "regen": true}))),
+        ).await);
+        assert_forbidden("DELETE /auth/totp_hash/:key_id", super::totp_hash::key_id::delete(
+            State(state.clone()),
+            read_only_bot(),
+            Path(value(json!({"key_id": "backup"}))),
+        ).await);
+        assert_forbidden("GET /auth/recovery", super::recovery::get(
+            State(state.clone()),
+            read_only_bot(),
+        ).await);
+        assert_forbidden("POST /auth/recovery", super::recovery::post(
+            State(state.clone()),
+            read_only_bot(),
+            audit(),
+            Json(value(json!({"count": null, "regen": true}))),
+        ).await);
+        assert_forbidden("POST /auth/password", super::password::post(
+            State(state.clone()),
+            read_only_bot(),
+            audit(),
+            Json(value(json!({"current": null, "updated": "password", "confirm": "password"}))),
+        ).await);
+        assert_forbidden("DELETE /auth/password", super::password::delete(
+            State(state.clone()),
+            read_only_bot(),
+            audit(),
+            Json(value(json!({"current": null}))),
+        ).await);
+        assert_forbidden("POST /auth/token", super::token::post(
+            State(state.clone()),
+            read_only_bot(),
+        ).await);
+        assert_forbidden("GET /auth/session", super::session::get(
+            State(state.clone()),
+            read_only_bot(),
+        ).await);
+        assert_forbidden("DELETE /auth/session", super::session::delete(
+            State(state.clone()),
+            read_only_bot(),
+            audit(),
+        ).await);
+        assert_forbidden("DELETE /auth/session/:session_id", super::session::session_id::delete(
+            State(state.clone()),
+            read_only_bot(),
+            audit(),
+            Path(value(json!({"session_id": "session"}))),
+        ).await);
+    }
+}
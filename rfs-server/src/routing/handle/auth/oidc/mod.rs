@@ -0,0 +1,52 @@
+use rfs_lib::schema;
+use axum::http::{HeaderMap, StatusCode, Response};
+use axum::extract::State;
+use axum::response::IntoResponse;
+
+use crate::net::{self, error};
+use crate::state::ArcShared;
+use crate::sec::authn::oidc::Provider;
+use crate::sec::authn::oidc::link::{self, PendingLogin};
+
+pub mod callback;
+
+pub fn provider(state: &ArcShared) -> error::Result<&Provider> {
+    state.auth().oidc().ok_or(error::Error::new()
+        .status(StatusCode::NOT_FOUND)
+        .kind("OidcDisabled")
+        .message("identity provider login is not enabled"))
+}
+
+/// starts a login with the identity provider
+///
+/// browsers are redirected to the provider while other clients are given
+/// the url to send the user to. the pending cookie must be sent back with
+/// the callback so that a login started by someone else cannot be completed
+/// in this browser
+pub async fn get(
+    State(state): State<ArcShared>,
+    headers: HeaderMap,
+) -> error::Result<impl IntoResponse> {
+    let provider = provider(&state)?;
+    let conn = state.pool().get().await?;
+
+    let pending = PendingLogin::create(&conn).await?;
+    let url = provider.authorize_url(&pending).await?;
+    let pending_cookie = link::create_pending_cookie(state.auth(), &pending);
+
+    if net::html::is_html_accept(&headers)?.is_some() {
+        return Ok(Response::builder()
+            .status(StatusCode::SEE_OTHER)
+            .header("location", url)
+            .header("set-cookie", pending_cookie)
+            .body(axum::body::Empty::new())?
+            .into_response());
+    }
+
+    let wrapper = rfs_lib::json::Wrapper::new(schema::auth::OidcLogin { url })
+        .with_message("proceed to the identity provider");
+
+    Ok(net::Json::new(wrapper)
+        .with_header("set-cookie", pending_cookie)
+        .into_response())
+}
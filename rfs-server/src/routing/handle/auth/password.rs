@@ -5,14 +5,19 @@ use axum::response::IntoResponse;
 
 use crate::net::{self, error};
 use crate::state::ArcShared;
+use crate::sec::audit;
 use crate::sec::authn::initiator::Initiator;
+use crate::sec::authz;
 use crate::sec::authn::password::Password;
 
 pub async fn post(
     State(state): State<ArcShared>,
     initiator: Initiator,
+    audit: audit::Context,
     axum::Json(json): axum::Json<actions::auth::CreatePassword>,
 ) -> error::Result<impl IntoResponse> {
+    authz::require_session(&initiator)?;
+
     let mut conn = state.pool().get().await?;
 
     if let Some(current) = Password::retrieve(&conn, initiator.user().id()).await? {
@@ -45,11 +50,16 @@ pub async fn post(
 
     let transaction = conn.transaction().await?;
 
-    Password::builder(initiator.user().id().clone())
+    Password::builder(initiator.user().id().clone(), json.updated)
         .with_secret(state.auth().secrets().latest())
         .build(&transaction)
         .await?;
 
+    audit.entry(audit::Event::PasswordChanged)
+        .with_initiator(&initiator)
+        .record(&transaction)
+        .await?;
+
     transaction.commit().await?;
 
     Ok(net::Json::empty()
@@ -59,8 +69,11 @@ pub async fn post(
 pub async fn delete(
     State(state): State<ArcShared>,
     initiator: Initiator,
+    audit: audit::Context,
     axum::Json(json): axum::Json<actions::auth::DeletePassword>,
 ) -> error::Result<impl IntoResponse> {
+    authz::require_session(&initiator)?;
+
     let mut conn = state.pool().get().await?;
 
     if let Some(current) = Password::retrieve(
@@ -90,6 +103,11 @@ pub async fn delete(
 
         current.delete(&transaction).await?;
 
+        audit.entry(audit::Event::PasswordRemoved)
+            .with_initiator(&initiator)
+            .record(&transaction)
+            .await?;
+
         transaction.commit().await?;
 
         Ok(net::Json::empty()
@@ -0,0 +1,99 @@
+use rfs_lib::{actions, schema};
+use axum::http::StatusCode;
+use axum::extract::State;
+use axum::response::IntoResponse;
+
+use crate::net::{self, error};
+use crate::state::ArcShared;
+use crate::sec::audit;
+use crate::sec::authn::initiator::Initiator;
+use crate::sec::authz;
+use crate::sec::authn::totp::{self, TotpHash};
+
+/// reports how many recovery codes the user has left
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: Initiator,
+) -> error::Result<impl IntoResponse> {
+    authz::require_session(&initiator)?;
+
+    let conn = state.pool().get().await?;
+
+    let (total, remaining) = TotpHash::count(&conn, initiator.user().id()).await?;
+
+    let mut wrapper = rfs_lib::json::Wrapper::new(schema::auth::RecoveryStatus {
+        total,
+        remaining,
+    });
+
+    if let Some(warning) = totp::low_recovery_warning(remaining) {
+        wrapper = wrapper.with_message(warning);
+    }
+
+    Ok(net::Json::new(wrapper))
+}
+
+/// creates a new set of recovery codes for the user
+///
+/// the codes are only returned here. regenerating will invalidate any
+/// codes that were previously created
+pub async fn post(
+    State(state): State<ArcShared>,
+    initiator: Initiator,
+    audit: audit::Context,
+    axum::Json(json): axum::Json<actions::auth::CreateRecoveryCodes>,
+) -> error::Result<impl IntoResponse> {
+    authz::require_session(&initiator)?;
+
+    let count = json.count
+        .map(|v| v as usize)
+        .unwrap_or(totp::DEFAULT_RECOVERY_CODES);
+
+    if count == 0 || count > totp::MAX_RECOVERY_CODES {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidCount")
+            .message(format!(
+                "recovery code count must be between 1 and {}",
+                totp::MAX_RECOVERY_CODES
+            )));
+    }
+
+    let mut conn = state.pool().get().await?;
+
+    if !json.regen {
+        let (total, _remaining) = TotpHash::count(&conn, initiator.user().id()).await?;
+
+        if total != 0 {
+            return Err(error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("RecoveryCodesExist")
+                .message("recovery codes already exist. regenerate them to replace the existing codes"));
+        }
+    }
+
+    let transaction = conn.transaction().await?;
+
+    let created = TotpHash::generate_batch(&transaction, initiator.user().id(), count).await?;
+
+    audit.entry(audit::Event::RecoveryCodesCreated)
+        .with_initiator(&initiator)
+        .with_details(serde_json::json!({"count": count}))
+        .record(&transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    let list = created.into_iter()
+        .map(|(totp_hash, code)| schema::auth::TotpHashCode {
+            key: totp_hash.key().to_owned(),
+            code,
+        })
+        .collect();
+
+    let wrapper = rfs_lib::json::ListWrapper::with_vec(list)
+        .with_message("created recovery codes. they will not be shown again");
+
+    Ok(net::Json::new(wrapper)
+        .with_status(StatusCode::CREATED))
+}
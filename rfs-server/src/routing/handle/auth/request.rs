@@ -1,25 +1,31 @@
 use rfs_lib::schema;
 use rfs_lib::actions;
+use std::net::SocketAddr;
+
 use axum::debug_handler;
 use axum::http::{HeaderMap, StatusCode};
-use axum::extract::State;
+use axum::http::header::USER_AGENT;
+use axum::extract::{State, ConnectInfo};
 use axum::response::IntoResponse;
 
 use crate::net::{self, error};
 use crate::state::ArcShared;
 use crate::user;
-use crate::sec::authn::{session, Authenticate, Verify};
+use crate::sec::audit;
+use crate::sec::authn::{session, lockout, ldap, Authenticate, Verify};
 use crate::sec::authn::initiator::{self, LookupError};
 
 #[debug_handler]
 pub async fn post(
     State(state): State<ArcShared>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    audit: audit::Context,
     headers: HeaderMap,
     axum::Json(json): axum::Json<actions::auth::RequestUser>,
 ) -> error::Result<impl IntoResponse> {
     let mut conn = state.pool().get().await?;
 
-    match initiator::lookup_header_map(state.auth(), &conn, &headers).await {
+    match initiator::lookup_header_map(state.auth(), &conn, &headers, &audit).await {
         Ok(_) => {
             return Ok(net::Json::empty()
                 .with_message("session already authenticated")
@@ -33,14 +39,52 @@ pub async fn post(
         }
     }
 
-    let Some(user) = user::User::query_with_username(&mut conn, &json.username).await? else {
+    lockout::check(&conn, &[lockout::Subject::Ip(addr.ip())]).await?;
+
+    let found = user::User::query_with_username(&conn, &json.username).await?;
+
+    // users that only exist in the directory are created once their
+    // password is checked in submit. the directory is not searched here so
+    // every unknown username gets the same response
+    if found.is_none() && state.auth().ldap().map(|d| *d.provision()).unwrap_or(false) {
+        let pending = ldap::link::PendingLogin::create(&conn, json.username).await?;
+        let pending_cookie = ldap::link::create_pending_cookie(state.auth(), &pending);
+
+        let json = rfs_lib::json::Wrapper::new(schema::auth::AuthMethod::Ldap)
+            .with_kind("AuthRequired")
+            .with_message("proceed with requested auth method");
+
+        return Ok(net::Json::new(json)
+            .with_header("set-cookie", pending_cookie)
+            .into_response());
+    }
+
+    let Some(user) = found else {
+        // unknown usernames count against the ip to slow down enumeration
+        lockout::record_failure(
+            &conn,
+            state.auth().lockout(),
+            &[lockout::Subject::Ip(addr.ip())]
+        ).await?;
+
         return Err(error::Error::new()
             .status(StatusCode::NOT_FOUND)
             .kind("UserNotFound")
             .message("provided username was not found"));
     };
 
+    lockout::check(&conn, &[lockout::Subject::User(user.id().clone())]).await?;
+
     let mut builder = session::Session::builder(user.id().clone());
+    builder.ip(addr.ip())
+        .lifetimes(*state.auth().session_info().lifetimes());
+
+    if let Some(user_agent) = headers.get(USER_AGENT) {
+        if let Ok(value) = user_agent.to_str() {
+            builder.user_agent(value.to_owned());
+        }
+    }
+
     let mut json = rfs_lib::json::Wrapper::new(schema::auth::AuthMethod::None)
         .with_kind("Authenticated")
         .with_message("session authenticated");
@@ -53,6 +97,20 @@ pub async fn post(
                 json = json.with_payload(schema::auth::AuthMethod::Password)
                     .with_kind("AuthRequired")
                     .with_message("proceed with requested auth method");
+            },
+            Authenticate::Ldap => {
+                builder.auth_method(session::AuthMethod::Ldap);
+
+                json = json.with_payload(schema::auth::AuthMethod::Ldap)
+                    .with_kind("AuthRequired")
+                    .with_message("proceed with requested auth method");
+            },
+            Authenticate::Oidc => {
+                builder.auth_method(session::AuthMethod::Oidc);
+
+                json = json.with_payload(schema::auth::AuthMethod::Oidc)
+                    .with_kind("AuthRequired")
+                    .with_message("proceed with identity provider login");
             }
         }
 
@@ -0,0 +1,77 @@
+use rfs_lib::actions;
+use axum::http::StatusCode;
+use axum::extract::State;
+use axum::response::IntoResponse;
+
+use crate::net::{self, error};
+use crate::state::ArcShared;
+use crate::user;
+use crate::sec::audit;
+use crate::sec::authn::password::Password;
+use crate::email::token::{EmailToken, TokenKind};
+
+pub async fn post(
+    State(state): State<ArcShared>,
+    audit: audit::Context,
+    axum::Json(json): axum::Json<actions::auth::CompleteReset>,
+) -> error::Result<impl IntoResponse> {
+    if json.updated != json.confirm {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidUpdatedPassword")
+            .message("miss match updated and confirmed"));
+    }
+
+    if json.updated.is_empty() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidUpdatedPassword")
+            .message("password cannot be empty"));
+    }
+
+    let mut conn = state.pool().get().await?;
+    let transaction = conn.transaction().await?;
+
+    let found = EmailToken::take(&transaction, &json.token, TokenKind::Reset).await?;
+
+    // the user must still exist and have the email that the token was sent to
+    let user = if let Some(token) = &found {
+        user::User::query_with_id(&transaction, &token.user_id).await?
+            .filter(|user| user.email()
+                .map(|email| *email.verified() && *email.email() == token.email)
+                .unwrap_or(false))
+    } else {
+        None
+    };
+
+    let Some(user) = user else {
+        // commit so that an expired token is still removed
+        transaction.commit().await?;
+
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidToken")
+            .message("the provided token is invalid or expired"));
+    };
+
+    Password::builder(user.id().clone(), json.updated)
+        .with_secret(state.auth().secrets().latest())
+        .build(&transaction)
+        .await?;
+
+    // anyone that was logged in with the previous password is logged out
+    let _ = transaction.execute(
+        "delete from auth_session where user_id = $1",
+        &[user.id()]
+    ).await?;
+
+    audit.entry(audit::Event::PasswordReset)
+        .with_user_id(user.id().clone())
+        .record(&transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    Ok(net::Json::empty()
+        .with_message("password reset successfully"))
+}
@@ -0,0 +1,116 @@
+pub mod request;
+pub mod complete;
+
+#[cfg(test)]
+mod test {
+    use axum::Json;
+    use axum::extract::State;
+    use axum::http::StatusCode;
+    use rfs_lib::actions::auth::{RequestReset, CompleteReset};
+
+    use crate::state::ArcShared;
+    use crate::testing::{database_with, status, audit_context, SmtpSink};
+    use crate::user;
+
+    const EMAIL: &str = "tester@example.com";
+
+    async fn setup(verified: bool) -> Option<(ArcShared, SmtpSink)> {
+        let sink = SmtpSink::start().await;
+        let state = database_with(|builder| sink.configure(builder)).await?;
+
+        let conn = state.pool().get().await.unwrap();
+        let mut builder = user::User::builder(state.ids().wait_user_id().unwrap(), "tester".into());
+        builder.set_email(EMAIL.into());
+        builder.set_email_verified(verified);
+        builder.build(&conn).await.unwrap();
+
+        Some((state, sink))
+    }
+
+    async fn request(state: &ArcShared, username: &str) -> StatusCode {
+        status(super::request::post(
+            State(state.clone()),
+            Json(RequestReset { username: username.into() }),
+        ).await)
+    }
+
+    async fn complete(state: &ArcShared, token: &str) -> StatusCode {
+        status(super::complete::post(
+            State(state.clone()),
+            audit_context(),
+            Json(CompleteReset {
+                token: token.into(),
+                updated: "updated password".into(),
+                confirm: "updated password".into(),
+            }),
+        ).await)
+    }
+
+    #[tokio::test]
+    async fn token_is_delivered_and_single_use() {
+        let Some((state, sink)) = setup(true).await else {
+            return;
+        };
+
+        assert_eq!(request(&state, "tester").await, StatusCode::OK);
+
+        let messages = sink.messages();
+
+        assert_eq!(messages.len(), 1, "reset email was not delivered");
+        assert!(messages[0].contains(EMAIL), "email was not sent to the user");
+
+        let token = sink.last_token().expect("token was not in the email");
+
+        assert_eq!(complete(&state, &token).await, StatusCode::OK);
+        assert_eq!(
+            complete(&state, &token).await,
+            StatusCode::BAD_REQUEST,
+            "token was used twice"
+        );
+
+        let conn = state.pool().get().await.unwrap();
+        let passwords: i64 = conn.query_one("select count(*) from auth_password", &[])
+            .await
+            .unwrap()
+            .get(0);
+
+        assert_eq!(passwords, 1, "password was not set");
+    }
+
+    #[tokio::test]
+    async fn expired_token_is_rejected() {
+        let Some((state, sink)) = setup(true).await else {
+            return;
+        };
+
+        assert_eq!(request(&state, "tester").await, StatusCode::OK);
+
+        let token = sink.last_token().expect("token was not in the email");
+
+        {
+            let conn = state.pool().get().await.unwrap();
+
+            conn.execute(
+                "update user_email_tokens set expires = now() - interval '1 minute'",
+                &[]
+            ).await.unwrap();
+        }
+
+        assert_eq!(
+            complete(&state, &token).await,
+            StatusCode::BAD_REQUEST,
+            "expired token was accepted"
+        );
+    }
+
+    #[tokio::test]
+    async fn unknown_and_unverified_users_get_nothing() {
+        let Some((state, sink)) = setup(false).await else {
+            return;
+        };
+
+        assert_eq!(request(&state, "tester").await, StatusCode::OK);
+        assert_eq!(request(&state, "nobody").await, StatusCode::OK);
+        assert!(sink.messages().is_empty(), "email was sent to an unverified address");
+    }
+}
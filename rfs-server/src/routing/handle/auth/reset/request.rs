@@ -0,0 +1,65 @@
+use rfs_lib::actions;
+use axum::http::StatusCode;
+use axum::extract::State;
+use axum::response::IntoResponse;
+
+use crate::net::{self, error};
+use crate::state::ArcShared;
+use crate::user;
+use crate::email::token::{EmailToken, TokenKind};
+
+pub async fn post(
+    State(state): State<ArcShared>,
+    axum::Json(json): axum::Json<actions::auth::RequestReset>,
+) -> error::Result<impl IntoResponse> {
+    let Some(mailer) = state.email() else {
+        return Err(error::Error::new()
+            .status(StatusCode::SERVICE_UNAVAILABLE)
+            .kind("EmailDisabled")
+            .message("the server is not able to send emails"));
+    };
+
+    // the response is the same regardless of the user existing so that
+    // usernames cannot be discovered with this endpoint
+    let response = net::Json::empty()
+        .with_message("if the user has a verified email a reset token was sent");
+
+    let mut conn = state.pool().get().await?;
+
+    let Some(user) = user::User::query_with_username(&conn, &json.username).await? else {
+        return Ok(response);
+    };
+
+    let Some(email) = user.email() else {
+        return Ok(response);
+    };
+
+    // only send to addresses that the user has shown they control
+    if !*email.verified() {
+        return Ok(response);
+    }
+
+    let transaction = conn.transaction().await?;
+
+    let (_, token) = EmailToken::create(
+        &transaction,
+        user.id().clone(),
+        TokenKind::Reset,
+        email.email().clone(),
+    ).await?;
+
+    mailer.send(
+        email.email(),
+        "Password reset",
+        format!(
+            "A password reset was requested for \"{}\". Use the following token to set a new password.\n\n{}\n\nThe token will expire in {} hour. If you did not request a reset this email can be ignored.",
+            user.username(),
+            token,
+            TokenKind::Reset.lifetime().num_hours(),
+        )
+    ).await?;
+
+    transaction.commit().await?;
+
+    Ok(response)
+}
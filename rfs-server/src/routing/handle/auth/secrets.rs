@@ -0,0 +1,47 @@
+use std::collections::BTreeMap;
+
+use rfs_lib::schema;
+use axum::extract::State;
+use axum::response::IntoResponse;
+
+use crate::net::{self, error};
+use crate::state::ArcShared;
+use crate::sec::authn::initiator::Initiator;
+use crate::sec::authn::password::Password;
+use crate::sec::authz;
+
+/// reports how many passwords are hashed with each secret version
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: Initiator,
+) -> error::Result<impl IntoResponse> {
+    authz::require_permission(&initiator, authz::Permission::UserManage)?;
+
+    let conn = state.pool().get().await?;
+
+    let secrets = state.auth().secrets();
+    let latest = *secrets.latest().version();
+    let mut versions: BTreeMap<u32, u64> = secrets.versions()
+        .iter()
+        .map(|version| (*version, 0))
+        .collect();
+
+    // versions that are no longer loaded will still show up if passwords
+    // are using them
+    for (version, count) in Password::version_counts(&conn).await? {
+        versions.insert(version, count);
+    }
+
+    let list = versions.into_iter()
+        .map(|(version, passwords)| schema::auth::SecretVersion {
+            version,
+            loaded: secrets.get(&version).is_some(),
+            latest: version == latest,
+            passwords,
+        })
+        .collect();
+
+    let wrapper = rfs_lib::json::ListWrapper::with_vec(list);
+
+    Ok(net::Json::new(wrapper))
+}
@@ -0,0 +1,67 @@
+use axum::extract::State;
+use axum::response::IntoResponse;
+
+use crate::net::{self, error};
+use crate::state::ArcShared;
+use crate::sec::audit;
+use crate::sec::authz;
+use crate::sec::authn::session::Session;
+use crate::sec::authn::initiator::{Initiator, Mechanism};
+
+pub mod session_id;
+
+/// the session used by the initiator. sessions cannot be managed with
+/// other mechanisms
+pub fn current_session(initiator: &Initiator) -> error::Result<&Session> {
+    match initiator.mechanism() {
+        Mechanism::Session(session) => Ok(session),
+        _ => Err(authz::session_required()),
+    }
+}
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: Initiator,
+) -> error::Result<impl IntoResponse> {
+    let current = current_session(&initiator)?;
+
+    let conn = state.pool().get().await?;
+
+    let list = Session::retrieve_user(&conn, initiator.user().id())
+        .await?
+        .into_iter()
+        .map(|session| {
+            let is_current = session.token == current.token;
+
+            session.into_schema(is_current)
+        })
+        .collect();
+
+    let wrapper = rfs_lib::json::ListWrapper::with_vec(list);
+
+    Ok(net::Json::new(wrapper))
+}
+
+pub async fn delete(
+    State(state): State<ArcShared>,
+    initiator: Initiator,
+    audit: audit::Context,
+) -> error::Result<impl IntoResponse> {
+    let current = current_session(&initiator)?;
+
+    let mut conn = state.pool().get().await?;
+    let transaction = conn.transaction().await?;
+
+    let revoked = current.delete_others(&transaction).await?;
+
+    audit.entry(audit::Event::SessionRevoked)
+        .with_initiator(&initiator)
+        .with_details(serde_json::json!({"others": revoked}))
+        .record(&transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    Ok(net::Json::empty()
+        .with_message("revoked all other sessions"))
+}
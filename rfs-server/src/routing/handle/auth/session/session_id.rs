@@ -0,0 +1,64 @@
+use axum::http::StatusCode;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+
+use crate::net::{self, error};
+use crate::state::ArcShared;
+use crate::sec::audit;
+use crate::sec::authn::session::{self, Session};
+use crate::sec::authn::initiator::Initiator;
+
+use super::current_session;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    session_id: String,
+}
+
+pub async fn delete(
+    State(state): State<ArcShared>,
+    initiator: Initiator,
+    audit: audit::Context,
+    Path(PathParams { session_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    let current = current_session(&initiator)?;
+
+    let mut conn = state.pool().get().await?;
+
+    let Some(found) = Session::retrieve_user(&conn, initiator.user().id())
+        .await?
+        .into_iter()
+        .find(|session| session.public_id() == session_id) else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("SessionNotFound")
+            .message("requested session was not found"));
+    };
+
+    let transaction = conn.transaction().await?;
+
+    found.delete(&transaction).await?;
+
+    audit.entry(audit::Event::SessionRevoked)
+        .with_initiator(&initiator)
+        .with_details(serde_json::json!({"session": session_id}))
+        .record(&transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    // revoking the current session is the same as logging out
+    if found.token == current.token {
+        let session_cookie = session::expire_session_cookie(state.auth());
+
+        return Ok(net::Json::empty()
+            .with_message("revoked session")
+            .with_header("set-cookie", session_cookie)
+            .into_response());
+    }
+
+    Ok(net::Json::empty()
+        .with_message("revoked session")
+        .into_response())
+}
@@ -1,40 +1,186 @@
 use rfs_lib::schema;
 use rfs_lib::actions;
+use std::net::SocketAddr;
+
 use axum::http::{HeaderMap, StatusCode};
-use axum::extract::State;
-use axum::response::IntoResponse;
+use axum::http::header::USER_AGENT;
+use axum::extract::{State, ConnectInfo};
+use axum::response::{IntoResponse, Response};
+use deadpool_postgres::Object;
 
 use crate::net::{self, error};
 use crate::state::ArcShared;
-use crate::sec::authn::{totp, password};
+use crate::user;
+use crate::sec::audit;
+use crate::sec::authn::{totp, password, lockout, ldap, session};
 use crate::sec::authn::session::{VerifyMethod, AuthMethod};
 use crate::sec::authn::initiator::{self, LookupError};
 
+fn invalid_auth_method() -> error::Error {
+    error::Error::new()
+        .status(StatusCode::UNAUTHORIZED)
+        .kind("InvalidAuthMethod")
+        .message("invalid auth method provided")
+}
+
+/// completes a login for a username that only exists in the directory
+///
+/// the user, its directory link, its groups and the session are only
+/// created once the directory accepts the password
+async fn submit_pending(
+    state: &ArcShared,
+    addr: SocketAddr,
+    audit: &audit::Context,
+    headers: &HeaderMap,
+    mut conn: Object,
+    token: &str,
+    json: actions::auth::SubmitAuth,
+) -> error::Result<Response> {
+    let Some(directory) = state.auth().ldap().filter(|d| *d.provision()) else {
+        return Err(invalid_auth_method());
+    };
+
+    let actions::auth::SubmitAuth::Password(given) = json else {
+        return Err(invalid_auth_method());
+    };
+
+    let subjects = [lockout::Subject::Ip(addr.ip())];
+
+    lockout::check(&conn, &subjects).await?;
+
+    let Some(pending) = ldap::link::PendingLogin::retrieve(&conn, token).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidLdapPending")
+            .message("the login request was not found or has expired"));
+    };
+
+    let Some(entry) = directory.authenticate(&pending.username, &given).await? else {
+        lockout::record_failure(&conn, state.auth().lockout(), &subjects).await?;
+
+        audit.entry(audit::Event::LoginFailed)
+            .with_details(serde_json::json!({
+                "reason": "invalid ldap password",
+                "username": pending.username,
+            }))
+            .record(&conn)
+            .await?;
+
+        return Err(error::Error::new()
+            .status(StatusCode::UNAUTHORIZED)
+            .kind("InvalidPassword")
+            .message("provided password is invalid"));
+    };
+
+    let transaction = conn.transaction().await?;
+
+    if user::username_check(&transaction, &pending.username).await?.is_some() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("UsernameExists")
+            .message("a local user already has the requested username"));
+    }
+
+    let mut builder = user::User::builder(
+        state.ids().wait_user_id()?,
+        pending.username.clone()
+    );
+
+    if let Some(email) = entry.email {
+        if user::email_check(&transaction, &email).await?.is_none() {
+            builder.set_email(email);
+        }
+    }
+
+    let user = builder.build(&transaction).await?;
+
+    ldap::link::Link::create(&transaction, user.id().clone(), entry.dn).await?;
+
+    if *directory.sync_groups() {
+        ldap::link::sync_groups(&transaction, user.id(), &entry.groups).await?;
+    }
+
+    pending.delete(&transaction).await?;
+
+    // a new user has nothing to verify with so the session is active
+    let mut builder = session::Session::builder(user.id().clone());
+    builder.ip(addr.ip())
+        .auth_method(AuthMethod::Ldap)
+        .authenticated(true)
+        .lifetimes(*state.auth().session_info().lifetimes());
+
+    if let Some(user_agent) = headers.get(USER_AGENT) {
+        if let Ok(value) = user_agent.to_str() {
+            builder.user_agent(value.to_owned());
+        }
+    }
+
+    let session = builder.build(&transaction).await?;
+
+    audit.entry(audit::Event::Login)
+        .with_session(&session)
+        .with_details(serde_json::json!({"method": "ldap", "provisioned": true}))
+        .record(&transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    let session_cookie = session::create_session_cookie(state.auth(), &session);
+    let pending_cookie = ldap::link::expire_pending_cookie(state.auth());
+
+    let json_root = rfs_lib::json::Wrapper::new(schema::auth::VerifyMethod::None)
+        .with_message("session authenticated");
+
+    Ok(net::Json::new(json_root)
+        .with_header("set-cookie", session_cookie)
+        .with_header("set-cookie", pending_cookie)
+        .into_response())
+}
+
 pub async fn post(
     State(state): State<ArcShared>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    audit: audit::Context,
     headers: HeaderMap,
     axum::Json(json): axum::Json<actions::auth::SubmitAuth>,
 ) -> error::Result<impl IntoResponse> {
     let mut conn = state.pool().get().await?;
 
-    let mut session = match initiator::lookup_header_map(state.auth(), &conn, &headers).await {
+    let mut session = match initiator::lookup_header_map(state.auth(), &conn, &headers, &audit).await {
         Ok(initiator) => {
             return Ok(net::Json::empty()
                 .with_message("session already authenticated")
                 .into_response());
         },
         Err(err) => match err {
-            LookupError::SessionUnauthenticated(session) => session,
+            LookupError::SessionUnauthenticated(session) => *session,
             LookupError::SessionUnverified(_) => {
                 return Err(error::Error::new()
                     .message("session already authenticated, must verify"));
             },
+            LookupError::MechanismNotFound => {
+                if let Some(token) = net::cookie::find(&headers, ldap::link::PENDING_COOKIE)? {
+                    return submit_pending(&state, addr, &audit, &headers, conn, token, json).await;
+                }
+
+                return Err(LookupError::MechanismNotFound.into());
+            },
             _ => {
                 return Err(err.into());
             }
         }
     };
 
+    let subjects = [
+        lockout::Subject::User(session.user_id.clone()),
+        lockout::Subject::Ip(addr.ip()),
+    ];
+
+    lockout::check(&conn, &subjects).await?;
+
+    // changes from the directory are saved with the session
+    let mut ldap_sync = None;
+
     match json {
         actions::auth::SubmitAuth::None => match session.auth_method {
             AuthMethod::None => {
@@ -62,13 +208,87 @@ pub async fn post(
                         .source("password secret version not found. unable verify user password"));
                 };
 
-                if !user_password.verify(given, secret)? {
+                if !user_password.verify(&given, secret)? {
+                    lockout::record_failure(&conn, state.auth().lockout(), &subjects).await?;
+
+                    audit.entry(audit::Event::LoginFailed)
+                        .with_session(&session)
+                        .with_details(serde_json::json!({"reason": "invalid password"}))
+                        .record(&conn)
+                        .await?;
+
+                    return Err(error::Error::new()
+                        .status(StatusCode::UNAUTHORIZED)
+                        .kind("InvalidPassword")
+                        .message("provided password is invalid"));
+                }
+
+                // passwords hashed with an older secret are moved to the
+                // latest now that the plain text is available
+                let latest = state.auth().secrets().latest();
+
+                if latest.version() != user_password.version() {
+                    password::Password::builder(session.user_id.clone(), given)
+                        .with_secret(latest)
+                        .build(&conn)
+                        .await?;
+                }
+
+                session.authenticated = true;
+            },
+            AuthMethod::Ldap => {
+                let Some(directory) = state.auth().ldap() else {
+                    return Err(error::Error::new()
+                        .source("session required ldap but ldap is not configured"));
+                };
+
+                let Some(link) = ldap::link::Link::retrieve(&conn, &session.user_id).await? else {
+                    return Err(error::Error::new()
+                        .source("session required ldap but user ldap link was not found"));
+                };
+
+                let Some(user) = user::User::query_with_id(&conn, &session.user_id).await? else {
+                    return Err(error::Error::new()
+                        .source("session user was not found"));
+                };
+
+                let Some(entry) = directory.authenticate(user.username(), &given).await? else {
+                    lockout::record_failure(&conn, state.auth().lockout(), &subjects).await?;
+
+                    audit.entry(audit::Event::LoginFailed)
+                        .with_session(&session)
+                        .with_details(serde_json::json!({"reason": "invalid ldap password"}))
+                        .record(&conn)
+                        .await?;
+
                     return Err(error::Error::new()
                         .status(StatusCode::UNAUTHORIZED)
                         .kind("InvalidPassword")
                         .message("provided password is invalid"));
+                };
+
+                // the username was found on a different account than the
+                // one that was linked. the link is left as is since the
+                // local user belongs to the original account
+                if entry.dn != link.dn {
+                    audit.entry(audit::Event::LoginFailed)
+                        .with_session(&session)
+                        .with_details(serde_json::json!({
+                            "reason": "ldap dn changed",
+                            "linked_dn": link.dn,
+                            "found_dn": entry.dn,
+                        }))
+                        .record(&conn)
+                        .await?;
+
+                    return Err(error::Error::new()
+                        .status(StatusCode::FORBIDDEN)
+                        .kind("LdapAccountChanged")
+                        .message("the directory account does not match the account linked to the user"));
                 }
 
+                ldap_sync = Some((directory, link, entry));
+
                 session.authenticated = true;
             },
             _ => {
@@ -101,11 +321,37 @@ pub async fn post(
         }
     };
 
+    // the session is now active so it gets the full lifetime
+    if session.authenticated && session.verified {
+        session.expires = state.auth()
+            .session_info()
+            .lifetimes()
+            .active_expires(&session.issued_on, &chrono::Utc::now());
+    }
+
     {
         let transaction = conn.transaction().await?;
 
+        if let Some((directory, mut link, entry)) = ldap_sync {
+            link.touch(&transaction).await?;
+
+            if *directory.sync_groups() {
+                ldap::link::sync_groups(&transaction, &session.user_id, &entry.groups).await?;
+            }
+        }
+
         session.update(&transaction).await?;
 
+        // failures are only forgotten once the login is complete
+        if session.verified {
+            lockout::clear(&transaction, &subjects[0]).await?;
+
+            audit.entry(audit::Event::Login)
+                .with_session(&session)
+                .record(&transaction)
+                .await?;
+        }
+
         transaction.commit().await?;
     }
 
@@ -114,3 +360,298 @@ pub async fn post(
 
     Ok(net::Json::new(json_root).into_response())
 }
+
+#[cfg(test)]
+mod test {
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+
+    use axum::extract::{State, ConnectInfo};
+    use axum::http::{HeaderMap, StatusCode};
+    use rfs_lib::actions;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use crate::state::ArcShared;
+    use crate::testing::{database_with, status, audit_context};
+    use crate::user;
+    use crate::sec::authn::{ldap, session};
+    use crate::sec::authn::session::AuthMethod;
+
+    const USERNAME: &str = "tester";
+    const PASSWORD: &str = "directory password";
+    const LINKED_DN: &str = "uid=tester,ou=people,dc=example";
+
+    /// the only account in the mock directory
+    struct Account {
+        dn: String,
+        password: String,
+    }
+
+    /// an ldap server that knows enough of the protocol to answer the
+    /// binds and searches made by a login. every search finds the account
+    #[derive(Clone)]
+    struct MockDirectory {
+        url: String,
+        account: Arc<Mutex<Account>>,
+    }
+
+    impl MockDirectory {
+        async fn start() -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let directory = MockDirectory {
+                url: format!("ldap://{}", listener.local_addr().unwrap()),
+                account: Arc::new(Mutex::new(Account {
+                    dn: LINKED_DN.to_owned(),
+                    password: PASSWORD.to_owned(),
+                })),
+            };
+
+            let cloned = directory.clone();
+
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(cloned.clone().serve(stream));
+                }
+            });
+
+            directory
+        }
+
+        /// moves the account to a different dn
+        fn set_dn(&self, dn: &str) {
+            self.account.lock().unwrap().dn = dn.to_owned();
+        }
+
+        async fn serve(self, mut stream: TcpStream) {
+            while let Some(message) = read_tlv(&mut stream).await {
+                let Some((_, body, _)) = split_tlv(&message) else {
+                    return;
+                };
+                let Some((_, msg_id, rest)) = split_tlv(body) else {
+                    return;
+                };
+                let Some((op, content, _)) = split_tlv(rest) else {
+                    return;
+                };
+
+                let response = match op {
+                    // bind request
+                    0x60 => {
+                        let (_, _version, rest) = split_tlv(content).unwrap();
+                        let (_, dn, rest) = split_tlv(rest).unwrap();
+                        let (_, password, _) = split_tlv(rest).unwrap();
+
+                        let code = {
+                            let account = self.account.lock().unwrap();
+
+                            if dn == account.dn.as_bytes() && password == account.password.as_bytes() {
+                                0
+                            } else {
+                                49
+                            }
+                        };
+
+                        message_tlv(msg_id, &tlv(0x61, &result(code)))
+                    },
+                    // search request
+                    0x63 => {
+                        let dn = self.account.lock().unwrap().dn.clone();
+                        let mut response = message_tlv(
+                            msg_id,
+                            &tlv(0x64, &[tlv(0x04, dn.as_bytes()), tlv(0x30, &[])].concat())
+                        );
+                        response.extend(message_tlv(msg_id, &tlv(0x65, &result(0))));
+                        response
+                    },
+                    _ => return,
+                };
+
+                if stream.write_all(&response).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut rtn = vec![tag];
+        let len = content.len();
+
+        if len < 0x80 {
+            rtn.push(len as u8);
+        } else {
+            rtn.extend([0x82, (len >> 8) as u8, len as u8]);
+        }
+
+        rtn.extend_from_slice(content);
+        rtn
+    }
+
+    fn message_tlv(msg_id: &[u8], op: &[u8]) -> Vec<u8> {
+        tlv(0x30, &[tlv(0x02, msg_id), op.to_vec()].concat())
+    }
+
+    /// the result code, matched dn and diagnostic message of a response
+    fn result(code: u8) -> Vec<u8> {
+        [tlv(0x0a, &[code]), tlv(0x04, &[]), tlv(0x04, &[])].concat()
+    }
+
+    /// splits the first value from the given bytes giving its tag, its
+    /// contents and the bytes after it
+    fn split_tlv(bytes: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+        let (&tag, rest) = bytes.split_first()?;
+        let (&first, mut rest) = rest.split_first()?;
+        let mut len = first as usize;
+
+        if first & 0x80 != 0 {
+            let count = (first & 0x7f) as usize;
+            len = 0;
+
+            for &byte in rest.get(..count)? {
+                len = (len << 8) | byte as usize;
+            }
+
+            rest = &rest[count..];
+        }
+
+        let content = rest.get(..len)?;
+
+        Some((tag, content, &rest[len..]))
+    }
+
+    /// reads a full message from the stream
+    async fn read_tlv(stream: &mut TcpStream) -> Option<Vec<u8>> {
+        let mut header = vec![0u8; 2];
+        stream.read_exact(&mut header).await.ok()?;
+
+        let mut len = header[1] as usize;
+
+        if header[1] & 0x80 != 0 {
+            let mut bytes = vec![0u8; (header[1] & 0x7f) as usize];
+            stream.read_exact(&mut bytes).await.ok()?;
+
+            len = bytes.iter().fold(0, |acc, byte| (acc << 8) | *byte as usize);
+            header.extend(bytes);
+        }
+
+        let mut content = vec![0u8; len];
+        stream.read_exact(&mut content).await.ok()?;
+
+        header.extend(content);
+
+        Some(header)
+    }
+
+    /// creates a user linked to the directory and a session that needs the
+    /// directory password. gives back the session cookie
+    async fn setup() -> Option<(ArcShared, MockDirectory, String)> {
+        let directory = MockDirectory::start().await;
+
+        let state = database_with(|builder| {
+            builder.sec()
+                .ldap()
+                .set_url(directory.url.clone())
+                .set_base_dn(String::from("dc=example"));
+        }).await?;
+
+        let conn = state.pool().get().await.unwrap();
+        let user = user::User::builder(state.ids().wait_user_id().unwrap(), USERNAME.to_owned())
+            .build(&conn)
+            .await
+            .unwrap();
+
+        ldap::link::Link::create(&conn, user.id().clone(), LINKED_DN.to_owned())
+            .await
+            .unwrap();
+
+        let mut builder = session::Session::builder(user.id().clone());
+        builder.auth_method(AuthMethod::Ldap)
+            .lifetimes(*state.auth().session_info().lifetimes());
+
+        let Ok(session) = builder.build(&conn).await else {
+            panic!("failed to create session");
+        };
+
+        let hash = session::create_hash(state.auth(), &session.token);
+        let cookie = format!("session_id={}", session::encode_base64(&session.token, hash));
+
+        Some((state, directory, cookie))
+    }
+
+    async fn submit(state: &ArcShared, cookie: &str, password: &str) -> StatusCode {
+        let mut headers = HeaderMap::new();
+        headers.insert("cookie", cookie.parse().unwrap());
+
+        let result = super::post(
+            State(state.clone()),
+            ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))),
+            audit_context(),
+            headers,
+            axum::Json(actions::auth::SubmitAuth::Password(password.to_owned())),
+        ).await;
+
+        status(result)
+    }
+
+    async fn linked_dn(state: &ArcShared) -> String {
+        let conn = state.pool().get().await.unwrap();
+
+        conn.query_one("select dn from auth_ldap", &[])
+            .await
+            .unwrap()
+            .get(0)
+    }
+
+    #[tokio::test]
+    async fn ldap_login_with_linked_dn() {
+        let Some((state, _directory, cookie)) = setup().await else {
+            return;
+        };
+
+        assert_eq!(
+            submit(&state, &cookie, "wrong password").await,
+            StatusCode::UNAUTHORIZED,
+            "invalid directory password was accepted"
+        );
+        assert_eq!(
+            submit(&state, &cookie, PASSWORD).await,
+            StatusCode::OK,
+            "valid directory password was rejected"
+        );
+    }
+
+    #[tokio::test]
+    async fn ldap_login_rejects_changed_dn() {
+        let Some((state, directory, cookie)) = setup().await else {
+            return;
+        };
+
+        let other_dn = "uid=tester,ou=contractors,dc=example";
+
+        directory.set_dn(other_dn);
+
+        assert_eq!(
+            submit(&state, &cookie, PASSWORD).await,
+            StatusCode::FORBIDDEN,
+            "login succeeded for a different directory account"
+        );
+        assert_eq!(linked_dn(&state).await, LINKED_DN, "the link was moved to the other account");
+
+        let conn = state.pool().get().await.unwrap();
+        let details: serde_json::Value = conn.query_one(
+            "select details from audit_log where event = 'login_failed'",
+            &[]
+        ).await.unwrap().get(0);
+
+        assert_eq!(details["reason"], "ldap dn changed");
+        assert_eq!(details["found_dn"], other_dn);
+
+        let authenticated: bool = conn.query_one("select authenticated from auth_session", &[])
+            .await
+            .unwrap()
+            .get(0);
+
+        assert!(!authenticated, "session was authenticated");
+    }
+}
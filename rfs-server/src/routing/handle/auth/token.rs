@@ -0,0 +1,38 @@
+use rfs_lib::schema;
+use axum::http::StatusCode;
+use axum::extract::State;
+use axum::response::IntoResponse;
+
+use crate::net::{self, error};
+use crate::state::ArcShared;
+use crate::sec::authn::access::AccessToken;
+use crate::sec::authn::initiator::Initiator;
+
+use super::session::current_session;
+
+/// creates a short lived bearer token for the current session
+///
+/// the token is only valid while the session is and is not able to create
+/// other tokens
+pub async fn post(
+    State(state): State<ArcShared>,
+    initiator: Initiator,
+) -> error::Result<impl IntoResponse> {
+    let session = current_session(&initiator)?;
+
+    let conn = state.pool().get().await?;
+
+    let (token, bearer) = AccessToken::create(
+        &conn,
+        session,
+        *state.auth().access_lifetime()
+    ).await?;
+
+    let wrapper = rfs_lib::json::Wrapper::new(schema::auth::AccessToken {
+        token: bearer,
+        expires: token.expires,
+    }).with_message("access token created");
+
+    Ok(net::Json::new(wrapper)
+        .with_status(StatusCode::CREATED))
+}
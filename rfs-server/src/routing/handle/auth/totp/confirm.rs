@@ -0,0 +1,86 @@
+use std::net::SocketAddr;
+
+use rfs_lib::actions;
+use axum::http::StatusCode;
+use axum::extract::{State, ConnectInfo};
+use axum::response::IntoResponse;
+
+use crate::net::{self, error};
+use crate::state::ArcShared;
+use crate::sec::audit;
+use crate::sec::authn::initiator::Initiator;
+use crate::sec::authz;
+use crate::sec::authn::totp::{self, Totp, TotpCheck, AttemptResult};
+
+pub async fn post(
+    State(state): State<ArcShared>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    initiator: Initiator,
+    audit: audit::Context,
+    axum::Json(json): axum::Json<actions::auth::ConfirmTotp>,
+) -> error::Result<impl IntoResponse> {
+    authz::require_session(&initiator)?;
+
+    let mut conn = state.pool().get().await?;
+
+    let Some(mut totp) = Totp::retrieve(&conn, initiator.user().id()).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("TotpNotFound")
+            .message("no totp available"));
+    };
+
+    if *totp.verified() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("TotpVerified")
+            .message("totp is already verified"));
+    }
+
+    let check = totp.verify(&json.code, state.auth().totp_window())?;
+
+    let TotpCheck::Valid(time_step) = check else {
+        totp::record_attempt(
+            &conn,
+            initiator.user().id(),
+            None,
+            AttemptResult::from(&check),
+            Some(addr.ip()),
+        ).await?;
+
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidCode")
+            .message("invalid totp code provided"));
+    };
+
+    let transaction = conn.transaction().await?;
+
+    // the code used to confirm cannot be used again to verify a session
+    if !totp.accept_step(&transaction, time_step).await? {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("CodeUsed")
+            .message("totp code has already been used"));
+    }
+
+    totp.set_verified(&transaction).await?;
+
+    totp::record_attempt(
+        &transaction,
+        initiator.user().id(),
+        Some(time_step),
+        AttemptResult::Accepted,
+        Some(addr.ip()),
+    ).await?;
+
+    audit.entry(audit::Event::TotpEnabled)
+        .with_initiator(&initiator)
+        .record(&transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    Ok(net::Json::empty()
+        .with_message("totp verified"))
+}
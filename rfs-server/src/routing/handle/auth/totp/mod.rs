@@ -1,3 +1,4 @@
+use rfs_lib::schema;
 use rfs_lib::actions;
 use axum::http::StatusCode;
 use axum::extract::State;
@@ -5,21 +6,45 @@ use axum::response::IntoResponse;
 
 use crate::net::{self, error};
 use crate::state::ArcShared;
+use crate::sec::audit;
 use crate::sec::authn::initiator::Initiator;
+use crate::sec::authz;
 use crate::sec::authn::totp::Totp;
 
+pub mod confirm;
+
+/// the issuer shown in authenticator apps
+pub const TOTP_ISSUER: &str = "rfs";
+
+/// creates a qr code svg for the given data
+fn qr_svg(data: &str) -> error::Result<String> {
+    use qrcode::render::svg;
+
+    let code = qrcode::QrCode::new(data.as_bytes())?;
+
+    Ok(code.render::<svg::Color>()
+        .min_dimensions(200, 200)
+        .build())
+}
+
 pub async fn post(
     State(state): State<ArcShared>,
     initiator: Initiator,
     axum::Json(json): axum::Json<actions::auth::CreateTotp>,
 ) -> error::Result<impl IntoResponse> {
+    authz::require_session(&initiator)?;
+
     let mut conn = state.pool().get().await?;
 
-    if let Some(existing) = Totp::retrieve(&conn, initiator.user().id()).await? {
-        return Err(error::Error::new()
-            .status(StatusCode::BAD_REQUEST)
-            .kind("TotpExists")
-            .message("totp already exists"));
+    let existing = Totp::retrieve(&conn, initiator.user().id()).await?;
+
+    if let Some(totp) = &existing {
+        if *totp.verified() {
+            return Err(error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("TotpExists")
+                .message("totp already exists"));
+        }
     }
 
     let mut new_totp = Totp::builder(initiator.user().id().clone());
@@ -53,19 +78,38 @@ pub async fn post(
 
     let transaction = conn.transaction().await?;
 
-    new_totp.build(&transaction).await?;
+    // a pending totp is replaced since the user never confirmed it
+    if let Some(pending) = existing {
+        pending.delete(&transaction).await?;
+    }
+
+    let totp = new_totp.build(&transaction).await?;
+
+    let uri = totp.provisioning_uri(TOTP_ISSUER, initiator.user().username());
+    let qr_svg = qr_svg(&uri)?;
 
     transaction.commit().await?;
 
-    Ok(net::Json::empty()
-       .with_status(StatusCode::CREATED)
-       .with_message("create totp"))
+    let wrapper = rfs_lib::json::Wrapper::new(schema::auth::TotpEnrollment {
+        algo: totp.algo().as_str().to_owned(),
+        digits: *totp.digits(),
+        step: *totp.step(),
+        secret: totp.secret_base32(),
+        uri,
+        qr_svg,
+    }).with_message("created totp. confirm with a code to enable");
+
+    Ok(net::Json::new(wrapper)
+       .with_status(StatusCode::CREATED))
 }
 
 pub async fn delete(
     State(state): State<ArcShared>,
     initiator: Initiator,
+    audit: audit::Context,
 ) -> error::Result<impl IntoResponse> {
+    authz::require_session(&initiator)?;
+
     let mut conn = state.pool().get().await?;
 
     if let Some(totp) = Totp::retrieve(&conn, initiator.user().id()).await? {
@@ -73,6 +117,11 @@ pub async fn delete(
 
         totp.delete(&transaction).await?;
 
+        audit.entry(audit::Event::TotpDisabled)
+            .with_initiator(&initiator)
+            .record(&transaction)
+            .await?;
+
         transaction.commit().await?;
 
         Ok(net::Json::empty()
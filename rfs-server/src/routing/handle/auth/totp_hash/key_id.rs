@@ -1,4 +1,4 @@
-use rfs_lib::actions;
+use rfs_lib::{actions, schema};
 use axum::http::StatusCode;
 use axum::extract::{Path, State};
 use axum::response::{IntoResponse};
@@ -7,6 +7,7 @@ use serde::Deserialize;
 use crate::net::{self, error};
 use crate::state::ArcShared;
 use crate::sec::authn::initiator::Initiator;
+use crate::sec::authz;
 use crate::sec::authn::totp::TotpHash;
 
 #[derive(Deserialize)]
@@ -20,6 +21,8 @@ pub async fn put(
     Path(TotpHashParams { key_id }): Path<TotpHashParams>,
     axum::Json(json): axum::Json<actions::auth::UpdateTotpHash>,
 ) -> error::Result<impl IntoResponse> {
+    authz::require_session(&initiator)?;
+
     let mut conn = state.pool().get().await?;
 
     let Some(mut totp_hash) = TotpHash::retrieve_key(
@@ -37,9 +40,11 @@ pub async fn put(
         totp_hash.set_key(new_key);
     }
 
-    if json.regen {
-        totp_hash.regen_hash(None)?;
-    }
+    let code = if json.regen {
+        Some(totp_hash.regen_hash(None)?)
+    } else {
+        None
+    };
 
     let transaction = conn.transaction().await?;
 
@@ -52,8 +57,19 @@ pub async fn put(
 
     transaction.commit().await?;
 
-    Ok(net::Json::empty()
-        .with_message("updated totp hash"))
+    if let Some(code) = code {
+        let wrapper = rfs_lib::json::Wrapper::new(schema::auth::TotpHashCode {
+            key: totp_hash.key().to_owned(),
+            code,
+        })
+            .with_message("updated totp hash");
+
+        Ok(net::Json::new(wrapper).into_response())
+    } else {
+        Ok(net::Json::empty()
+            .with_message("updated totp hash")
+            .into_response())
+    }
 }
 
 pub async fn delete(
@@ -61,6 +77,8 @@ pub async fn delete(
     initiator: Initiator,
     Path(TotpHashParams { key_id }): Path<TotpHashParams>,
 ) -> error::Result<impl IntoResponse> {
+    authz::require_session(&initiator)?;
+
     let mut conn = state.pool().get().await?;
 
     let Some(totp_hash) = TotpHash::retrieve_key(
@@ -1,4 +1,4 @@
-use rfs_lib::actions;
+use rfs_lib::{actions, schema};
 use axum::http::StatusCode;
 use axum::extract::State;
 use axum::response::IntoResponse;
@@ -6,6 +6,7 @@ use axum::response::IntoResponse;
 use crate::net::{self, error};
 use crate::state::ArcShared;
 use crate::sec::authn::initiator::Initiator;
+use crate::sec::authz;
 use crate::sec::authn::totp::TotpHash;
 
 pub mod key_id;
@@ -15,15 +16,24 @@ pub async fn post(
     initiator: Initiator,
     axum::Json(json): axum::Json<actions::auth::CreateTotpHash>,
 ) -> error::Result<impl IntoResponse> {
+    authz::require_session(&initiator)?;
+
     let mut conn = state.pool().get().await?;
     let transaction = conn.transaction().await?;
 
-    TotpHash::builder(initiator.user().id().clone(), json.key)
+    let (totp_hash, code) = TotpHash::builder(initiator.user().id().clone(), json.key)
         .build(&transaction)
         .await?;
 
-    Ok(net::Json::empty()
-        .with_status(StatusCode::CREATED)
-        .with_message("created totp hash"))
+    transaction.commit().await?;
+
+    let wrapper = rfs_lib::json::Wrapper::new(schema::auth::TotpHashCode {
+        key: totp_hash.key().to_owned(),
+        code,
+    })
+        .with_message("created totp hash");
+
+    Ok(net::Json::new(wrapper)
+        .with_status(StatusCode::CREATED))
 }
 
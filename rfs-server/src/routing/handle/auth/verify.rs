@@ -1,29 +1,34 @@
 use rfs_lib::actions::auth::SubmitVerify;
+use std::net::SocketAddr;
+
 use axum::http::{HeaderMap, StatusCode};
-use axum::extract::State;
+use axum::extract::{State, ConnectInfo};
 use axum::response::IntoResponse;
 
 use crate::net::{self, error};
 use crate::state::ArcShared;
-use crate::sec::authn::totp;
+use crate::sec::audit;
+use crate::sec::authn::{totp, lockout};
 use crate::sec::authn::initiator::{self, LookupError};
 use crate::sec::authn::session::VerifyMethod;
 
 pub async fn post(
     State(state): State<ArcShared>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    audit: audit::Context,
     headers: HeaderMap,
     axum::Json(json): axum::Json<SubmitVerify>,
 ) -> error::Result<impl IntoResponse> {
     let mut conn = state.pool().get().await?;
 
-    let mut session = match initiator::lookup_header_map(state.auth(), &conn, &headers).await {
+    let mut session = match initiator::lookup_header_map(state.auth(), &conn, &headers, &audit).await {
         Ok(initiator) => {
             return Ok(net::Json::empty()
                 .with_message("session already verified")
                 .into_response());
         },
         Err(err) => match err {
-            LookupError::SessionUnverified(session) => session,
+            LookupError::SessionUnverified(session) => *session,
             LookupError::SessionUnauthenticated(_) => {
                 return Err(error::Error::new()
                     .message("session must be authenticated"));
@@ -34,6 +39,15 @@ pub async fn post(
         }
     };
 
+    let subjects = [
+        lockout::Subject::User(session.user_id.clone()),
+        lockout::Subject::Ip(addr.ip()),
+    ];
+
+    lockout::check(&conn, &subjects).await?;
+
+    let mut warning = None;
+
     match json {
         SubmitVerify::None => match session.verify_method {
             VerifyMethod::None => {},
@@ -46,18 +60,65 @@ pub async fn post(
         },
         SubmitVerify::Totp(code) => match session.verify_method {
             VerifyMethod::Totp => {
-                use rust_otp::VerifyResult;
+                use totp::{TotpCheck, AttemptResult};
 
-                let Some(totp) = totp::Totp::retrieve(&conn, &session.user_id).await? else {
+                let Some(mut totp) = totp::Totp::retrieve(&conn, &session.user_id).await? else {
                     return Err(error::Error::new()
                         .source("session required totp verify but user totp was not found"));
                 };
 
-                let result = totp.verify(&code)?;
+                let check = match totp.verify(&code, state.auth().totp_window())? {
+                    TotpCheck::Valid(time_step) => {
+                        // another request may have used the same code since
+                        // it was retrieved
+                        if totp.accept_step(&conn, time_step).await? {
+                            TotpCheck::Valid(time_step)
+                        } else {
+                            TotpCheck::Replayed(time_step)
+                        }
+                    },
+                    check => check,
+                };
+
+                let time_step = match &check {
+                    TotpCheck::Valid(time_step) |
+                    TotpCheck::Replayed(time_step) => Some(*time_step),
+                    TotpCheck::Invalid => None,
+                };
+
+                totp::record_attempt(
+                    &conn,
+                    &session.user_id,
+                    time_step,
+                    AttemptResult::from(&check),
+                    Some(addr.ip()),
+                ).await?;
+
+                match check {
+                    TotpCheck::Valid(_) => {},
+                    TotpCheck::Replayed(_) => {
+                        lockout::record_failure(&conn, state.auth().lockout(), &subjects).await?;
+
+                        audit.entry(audit::Event::LoginFailed)
+                            .with_session(&session)
+                            .with_details(serde_json::json!({"reason": "totp code used"}))
+                            .record(&conn)
+                            .await?;
+
+                        return Err(error::Error::new()
+                            .status(StatusCode::UNAUTHORIZED)
+                            .kind("CodeUsed")
+                            .message("totp code has already been used"));
+                    },
+                    TotpCheck::Invalid => {
+                        lockout::record_failure(&conn, state.auth().lockout(), &subjects).await?;
+
+                        audit.entry(audit::Event::LoginFailed)
+                            .with_session(&session)
+                            .with_details(serde_json::json!({"reason": "invalid totp code"}))
+                            .record(&conn)
+                            .await?;
 
-                match result {
-                    VerifyResult::Valid => {},
-                    _ => {
                         return Err(error::Error::new()
                             .status(StatusCode::UNAUTHORIZED)
                             .kind("InvalidCode")
@@ -79,6 +140,14 @@ pub async fn post(
                     &session.user_id, 
                     &hash
                 ).await? else {
+                    lockout::record_failure(&conn, state.auth().lockout(), &subjects).await?;
+
+                    audit.entry(audit::Event::LoginFailed)
+                        .with_session(&session)
+                        .with_details(serde_json::json!({"reason": "invalid recovery code"}))
+                        .record(&conn)
+                        .await?;
+
                     return Err(error::Error::new()
                         .status(StatusCode::UNAUTHORIZED)
                         .kind("TotpHashInvalid")
@@ -86,6 +155,14 @@ pub async fn post(
                 };
 
                 if *totp_hash.used() || !totp_hash.verify(hash) {
+                    lockout::record_failure(&conn, state.auth().lockout(), &subjects).await?;
+
+                    audit.entry(audit::Event::LoginFailed)
+                        .with_session(&session)
+                        .with_details(serde_json::json!({"reason": "invalid recovery code"}))
+                        .record(&conn)
+                        .await?;
+
                     return Err(error::Error::new()
                         .status(StatusCode::UNAUTHORIZED)
                         .kind("TotpHashInvalid")
@@ -101,6 +178,10 @@ pub async fn post(
 
                     transaction.commit().await?;
                 }
+
+                let (_total, remaining) = totp::TotpHash::count(&conn, &session.user_id).await?;
+
+                warning = totp::low_recovery_warning(remaining);
             },
             _ => {
                 return Err(error::Error::new()
@@ -111,6 +192,12 @@ pub async fn post(
         }
     }
 
+    // the session is now active so it gets the full lifetime
+    session.expires = state.auth()
+        .session_info()
+        .lifetimes()
+        .active_expires(&session.issued_on, &chrono::Utc::now());
+
     {
         let transaction = conn.transaction().await?;
 
@@ -118,10 +205,23 @@ pub async fn post(
 
         session.update(&transaction).await?;
 
+        lockout::clear(&transaction, &subjects[0]).await?;
+
+        audit.entry(audit::Event::Login)
+            .with_session(&session)
+            .record(&transaction)
+            .await?;
+
         transaction.commit().await?;
     }
 
+    let message = if let Some(warning) = warning {
+        format!("session verified. {}", warning)
+    } else {
+        String::from("session verified")
+    };
+
     Ok(net::Json::empty()
-        .with_message("session verified")
+        .with_message(message)
         .into_response())
 }
@@ -0,0 +1,66 @@
+use axum::http::StatusCode;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use rfs_lib::ids;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::sec::authz;
+use crate::fs;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    fs_id: ids::FSId,
+    group_id: ids::GroupId,
+}
+
+pub async fn delete(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { fs_id, group_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    let mut conn = state.pool().get().await?;
+
+    let Some(item) = fs::Item::retrieve(&conn, &fs_id).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    };
+
+    let ability = authz::initiator_fs_ability(&conn, &initiator, &item).await?;
+
+    if ability.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    }
+
+    if !authz::has_ability(&ability, authz::Ability::Manage) {
+        return Err(authz::permission_denied());
+    }
+
+    let transaction = conn.transaction().await?;
+
+    if !authz::access::drop_group_access(
+        &transaction,
+        "fs_group_access",
+        "fs_id",
+        &fs_id,
+        &group_id
+    ).await? {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("AccessNotFound")
+            .message("requested group does not have access to the fs item"));
+    }
+
+    transaction.commit().await?;
+
+    Ok(net::Json::empty()
+        .with_message("removed fs item group access"))
+}
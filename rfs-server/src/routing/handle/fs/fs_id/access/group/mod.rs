@@ -0,0 +1,123 @@
+use axum::http::StatusCode;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use rfs_lib::ids;
+use rfs_lib::actions::access::SetGroupAccess;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::sec::authz;
+use crate::fs;
+
+pub mod group_id;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    fs_id: ids::FSId,
+}
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { fs_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    let conn = state.pool().get().await?;
+
+    let Some(item) = fs::Item::retrieve(&conn, &fs_id).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    };
+
+    let ability = authz::initiator_fs_ability(&conn, &initiator, &item).await?;
+
+    if ability.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    }
+
+    if !authz::has_ability(&ability, authz::Ability::Manage) {
+        return Err(authz::permission_denied());
+    }
+
+    let list = authz::access::get_group_access(
+        &conn,
+        "fs_group_access",
+        "fs_id",
+        &fs_id
+    ).await?
+        .into_iter()
+        .map(|access| access.into_schema())
+        .collect();
+
+    let wrapper = rfs_lib::json::ListWrapper::with_vec(list);
+
+    Ok(net::Json::new(wrapper))
+}
+
+pub async fn put(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { fs_id }): Path<PathParams>,
+    axum::Json(json): axum::Json<SetGroupAccess>,
+) -> error::Result<impl IntoResponse> {
+    let mut conn = state.pool().get().await?;
+
+    let Some(item) = fs::Item::retrieve(&conn, &fs_id).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    };
+
+    let ability = authz::initiator_fs_ability(&conn, &initiator, &item).await?;
+
+    if ability.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    }
+
+    if !authz::has_ability(&ability, authz::Ability::Manage) {
+        return Err(authz::permission_denied());
+    }
+
+    if let fs::Item::Root(_) = &item {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidFSItem")
+            .message("access to the root of a storage medium is managed by the storage medium"));
+    }
+
+    if authz::group::Group::retrieve(&conn, &json.group_id).await?.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("GroupNotFound")
+            .message("requested group was not found"));
+    }
+
+    let transaction = conn.transaction().await?;
+
+    let access = authz::access::set_group_access(
+        &transaction,
+        "fs_group_access",
+        "fs_id",
+        &fs_id,
+        &json.group_id,
+        &json.ability.into()
+    ).await?;
+
+    transaction.commit().await?;
+
+    let wrapper = rfs_lib::json::Wrapper::new(access.into_schema())
+        .with_message("updated fs item group access");
+
+    Ok(net::Json::new(wrapper))
+}
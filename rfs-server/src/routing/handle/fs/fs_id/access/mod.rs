@@ -0,0 +1,140 @@
+use axum::http::StatusCode;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use rfs_lib::ids;
+use rfs_lib::actions::access::SetAccess;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::sec::authz;
+use crate::fs;
+use crate::storage;
+use crate::user;
+
+pub mod user_id;
+pub mod group;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    fs_id: ids::FSId,
+}
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { fs_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    let conn = state.pool().get().await?;
+
+    let Some(item) = fs::Item::retrieve(&conn, &fs_id).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    };
+
+    let ability = authz::initiator_fs_ability(&conn, &initiator, &item).await?;
+
+    if ability.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    }
+
+    if !authz::has_ability(&ability, authz::Ability::Manage) {
+        return Err(authz::permission_denied());
+    }
+
+    let list = authz::access::get_access(
+        &conn,
+        "fs_access",
+        "fs_id",
+        &fs_id
+    ).await?
+        .into_iter()
+        .map(|access| access.into_schema())
+        .collect();
+
+    let wrapper = rfs_lib::json::ListWrapper::with_vec(list);
+
+    Ok(net::Json::new(wrapper))
+}
+
+pub async fn put(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { fs_id }): Path<PathParams>,
+    axum::Json(json): axum::Json<SetAccess>,
+) -> error::Result<impl IntoResponse> {
+    let mut conn = state.pool().get().await?;
+
+    let Some(item) = fs::Item::retrieve(&conn, &fs_id).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    };
+
+    let ability = authz::initiator_fs_ability(&conn, &initiator, &item).await?;
+
+    if ability.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    }
+
+    if !authz::has_ability(&ability, authz::Ability::Manage) {
+        return Err(authz::permission_denied());
+    }
+
+    if let fs::Item::Root(_) = &item {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidFSItem")
+            .message("access to the root of a storage medium is managed by the storage medium"));
+    }
+
+    let Some(medium) = storage::Medium::retrieve(&conn, item.storage_id()).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("StorageNotFound")
+            .message("requested storage item was not found"));
+    };
+
+    if json.user_id == medium.user_id {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidUser")
+            .message("the owner of the storage medium cannot be given access"));
+    }
+
+    if user::User::query_with_id(&conn, &json.user_id).await?.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("UserNotFound")
+            .message("requested user was not found"));
+    }
+
+    let transaction = conn.transaction().await?;
+
+    let access = authz::access::set_access(
+        &transaction,
+        "fs_access",
+        "fs_id",
+        &fs_id,
+        &json.user_id,
+        &json.ability.into()
+    ).await?;
+
+    transaction.commit().await?;
+
+    let wrapper = rfs_lib::json::Wrapper::new(access.into_schema())
+        .with_message("updated fs item access");
+
+    Ok(net::Json::new(wrapper))
+}
@@ -0,0 +1,52 @@
+use axum::http::StatusCode;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use rfs_lib::ids;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::sec::authz;
+use crate::fs;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    fs_id: ids::FSId,
+}
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { fs_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    let conn = state.pool().get().await?;
+
+    let Some(item) = fs::Item::retrieve(&conn, &fs_id).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    };
+
+    if authz::initiator_fs_ability(&conn, &initiator, &item).await?.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    }
+
+    if item.is_file() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidFSItem")
+            .message("requested fs item is a file and has no contents"));
+    }
+
+    let list = fs::list_contents(&conn, &fs_id).await?;
+
+    let wrapper = rfs_lib::json::ListWrapper::with_vec(list);
+
+    Ok(net::Json::new(wrapper))
+}
@@ -16,13 +16,21 @@ use rfs_lib::{ids, schema};
 use crate::net;
 use crate::net::error;
 use crate::state::ArcShared;
+use crate::sec::audit;
 use crate::sec::authn::initiator;
+use crate::sec::authz;
 use crate::util;
 use crate::util::sql;
 use crate::storage;
 use crate::fs;
 use crate::tags;
 
+pub mod contents;
+pub mod access;
+pub mod data;
+pub mod share;
+pub mod sign;
+
 async fn stream_to_writer<W>(
     mut stream: BodyStream,
     hasher: &mut blake3::Hasher,
@@ -87,6 +95,13 @@ pub async fn get(
             .message("requested fs item was not found"));
     };
 
+    if authz::initiator_fs_ability(&conn, &initiator, &item).await?.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    }
+
     let wrapper = rfs_lib::json::Wrapper::new(item.into_schema());
 
     Ok(net::Json::new(wrapper))
@@ -96,6 +111,7 @@ pub async fn get(
 pub async fn post(
     State(state): State<ArcShared>,
     initiator: initiator::Initiator,
+    audit: audit::Context,
     headers: HeaderMap,
     Path(PathParams { fs_id }): Path<PathParams>,
     axum::Json(json): axum::Json<rfs_lib::actions::fs::CreateDir>,
@@ -109,6 +125,19 @@ pub async fn post(
             .message("requested fs item was not found"));
     };
 
+    let ability = authz::initiator_fs_ability(&conn, &initiator, &item).await?;
+
+    if ability.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSNotFound")
+            .message("requested fs item was not found"));
+    }
+
+    if !authz::has_ability(&ability, authz::Ability::Write) {
+        return Err(authz::permission_denied());
+    }
+
     let Some(medium) = storage::Medium::retrieve(
         &conn,
         item.storage_id()
@@ -125,6 +154,11 @@ pub async fn post(
     let created = chrono::Utc::now();
     let basename = json.basename;
     let comment = json.comment;
+
+    if !fs::basename_valid(&basename) {
+        return Err(invalid_basename());
+    }
+
     let path;
     let parent;
 
@@ -217,6 +251,15 @@ pub async fn post(
         deleted: None
     });
 
+    audit.entry(audit::Event::FsCreated)
+        .with_initiator(&initiator)
+        .with_details(serde_json::json!({
+            "fs_id": rtn.id(),
+            "storage_id": rtn.storage_id(),
+        }))
+        .record(&transaction)
+        .await?;
+
     transaction.commit().await?;
 
     let wrapper = rfs_lib::json::Wrapper::new(rtn.into_schema());
@@ -224,6 +267,193 @@ pub async fn post(
     Ok(net::Json::new(wrapper))
 }
 
+fn invalid_basename() -> error::Error {
+    error::Error::new()
+        .status(StatusCode::BAD_REQUEST)
+        .kind("InvalidBasename")
+        .message("the given basename is not a valid file name")
+}
+
+/// retrieves the basename of an upload from the query or the x-basename
+/// header, rejecting it before anything is written if it is not valid
+pub fn upload_basename(
+    query: Option<String>,
+    headers: &HeaderMap
+) -> error::Result<String> {
+    let basename = if let Some(value) = query {
+        value
+    } else if let Some(value) = headers.get("x-basename") {
+        value.to_str()?.to_owned()
+    } else {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("NoBasename")
+            .message("no basename was provided"));
+    };
+
+    if !fs::basename_valid(&basename) {
+        return Err(invalid_basename());
+    }
+
+    Ok(basename)
+}
+
+/// creates a new file under the given container from the provided body
+/// stream
+///
+/// the container must be either a root or directory. the returned upload is
+/// only moved into place once persisted which should happen after the
+/// transaction has been committed
+pub async fn create_file(
+    state: &ArcShared,
+    conn: &impl GenericClient,
+    user_id: ids::UserId,
+    item: fs::Item,
+    basename: String,
+    mime: mime::Mime,
+    stream: BodyStream,
+) -> error::Result<(fs::Item, fs::upload::Upload)> {
+    let created = chrono::Utc::now();
+    let size: u64;
+    let hash: blake3::Hash;
+    let path: PathBuf;
+    let parent;
+
+    if !fs::basename_valid(&basename) {
+        return Err(invalid_basename());
+    }
+
+    if fs::name_check(conn, item.id(), &basename).await?.is_some() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("AlreadyExists")
+            .message("the given basename already exists in this container"));
+    }
+
+    let Some(medium) = storage::Medium::retrieve(
+        conn,
+        item.storage_id()
+    ).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("StorageNotFound")
+            .message("requested storage item was not found"));
+    };
+
+    let id = state.ids().wait_fs_id()?;
+
+    match item {
+        fs::Item::Root(root) => {
+            path = PathBuf::new();
+            parent = root.id.clone();
+        },
+        fs::Item::Directory(dir) => {
+            path = dir.path.join(&dir.basename);
+            parent = dir.id.clone();
+        },
+        fs::Item::File(_) => {
+            return Err(error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("InvalidFSItem")
+                .message("cannot create file under file"));
+        }
+    }
+
+    let (storage, upload) = match &medium.type_ {
+        storage::types::Type::Local(local) => {
+            let mut full = local.path.join(&path);
+            full.push(&basename);
+
+            tracing::debug!(
+                "new file path: {:?}",
+                full.display()
+            );
+
+            if full.try_exists()? {
+                return Err(error::Error::new()
+                    .status(StatusCode::BAD_REQUEST)
+                    .kind("FileExists")
+                    .message("a file exists that is unknown to the server"));
+            }
+
+            let mut hasher = blake3::Hasher::new();
+            let mut upload = state.uploads().create(full).await?;
+            let mut writer = BufWriter::new(upload.file_mut());
+
+            size = stream_to_writer(stream, &mut hasher, &mut writer).await?;
+            hash = hasher.finalize();
+
+            (storage::fs::Storage::Local(storage::fs::Local {
+                id: medium.id.clone()
+            }), upload)
+        }
+    };
+
+    {
+        let pg_path = path.to_str().unwrap();
+        let pg_storage = PgJson(&storage);
+        let pg_mime_type = mime.type_().as_str();
+        let pg_mime_subtype = mime.subtype().as_str();
+        let pg_hash = hash.as_bytes().as_slice();
+        let pg_size: i64 = TryFrom::try_from(size)
+            .map_err(|_| error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("MaxFileSize")
+                .message("the provided file is too large for the system")
+                .source("total bytes written exceeds i64"))?;
+
+        let _ = conn.execute(
+            "\
+            insert into fs(\
+                id, \
+                user_id, \
+                parent, \
+                basename, \
+                fs_type, \
+                fs_path, \
+                fs_size, \
+                hash, \
+                s_data, \
+                mime_type, \
+                mime_subtype, \
+                created\
+            ) values \
+            ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)",
+            &[
+                &id,
+                &user_id,
+                &parent,
+                &basename,
+                &fs::consts::FILE_TYPE,
+                &pg_path,
+                &pg_size,
+                &pg_hash,
+                &pg_storage,
+                &pg_mime_type,
+                &pg_mime_subtype,
+                &created
+            ]
+        ).await?;
+    }
+
+    Ok((fs::Item::File(fs::File {
+        id,
+        user_id,
+        storage,
+        parent,
+        basename,
+        path,
+        mime,
+        size,
+        hash,
+        tags: Default::default(),
+        comment: None,
+        created,
+        updated: None,
+        deleted: None,
+    }), upload))
+}
+
 #[derive(Deserialize)]
 pub struct PutQuery {
     basename: Option<String>,
@@ -234,6 +464,7 @@ pub struct PutQuery {
 pub async fn put(
     State(state): State<ArcShared>,
     initiator: initiator::Initiator,
+    audit: audit::Context,
     headers: HeaderMap,
     Path(PathParams { fs_id }): Path<PathParams>,
     Query(PutQuery { basename, overwrite }): Query<PutQuery>,
@@ -248,15 +479,18 @@ pub async fn put(
             .message("requested fs item was not found"));
     };
 
-    let Some(medium) = storage::Medium::retrieve(
-        &conn,
-        item.storage_id()
-    ).await? else {
+    let ability = authz::initiator_fs_ability(&conn, &initiator, &item).await?;
+
+    if ability.is_none() {
         return Err(error::Error::new()
             .status(StatusCode::NOT_FOUND)
-            .kind("StorageNotFound")
-            .message("requested storage item was not found"));
-    };
+            .kind("FSNotFound")
+            .message("requested fs item was not found"));
+    }
+
+    if !authz::has_ability(&ability, authz::Ability::Write) {
+        return Err(authz::permission_denied());
+    }
 
     let transaction = conn.transaction().await?;
     let created = chrono::Utc::now();
@@ -270,142 +504,35 @@ pub async fn put(
             .message("no content-type was specified for the file"));
     };
 
-    let rtn = if !item.is_file() {
-        let id = state.ids().wait_fs_id()?;
-        let user_id = initiator.user().id().clone();
-        let size: u64;
-        let hash: blake3::Hash;
-        let path: PathBuf;
-        let parent;
-
-        let basename = if let Some(value) = basename {
-            value
-        } else if let Some(value) = headers.get("x-basename") {
-            value.to_str()?.to_owned()
-        } else {
-            return Err(error::Error::new()
-                .status(StatusCode::BAD_REQUEST)
-                .kind("NoBasename")
-                .message("no basename was provided"));
-        };
-
-        if let Some(id) = fs::name_check(&transaction, item.id(), &basename).await? {
-            return Err(error::Error::new()
-                .status(StatusCode::BAD_REQUEST)
-                .kind("AlreadyExists")
-                .message("the given basename already exists in this container"));
-        }
-
-        match item {
-            fs::Item::Root(root) => {
-                path = PathBuf::new();
-                parent = root.id.clone();
-            },
-            fs::Item::Directory(dir) => {
-                path = dir.path.join(&dir.basename);
-                parent = dir.id.clone();
-            },
-            fs::Item::File(_) => unreachable!()
-        }
-
-        let storage = match &medium.type_ {
-            storage::types::Type::Local(local) => {
-                let mut full = local.path.join(&path);
-                full.push(&basename);
-
-                tracing::debug!(
-                    "new file path: {:?}",
-                    full.display()
-                );
-
-                if full.try_exists()? {
-                    return Err(error::Error::new()
-                        .status(StatusCode::BAD_REQUEST)
-                        .kind("FileExists")
-                        .message("a file exists that is unknown to the server"));
-                }
-
-                let mut hasher = blake3::Hasher::new();
-                let file = tokio::fs::OpenOptions::new()
-                    .write(true)
-                    .create_new(true)
-                    .open(full)
-                    .await?;
-                let mut writer = BufWriter::new(file);
-
-                size = stream_to_writer(stream, &mut hasher, &mut writer).await?;
-                hash = hasher.finalize();
-
-                storage::fs::Storage::Local(storage::fs::Local {
-                    id: medium.id.clone()
-                })
-            }
-        };
+    let event = if item.is_file() {
+        audit::Event::FsOverwritten
+    } else {
+        audit::Event::FsCreated
+    };
 
-        {
-            let pg_path = path.to_str().unwrap();
-            let pg_storage = PgJson(&storage);
-            let pg_mime_type = mime.type_().as_str();
-            let pg_mime_subtype = mime.subtype().as_str();
-            let pg_hash = hash.as_bytes().as_slice();
-            let pg_size: i64 = TryFrom::try_from(size)
-                .map_err(|_| error::Error::new()
-                    .status(StatusCode::BAD_REQUEST)
-                    .kind("MaxFileSize")
-                    .message("the provided file is too large for the system")
-                    .source("total bytes written exceeds i64"))?;
+    let (rtn, upload) = if !item.is_file() {
+        let basename = upload_basename(basename, &headers)?;
 
-            let _ = transaction.execute(
-                "\
-                insert into fs(\
-                    id, \
-                    user_id, \
-                    parent, \
-                    basename, \
-                    fs_type, \
-                    fs_path, \
-                    fs_size, \
-                    hash, \
-                    s_data, \
-                    mime_type, \
-                    mime_subtype, \
-                    created\
-                ) values \
-                ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)",
-                &[
-                    &id,
-                    &user_id,
-                    &parent,
-                    &basename,
-                    &fs::consts::FILE_TYPE,
-                    &pg_path,
-                    &pg_size,
-                    &pg_hash,
-                    &pg_storage,
-                    &pg_mime_type,
-                    &pg_mime_subtype,
-                    &created
-                ]
-            ).await?;
-        }
-
-        fs::Item::File(fs::File {
-            id,
-            user_id,
-            storage,
-            parent,
+        create_file(
+            &state,
+            &transaction,
+            initiator.user().id().clone(),
+            item,
             basename,
-            path,
             mime,
-            size,
-            hash,
-            tags: Default::default(),
-            comment: None,
-            created,
-            updated: None,
-            deleted: None,
-        })
+            stream
+        ).await?
     } else {
+        let Some(medium) = storage::Medium::retrieve(
+            &transaction,
+            item.storage_id()
+        ).await? else {
+            return Err(error::Error::new()
+                .status(StatusCode::NOT_FOUND)
+                .kind("StorageNotFound")
+                .message("requested storage item was not found"));
+        };
+
         let mut file = item.into_file();
         let size: u64;
         let hash: blake3::Hash;
@@ -417,7 +544,8 @@ pub async fn put(
                 .message("the providied mime type does not match the current file"));
         }
 
-        match &medium.type_ {
+        // the current contents are kept until the transaction is committed
+        let upload = match &medium.type_ {
             storage::types::Type::Local(local) => {
                 let mut full = local.path.join(&file.path);
                 full.push(&file.basename);
@@ -430,14 +558,13 @@ pub async fn put(
                 }
 
                 let mut hasher = blake3::Hasher::new();
-                let file = tokio::fs::OpenOptions::new()
-                    .write(true)
-                    .open(full)
-                    .await?;
-                let mut writer = BufWriter::new(file);
+                let mut upload = state.uploads().create(full).await?;
+                let mut writer = BufWriter::new(upload.file_mut());
 
                 size = stream_to_writer(stream, &mut hasher, &mut writer).await?;
                 hash = hasher.finalize();
+
+                upload
             }
         };
 
@@ -465,11 +592,22 @@ pub async fn put(
         file.size = size;
         file.hash = hash;
 
-        fs::Item::File(file)
+        (fs::Item::File(file), upload)
     };
 
+    audit.entry(event)
+        .with_initiator(&initiator)
+        .with_details(serde_json::json!({
+            "fs_id": rtn.id(),
+            "storage_id": rtn.storage_id(),
+        }))
+        .record(&transaction)
+        .await?;
+
     transaction.commit().await?;
 
+    upload.persist().await?;
+
     let wrapper = rfs_lib::json::Wrapper::new(rtn.into_schema());
 
     Ok(net::Json::new(wrapper))
@@ -493,6 +631,19 @@ pub async fn patch(
             .message("requested fs item was not found"));
     };
 
+    let ability = authz::initiator_fs_ability(&conn, &initiator, &item).await?;
+
+    if ability.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    }
+
+    if !authz::has_ability(&ability, authz::Ability::Write) {
+        return Err(authz::permission_denied());
+    }
+
     if !json.has_work() {
         return Err(error::Error::new()
             .status(StatusCode::BAD_REQUEST)
@@ -554,10 +705,307 @@ pub async fn patch(
     Ok(net::Json::new(wrapper))
 }
 
+/// removes the fs item along with everything under it from the database
+/// and the storage medium
 pub async fn delete(
     State(state): State<ArcShared>,
     initiator: initiator::Initiator,
+    audit: audit::Context,
     Path(PathParams { fs_id }): Path<PathParams>,
 ) -> error::Result<impl IntoResponse> {
-    Ok(net::Json::empty())
+    let mut conn = state.pool().get().await?;
+
+    let Some(item) = fs::Item::retrieve(&conn, &fs_id).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    };
+
+    let ability = authz::initiator_fs_ability(&conn, &initiator, &item).await?;
+
+    if ability.is_none() || item.is_deleted() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    }
+
+    if !authz::has_ability(&ability, authz::Ability::Write) {
+        return Err(authz::permission_denied());
+    }
+
+    let (path, basename) = match &item {
+        fs::Item::Root(_) => {
+            return Err(error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("InvalidFSItem")
+                .message("the root of a storage medium is deleted with the storage medium"));
+        },
+        fs::Item::Directory(dir) => (&dir.path, &dir.basename),
+        fs::Item::File(file) => (&file.path, &file.basename),
+    };
+
+    let Some(medium) = storage::Medium::retrieve(
+        &conn,
+        item.storage_id()
+    ).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("StorageNotFound")
+            .message("requested storage item was not found"));
+    };
+
+    let transaction = conn.transaction().await?;
+
+    let ids: Vec<i64> = transaction.query(
+        "\
+        with recursive fs_tree as (\
+            select fs.id \
+            from fs \
+            where fs.id = $1 \
+            union \
+            select fs.id \
+            from fs \
+                join fs_tree on \
+                    fs.parent = fs_tree.id\
+        ) \
+        select fs_tree.id from fs_tree",
+        &[&fs_id]
+    ).await?
+        .into_iter()
+        .map(|row| row.get(0))
+        .collect();
+
+    for table in ["fs_tags", "fs_access", "fs_group_access", "fs_share", "fs_checksums"] {
+        let query = format!("delete from {table} where fs_id = any($1)");
+
+        let _ = transaction.execute(query.as_str(), &[&ids]).await?;
+    }
+
+    let _ = transaction.execute(
+        "delete from fs where id = any($1)",
+        &[&ids]
+    ).await?;
+
+    audit.entry(audit::Event::FsDeleted)
+        .with_initiator(&initiator)
+        .with_details(serde_json::json!({
+            "fs_id": fs_id,
+            "storage_id": item.storage_id(),
+            "removed": ids.len(),
+        }))
+        .record(&transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    match &medium.type_ {
+        storage::types::Type::Local(local) => {
+            let mut full = local.path.join(path);
+            full.push(basename);
+
+            let result = if item.is_file() {
+                tokio::fs::remove_file(&full).await
+            } else {
+                tokio::fs::remove_dir_all(&full).await
+            };
+
+            if let Err(err) = result {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    return Err(err.into());
+                }
+            }
+        }
+    }
+
+    Ok(net::Json::empty()
+        .with_message("deleted fs item"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use axum::Json;
+    use axum::extract::{Path, State};
+    use axum::http::{HeaderMap, StatusCode};
+    use rfs_lib::ids;
+    use rfs_lib::actions::fs::CreateDir;
+
+    use crate::fs;
+    use crate::sec::authz::{self, Ability};
+    use crate::state::ArcShared;
+    use crate::storage;
+    use crate::testing::{database, initiator, status, audit_context};
+    use crate::user;
+
+    use super::PathParams;
+
+    /// a local storage medium owned by one user that another user has the
+    /// given ability for
+    struct Shared {
+        path: PathBuf,
+        medium: storage::Medium,
+        root: fs::Root,
+        other: user::User,
+    }
+
+    impl Shared {
+        async fn create(state: &ArcShared, ability: Ability) -> Self {
+            let conn = state.pool().get().await.unwrap();
+
+            let owner = user::User::builder(state.ids().wait_user_id().unwrap(), "owner".into())
+                .build(&conn)
+                .await
+                .unwrap();
+            let other = user::User::builder(state.ids().wait_user_id().unwrap(), "other".into())
+                .build(&conn)
+                .await
+                .unwrap();
+
+            let path = std::env::temp_dir()
+                .join(format!("rfs_test_{:016x}", rand::random::<u64>()));
+            let Ok(local) = storage::types::Local::build(path.clone()).await else {
+                panic!("failed to create local storage");
+            };
+            let Ok(medium) = storage::Medium::builder(
+                state.ids().wait_storage_id().unwrap(),
+                owner.id().clone(),
+                "shared".into(),
+                local
+            ).build(&conn).await else {
+                panic!("failed to create storage medium");
+            };
+            let Ok(root) = fs::Root::builder(
+                state.ids().wait_fs_id().unwrap(),
+                owner.id().clone(),
+                &medium
+            ).build(&conn).await else {
+                panic!("failed to create storage root");
+            };
+
+            let shared = Shared { path, medium, root, other };
+            shared.set_ability(state, ability).await;
+            shared
+        }
+
+        async fn set_ability(&self, state: &ArcShared, ability: Ability) {
+            let conn = state.pool().get().await.unwrap();
+
+            authz::access::set_access(
+                &conn, "storage_access", "storage_id", &self.medium.id, self.other.id(), &ability
+            ).await.unwrap();
+        }
+
+        /// creates a directory as the other user
+        async fn create_dir(&self, state: &ArcShared, parent: ids::FSId, basename: &str) -> StatusCode {
+            let result = super::post(
+                State(state.clone()),
+                initiator(self.other.id().clone(), []),
+                audit_context(),
+                HeaderMap::new(),
+                Path(PathParams { fs_id: parent }),
+                Json(CreateDir {
+                    basename: basename.into(),
+                    tags: None,
+                    comment: None,
+                }),
+            ).await;
+
+            status(result)
+        }
+
+        /// deletes an item as the other user
+        async fn delete(&self, state: &ArcShared, fs_id: ids::FSId) -> StatusCode {
+            let result = super::delete(
+                State(state.clone()),
+                initiator(self.other.id().clone(), []),
+                audit_context(),
+                Path(PathParams { fs_id }),
+            ).await;
+
+            status(result)
+        }
+    }
+
+    impl Drop for Shared {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.path);
+        }
+    }
+
+    async fn find_id(state: &ArcShared, basename: &str) -> Option<ids::FSId> {
+        let conn = state.pool().get().await.unwrap();
+
+        conn.query_opt("select id from fs where basename = $1", &[&basename])
+            .await
+            .unwrap()
+            .map(|row| row.get(0))
+    }
+
+    #[tokio::test]
+    async fn revoked_creator_is_denied() {
+        let Some(state) = database().await else {
+            return;
+        };
+        let shared = Shared::create(&state, Ability::Write).await;
+
+        assert_eq!(
+            shared.create_dir(&state, shared.root.id.clone(), "created").await,
+            StatusCode::OK,
+            "creator failed to create directory"
+        );
+
+        let created = find_id(&state, "created").await.unwrap();
+
+        shared.set_ability(&state, Ability::Read).await;
+
+        assert_eq!(
+            shared.create_dir(&state, created, "nested").await,
+            StatusCode::FORBIDDEN,
+            "revoked creator created directory"
+        );
+    }
+
+    #[tokio::test]
+    async fn delete_removes_tree_and_records_event() {
+        let Some(state) = database().await else {
+            return;
+        };
+        let shared = Shared::create(&state, Ability::Write).await;
+
+        shared.create_dir(&state, shared.root.id.clone(), "parent").await;
+        let parent = find_id(&state, "parent").await.unwrap();
+        shared.create_dir(&state, parent.clone(), "child").await;
+
+        assert_eq!(
+            shared.delete(&state, shared.root.id.clone()).await,
+            StatusCode::BAD_REQUEST,
+            "deleted storage root"
+        );
+
+        shared.set_ability(&state, Ability::Read).await;
+
+        assert_eq!(
+            shared.delete(&state, parent.clone()).await,
+            StatusCode::FORBIDDEN,
+            "deleted without write"
+        );
+
+        shared.set_ability(&state, Ability::Write).await;
+
+        assert_eq!(shared.delete(&state, parent.clone()).await, StatusCode::OK);
+        assert!(find_id(&state, "parent").await.is_none(), "parent was not removed");
+        assert!(find_id(&state, "child").await.is_none(), "child was not removed");
+        assert!(!shared.path.join("parent").exists(), "directory was not removed from disk");
+
+        let conn = state.pool().get().await.unwrap();
+        let events: i64 = conn.query_one(
+            "select count(*) from audit_log where event = 'fs_deleted' and user_id = $1",
+            &[shared.other.id()]
+        ).await.unwrap().get(0);
+
+        assert_eq!(events, 1, "delete was not recorded");
+    }
 }
@@ -0,0 +1,153 @@
+use axum::http::StatusCode;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use rfs_lib::ids;
+use rfs_lib::actions::share::CreateShare;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::sec::authz;
+use crate::fs;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    fs_id: ids::FSId,
+}
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { fs_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    let conn = state.pool().get().await?;
+
+    let Some(item) = fs::Item::retrieve(&conn, &fs_id).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    };
+
+    let ability = authz::initiator_fs_ability(&conn, &initiator, &item).await?;
+
+    if ability.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    }
+
+    if !authz::has_ability(&ability, authz::Ability::Manage) {
+        return Err(authz::permission_denied());
+    }
+
+    let list = fs::share::Share::retrieve_fs(&conn, &fs_id)
+        .await?
+        .into_iter()
+        .map(|share| share.into_schema())
+        .collect();
+
+    let wrapper = rfs_lib::json::ListWrapper::with_vec(list);
+
+    Ok(net::Json::new(wrapper))
+}
+
+pub async fn post(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { fs_id }): Path<PathParams>,
+    axum::Json(json): axum::Json<CreateShare>,
+) -> error::Result<impl IntoResponse> {
+    let mut conn = state.pool().get().await?;
+
+    let Some(item) = fs::Item::retrieve(&conn, &fs_id).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    };
+
+    let ability = authz::initiator_fs_ability(&conn, &initiator, &item).await?;
+
+    if ability.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    }
+
+    if !authz::has_ability(&ability, authz::Ability::Manage) {
+        return Err(authz::permission_denied());
+    }
+
+    let mode: fs::share::Mode = json.mode.into();
+
+    match &item {
+        fs::Item::Root(_) => {
+            return Err(error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("InvalidFSItem")
+                .message("cannot create a share link for the root of a storage medium"));
+        },
+        fs::Item::File(_) => if mode == fs::share::Mode::UploadOnly {
+            return Err(error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("InvalidShareMode")
+                .message("upload only share links can only be created for directories"));
+        },
+        fs::Item::Directory(_) => {}
+    }
+
+    let mut builder = fs::share::Share::builder(
+        fs_id,
+        initiator.user().id().clone(),
+        mode
+    );
+
+    if let Some(expires) = json.expires {
+        if expires <= chrono::Utc::now() {
+            return Err(error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("InvalidExpires")
+                .message("the expiration for the share link has already passed"));
+        }
+
+        builder.set_expires(expires);
+    }
+
+    if let Some(password) = json.password {
+        if password.is_empty() {
+            return Err(error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("InvalidPassword")
+                .message("the password for the share link cannot be empty"));
+        }
+
+        builder.set_password(password);
+    }
+
+    if let Some(max) = json.max_downloads {
+        if max == 0 || max > i32::MAX as u32 {
+            return Err(error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("InvalidMaxDownloads")
+                .message("the max downloads for the share link is out of range"));
+        }
+
+        builder.set_max_downloads(max);
+    }
+
+    let transaction = conn.transaction().await?;
+
+    let share = builder.build(&transaction, state.auth().secrets().latest()).await?;
+
+    transaction.commit().await?;
+
+    let wrapper = rfs_lib::json::Wrapper::new(share.into_schema())
+        .with_message("created share link");
+
+    Ok(net::Json::new(wrapper))
+}
@@ -0,0 +1,101 @@
+use axum::http::StatusCode;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use rfs_lib::ids;
+use rfs_lib::schema::fs::SignedUrl;
+use rfs_lib::actions::fs::SignUrl;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::sec::authn::signed;
+use crate::sec::authz;
+use crate::fs;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    fs_id: ids::FSId,
+}
+
+pub async fn post(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { fs_id }): Path<PathParams>,
+    axum::Json(json): axum::Json<SignUrl>,
+) -> error::Result<impl IntoResponse> {
+    let key = signed::signing_key(state.auth())?;
+
+    let conn = state.pool().get().await?;
+
+    let Some(item) = fs::Item::retrieve(&conn, &fs_id).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    };
+
+    let ability = authz::initiator_fs_ability(&conn, &initiator, &item).await?;
+
+    if ability.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    }
+
+    let operation: signed::Operation = json.operation.into();
+
+    match operation {
+        signed::Operation::Download => {
+            if !item.is_file() {
+                return Err(error::Error::new()
+                    .status(StatusCode::BAD_REQUEST)
+                    .kind("InvalidFSItem")
+                    .message("download urls can only be created for files"));
+            }
+        },
+        signed::Operation::Upload => {
+            if item.is_file() {
+                return Err(error::Error::new()
+                    .status(StatusCode::BAD_REQUEST)
+                    .kind("InvalidFSItem")
+                    .message("upload urls can only be created for directories"));
+            }
+
+            if !authz::has_ability(&ability, authz::Ability::Write) {
+                return Err(authz::permission_denied());
+            }
+        }
+    }
+
+    let expires_in = json.expires_in.unwrap_or(signed::DEFAULT_EXPIRES_IN);
+
+    if expires_in == 0 || expires_in > signed::MAX_EXPIRES_IN {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidExpiresIn")
+            .message("the requested expiration for the url is out of range"));
+    }
+
+    let claims = signed::Claims {
+        operation,
+        fs_id: &fs_id,
+        user_id: initiator.user().id(),
+        expires: chrono::Utc::now().timestamp() + expires_in as i64,
+    };
+
+    let url = signed::create_url(key, &claims);
+    let expires = claims.expires_datetime()
+        .ok_or(error::Error::new()
+            .source("signed url expiration is not a valid datetime"))?;
+
+    let wrapper = rfs_lib::json::Wrapper::new(SignedUrl {
+        operation: operation.into_schema(),
+        url,
+        expires
+    });
+
+    Ok(net::Json::new(wrapper))
+}
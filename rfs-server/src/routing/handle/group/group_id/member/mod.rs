@@ -0,0 +1,85 @@
+use axum::http::StatusCode;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use rfs_lib::actions::group::SetMember;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::sec::authz;
+use crate::sec::authz::group::Member;
+use crate::user;
+
+use super::PathParams;
+use super::super::{retrieve_group, GroupAccess};
+
+pub mod user_id;
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { group_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    let conn = state.pool().get().await?;
+
+    let (group, _) = retrieve_group(&conn, &initiator, &group_id).await?;
+
+    let list = Member::retrieve_group(&conn, &group.id)
+        .await?
+        .into_iter()
+        .map(|member| member.into_schema())
+        .collect();
+
+    let wrapper = rfs_lib::json::ListWrapper::with_vec(list);
+
+    Ok(net::Json::new(wrapper))
+}
+
+pub async fn put(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { group_id }): Path<PathParams>,
+    axum::Json(json): axum::Json<SetMember>,
+) -> error::Result<impl IntoResponse> {
+    let mut conn = state.pool().get().await?;
+
+    let (group, access) = retrieve_group(&conn, &initiator, &group_id).await?;
+
+    if access != GroupAccess::Admin {
+        return Err(authz::permission_denied());
+    }
+
+    if user::User::query_with_id(&conn, &json.user_id).await?.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("UserNotFound")
+            .message("requested user was not found"));
+    }
+
+    let current = Member::retrieve(&conn, &group.id, &json.user_id).await?;
+    let current_admin = current.map(|member| member.admin).unwrap_or(false);
+
+    // group admins can manage members but only user managers can change
+    // who the admins of a group are
+    if json.admin != current_admin &&
+        !initiator.has_permission(&authz::Permission::UserManage) {
+        return Err(authz::permission_denied());
+    }
+
+    let transaction = conn.transaction().await?;
+
+    Member::set(&transaction, &group.id, &json.user_id, json.admin).await?;
+
+    let Some(member) = Member::retrieve(&transaction, &group.id, &json.user_id).await? else {
+        return Err(error::Error::new()
+            .source("group member not found after being set"));
+    };
+
+    transaction.commit().await?;
+
+    let wrapper = rfs_lib::json::Wrapper::new(member.into_schema())
+        .with_message("updated group member");
+
+    Ok(net::Json::new(wrapper))
+}
@@ -0,0 +1,62 @@
+use axum::http::StatusCode;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use rfs_lib::ids;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::sec::authz;
+use crate::sec::authz::group::Member;
+
+use super::super::super::{retrieve_group, GroupAccess};
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    group_id: ids::GroupId,
+    user_id: ids::UserId,
+}
+
+pub async fn delete(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { group_id, user_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    if initiator.bot().is_some() {
+        return Err(authz::permission_denied());
+    }
+
+    let mut conn = state.pool().get().await?;
+
+    let (group, access) = retrieve_group(&conn, &initiator, &group_id).await?;
+
+    let Some(member) = Member::retrieve(&conn, &group.id, &user_id).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("MemberNotFound")
+            .message("requested user is not a member of the group"));
+    };
+
+    // members are allowed to remove themselves. only user managers are able
+    // to remove other admins
+    if user_id != *initiator.user().id() {
+        if access != GroupAccess::Admin {
+            return Err(authz::permission_denied());
+        }
+
+        if member.admin && !initiator.has_permission(&authz::Permission::UserManage) {
+            return Err(authz::permission_denied());
+        }
+    }
+
+    let transaction = conn.transaction().await?;
+
+    Member::drop(&transaction, &group.id, &user_id).await?;
+
+    transaction.commit().await?;
+
+    Ok(net::Json::empty()
+        .with_message("removed group member"))
+}
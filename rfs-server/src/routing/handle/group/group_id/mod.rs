@@ -0,0 +1,110 @@
+use axum::http::StatusCode;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use rfs_lib::ids;
+use rfs_lib::actions::group::UpdateGroup;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::sec::authz;
+
+use super::retrieve_group;
+
+pub mod member;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    group_id: ids::GroupId,
+}
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { group_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    let conn = state.pool().get().await?;
+
+    let (group, _) = retrieve_group(&conn, &initiator, &group_id).await?;
+
+    let wrapper = rfs_lib::json::Wrapper::new(group.into_schema());
+
+    Ok(net::Json::new(wrapper))
+}
+
+pub async fn put(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { group_id }): Path<PathParams>,
+    axum::Json(json): axum::Json<UpdateGroup>,
+) -> error::Result<impl IntoResponse> {
+    authz::require_permission(&initiator, authz::Permission::UserManage)?;
+
+    let mut conn = state.pool().get().await?;
+
+    let (mut group, _) = retrieve_group(&conn, &initiator, &group_id).await?;
+
+    if !json.has_work() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("NoWork")
+            .message("requested update with no changes"));
+    }
+
+    let transaction = conn.transaction().await?;
+
+    if let Some(name) = json.name {
+        if name.is_empty() {
+            return Err(error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("InvalidName")
+                .message("the group name cannot be empty"));
+        }
+
+        if let Some(found_id) = authz::group::name_check(&transaction, &name).await? {
+            if found_id != group_id {
+                return Err(error::Error::new()
+                    .status(StatusCode::BAD_REQUEST)
+                    .kind("NameExists")
+                    .message("requested group name already exists"));
+            }
+        }
+
+        let _ = transaction.execute(
+            "update groups set name = $2 where id = $1",
+            &[&group_id, &name]
+        ).await?;
+
+        group.name = name;
+    }
+
+    transaction.commit().await?;
+
+    let wrapper = rfs_lib::json::Wrapper::new(group.into_schema())
+        .with_message("updated group");
+
+    Ok(net::Json::new(wrapper))
+}
+
+pub async fn delete(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { group_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    authz::require_permission(&initiator, authz::Permission::UserManage)?;
+
+    let mut conn = state.pool().get().await?;
+
+    let (group, _) = retrieve_group(&conn, &initiator, &group_id).await?;
+
+    let transaction = conn.transaction().await?;
+
+    group.delete(&transaction).await?;
+
+    transaction.commit().await?;
+
+    Ok(net::Json::empty()
+        .with_message("deleted group"))
+}
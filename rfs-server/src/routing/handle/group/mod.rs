@@ -0,0 +1,144 @@
+use axum::http::StatusCode;
+use axum::extract::State;
+use axum::response::IntoResponse;
+use deadpool_postgres::GenericClient;
+use rfs_lib::ids;
+use rfs_lib::schema::group::ListItem;
+use rfs_lib::actions::group::CreateGroup;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::sec::authz;
+use crate::sec::authz::group::{Group, Member};
+
+pub mod group_id;
+
+/// what the initiator is allowed to do with a group
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GroupAccess {
+    Member,
+    Admin,
+}
+
+/// determines how the initiator is able to interact with a group
+///
+/// users with user management are treated as admins of every group. bots
+/// are only able to see groups that their owner is a member of
+pub async fn group_access(
+    conn: &impl GenericClient,
+    initiator: &initiator::Initiator,
+    group_id: &ids::GroupId,
+) -> error::Result<Option<GroupAccess>> {
+    if initiator.has_permission(&authz::Permission::UserManage) {
+        return Ok(Some(GroupAccess::Admin));
+    }
+
+    let Some(member) = Member::retrieve(conn, group_id, initiator.user().id()).await? else {
+        return Ok(None);
+    };
+
+    if member.admin && initiator.bot().is_none() {
+        Ok(Some(GroupAccess::Admin))
+    } else {
+        Ok(Some(GroupAccess::Member))
+    }
+}
+
+/// retrieves the group if the initiator is able to see it
+pub async fn retrieve_group(
+    conn: &impl GenericClient,
+    initiator: &initiator::Initiator,
+    group_id: &ids::GroupId,
+) -> error::Result<(Group, GroupAccess)> {
+    let found = Group::retrieve(conn, group_id).await?;
+    let access = group_access(conn, initiator, group_id).await?;
+
+    match (found, access) {
+        (Some(group), Some(access)) => Ok((group, access)),
+        _ => Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("GroupNotFound")
+            .message("requested group was not found"))
+    }
+}
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+) -> error::Result<impl IntoResponse> {
+    let conn = state.pool().get().await?;
+
+    let rows = if initiator.has_permission(&authz::Permission::UserManage) {
+        conn.query(
+            "\
+            select groups.id, \
+                   groups.name \
+            from groups \
+            order by groups.id",
+            &[]
+        ).await?
+    } else {
+        conn.query(
+            "\
+            select groups.id, \
+                   groups.name \
+            from groups \
+                join group_users on \
+                    groups.id = group_users.group_id \
+            where group_users.user_id = $1 \
+            order by groups.id",
+            &[initiator.user().id()]
+        ).await?
+    };
+
+    let list = rows.into_iter()
+        .map(|row| ListItem {
+            id: ids::group_id_from_pg(row.get(0)),
+            name: row.get(1),
+        })
+        .collect();
+
+    let wrapper = rfs_lib::json::ListWrapper::with_vec(list);
+
+    Ok(net::Json::new(wrapper))
+}
+
+pub async fn post(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    axum::Json(json): axum::Json<CreateGroup>,
+) -> error::Result<impl IntoResponse> {
+    authz::require_permission(&initiator, authz::Permission::UserManage)?;
+
+    let mut conn = state.pool().get().await?;
+
+    if json.name.is_empty() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidName")
+            .message("the group name cannot be empty"));
+    }
+
+    if authz::group::name_check(&conn, &json.name).await?.is_some() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("NameExists")
+            .message("requested group name already exists"));
+    }
+
+    let transaction = conn.transaction().await?;
+
+    let group = Group::builder(
+        state.ids().wait_group_id()?,
+        json.name
+    ).build(&transaction).await?;
+
+    transaction.commit().await?;
+
+    let wrapper = rfs_lib::json::Wrapper::new(group.into_schema())
+        .with_message("created group");
+
+    Ok(net::Json::new(wrapper))
+}
@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use axum::extract::State;
+use axum::response::IntoResponse;
+use rfs_lib::schema;
+
+use crate::net;
+use crate::state::ArcShared;
+
+/// how long the database has to respond before it is considered unavailable
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// checks that the database is reachable. responds with 503 if it is not so
+/// that the server can be taken out of rotation
+pub async fn get(State(state): State<ArcShared>) -> impl IntoResponse {
+    let check = async {
+        let conn = match state.pool().get().await {
+            Ok(conn) => conn,
+            Err(err) => {
+                tracing::warn!("health check failed to get connection: {}", err);
+                return false;
+            }
+        };
+
+        match conn.execute("select 1", &[]).await {
+            Ok(_) => true,
+            Err(err) => {
+                tracing::warn!("health check query failed: {}", err);
+                false
+            }
+        }
+    };
+
+    let database = match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+        Ok(result) => result,
+        Err(_) => {
+            tracing::warn!("health check timed out waiting for the database");
+            false
+        }
+    };
+
+    let status = state.pool().status();
+    let status_code = if database {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    let health = schema::health::Health {
+        database,
+        pool: schema::health::Pool {
+            max_size: status.max_size,
+            size: status.size,
+            available: status.available as i64,
+        },
+    };
+
+    let wrapper = rfs_lib::json::Wrapper::new(health);
+
+    net::Json::new(wrapper)
+        .with_status(status_code)
+}
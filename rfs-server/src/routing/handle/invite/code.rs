@@ -0,0 +1,42 @@
+use axum::http::StatusCode;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::sec::authz;
+use crate::user::invite::Invite;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    code: String,
+}
+
+pub async fn delete(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { code }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    authz::require_permission(&initiator, authz::Permission::UserManage)?;
+
+    let mut conn = state.pool().get().await?;
+
+    let Some(invite) = Invite::retrieve(&conn, &code).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("InviteNotFound")
+            .message("requested invite was not found"));
+    };
+
+    let transaction = conn.transaction().await?;
+
+    invite.delete(&transaction).await?;
+
+    transaction.commit().await?;
+
+    Ok(net::Json::empty()
+        .with_message("deleted invite"))
+}
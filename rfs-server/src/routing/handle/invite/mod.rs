@@ -0,0 +1,77 @@
+use axum::http::StatusCode;
+use axum::extract::State;
+use axum::response::IntoResponse;
+use rfs_lib::actions::user::CreateInvite;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::sec::authz;
+use crate::user::invite::Invite;
+
+pub mod code;
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+) -> error::Result<impl IntoResponse> {
+    authz::require_permission(&initiator, authz::Permission::UserManage)?;
+
+    let conn = state.pool().get().await?;
+
+    let list = Invite::retrieve_all(&conn)
+        .await?
+        .into_iter()
+        .map(|invite| invite.into_schema())
+        .collect();
+
+    let wrapper = rfs_lib::json::ListWrapper::with_vec(list);
+
+    Ok(net::Json::new(wrapper))
+}
+
+pub async fn post(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    axum::Json(json): axum::Json<CreateInvite>,
+) -> error::Result<impl IntoResponse> {
+    authz::require_permission(&initiator, authz::Permission::UserManage)?;
+
+    let mut conn = state.pool().get().await?;
+
+    let mut builder = Invite::builder(initiator.user().id().clone());
+
+    if let Some(max_uses) = json.max_uses {
+        if max_uses == 0 {
+            return Err(error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("InvalidMaxUses")
+                .message("max uses must be greater than 0"));
+        }
+
+        builder.set_max_uses(max_uses);
+    }
+
+    if let Some(expires) = json.expires {
+        if expires <= chrono::Utc::now() {
+            return Err(error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("InvalidExpires")
+                .message("expires must be in the future"));
+        }
+
+        builder.set_expires(expires);
+    }
+
+    let transaction = conn.transaction().await?;
+
+    let invite = builder.build(&transaction).await?;
+
+    transaction.commit().await?;
+
+    let wrapper = rfs_lib::json::Wrapper::new(invite.into_schema())
+        .with_message("created invite");
+
+    Ok(net::Json::new(wrapper))
+}
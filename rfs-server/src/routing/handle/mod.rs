@@ -7,9 +7,19 @@ use crate::net::{self, error};
 use crate::state::ArcShared;
 
 pub mod ping;
+pub mod health;
 pub mod auth;
 pub mod storage;
 pub mod fs;
+pub mod shared;
+pub mod share;
+pub mod signed;
+pub mod user;
+pub mod role;
+pub mod group;
+pub mod register;
+pub mod invite;
+pub mod audit;
 
 #[derive(Serialize)]
 pub struct RootContext {}
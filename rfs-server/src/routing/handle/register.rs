@@ -0,0 +1,133 @@
+use axum::http::{StatusCode, HeaderMap};
+use axum::extract::State;
+use axum::response::IntoResponse;
+use serde::Serialize;
+use rfs_lib::schema::user::RegistrationMode;
+use rfs_lib::actions::user::Register;
+
+use crate::net;
+use crate::net::error;
+use crate::state::{ArcShared, Registration};
+use crate::sec::authn::password::Password;
+use crate::user;
+use crate::user::invite::Invite;
+
+#[derive(Serialize)]
+pub struct RegisterContext {
+    mode: RegistrationMode,
+}
+
+#[derive(Serialize)]
+pub struct RegisterJson {
+    mode: RegistrationMode,
+}
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    headers: HeaderMap
+) -> error::Result<impl IntoResponse> {
+    let mode = state.registration().into_schema();
+
+    if net::html::is_html_accept(&headers)?.is_some() {
+        if state.templates().has_template("pages/register") {
+            let context = RegisterContext { mode };
+            let rendered = state.templates().render("pages/register", &context)?;
+
+            return Ok(net::html::html_response(rendered)?
+                .into_response());
+        }
+
+        return Ok(net::fs::response_file(
+            "register html",
+            state.pages().join("register.html")
+        ).await?.into_response())
+    }
+
+    Ok(net::Json::new(rfs_lib::json::Wrapper::new(RegisterJson { mode }))
+        .into_response())
+}
+
+pub async fn post(
+    State(state): State<ArcShared>,
+    axum::Json(json): axum::Json<Register>,
+) -> error::Result<impl IntoResponse> {
+    if *state.registration() == Registration::Disabled {
+        return Err(error::Error::new()
+            .status(StatusCode::FORBIDDEN)
+            .kind("RegistrationDisabled")
+            .message("registration is not available"));
+    }
+
+    let mut conn = state.pool().get().await?;
+
+    if json.username.is_empty() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidUsername")
+            .message("the username cannot be empty"));
+    }
+
+    if json.password.is_empty() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidPassword")
+            .message("the password cannot be empty"));
+    }
+
+    if user::username_check(&conn, &json.username).await?.is_some() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("UsernameExists")
+            .message("requested username already exists"));
+    }
+
+    if let Some(email) = &json.email {
+        if user::email_check(&conn, email).await?.is_some() {
+            return Err(error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("EmailExists")
+                .message("requested email already exists"));
+        }
+    }
+
+    let transaction = conn.transaction().await?;
+
+    if *state.registration() == Registration::Invite {
+        let Some(code) = &json.invite else {
+            return Err(error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("InviteRequired")
+                .message("an invite code is required to register"));
+        };
+
+        if !Invite::use_code(&transaction, code).await? {
+            return Err(error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("InvalidInvite")
+                .message("the invite code is invalid, expired or has been used"));
+        }
+    }
+
+    let mut builder = user::User::builder(
+        state.ids().wait_user_id()?,
+        json.username
+    );
+
+    if let Some(email) = json.email {
+        builder.set_email(email);
+    }
+
+    let user = builder.build(&transaction).await?;
+
+    Password::builder(user.id().clone(), json.password)
+        .with_secret(state.auth().secrets().latest())
+        .build(&transaction)
+        .await?;
+
+    transaction.commit().await?;
+
+    let wrapper = rfs_lib::json::Wrapper::new(user.into_schema())
+        .with_message("registered user");
+
+    Ok(net::Json::new(wrapper))
+}
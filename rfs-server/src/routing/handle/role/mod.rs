@@ -0,0 +1,93 @@
+use axum::http::StatusCode;
+use axum::extract::State;
+use axum::response::IntoResponse;
+use rfs_lib::schema::role::ListItem;
+use rfs_lib::actions::role::CreateRole;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::sec::authz;
+use crate::sec::authz::role::Role;
+
+pub mod role_id;
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+) -> error::Result<impl IntoResponse> {
+    authz::require_permission(&initiator, authz::Permission::RoleManage)?;
+
+    let conn = state.pool().get().await?;
+
+    let rows = conn.query(
+        "\
+        select authz_roles.id, \
+               authz_roles.name \
+        from authz_roles \
+        order by authz_roles.id",
+        &[]
+    ).await?;
+
+    let list = rows.into_iter()
+        .map(|row| ListItem {
+            id: rfs_lib::ids::role_id_from_pg(row.get(0)),
+            name: row.get(1),
+        })
+        .collect();
+
+    let wrapper = rfs_lib::json::ListWrapper::with_vec(list);
+
+    Ok(net::Json::new(wrapper))
+}
+
+pub async fn post(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    axum::Json(json): axum::Json<CreateRole>,
+) -> error::Result<impl IntoResponse> {
+    authz::require_permission(&initiator, authz::Permission::RoleManage)?;
+
+    let mut conn = state.pool().get().await?;
+
+    if json.name.is_empty() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidName")
+            .message("the role name cannot be empty"));
+    }
+
+    if authz::role::name_check(&conn, &json.name).await?.is_some() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("NameExists")
+            .message("requested role name already exists"));
+    }
+
+    let permissions: authz::permission::PermissionSet = json.permissions.into_iter()
+        .map(Into::into)
+        .collect();
+
+    authz::require_grantable(&initiator, &permissions)?;
+
+    let transaction = conn.transaction().await?;
+
+    let mut builder = Role::builder(
+        state.ids().wait_role_id()?,
+        json.name
+    );
+
+    for permission in permissions {
+        builder.add_permission(permission);
+    }
+
+    let role = builder.build(&transaction).await?;
+
+    transaction.commit().await?;
+
+    let wrapper = rfs_lib::json::Wrapper::new(role.into_schema())
+        .with_message("created role");
+
+    Ok(net::Json::new(wrapper))
+}
@@ -0,0 +1,156 @@
+use axum::http::StatusCode;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use rfs_lib::ids;
+use rfs_lib::actions::role::UpdateRole;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::sec::authz;
+use crate::sec::authz::permission::PermissionSet;
+use crate::sec::authz::role::Role;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    role_id: ids::RoleId,
+}
+
+fn role_not_found() -> error::Error {
+    error::Error::new()
+        .status(StatusCode::NOT_FOUND)
+        .kind("RoleNotFound")
+        .message("requested role was not found")
+}
+
+/// prevents the current user from removing their own ability to manage roles
+async fn lockout_check(
+    conn: &impl deadpool_postgres::GenericClient,
+    initiator: &initiator::Initiator,
+) -> error::Result<()> {
+    let permissions = authz::permission::user_permissions(conn, initiator.user().id()).await?;
+
+    if !permissions.contains(&authz::Permission::RoleManage) {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidRole")
+            .message("cannot remove role management from the current user"));
+    }
+
+    Ok(())
+}
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { role_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    authz::require_permission(&initiator, authz::Permission::RoleManage)?;
+
+    let conn = state.pool().get().await?;
+
+    let Some(role) = Role::retrieve(&conn, &role_id).await? else {
+        return Err(role_not_found());
+    };
+
+    let wrapper = rfs_lib::json::Wrapper::new(role.into_schema());
+
+    Ok(net::Json::new(wrapper))
+}
+
+pub async fn put(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { role_id }): Path<PathParams>,
+    axum::Json(json): axum::Json<UpdateRole>,
+) -> error::Result<impl IntoResponse> {
+    authz::require_permission(&initiator, authz::Permission::RoleManage)?;
+
+    let mut conn = state.pool().get().await?;
+
+    if Role::retrieve(&conn, &role_id).await?.is_none() {
+        return Err(role_not_found());
+    }
+
+    if !json.has_work() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("NoWork")
+            .message("requested update with no changes"));
+    }
+
+    let transaction = conn.transaction().await?;
+
+    if let Some(name) = &json.name {
+        if name.is_empty() {
+            return Err(error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("InvalidName")
+                .message("the role name cannot be empty"));
+        }
+
+        if let Some(found_id) = authz::role::name_check(&transaction, name).await? {
+            if found_id != role_id {
+                return Err(error::Error::new()
+                    .status(StatusCode::BAD_REQUEST)
+                    .kind("NameExists")
+                    .message("requested role name already exists"));
+            }
+        }
+
+        let _ = transaction.execute(
+            "update authz_roles set name = $2 where id = $1",
+            &[&role_id, name]
+        ).await?;
+    }
+
+    if let Some(permissions) = json.permissions {
+        let set: PermissionSet = permissions.into_iter()
+            .map(Into::into)
+            .collect();
+
+        authz::require_grantable(&initiator, &set)?;
+
+        authz::role::set_permissions(&transaction, &role_id, &set).await?;
+
+        lockout_check(&transaction, &initiator).await?;
+    }
+
+    let Some(role) = Role::retrieve(&transaction, &role_id).await? else {
+        return Err(role_not_found());
+    };
+
+    transaction.commit().await?;
+
+    let wrapper = rfs_lib::json::Wrapper::new(role.into_schema())
+        .with_message("updated role");
+
+    Ok(net::Json::new(wrapper))
+}
+
+pub async fn delete(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { role_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    authz::require_permission(&initiator, authz::Permission::RoleManage)?;
+
+    let mut conn = state.pool().get().await?;
+
+    let Some(role) = Role::retrieve(&conn, &role_id).await? else {
+        return Err(role_not_found());
+    };
+
+    let transaction = conn.transaction().await?;
+
+    role.delete(&transaction).await?;
+
+    lockout_check(&transaction, &initiator).await?;
+
+    transaction.commit().await?;
+
+    Ok(net::Json::empty()
+        .with_message("deleted role"))
+}
@@ -0,0 +1,27 @@
+use axum::extract::State;
+use axum::response::IntoResponse;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::fs;
+
+pub mod token;
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+) -> error::Result<impl IntoResponse> {
+    let conn = state.pool().get().await?;
+
+    let list = fs::share::Share::retrieve_user(&conn, initiator.user().id())
+        .await?
+        .into_iter()
+        .map(|share| share.into_schema())
+        .collect();
+
+    let wrapper = rfs_lib::json::ListWrapper::with_vec(list);
+
+    Ok(net::Json::new(wrapper))
+}
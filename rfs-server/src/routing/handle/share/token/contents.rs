@@ -0,0 +1,69 @@
+use std::net::SocketAddr;
+
+use axum::http::{StatusCode, HeaderMap};
+use axum::extract::{Path, Query, State, ConnectInfo};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use rfs_lib::ids;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authz;
+use crate::fs;
+
+use super::{PathParams, lookup_share};
+
+#[derive(Deserialize)]
+pub struct GetQuery {
+    fs_id: Option<ids::FSId>,
+}
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(PathParams { token }): Path<PathParams>,
+    Query(GetQuery { fs_id }): Query<GetQuery>,
+) -> error::Result<impl IntoResponse> {
+    let conn = state.pool().get().await?;
+
+    let (share, item) = lookup_share(&conn, &state, &addr, &token, &headers).await?;
+
+    if share.mode != fs::share::Mode::ReadOnly {
+        return Err(authz::permission_denied());
+    }
+
+    let target = if let Some(fs_id) = fs_id {
+        if !fs::within_check(&conn, item.id(), &fs_id).await? {
+            return Err(error::Error::new()
+                .status(StatusCode::NOT_FOUND)
+                .kind("FSItemNotFound")
+                .message("requested fs item was not found"));
+        }
+
+        let Some(found) = fs::Item::retrieve(&conn, &fs_id).await? else {
+            return Err(error::Error::new()
+                .status(StatusCode::NOT_FOUND)
+                .kind("FSItemNotFound")
+                .message("requested fs item was not found"));
+        };
+
+        found
+    } else {
+        item
+    };
+
+    if target.is_file() || target.is_deleted() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidFSItem")
+            .message("requested fs item is not a directory"));
+    }
+
+    let list = fs::list_contents(&conn, target.id()).await?;
+
+    let wrapper = rfs_lib::json::ListWrapper::with_vec(list);
+
+    Ok(net::Json::new(wrapper))
+}
@@ -0,0 +1,305 @@
+use std::net::SocketAddr;
+use std::str::FromStr;
+
+use axum::http::{StatusCode, HeaderMap};
+use axum::extract::{Path, Query, State, ConnectInfo, BodyStream};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use rfs_lib::ids;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authz;
+use crate::storage;
+use crate::fs;
+use crate::routing::handle::fs::fs_id::{create_file, upload_basename};
+
+use super::{PathParams, lookup_share};
+
+fn share_exhausted() -> error::Error {
+    error::Error::new()
+        .status(StatusCode::GONE)
+        .kind("ShareExhausted")
+        .message("requested share link has reached its max number of downloads")
+}
+
+#[derive(Deserialize)]
+pub struct GetQuery {
+    fs_id: Option<ids::FSId>,
+}
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(PathParams { token }): Path<PathParams>,
+    Query(GetQuery { fs_id }): Query<GetQuery>,
+) -> error::Result<impl IntoResponse> {
+    let mut conn = state.pool().get().await?;
+
+    let (mut share, item) = lookup_share(&conn, &state, &addr, &token, &headers).await?;
+
+    if share.mode != fs::share::Mode::ReadOnly {
+        return Err(authz::permission_denied());
+    }
+
+    let target = if let Some(fs_id) = fs_id {
+        if !fs::within_check(&conn, item.id(), &fs_id).await? {
+            return Err(error::Error::new()
+                .status(StatusCode::NOT_FOUND)
+                .kind("FSItemNotFound")
+                .message("requested fs item was not found"));
+        }
+
+        let Some(found) = fs::Item::retrieve(&conn, &fs_id).await? else {
+            return Err(error::Error::new()
+                .status(StatusCode::NOT_FOUND)
+                .kind("FSItemNotFound")
+                .message("requested fs item was not found"));
+        };
+
+        found
+    } else {
+        item
+    };
+
+    if target.is_deleted() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    }
+
+    let Some(file) = target.try_into_file() else {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidFSItem")
+            .message("requested fs item is not a file"));
+    };
+
+    let Some(medium) = storage::Medium::retrieve(
+        &conn,
+        file.storage.id()
+    ).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("StorageNotFound")
+            .message("requested storage item was not found"));
+    };
+
+    if share.is_exhausted() {
+        return Err(share_exhausted());
+    }
+
+    // the download is only kept if the file could be opened. the row stays
+    // locked until then so other requests cannot take the last download
+    let transaction = conn.transaction().await?;
+
+    if !share.record_download(&transaction).await? {
+        return Err(share_exhausted());
+    }
+
+    let response = net::fs::stream_fs_file(&medium, &file).await?;
+
+    transaction.commit().await?;
+
+    Ok(response)
+}
+
+#[derive(Deserialize)]
+pub struct PutQuery {
+    basename: Option<String>,
+}
+
+pub async fn put(
+    State(state): State<ArcShared>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(PathParams { token }): Path<PathParams>,
+    Query(PutQuery { basename }): Query<PutQuery>,
+    stream: BodyStream,
+) -> error::Result<impl IntoResponse> {
+    let mut conn = state.pool().get().await?;
+
+    let (share, item) = lookup_share(&conn, &state, &addr, &token, &headers).await?;
+
+    if share.mode != fs::share::Mode::UploadOnly {
+        return Err(authz::permission_denied());
+    }
+
+    let mime = if let Some(value) = headers.get("content-type") {
+        mime::Mime::from_str(value.to_str()?)?
+    } else {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("NoContentType")
+            .message("no content-type was specified for the file"));
+    };
+
+    let basename = upload_basename(basename, &headers)?;
+
+    let transaction = conn.transaction().await?;
+
+    // upload only links do not grant read access so nothing about the new
+    // file is returned
+    let (_, upload) = create_file(
+        &state,
+        &transaction,
+        share.user_id.clone(),
+        item,
+        basename,
+        mime,
+        stream
+    ).await?;
+
+    transaction.commit().await?;
+
+    upload.persist().await?;
+
+    Ok(net::Json::empty()
+        .with_message("uploaded file"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::SocketAddr;
+    use std::path::PathBuf;
+
+    use axum::extract::{Path, Query, State, ConnectInfo};
+    use axum::http::{HeaderMap, StatusCode};
+    use rfs_lib::ids;
+
+    use crate::fs;
+    use crate::state::ArcShared;
+    use crate::storage;
+    use crate::testing::{database, status};
+    use crate::user;
+
+    use super::super::PathParams;
+    use super::GetQuery;
+
+    const CONTENTS: &[u8] = b"hello";
+
+    /// a file in local storage that is shared with a single download
+    struct Shared {
+        path: PathBuf,
+        token: String,
+    }
+
+    impl Shared {
+        async fn create(state: &ArcShared) -> Self {
+            let conn = state.pool().get().await.unwrap();
+
+            let owner = user::User::builder(state.ids().wait_user_id().unwrap(), "owner".into())
+                .build(&conn)
+                .await
+                .unwrap();
+
+            let path = std::env::temp_dir()
+                .join(format!("rfs_test_{:016x}", rand::random::<u64>()));
+            let Ok(local) = storage::types::Local::build(path.clone()).await else {
+                panic!("failed to create local storage");
+            };
+            let Ok(medium) = storage::Medium::builder(
+                state.ids().wait_storage_id().unwrap(),
+                owner.id().clone(),
+                "shared".into(),
+                local
+            ).build(&conn).await else {
+                panic!("failed to create storage medium");
+            };
+            let Ok(root) = fs::Root::builder(
+                state.ids().wait_fs_id().unwrap(),
+                owner.id().clone(),
+                &medium
+            ).build(&conn).await else {
+                panic!("failed to create storage root");
+            };
+
+            let file_id: ids::FSId = state.ids().wait_fs_id().unwrap();
+
+            conn.execute(
+                "\
+                insert into fs (\
+                    id, user_id, parent, basename, fs_type, fs_path, fs_size, \
+                    hash, s_data, mime_type, mime_subtype, created\
+                ) \
+                select $1, user_id, id, 'shared.txt', 1, '', 5, \
+                       $3, s_data, 'text', 'plain', now() \
+                from fs \
+                where id = $2",
+                &[&file_id, &root.id, &blake3::hash(CONTENTS).as_bytes().as_slice()]
+            ).await.unwrap();
+
+            let mut builder = fs::share::Share::builder(
+                file_id,
+                owner.id().clone(),
+                fs::share::Mode::ReadOnly
+            );
+            builder.set_max_downloads(1);
+
+            let Ok(share) = builder.build(&conn, state.auth().secrets().latest()).await else {
+                panic!("failed to create share");
+            };
+
+            Shared {
+                path: path.canonicalize().unwrap(),
+                token: share.token,
+            }
+        }
+
+        async fn download(&self, state: &ArcShared) -> StatusCode {
+            let result = super::get(
+                State(state.clone()),
+                ConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))),
+                HeaderMap::new(),
+                Path(PathParams { token: self.token.clone() }),
+                Query(GetQuery { fs_id: None }),
+            ).await;
+
+            status(result)
+        }
+
+        async fn downloads(&self, state: &ArcShared) -> i32 {
+            let conn = state.pool().get().await.unwrap();
+
+            conn.query_one("select downloads from fs_share where token = $1", &[&self.token])
+                .await
+                .unwrap()
+                .get(0)
+        }
+    }
+
+    impl Drop for Shared {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.path);
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_download_is_not_counted() {
+        let Some(state) = database().await else {
+            return;
+        };
+        let shared = Shared::create(&state).await;
+
+        assert_eq!(
+            shared.download(&state).await,
+            StatusCode::NOT_FOUND,
+            "missing file was downloaded"
+        );
+        assert_eq!(shared.downloads(&state).await, 0, "failed download was counted");
+
+        std::fs::write(shared.path.join("shared.txt"), CONTENTS).unwrap();
+
+        assert_eq!(shared.download(&state).await, StatusCode::OK);
+        assert_eq!(shared.downloads(&state).await, 1);
+        assert_eq!(
+            shared.download(&state).await,
+            StatusCode::GONE,
+            "downloaded past the max downloads"
+        );
+        assert_eq!(shared.downloads(&state).await, 1);
+    }
+}
@@ -0,0 +1,167 @@
+use std::net::SocketAddr;
+
+use axum::http::{StatusCode, HeaderMap};
+use axum::extract::{Path, State, ConnectInfo};
+use axum::response::IntoResponse;
+use deadpool_postgres::GenericClient;
+use serde::Deserialize;
+use rfs_lib::schema::share::PublicShare;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::{initiator, lockout};
+use crate::sec::authz;
+use crate::fs;
+
+pub mod contents;
+pub mod data;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    token: String,
+}
+
+fn share_not_found() -> error::Error {
+    error::Error::new()
+        .status(StatusCode::NOT_FOUND)
+        .kind("ShareNotFound")
+        .message("requested share link was not found")
+}
+
+/// retrieves the share link for the given token and verifies that it is
+/// still usable by the requester and that the creator still has access to
+/// the shared item
+///
+/// if the share link has a password it is expected to be provided in the
+/// `x-share-password` header. failed attempts are tracked against the share
+/// and the address of the requester
+pub async fn lookup_share(
+    conn: &impl GenericClient,
+    state: &ArcShared,
+    addr: &SocketAddr,
+    token: &str,
+    headers: &HeaderMap,
+) -> error::Result<(fs::share::Share, fs::Item)> {
+    let Some(share) = fs::share::Share::retrieve(conn, token).await? else {
+        return Err(share_not_found());
+    };
+
+    if share.is_expired() {
+        return Err(error::Error::new()
+            .status(StatusCode::GONE)
+            .kind("ShareExpired")
+            .message("requested share link has expired"));
+    }
+
+    if share.password.is_some() {
+        let subjects = [lockout::Subject::Share(share.token.clone(), addr.ip())];
+
+        lockout::check(conn, &subjects).await?;
+
+        let Some(given) = headers.get("x-share-password") else {
+            return Err(error::Error::new()
+                .status(StatusCode::UNAUTHORIZED)
+                .kind("PasswordNotProvided")
+                .message("a password is required for this share link"));
+        };
+
+        let Some(valid) = share.verify_password(given.as_bytes(), state.auth().secrets())? else {
+            return Err(error::Error::new()
+                .source("share password secret version not found. unable to verify share password"));
+        };
+
+        if !valid {
+            lockout::record_failure(conn, state.auth().lockout(), &subjects).await?;
+
+            return Err(error::Error::new()
+                .status(StatusCode::UNAUTHORIZED)
+                .kind("InvalidPassword")
+                .message("provided password is invalid"));
+        }
+
+        lockout::clear(conn, &subjects[0]).await?;
+    }
+
+    let Some(item) = fs::Item::retrieve(conn, &share.fs_id).await? else {
+        return Err(share_not_found());
+    };
+
+    if item.is_deleted() {
+        return Err(share_not_found());
+    }
+
+    // the share only grants what its creator is still able to do
+    let required = match share.mode {
+        fs::share::Mode::ReadOnly => authz::Ability::Read,
+        fs::share::Mode::UploadOnly => authz::Ability::Write,
+    };
+    let groups = authz::group::user_groups(conn, &share.user_id).await?;
+    let ability = authz::fs_ability(conn, &share.user_id, &groups, &item).await?;
+
+    if !authz::has_ability(&ability, required) {
+        return Err(authz::permission_denied());
+    }
+
+    Ok((share, item))
+}
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(PathParams { token }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    let conn = state.pool().get().await?;
+
+    let (share, item) = lookup_share(&conn, &state, &addr, &token, &headers).await?;
+
+    let Some(list_item) = fs::list_item_retrieve(&conn, item.id()).await? else {
+        return Err(share_not_found());
+    };
+
+    let wrapper = rfs_lib::json::Wrapper::new(PublicShare {
+        mode: share.mode.into_schema(),
+        expires: share.expires,
+        item: list_item,
+    });
+
+    Ok(net::Json::new(wrapper))
+}
+
+pub async fn delete(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { token }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    let mut conn = state.pool().get().await?;
+
+    let Some(share) = fs::share::Share::retrieve(&conn, &token).await? else {
+        return Err(share_not_found());
+    };
+
+    if share.user_id != *initiator.user().id() {
+        let ability = if let Some(item) = fs::Item::retrieve(&conn, &share.fs_id).await? {
+            authz::initiator_fs_ability(&conn, &initiator, &item).await?
+        } else {
+            None
+        };
+
+        if ability.is_none() {
+            return Err(share_not_found());
+        }
+
+        if !authz::has_ability(&ability, authz::Ability::Manage) {
+            return Err(authz::permission_denied());
+        }
+    }
+
+    let transaction = conn.transaction().await?;
+
+    share.delete(&transaction).await?;
+
+    transaction.commit().await?;
+
+    Ok(net::Json::empty()
+        .with_message("revoked share link"))
+}
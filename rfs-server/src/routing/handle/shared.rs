@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use axum::extract::State;
+use axum::response::IntoResponse;
+use rfs_lib::schema::access::SharedItems;
+use rfs_lib::schema::storage::StorageListItem;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::sec::authz;
+use crate::util::sql;
+use crate::fs;
+
+/// lists the storage mediums and fs items that other users have given the
+/// initiator access to either directly or through a group
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+) -> error::Result<impl IntoResponse> {
+    let conn = state.pool().get().await?;
+    let group_list = authz::group::pg_group_list(initiator.groups());
+    let params: sql::ParamsVec = vec![initiator.user().id(), &group_list];
+
+    let storage_fut = conn.query(
+        "\
+        select storage.id, \
+               storage.name, \
+               storage.user_id, \
+               storage.s_data \
+        from storage \
+        where (\
+            storage.id in (\
+                select storage_access.storage_id \
+                from storage_access \
+                where storage_access.user_id = $1\
+            ) or \
+            storage.id in (\
+                select storage_group_access.storage_id \
+                from storage_group_access \
+                where storage_group_access.group_id = any($2)\
+            )\
+        ) and storage.user_id != $1 and \
+              storage.deleted is null \
+        order by storage.id",
+        params.as_slice()
+    );
+    let fs_fut = conn.query(
+        "\
+        select fs.id, \
+               fs.user_id, \
+               fs.parent, \
+               fs.basename, \
+               fs.fs_type, \
+               fs.fs_path, \
+               fs.fs_size, \
+               fs.mime_type, \
+               fs.mime_subtype \
+        from fs \
+        where (\
+            fs.id in (\
+                select fs_access.fs_id \
+                from fs_access \
+                where fs_access.user_id = $1\
+            ) or \
+            fs.id in (\
+                select fs_group_access.fs_id \
+                from fs_group_access \
+                where fs_group_access.group_id = any($2)\
+            )\
+        ) and fs.user_id != $1 and \
+              fs.deleted is null \
+        order by fs.id",
+        params.as_slice()
+    );
+
+    let (storage_rows, fs_rows) = tokio::try_join!(storage_fut, fs_fut)?;
+
+    let shared = SharedItems {
+        storage: storage_rows.into_iter()
+            .map(|row| StorageListItem {
+                id: row.get(0),
+                name: row.get(1),
+                user_id: row.get(2),
+                type_: sql::de_from_sql(row.get(3)),
+                tags: HashMap::new(),
+            })
+            .collect(),
+        fs: fs_rows.iter()
+            .map(fs::list_item_from_row)
+            .collect(),
+    };
+
+    let wrapper = rfs_lib::json::Wrapper::new(shared);
+
+    Ok(net::Json::new(wrapper))
+}
@@ -0,0 +1,202 @@
+use std::str::FromStr;
+
+use axum::http::{StatusCode, HeaderMap};
+use axum::extract::{Path, Query, State, BodyStream};
+use axum::response::IntoResponse;
+use deadpool_postgres::GenericClient;
+use serde::Deserialize;
+use rfs_lib::ids;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::signed;
+use crate::sec::state::SessionKey;
+use crate::sec::authz;
+use crate::storage;
+use crate::fs;
+use crate::routing::handle::fs::fs_id::{create_file, upload_basename};
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    fs_id: ids::FSId,
+}
+
+#[derive(Deserialize)]
+pub struct SignedQuery {
+    user_id: ids::UserId,
+    expires: i64,
+    signature: String,
+    basename: Option<String>,
+}
+
+/// verifies the signature for the request and retrieves the requested fs
+/// item if the user that created the url still has the required ability
+async fn lookup_signed(
+    conn: &impl GenericClient,
+    key: &SessionKey,
+    operation: signed::Operation,
+    fs_id: &ids::FSId,
+    query: &SignedQuery,
+) -> error::Result<fs::Item> {
+    let claims = signed::Claims {
+        operation,
+        fs_id,
+        user_id: &query.user_id,
+        expires: query.expires,
+    };
+
+    if !signed::verify(key, &claims, &query.signature) {
+        return Err(error::Error::new()
+            .status(StatusCode::FORBIDDEN)
+            .kind("InvalidSignature")
+            .message("the provided signature is invalid"));
+    }
+
+    if claims.is_expired() {
+        return Err(error::Error::new()
+            .status(StatusCode::FORBIDDEN)
+            .kind("SignatureExpired")
+            .message("the provided signature has expired"));
+    }
+
+    let Some(item) = fs::Item::retrieve(conn, fs_id).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    };
+
+    if item.is_deleted() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("FSItemNotFound")
+            .message("requested fs item was not found"));
+    }
+
+    let required = match operation {
+        signed::Operation::Download => authz::Ability::Read,
+        signed::Operation::Upload => authz::Ability::Write,
+    };
+    let groups = authz::group::user_groups(conn, &query.user_id).await?;
+    let ability = authz::fs_ability(conn, &query.user_id, &groups, &item).await?;
+
+    if !authz::has_ability(&ability, required) {
+        return Err(authz::permission_denied());
+    }
+
+    Ok(item)
+}
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    Path(PathParams { fs_id }): Path<PathParams>,
+    Query(query): Query<SignedQuery>,
+) -> error::Result<impl IntoResponse> {
+    let key = signed::signing_key(state.auth())?;
+    let conn = state.pool().get().await?;
+
+    let item = lookup_signed(
+        &conn,
+        key,
+        signed::Operation::Download,
+        &fs_id,
+        &query
+    ).await?;
+
+    let Some(file) = item.try_into_file() else {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidFSItem")
+            .message("requested fs item is not a file"));
+    };
+
+    let Some(medium) = storage::Medium::retrieve(
+        &conn,
+        file.storage.id()
+    ).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("StorageNotFound")
+            .message("requested storage item was not found"));
+    };
+
+    net::fs::stream_fs_file(&medium, &file).await
+}
+
+pub async fn put(
+    State(state): State<ArcShared>,
+    headers: HeaderMap,
+    Path(PathParams { fs_id }): Path<PathParams>,
+    Query(query): Query<SignedQuery>,
+    stream: BodyStream,
+) -> error::Result<impl IntoResponse> {
+    let key = signed::signing_key(state.auth())?;
+    let mut conn = state.pool().get().await?;
+
+    let item = lookup_signed(
+        &conn,
+        key,
+        signed::Operation::Upload,
+        &fs_id,
+        &query
+    ).await?;
+
+    let mime = if let Some(value) = headers.get("content-type") {
+        mime::Mime::from_str(value.to_str()?)?
+    } else {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("NoContentType")
+            .message("no content-type was specified for the file"));
+    };
+
+    let basename = upload_basename(query.basename, &headers)?;
+
+    let transaction = conn.transaction().await?;
+
+    let (rtn, upload) = create_file(
+        &state,
+        &transaction,
+        query.user_id,
+        item,
+        basename,
+        mime,
+        stream
+    ).await?;
+
+    transaction.commit().await?;
+
+    upload.persist().await?;
+
+    let wrapper = rfs_lib::json::Wrapper::new(rtn.into_schema());
+
+    Ok(net::Json::new(wrapper))
+}
+
+#[cfg(test)]
+mod test {
+    use axum::extract::{Path, Query, State};
+    use axum::http::StatusCode;
+    use rfs_lib::ids;
+
+    use crate::testing::{shared, status};
+
+    use super::{PathParams, SignedQuery};
+
+    #[tokio::test]
+    async fn disabled_without_signing_secret() {
+        let result = super::get(
+            State(shared()),
+            Path(PathParams { fs_id: ids::fs_id_from_pg(10) }),
+            Query(SignedQuery {
+                user_id: ids::user_id_from_pg(1),
+                expires: chrono::Utc::now().timestamp() + 60,
+                signature: String::new(),
+                basename: None,
+            }),
+        ).await;
+
+        assert_eq!(status(result), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1 @@
+pub mod fs_id;
@@ -14,7 +14,9 @@ use rfs_lib::actions::storage::{CreateStorage, CreateStorageType};
 use crate::net;
 use crate::net::error;
 use crate::state::ArcShared;
+use crate::sec::audit;
 use crate::sec::authn::initiator;
+use crate::sec::authz;
 use crate::util::sql;
 use crate::storage;
 use crate::fs;
@@ -28,18 +30,31 @@ pub async fn get(
     headers: HeaderMap,
 ) -> error::Result<impl IntoResponse> {
     let conn = state.pool().get().await?;
-    let params = [initiator.user().id()];
+    let group_list = authz::group::pg_group_list(initiator.groups());
+    let params: sql::ParamsVec = vec![initiator.user().id(), &group_list];
 
     let fut = conn.query_raw(
         "\
         select storage.id, \
                storage.name, \
                storage.user_id, \
-               storage.s_data, \
+               storage.s_data \
         from storage \
-        where storage.user_id = $1 \
+        where (\
+            storage.user_id = $1 or \
+            storage.id in (\
+                select storage_access.storage_id \
+                from storage_access \
+                where storage_access.user_id = $1\
+            ) or \
+            storage.id in (\
+                select storage_group_access.storage_id \
+                from storage_group_access \
+                where storage_group_access.group_id = any($2)\
+            )\
+        ) and storage.deleted is null \
         order by storage.id",
-        &params
+        params.iter().copied()
     );
     let tags_fut = conn.query_raw(
         "\
@@ -49,9 +64,21 @@ pub async fn get(
         from storage_tags \
             join storage on \
                 storage_tags.storage_id = storage.id \
-        where storage.user_i = $1 \
-        order storage_tags.storage_id",
-        &params
+        where (\
+            storage.user_id = $1 or \
+            storage.id in (\
+                select storage_access.storage_id \
+                from storage_access \
+                where storage_access.user_id = $1\
+            ) or \
+            storage.id in (\
+                select storage_group_access.storage_id \
+                from storage_group_access \
+                where storage_group_access.group_id = any($2)\
+            )\
+        ) and storage.deleted is null \
+        order by storage_tags.storage_id",
+        params.iter().copied()
     );
 
     let (mut result, mut tags_result) = tokio::try_join!(fut, tags_fut)?;
@@ -96,9 +123,9 @@ pub async fn get(
             let row_id: ids::StorageId = tag_row.get(0);
 
             if item.id == row_id {
-                item.tags.insert(row.get(1), row.get(2));
+                item.tags.insert(tag_row.get(1), tag_row.get(2));
             } else {
-                current = Some((row_id, row.get(1), row.get(2)));
+                current = Some((row_id, tag_row.get(1), tag_row.get(2)));
                 break;
             }
         }
@@ -114,6 +141,7 @@ pub async fn get(
 pub async fn post(
     State(state): State<ArcShared>,
     initiator: initiator::Initiator,
+    audit: audit::Context,
     axum::Json(json): axum::Json<CreateStorage>,
 ) -> error::Result<impl IntoResponse> {
     tracing::event!(
@@ -121,12 +149,29 @@ pub async fn post(
         "creating new storage medium"
     );
 
-    let mut conn = state.pool().get().await?;
+    authz::require_permission(&initiator, authz::Permission::StorageCreate)?;
 
-    conn.execute("select * from users", &[]).await?;
+    let mut conn = state.pool().get().await?;
 
     let type_: storage::types::Type = match json.type_ {
         CreateStorageType::Local { path } => {
+            let path = if initiator.has_permission(&authz::Permission::StorageLocalAny) {
+                path
+            } else {
+                let Some(root) = state.storage_root() else {
+                    return Err(authz::permission_denied());
+                };
+
+                let Some(resolved) = storage::types::Local::resolve_within(root, &path).await? else {
+                    return Err(error::Error::new()
+                        .status(StatusCode::BAD_REQUEST)
+                        .kind("PathNotAllowed")
+                        .message("the requested path is outside of the storage root"));
+                };
+
+                resolved
+            };
+
             storage::types::Type::Local(storage::types::Local::build(path).await?)
         }
     };
@@ -149,6 +194,15 @@ pub async fn post(
         &storage
     ).build(&transaction).await?;
 
+    audit.entry(audit::Event::StorageCreated)
+        .with_initiator(&initiator)
+        .with_details(serde_json::json!({
+            "storage_id": storage.id,
+            "root_id": root.id,
+        }))
+        .record(&transaction)
+        .await?;
+
     transaction.commit().await?;
 
     let rtn = rfs_lib::json::Wrapper::new(storage.into_schema())
@@ -0,0 +1,136 @@
+use axum::http::StatusCode;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use rfs_lib::ids;
+use rfs_lib::actions::access::SetGroupAccess;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::sec::authz;
+use crate::storage;
+
+pub mod group_id;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    storage_id: ids::StorageId,
+}
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { storage_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    let conn = state.pool().get().await?;
+
+    let Some(medium) = storage::Medium::retrieve(
+        &conn,
+        &storage_id
+    ).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("StorageNotFound")
+            .message("requested storage item was not found"));
+    };
+
+    if medium.deleted.is_some() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("StorageNotFound")
+            .message("requested storage item was not found"));
+    }
+
+    let ability = authz::initiator_storage_ability(&conn, &initiator, &medium).await?;
+
+    if ability.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("StorageNotFound")
+            .message("requested storage item was not found"));
+    }
+
+    if !authz::has_ability(&ability, authz::Ability::Manage) {
+        return Err(authz::permission_denied());
+    }
+
+    let list = authz::access::get_group_access(
+        &conn,
+        "storage_group_access",
+        "storage_id",
+        &storage_id
+    ).await?
+        .into_iter()
+        .map(|access| access.into_schema())
+        .collect();
+
+    let wrapper = rfs_lib::json::ListWrapper::with_vec(list);
+
+    Ok(net::Json::new(wrapper))
+}
+
+pub async fn put(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { storage_id }): Path<PathParams>,
+    axum::Json(json): axum::Json<SetGroupAccess>,
+) -> error::Result<impl IntoResponse> {
+    let mut conn = state.pool().get().await?;
+
+    let Some(medium) = storage::Medium::retrieve(
+        &conn,
+        &storage_id
+    ).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("StorageNotFound")
+            .message("requested storage item was not found"));
+    };
+
+    if medium.deleted.is_some() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("StorageNotFound")
+            .message("requested storage item was not found"));
+    }
+
+    let ability = authz::initiator_storage_ability(&conn, &initiator, &medium).await?;
+
+    if ability.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("StorageNotFound")
+            .message("requested storage item was not found"));
+    }
+
+    if !authz::has_ability(&ability, authz::Ability::Manage) {
+        return Err(authz::permission_denied());
+    }
+
+    if authz::group::Group::retrieve(&conn, &json.group_id).await?.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("GroupNotFound")
+            .message("requested group was not found"));
+    }
+
+    let transaction = conn.transaction().await?;
+
+    let access = authz::access::set_group_access(
+        &transaction,
+        "storage_group_access",
+        "storage_id",
+        &storage_id,
+        &json.group_id,
+        &json.ability.into()
+    ).await?;
+
+    transaction.commit().await?;
+
+    let wrapper = rfs_lib::json::Wrapper::new(access.into_schema())
+        .with_message("updated storage group access");
+
+    Ok(net::Json::new(wrapper))
+}
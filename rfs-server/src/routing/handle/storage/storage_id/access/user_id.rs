@@ -0,0 +1,78 @@
+use axum::http::StatusCode;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use rfs_lib::ids;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::sec::authz;
+use crate::storage;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    storage_id: ids::StorageId,
+    user_id: ids::UserId,
+}
+
+pub async fn delete(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { storage_id, user_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    let mut conn = state.pool().get().await?;
+
+    let Some(medium) = storage::Medium::retrieve(
+        &conn,
+        &storage_id
+    ).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("StorageNotFound")
+            .message("requested storage item was not found"));
+    };
+
+    if medium.deleted.is_some() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("StorageNotFound")
+            .message("requested storage item was not found"));
+    }
+
+    let ability = authz::initiator_storage_ability(&conn, &initiator, &medium).await?;
+
+    if ability.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("StorageNotFound")
+            .message("requested storage item was not found"));
+    }
+
+    // users are allowed to remove their own access
+    if user_id != *initiator.user().id() &&
+        !authz::has_ability(&ability, authz::Ability::Manage) {
+        return Err(authz::permission_denied());
+    }
+
+    let transaction = conn.transaction().await?;
+
+    if !authz::access::drop_access(
+        &transaction,
+        "storage_access",
+        "storage_id",
+        &storage_id,
+        &user_id
+    ).await? {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("AccessNotFound")
+            .message("requested user does not have access to the storage medium"));
+    }
+
+    transaction.commit().await?;
+
+    Ok(net::Json::empty()
+        .with_message("removed storage access"))
+}
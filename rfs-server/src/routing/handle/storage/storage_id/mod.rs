@@ -11,12 +11,15 @@ use rfs_lib::actions::storage::{UpdateStorage, UpdateStorageType};
 use crate::net;
 use crate::net::error;
 use crate::state::ArcShared;
+use crate::sec::audit;
 use crate::sec::authn::initiator;
+use crate::sec::authz;
 use crate::util::sql;
 use crate::storage;
 use crate::tags;
 
 pub mod root;
+pub mod access;
 
 #[derive(Deserialize)]
 pub struct PathParams {
@@ -47,6 +50,13 @@ pub async fn get(
             .message("requested storage item was not found"));
     }
 
+    if authz::initiator_storage_ability(&conn, &initiator, &medium).await?.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("StorageNotFound")
+            .message("requested storage item was not found"));
+    }
+
     let rtn = rfs_lib::json::Wrapper::new(medium.into_schema());
 
     Ok(net::Json::new(rtn))
@@ -77,6 +87,19 @@ pub async fn put(
             .message("requested storage item was not found"));
     }
 
+    let ability = authz::initiator_storage_ability(&conn, &initiator, &medium).await?;
+
+    if ability.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("StorageNotFound")
+            .message("requested storage item was not found"));
+    }
+
+    if !authz::has_ability(&ability, authz::Ability::Manage) {
+        return Err(authz::permission_denied());
+    }
+
     if !json.has_work() {
         return Err(error::Error::new()
             .status(StatusCode::BAD_REQUEST)
@@ -94,7 +117,7 @@ pub async fn put(
         update_params.push(&updated);
 
         if let Some(name) = &json.name {
-            if let Some(found_id) = storage::name_check(&transaction, initiator.user().id(), name).await? {
+            if let Some(found_id) = storage::name_check(&transaction, &medium.user_id, name).await? {
                 if found_id != storage_id {
                     return Err(error::Error::new()
                         .status(StatusCode::BAD_REQUEST)
@@ -118,7 +141,7 @@ pub async fn put(
             }
         }
 
-        write!(&mut update_query, "where storage_id = $1").unwrap();
+        write!(&mut update_query, "where id = $1").unwrap();
 
         transaction.execute(update_query.as_str(), update_params.as_slice()).await?;
     }
@@ -146,6 +169,7 @@ pub async fn put(
 pub async fn delete(
     State(state): State<ArcShared>,
     initiator: initiator::Initiator,
+    audit: audit::Context,
     Path(PathParams { storage_id }): Path<PathParams>,
 ) -> error::Result<impl IntoResponse> {
     let mut conn = state.pool().get().await?;
@@ -160,22 +184,48 @@ pub async fn delete(
             .message("requested storage item was not found"));
     };
 
+    if medium.deleted.is_some() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("StorageNotFound")
+            .message("requested storage item was not found"));
+    }
+
+    if medium.user_id != *initiator.user().id() {
+        if authz::initiator_storage_ability(&conn, &initiator, &medium).await?.is_none() {
+            return Err(error::Error::new()
+                .status(StatusCode::NOT_FOUND)
+                .kind("StorageNotFound")
+                .message("requested storage item was not found"));
+        }
+
+        return Err(authz::permission_denied());
+    }
+
     let deleted = chrono::Utc::now();
 
     let transaction = conn.transaction().await?;
 
     // soft delete fs items
     let _ = transaction.execute(
-        "update fs set deleted = $2 where storage_id = $1",
+        "update fs set deleted = $2 where (fs.s_data->>'id')::bigint = $1",
         &[&storage_id, &deleted]
     ).await?;
 
     // soft delete storage item
     let _ = transaction.execute(
-        "update storage set deleted = $2 where storage_id = $1",
+        "update storage set deleted = $2 where id = $1",
         &[&storage_id, &deleted]
     ).await?;
 
+    audit.entry(audit::Event::StorageDeleted)
+        .with_initiator(&initiator)
+        .with_details(serde_json::json!({"storage_id": storage_id}))
+        .record(&transaction)
+        .await?;
+
+    transaction.commit().await?;
+
     let body = rfs_lib::json::Wrapper::new(())
         .with_message("deleted storage");
 
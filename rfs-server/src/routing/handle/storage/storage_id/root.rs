@@ -8,6 +8,7 @@ use crate::net;
 use crate::net::error;
 use crate::state::ArcShared;
 use crate::sec::authn::initiator;
+use crate::sec::authz;
 use crate::storage;
 use crate::fs;
 
@@ -40,6 +41,13 @@ pub async fn get(
             .message("requested storage item was not found"));
     }
 
+    if authz::initiator_storage_ability(&conn, &initiator, &medium).await?.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("StorageNotFound")
+            .message("requested storage item was not found"));
+    }
+
     let Some(root) = fs::Root::storage_id_retrieve(&conn, &storage_id).await? else {
         return Err(error::Error::new()
             .status(StatusCode::NOT_FOUND)
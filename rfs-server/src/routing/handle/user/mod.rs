@@ -0,0 +1,124 @@
+use axum::http::StatusCode;
+use axum::extract::State;
+use axum::response::IntoResponse;
+use rfs_lib::schema::user::ListItem;
+use rfs_lib::actions::user::CreateUser;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::sec::authn::password::Password;
+use crate::sec::authz;
+use crate::user;
+
+pub mod user_id;
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+) -> error::Result<impl IntoResponse> {
+    authz::require_permission(&initiator, authz::Permission::UserManage)?;
+
+    let conn = state.pool().get().await?;
+
+    let rows = conn.query(
+        "\
+        select users.id, \
+               users.username \
+        from users \
+        where users.deleted is null \
+        order by users.id",
+        &[]
+    ).await?;
+
+    let list = rows.into_iter()
+        .map(|row| ListItem {
+            id: rfs_lib::ids::user_id_from_pg(row.get(0)),
+            username: row.get(1),
+        })
+        .collect();
+
+    let wrapper = rfs_lib::json::ListWrapper::with_vec(list);
+
+    Ok(net::Json::new(wrapper))
+}
+
+pub async fn post(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    axum::Json(json): axum::Json<CreateUser>,
+) -> error::Result<impl IntoResponse> {
+    authz::require_permission(&initiator, authz::Permission::UserManage)?;
+
+    if json.roles.as_ref().is_some_and(|roles| !roles.is_empty()) {
+        authz::require_permission(&initiator, authz::Permission::RoleManage)?;
+    }
+
+    let mut conn = state.pool().get().await?;
+
+    if json.username.is_empty() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidUsername")
+            .message("the username cannot be empty"));
+    }
+
+    if user::username_check(&conn, &json.username).await?.is_some() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("UsernameExists")
+            .message("requested username already exists"));
+    }
+
+    if let Some(email) = &json.email {
+        if user::email_check(&conn, email).await?.is_some() {
+            return Err(error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("EmailExists")
+                .message("requested email already exists"));
+        }
+    }
+
+    if let Some(roles) = &json.roles {
+        if authz::role::missing_check(&conn, roles).await?.is_some() {
+            return Err(error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("RoleNotFound")
+                .message("requested role was not found"));
+        }
+
+        authz::require_assignable(&conn, &initiator, roles).await?;
+    }
+
+    let transaction = conn.transaction().await?;
+
+    let mut builder = user::User::builder(
+        state.ids().wait_user_id()?,
+        json.username
+    );
+
+    if let Some(email) = json.email {
+        builder.set_email(email);
+    }
+
+    let user = builder.build(&transaction).await?;
+
+    if let Some(password) = json.password {
+        Password::builder(user.id().clone(), password)
+            .with_secret(state.auth().secrets().latest())
+            .build(&transaction)
+            .await?;
+    }
+
+    if let Some(roles) = json.roles {
+        authz::role::set_user_roles(&transaction, user.id(), &roles).await?;
+    }
+
+    transaction.commit().await?;
+
+    let wrapper = rfs_lib::json::Wrapper::new(user.into_schema())
+        .with_message("created user");
+
+    Ok(net::Json::new(wrapper))
+}
@@ -0,0 +1,129 @@
+use axum::http::StatusCode;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use deadpool_postgres::GenericClient;
+use rfs_lib::ids;
+use rfs_lib::actions::bot::UpdateBot;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::bot;
+
+use super::require_bot_access;
+
+pub mod token;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    user_id: ids::UserId,
+    bot_id: ids::BotId,
+}
+
+/// retrieves the bot and checks that it is owned by the given user
+pub async fn retrieve_bot(
+    conn: &impl GenericClient,
+    user_id: &ids::UserId,
+    bot_id: &ids::BotId,
+) -> error::Result<bot::Bot> {
+    match bot::Bot::retrieve(conn, bot_id).await? {
+        Some(bot) if bot.user_id == *user_id => Ok(bot),
+        _ => Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("BotNotFound")
+            .message("requested bot was not found"))
+    }
+}
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { user_id, bot_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    require_bot_access(&initiator, &user_id)?;
+
+    let conn = state.pool().get().await?;
+
+    let bot = retrieve_bot(&conn, &user_id, &bot_id).await?;
+
+    let wrapper = rfs_lib::json::Wrapper::new(bot.into_schema());
+
+    Ok(net::Json::new(wrapper))
+}
+
+pub async fn put(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { user_id, bot_id }): Path<PathParams>,
+    axum::Json(json): axum::Json<UpdateBot>,
+) -> error::Result<impl IntoResponse> {
+    require_bot_access(&initiator, &user_id)?;
+
+    let mut conn = state.pool().get().await?;
+
+    let mut bot = retrieve_bot(&conn, &user_id, &bot_id).await?;
+
+    if !json.has_work() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("NoWork")
+            .message("requested update with no changes"));
+    }
+
+    let transaction = conn.transaction().await?;
+
+    if let Some(name) = json.name {
+        if name.is_empty() {
+            return Err(error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("InvalidName")
+                .message("the bot name cannot be empty"));
+        }
+
+        if let Some(found_id) = bot::name_check(&transaction, &user_id, &name).await? {
+            if found_id != bot_id {
+                return Err(error::Error::new()
+                    .status(StatusCode::BAD_REQUEST)
+                    .kind("NameExists")
+                    .message("requested bot name already exists"));
+            }
+        }
+
+        let _ = transaction.execute(
+            "update bots set name = $2 where id = $1",
+            &[&bot_id, &name]
+        ).await?;
+
+        bot.name = name;
+    }
+
+    transaction.commit().await?;
+
+    let wrapper = rfs_lib::json::Wrapper::new(bot.into_schema())
+        .with_message("updated bot");
+
+    Ok(net::Json::new(wrapper))
+}
+
+pub async fn delete(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { user_id, bot_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    require_bot_access(&initiator, &user_id)?;
+
+    let mut conn = state.pool().get().await?;
+
+    let bot = retrieve_bot(&conn, &user_id, &bot_id).await?;
+
+    let transaction = conn.transaction().await?;
+
+    bot.delete(&transaction).await?;
+
+    transaction.commit().await?;
+
+    Ok(net::Json::empty()
+        .with_message("deleted bot"))
+}
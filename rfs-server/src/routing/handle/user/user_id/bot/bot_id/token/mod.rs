@@ -0,0 +1,106 @@
+use axum::http::StatusCode;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use rfs_lib::schema::bot::CreatedToken;
+use rfs_lib::actions::bot::CreateToken;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::sec::authz;
+use crate::storage;
+use crate::bot;
+
+use super::{PathParams, retrieve_bot};
+use super::super::require_bot_access;
+
+pub mod token_id;
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { user_id, bot_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    require_bot_access(&initiator, &user_id)?;
+
+    let conn = state.pool().get().await?;
+
+    let bot = retrieve_bot(&conn, &user_id, &bot_id).await?;
+
+    let list = bot::token::Token::retrieve_bot(&conn, &bot.id)
+        .await?
+        .into_iter()
+        .map(|token| token.into_schema())
+        .collect();
+
+    let wrapper = rfs_lib::json::ListWrapper::with_vec(list);
+
+    Ok(net::Json::new(wrapper))
+}
+
+pub async fn post(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { user_id, bot_id }): Path<PathParams>,
+    axum::Json(json): axum::Json<CreateToken>,
+) -> error::Result<impl IntoResponse> {
+    require_bot_access(&initiator, &user_id)?;
+
+    let mut conn = state.pool().get().await?;
+
+    let bot = retrieve_bot(&conn, &user_id, &bot_id).await?;
+
+    if let Some(expires) = &json.expires {
+        if *expires <= chrono::Utc::now() {
+            return Err(error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("InvalidExpires")
+                .message("expires must be in the future"));
+        }
+    }
+
+    let mut builder = bot::token::Token::builder(bot.id);
+    builder.set_read_only(json.read_only);
+
+    if let Some(expires) = json.expires {
+        builder.set_expires(expires);
+    }
+
+    if let Some(storages) = json.storages {
+        // the owner of the bot must be able to access the storage mediums
+        // that the token is limited to
+        let groups = authz::group::user_groups(&conn, &user_id).await?;
+
+        for storage_id in &storages {
+            let accessible = match storage::Medium::retrieve(&conn, storage_id).await? {
+                Some(medium) if medium.deleted.is_none() => {
+                    authz::storage_ability(&conn, &user_id, &groups, &medium).await?.is_some()
+                },
+                _ => false
+            };
+
+            if !accessible {
+                return Err(error::Error::new()
+                    .status(StatusCode::NOT_FOUND)
+                    .kind("StorageNotFound")
+                    .message("requested storage item was not found"));
+            }
+        }
+
+        builder.set_storages(storages);
+    }
+
+    let transaction = conn.transaction().await?;
+
+    let (token, bearer) = builder.build(&transaction).await?;
+
+    transaction.commit().await?;
+
+    let wrapper = rfs_lib::json::Wrapper::new(CreatedToken {
+        token: bearer,
+        info: token.into_schema(),
+    }).with_message("created token");
+
+    Ok(net::Json::new(wrapper))
+}
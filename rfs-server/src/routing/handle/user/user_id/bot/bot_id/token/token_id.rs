@@ -0,0 +1,52 @@
+use axum::http::StatusCode;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use rfs_lib::ids;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::bot;
+
+use super::super::retrieve_bot;
+use super::super::super::require_bot_access;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    user_id: ids::UserId,
+    bot_id: ids::BotId,
+    token_id: String,
+}
+
+pub async fn delete(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { user_id, bot_id, token_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    require_bot_access(&initiator, &user_id)?;
+
+    let mut conn = state.pool().get().await?;
+
+    let bot = retrieve_bot(&conn, &user_id, &bot_id).await?;
+
+    let token = match bot::token::Token::retrieve(&conn, &token_id).await? {
+        Some(token) if token.bot_id == bot.id => token,
+        _ => {
+            return Err(error::Error::new()
+                .status(StatusCode::NOT_FOUND)
+                .kind("TokenNotFound")
+                .message("requested token was not found"));
+        }
+    };
+
+    let transaction = conn.transaction().await?;
+
+    token.delete(&transaction).await?;
+
+    transaction.commit().await?;
+
+    Ok(net::Json::empty()
+        .with_message("deleted token"))
+}
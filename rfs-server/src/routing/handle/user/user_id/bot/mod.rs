@@ -0,0 +1,105 @@
+use axum::http::StatusCode;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use rfs_lib::ids;
+use rfs_lib::schema::bot::ListItem;
+use rfs_lib::actions::bot::CreateBot;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::sec::authz;
+use crate::user;
+use crate::bot;
+
+use super::PathParams;
+
+pub mod bot_id;
+
+/// checks that the initiator is allowed to manage bots for the given user
+///
+/// bots are not allowed to manage other bots or tokens and only sessions
+/// are able to create them
+pub fn require_bot_access(
+    initiator: &initiator::Initiator,
+    user_id: &ids::UserId,
+) -> error::Result<()> {
+    authz::require_session(initiator)?;
+
+    if user_id != initiator.user().id() {
+        authz::require_permission(initiator, authz::Permission::UserManage)?;
+    }
+
+    Ok(())
+}
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { user_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    require_bot_access(&initiator, &user_id)?;
+
+    let conn = state.pool().get().await?;
+
+    let list = bot::Bot::retrieve_user(&conn, &user_id)
+        .await?
+        .into_iter()
+        .map(|bot| ListItem {
+            id: bot.id,
+            name: bot.name,
+        })
+        .collect();
+
+    let wrapper = rfs_lib::json::ListWrapper::with_vec(list);
+
+    Ok(net::Json::new(wrapper))
+}
+
+pub async fn post(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { user_id }): Path<PathParams>,
+    axum::Json(json): axum::Json<CreateBot>,
+) -> error::Result<impl IntoResponse> {
+    require_bot_access(&initiator, &user_id)?;
+
+    let mut conn = state.pool().get().await?;
+
+    if user::User::query_with_id(&conn, &user_id).await?.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("UserNotFound")
+            .message("requested user was not found"));
+    }
+
+    if json.name.is_empty() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidName")
+            .message("the bot name cannot be empty"));
+    }
+
+    if bot::name_check(&conn, &user_id, &json.name).await?.is_some() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("NameExists")
+            .message("requested bot name already exists"));
+    }
+
+    let transaction = conn.transaction().await?;
+
+    let bot = bot::Bot::builder(
+        state.ids().wait_bot_id()?,
+        user_id,
+        json.name
+    ).build(&transaction).await?;
+
+    transaction.commit().await?;
+
+    let wrapper = rfs_lib::json::Wrapper::new(bot.into_schema())
+        .with_message("created bot");
+
+    Ok(net::Json::new(wrapper))
+}
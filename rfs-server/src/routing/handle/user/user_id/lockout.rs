@@ -0,0 +1,37 @@
+use axum::http::StatusCode;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::{initiator, lockout};
+use crate::sec::authz;
+use crate::user;
+
+use super::PathParams;
+
+pub async fn delete(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { user_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    authz::require_permission(&initiator, authz::Permission::UserManage)?;
+
+    let conn = state.pool().get().await?;
+
+    if user::User::query_with_id(&conn, &user_id).await?.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("UserNotFound")
+            .message("requested user was not found"));
+    }
+
+    if lockout::clear(&conn, &lockout::Subject::User(user_id)).await? {
+        Ok(net::Json::empty()
+            .with_message("user unlocked"))
+    } else {
+        Ok(net::Json::empty()
+            .with_message("user was not locked"))
+    }
+}
@@ -0,0 +1,279 @@
+use std::fmt::Write;
+
+use axum::http::StatusCode;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use serde::Deserialize;
+use rfs_lib::ids;
+use rfs_lib::actions::user::UpdateUser;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::sec::authz;
+use crate::util::sql;
+use crate::user;
+
+pub mod roles;
+pub mod lockout;
+pub mod bot;
+
+#[derive(Deserialize)]
+pub struct PathParams {
+    user_id: ids::UserId,
+}
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { user_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    if user_id != *initiator.user().id() {
+        authz::require_permission(&initiator, authz::Permission::UserManage)?;
+    }
+
+    let conn = state.pool().get().await?;
+
+    let Some(user) = user::User::query_with_id(&conn, &user_id).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("UserNotFound")
+            .message("requested user was not found"));
+    };
+
+    let wrapper = rfs_lib::json::Wrapper::new(user.into_schema());
+
+    Ok(net::Json::new(wrapper))
+}
+
+pub async fn put(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { user_id }): Path<PathParams>,
+    axum::Json(json): axum::Json<UpdateUser>,
+) -> error::Result<impl IntoResponse> {
+    authz::require_permission(&initiator, authz::Permission::UserManage)?;
+
+    let mut conn = state.pool().get().await?;
+
+    if user::User::query_with_id(&conn, &user_id).await?.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("UserNotFound")
+            .message("requested user was not found"));
+    }
+
+    if !json.has_work() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("NoWork")
+            .message("requested update with no changes"));
+    }
+
+    let transaction = conn.transaction().await?;
+
+    {
+        let mut update_query = String::from("update users set");
+        let mut update_params = sql::ParamsVec::with_capacity(2);
+        let mut sep = " ";
+        update_params.push(&user_id);
+
+        if let Some(username) = &json.username {
+            if username.is_empty() {
+                return Err(error::Error::new()
+                    .status(StatusCode::BAD_REQUEST)
+                    .kind("InvalidUsername")
+                    .message("the username cannot be empty"));
+            }
+
+            if let Some(found_id) = user::username_check(&transaction, username).await? {
+                if found_id != user_id {
+                    return Err(error::Error::new()
+                        .status(StatusCode::BAD_REQUEST)
+                        .kind("UsernameExists")
+                        .message("requested username already exists"));
+                }
+            }
+
+            write!(
+                &mut update_query,
+                "{sep}username = ${}",
+                sql::push_param(&mut update_params, username)
+            ).unwrap();
+
+            sep = ", ";
+        }
+
+        if let Some(email) = &json.email {
+            if email.is_empty() {
+                write!(
+                    &mut update_query,
+                    "{sep}email = null, email_verified = false"
+                ).unwrap();
+            } else {
+                if let Some(found_id) = user::email_check(&transaction, email).await? {
+                    if found_id != user_id {
+                        return Err(error::Error::new()
+                            .status(StatusCode::BAD_REQUEST)
+                            .kind("EmailExists")
+                            .message("requested email already exists"));
+                    }
+                }
+
+                write!(
+                    &mut update_query,
+                    "{sep}email = ${}, email_verified = false",
+                    sql::push_param(&mut update_params, email)
+                ).unwrap();
+            }
+        }
+
+        write!(&mut update_query, " where id = $1").unwrap();
+
+        transaction.execute(update_query.as_str(), update_params.as_slice()).await?;
+    }
+
+    let Some(user) = user::User::query_with_id(&transaction, &user_id).await? else {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("UserNotFound")
+            .message("requested user was not found"));
+    };
+
+    transaction.commit().await?;
+
+    let wrapper = rfs_lib::json::Wrapper::new(user.into_schema())
+        .with_message("updated user");
+
+    Ok(net::Json::new(wrapper))
+}
+
+pub async fn delete(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { user_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    authz::require_permission(&initiator, authz::Permission::UserManage)?;
+
+    if user_id == *initiator.user().id() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("InvalidUser")
+            .message("cannot delete the current user"));
+    }
+
+    let mut conn = state.pool().get().await?;
+
+    if user::User::query_with_id(&conn, &user_id).await?.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("UserNotFound")
+            .message("requested user was not found"));
+    }
+
+    let deleted = chrono::Utc::now();
+
+    let transaction = conn.transaction().await?;
+
+    // drop any way the user could authenticate
+    let _ = transaction.execute(
+        "delete from auth_session where user_id = $1",
+        &[&user_id]
+    ).await?;
+    let _ = transaction.execute(
+        "delete from auth_password where user_id = $1",
+        &[&user_id]
+    ).await?;
+    let _ = transaction.execute(
+        "delete from auth_totp where user_id = $1",
+        &[&user_id]
+    ).await?;
+    let _ = transaction.execute(
+        "delete from auth_totp_attempts where user_id = $1",
+        &[&user_id]
+    ).await?;
+    let _ = transaction.execute(
+        "delete from auth_totp_hash where user_id = $1",
+        &[&user_id]
+    ).await?;
+    let _ = transaction.execute(
+        "delete from auth_ldap_groups where user_id = $1",
+        &[&user_id]
+    ).await?;
+    let _ = transaction.execute(
+        "delete from auth_ldap where user_id = $1",
+        &[&user_id]
+    ).await?;
+    let _ = transaction.execute(
+        "delete from auth_oidc_link where user_id = $1",
+        &[&user_id]
+    ).await?;
+    let _ = transaction.execute(
+        "delete from auth_access_tokens where user_id = $1",
+        &[&user_id]
+    ).await?;
+    let _ = transaction.execute(
+        "delete from user_email_tokens where user_id = $1",
+        &[&user_id]
+    ).await?;
+    let _ = crate::sec::authn::lockout::clear(
+        &transaction,
+        &crate::sec::authn::lockout::Subject::User(user_id.clone())
+    ).await?;
+
+    // drop any bots owned by the user along with their tokens
+    for bot in crate::bot::Bot::retrieve_user(&transaction, &user_id).await? {
+        bot.delete(&transaction).await?;
+    }
+
+    // remove anything shared with or by the user
+    let _ = transaction.execute(
+        "delete from storage_access where user_id = $1",
+        &[&user_id]
+    ).await?;
+    let _ = transaction.execute(
+        "delete from fs_access where user_id = $1",
+        &[&user_id]
+    ).await?;
+    let _ = transaction.execute(
+        "delete from group_users where user_id = $1",
+        &[&user_id]
+    ).await?;
+    let _ = transaction.execute(
+        "delete from authz_user_roles where user_id = $1",
+        &[&user_id]
+    ).await?;
+    let _ = transaction.execute(
+        "delete from fs_share where user_id = $1",
+        &[&user_id]
+    ).await?;
+
+    // soft delete fs items and storage mediums owned by the user
+    let _ = transaction.execute(
+        "\
+        update fs \
+        set deleted = $2 \
+        where (fs.s_data->>'id')::bigint in (\
+            select storage.id \
+            from storage \
+            where storage.user_id = $1\
+        ) and fs.deleted is null",
+        &[&user_id, &deleted]
+    ).await?;
+    let _ = transaction.execute(
+        "update storage set deleted = $2 where user_id = $1 and deleted is null",
+        &[&user_id, &deleted]
+    ).await?;
+
+    // soft delete the user
+    let _ = transaction.execute(
+        "update users set deleted = $2 where id = $1",
+        &[&user_id, &deleted]
+    ).await?;
+
+    transaction.commit().await?;
+
+    Ok(net::Json::empty()
+        .with_message("deleted user"))
+}
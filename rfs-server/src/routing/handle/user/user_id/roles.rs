@@ -0,0 +1,134 @@
+use axum::http::StatusCode;
+use axum::extract::{Path, State};
+use axum::response::IntoResponse;
+use rfs_lib::actions::user::SetRoles;
+
+use crate::net;
+use crate::net::error;
+use crate::state::ArcShared;
+use crate::sec::authn::initiator;
+use crate::sec::authz;
+use crate::user;
+
+use super::PathParams;
+
+pub async fn get(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { user_id }): Path<PathParams>,
+) -> error::Result<impl IntoResponse> {
+    if user_id != *initiator.user().id() {
+        authz::require_permission(&initiator, authz::Permission::UserManage)?;
+    }
+
+    let conn = state.pool().get().await?;
+
+    if user::User::query_with_id(&conn, &user_id).await?.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("UserNotFound")
+            .message("requested user was not found"));
+    }
+
+    let list = authz::role::user_roles(&conn, &user_id).await?;
+
+    let wrapper = rfs_lib::json::ListWrapper::with_vec(list);
+
+    Ok(net::Json::new(wrapper))
+}
+
+pub async fn put(
+    State(state): State<ArcShared>,
+    initiator: initiator::Initiator,
+    Path(PathParams { user_id }): Path<PathParams>,
+    axum::Json(json): axum::Json<SetRoles>,
+) -> error::Result<impl IntoResponse> {
+    authz::require_permission(&initiator, authz::Permission::UserManage)?;
+    authz::require_permission(&initiator, authz::Permission::RoleManage)?;
+
+    let mut conn = state.pool().get().await?;
+
+    if user::User::query_with_id(&conn, &user_id).await?.is_none() {
+        return Err(error::Error::new()
+            .status(StatusCode::NOT_FOUND)
+            .kind("UserNotFound")
+            .message("requested user was not found"));
+    }
+
+    if authz::role::missing_check(&conn, &json.roles).await?.is_some() {
+        return Err(error::Error::new()
+            .status(StatusCode::BAD_REQUEST)
+            .kind("RoleNotFound")
+            .message("requested role was not found"));
+    }
+
+    authz::require_assignable(&conn, &initiator, &json.roles).await?;
+
+    let transaction = conn.transaction().await?;
+
+    authz::role::set_user_roles(&transaction, &user_id, &json.roles).await?;
+
+    if user_id == *initiator.user().id() {
+        // prevent the current user from locking themselves out of managing
+        // users
+        let permissions = authz::permission::user_permissions(&transaction, &user_id).await?;
+
+        if !permissions.contains(&authz::Permission::UserManage) {
+            return Err(error::Error::new()
+                .status(StatusCode::BAD_REQUEST)
+                .kind("InvalidRoles")
+                .message("cannot remove user management from the current user"));
+        }
+    }
+
+    let list = authz::role::user_roles(&transaction, &user_id).await?;
+
+    transaction.commit().await?;
+
+    let wrapper = rfs_lib::json::ListWrapper::with_vec(list)
+        .with_message("updated user roles");
+
+    Ok(net::Json::new(wrapper))
+}
+
+#[cfg(test)]
+mod test {
+    use axum::Json;
+    use axum::extract::{Path, State};
+    use axum::http::StatusCode;
+    use rfs_lib::ids;
+    use rfs_lib::actions::user::{CreateUser, SetRoles};
+
+    use crate::sec::authz::Permission;
+    use crate::testing::{shared, initiator, status};
+
+    use super::PathParams;
+
+    #[tokio::test]
+    async fn user_manage_cannot_assign_roles() {
+        let state = shared();
+        let admin_role = ids::role_id_from_pg(1);
+
+        let result = super::put(
+            State(state.clone()),
+            initiator(ids::user_id_from_pg(2), [Permission::UserManage]),
+            Path(PathParams { user_id: ids::user_id_from_pg(2) }),
+            Json(SetRoles { roles: vec![admin_role.clone()] }),
+        ).await;
+
+        assert_eq!(status(result), StatusCode::FORBIDDEN, "assigned a role to themselves");
+
+        let result = crate::routing::handle::user::post(
+            State(state),
+            initiator(ids::user_id_from_pg(2), [Permission::UserManage]),
+            Json(CreateUser {
+                username: "new".into(),
+                email: None,
+                password: None,
+                roles: Some(vec![admin_role]),
+            }),
+        ).await;
+
+        assert_eq!(status(result), StatusCode::FORBIDDEN, "created a user with a role");
+    }
+}
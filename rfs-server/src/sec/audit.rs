@@ -0,0 +1,335 @@
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+
+use axum::http::request::Parts;
+use axum::extract::{FromRequestParts, ConnectInfo};
+use chrono::{DateTime, Utc, Duration};
+use rfs_lib::{ids, schema};
+use tokio_postgres::Error as PgError;
+use tokio_postgres::types::Json as PgJson;
+use deadpool_postgres::GenericClient;
+
+use crate::net::layer::request_id::RequestId;
+use crate::sec::authn::initiator::Initiator;
+use crate::sec::authn::session::Session;
+
+/// the security relevant actions that are recorded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Login,
+    LoginFailed,
+    Logout,
+    SessionRevoked,
+    TotpEnabled,
+    TotpDisabled,
+    RecoveryCodesCreated,
+    PasswordChanged,
+    PasswordRemoved,
+    PasswordReset,
+    StorageCreated,
+    StorageDeleted,
+    FsCreated,
+    FsOverwritten,
+    FsDeleted,
+}
+
+impl Event {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Event::Login => "login",
+            Event::LoginFailed => "login_failed",
+            Event::Logout => "logout",
+            Event::SessionRevoked => "session_revoked",
+            Event::TotpEnabled => "totp_enabled",
+            Event::TotpDisabled => "totp_disabled",
+            Event::RecoveryCodesCreated => "recovery_codes_created",
+            Event::PasswordChanged => "password_changed",
+            Event::PasswordRemoved => "password_removed",
+            Event::PasswordReset => "password_reset",
+            Event::StorageCreated => "storage_created",
+            Event::StorageDeleted => "storage_deleted",
+            Event::FsCreated => "fs_created",
+            Event::FsOverwritten => "fs_overwritten",
+            Event::FsDeleted => "fs_deleted",
+        }
+    }
+}
+
+/// information about the request that caused an event
+#[derive(Debug, Clone)]
+pub struct Context {
+    request_id: Option<u64>,
+    ip: Option<IpAddr>,
+}
+
+impl Context {
+    pub fn from_parts(parts: &Parts) -> Self {
+        let request_id = parts.extensions.get::<RequestId>()
+            .map(|found| *found.id());
+        let ip = parts.extensions.get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+
+        Context { request_id, ip }
+    }
+
+    pub fn ip(&self) -> Option<IpAddr> {
+        self.ip
+    }
+
+    pub fn entry(&self, event: Event) -> EntryBuilder<'_> {
+        EntryBuilder {
+            context: self,
+            event,
+            user_id: None,
+            mechanism: None,
+            details: None,
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for Context
+where
+    S: Sync
+{
+    type Rejection = Infallible;
+
+    fn from_request_parts<'life0, 'life1, 'async_trait>(
+        parts: &'life0 mut Parts,
+        _state: &'life1 S,
+    ) -> Pin<Box<dyn Future<Output = Result<Self, Self::Rejection>> + Send + 'async_trait>>
+    where
+        'life0: 'async_trait,
+        'life1: 'async_trait,
+        Self: 'async_trait
+    {
+        let context = Context::from_parts(parts);
+
+        Box::pin(async move { Ok(context) })
+    }
+}
+
+pub struct EntryBuilder<'a> {
+    context: &'a Context,
+    event: Event,
+    user_id: Option<ids::UserId>,
+    mechanism: Option<&'static str>,
+    details: Option<serde_json::Value>,
+}
+
+impl<'a> EntryBuilder<'a> {
+    /// records the user and mechanism of the initiator
+    pub fn with_initiator(mut self, initiator: &Initiator) -> Self {
+        self.user_id = Some(initiator.user().id().clone());
+        self.mechanism = Some(initiator.mechanism().name());
+        self
+    }
+
+    /// records the user of a session that may not be fully authenticated
+    pub fn with_session(mut self, session: &Session) -> Self {
+        self.user_id = Some(session.user_id.clone());
+        self.mechanism = Some("session");
+        self
+    }
+
+    pub fn with_user_id(mut self, user_id: ids::UserId) -> Self {
+        self.user_id = Some(user_id);
+        self
+    }
+
+    pub fn with_mechanism(mut self, mechanism: &'static str) -> Self {
+        self.mechanism = Some(mechanism);
+        self
+    }
+
+    /// additional information specific to the event
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    /// appends the entry to the log
+    ///
+    /// entries for failed attempts should be recorded outside of a
+    /// transaction so that they are kept when the request fails
+    pub async fn record(self, conn: &impl GenericClient) -> Result<(), PgError> {
+        let created = Utc::now();
+        let request_id = self.context.request_id.map(|id| id as i64);
+        let details = self.details.as_ref().map(PgJson);
+
+        let _ = conn.execute(
+            "\
+            insert into audit_log (\
+                event, \
+                user_id, \
+                mechanism, \
+                request_id, \
+                ip, \
+                details, \
+                created\
+            ) values \
+            ($1, $2, $3, $4, $5, $6, $7)",
+            &[
+                &self.event.as_str(),
+                &self.user_id,
+                &self.mechanism,
+                &request_id,
+                &self.context.ip,
+                &details,
+                &created
+            ]
+        ).await?;
+
+        Ok(())
+    }
+}
+
+pub struct Entry {
+    pub id: i64,
+    pub event: String,
+    pub user_id: Option<ids::UserId>,
+    pub mechanism: Option<String>,
+    pub request_id: Option<i64>,
+    pub ip: Option<IpAddr>,
+    pub details: Option<serde_json::Value>,
+    pub created: DateTime<Utc>,
+}
+
+impl Entry {
+    /// the columns to select for [`Entry::from_row`]
+    pub const COLUMNS: &'static str = "\
+        audit_log.id, \
+        audit_log.event, \
+        audit_log.user_id, \
+        audit_log.mechanism, \
+        audit_log.request_id, \
+        audit_log.ip, \
+        audit_log.details, \
+        audit_log.created";
+
+    pub fn from_row(row: tokio_postgres::Row) -> Self {
+        let details: Option<PgJson<serde_json::Value>> = row.get(6);
+
+        Entry {
+            id: row.get(0),
+            event: row.get(1),
+            user_id: row.get::<usize, Option<i64>>(2).map(ids::user_id_from_pg),
+            mechanism: row.get(3),
+            request_id: row.get(4),
+            ip: row.get(5),
+            details: details.map(|PgJson(value)| value),
+            created: row.get(7),
+        }
+    }
+
+    pub fn into_schema(self) -> schema::audit::Entry {
+        schema::audit::Entry {
+            id: self.id,
+            event: self.event,
+            user_id: self.user_id,
+            mechanism: self.mechanism,
+            request_id: self.request_id.map(|id| id as u64),
+            ip: self.ip.map(|ip| ip.to_string()),
+            details: self.details,
+            created: self.created,
+        }
+    }
+}
+
+/// removes entries that are older than the retention
+///
+/// the server is not able to delete from the audit log directly. the
+/// entries are removed by a function in the database that runs as the owner
+/// of the log
+pub async fn sweep(conn: &impl GenericClient, retention: &Duration) -> Result<u64, PgError> {
+    let days: i32 = retention.num_days()
+        .try_into()
+        .unwrap_or(i32::MAX);
+
+    let row = conn.query_one(
+        "select audit_log_sweep($1)",
+        &[&days]
+    ).await?;
+
+    Ok(row.get::<usize, i64>(0) as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::testing::{database, audit_context};
+
+    use super::*;
+
+    async fn insert_aged(conn: &impl GenericClient, age: Duration) {
+        let _ = conn.execute(
+            "insert into audit_log (event, created) values ('login', $1)",
+            &[&(Utc::now() - age)]
+        ).await.unwrap();
+    }
+
+    async fn count(conn: &impl GenericClient) -> i64 {
+        conn.query_one("select count(*) from audit_log", &[])
+            .await
+            .unwrap()
+            .get(0)
+    }
+
+    #[tokio::test]
+    async fn only_owner_can_change_entries() {
+        let Some(state) = database().await else {
+            return;
+        };
+        let conn = state.pool().get().await.unwrap();
+
+        audit_context().entry(Event::Login)
+            .with_mechanism("session")
+            .record(&conn)
+            .await
+            .unwrap();
+
+        assert_eq!(count(&conn).await, 1);
+
+        let owner: String = conn.query_one(
+            "select pg_get_userbyid(relowner)::text from pg_class where relname = 'audit_log'",
+            &[]
+        ).await.unwrap().get(0);
+
+        assert_eq!(owner, "rfs_audit");
+
+        let grantees: Vec<String> = conn.query(
+            "\
+            select distinct acl.grantee::regrole::text \
+            from pg_class, \
+                 aclexplode(pg_class.relacl) acl \
+            where pg_class.relname = 'audit_log' and \
+                  acl.privilege_type in ('UPDATE', 'DELETE', 'TRUNCATE')",
+            &[]
+        ).await
+            .unwrap()
+            .into_iter()
+            .map(|row| row.get(0))
+            .collect();
+
+        assert_eq!(grantees, vec![String::from("rfs_audit")], "others can change entries");
+    }
+
+    #[tokio::test]
+    async fn sweep_removes_expired_entries() {
+        let Some(state) = database().await else {
+            return;
+        };
+        let conn = state.pool().get().await.unwrap();
+
+        insert_aged(&conn, Duration::days(10)).await;
+        insert_aged(&conn, Duration::days(3)).await;
+        insert_aged(&conn, Duration::hours(12)).await;
+
+        assert_eq!(sweep(&conn, &Duration::days(5)).await.unwrap(), 1);
+        assert_eq!(count(&conn).await, 2);
+
+        // the retention is never less than a day
+        assert_eq!(sweep(&conn, &Duration::zero()).await.unwrap(), 1);
+        assert_eq!(count(&conn).await, 1);
+    }
+}
@@ -0,0 +1,187 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc, Duration};
+use rand::RngCore;
+use rfs_lib::ids;
+use tokio_postgres::Error as PgError;
+use deadpool_postgres::GenericClient;
+
+use crate::net;
+
+use super::session;
+
+/// the prefix that separates access tokens from bot tokens
+pub const TOKEN_PREFIX: &str = "at.";
+pub const TOKEN_ID_BYTES: usize = 16;
+pub const TOKEN_SECRET_BYTES: usize = 32;
+
+pub enum AccessError {
+    Rand(rand::Error),
+    Pg(PgError),
+}
+
+impl From<rand::Error> for AccessError {
+    fn from(err: rand::Error) -> Self {
+        AccessError::Rand(err)
+    }
+}
+
+impl From<PgError> for AccessError {
+    fn from(err: PgError) -> Self {
+        AccessError::Pg(err)
+    }
+}
+
+impl From<AccessError> for net::error::Error {
+    fn from(err: AccessError) -> net::error::Error {
+        match err {
+            AccessError::Rand(err) => err.into(),
+            AccessError::Pg(err) => err.into(),
+        }
+    }
+}
+
+fn gen_bytes<const N: usize>() -> Result<[u8; N], rand::Error> {
+    let mut bytes = [0u8; N];
+
+    rand::thread_rng().try_fill_bytes(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+/// splits an access token into its id and secret
+///
+/// tokens are formatted as "at.{id}.{secret}" with the id and secret being
+/// url safe base64 without padding. returns none if the value is not an
+/// access token
+pub fn split_token(given: &str) -> Option<(&str, Vec<u8>)> {
+    let (id, secret) = given.strip_prefix(TOKEN_PREFIX)?.split_once('.')?;
+
+    let decoded = URL_SAFE_NO_PAD.decode(secret).ok()?;
+
+    if decoded.len() != TOKEN_SECRET_BYTES {
+        return None;
+    }
+
+    Some((id, decoded))
+}
+
+/// a short lived bearer token minted from an authenticated session
+///
+/// the token is only valid for as long as the session it was created from
+pub struct AccessToken {
+    pub user_id: ids::UserId,
+    pub hash: blake3::Hash,
+    pub expires: DateTime<Utc>,
+}
+
+impl AccessToken {
+    /// creates a token for the session and returns it along with the full
+    /// bearer value. only the hash of the secret is stored
+    pub async fn create(
+        conn: &impl GenericClient,
+        session: &session::Session,
+        lifetime: Duration,
+    ) -> Result<(Self, String), AccessError> {
+        let created = Utc::now();
+        // the token cannot outlive the session
+        let expires = std::cmp::min(created + lifetime, session.expires);
+        let id = URL_SAFE_NO_PAD.encode(gen_bytes::<TOKEN_ID_BYTES>()?);
+        let secret = gen_bytes::<TOKEN_SECRET_BYTES>()?;
+        let hash = blake3::hash(&secret);
+
+        let _ = conn.execute(
+            "\
+            insert into auth_access_tokens (\
+                id, \
+                user_id, \
+                hash, \
+                session_token, \
+                expires, \
+                created\
+            ) values \
+            ($1, $2, $3, $4, $5, $6)",
+            &[
+                &id,
+                &session.user_id,
+                &hash.as_bytes().as_slice(),
+                &session.token.as_slice(),
+                &expires,
+                &created
+            ]
+        ).await?;
+
+        let bearer = format!("{}{}.{}", TOKEN_PREFIX, id, URL_SAFE_NO_PAD.encode(secret));
+
+        Ok((AccessToken {
+            user_id: session.user_id.clone(),
+            hash,
+            expires,
+        }, bearer))
+    }
+
+    /// retrieves the token if the session it was created from is still
+    /// active
+    pub async fn retrieve(
+        conn: &impl GenericClient,
+        id: &str,
+    ) -> Result<Option<Self>, PgError> {
+        let Some(row) = conn.query_opt(
+            "\
+            select auth_access_tokens.user_id, \
+                   auth_access_tokens.hash, \
+                   auth_access_tokens.expires \
+            from auth_access_tokens \
+                join auth_session on \
+                    auth_access_tokens.session_token = auth_session.token \
+            where auth_access_tokens.id = $1 and \
+                  auth_session.dropped = false and \
+                  auth_session.authenticated = true and \
+                  auth_session.verified = true and \
+                  auth_session.expires > $2",
+            &[&id, &Utc::now()]
+        ).await? else {
+            return Ok(None);
+        };
+
+        let hash_bytes: Vec<u8> = row.get(1);
+        let mut hash = [0u8; blake3::OUT_LEN];
+
+        if hash_bytes.len() == blake3::OUT_LEN {
+            hash.copy_from_slice(&hash_bytes);
+        }
+
+        Ok(Some(AccessToken {
+            user_id: ids::user_id_from_pg(row.get(0)),
+            hash: blake3::Hash::from(hash),
+            expires: row.get(2),
+        }))
+    }
+
+    /// compares the given secret against the stored hash in constant time
+    pub fn verify(&self, secret: &[u8]) -> bool {
+        blake3::hash(secret) == self.hash
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires < Utc::now()
+    }
+}
+
+/// removes all tokens created from the given session
+pub async fn delete_session_tokens(
+    conn: &impl GenericClient,
+    token: &session::token::SessionToken,
+) -> Result<u64, PgError> {
+    conn.execute(
+        "delete from auth_access_tokens where session_token = $1",
+        &[&token.as_slice()]
+    ).await
+}
+
+/// removes tokens that have expired
+pub async fn sweep(conn: &impl GenericClient) -> Result<u64, PgError> {
+    conn.execute(
+        "delete from auth_access_tokens where expires <= $1",
+        &[&Utc::now()]
+    ).await
+}
@@ -6,23 +6,48 @@ use axum::http::StatusCode;
 use axum::http::header::{HeaderMap, HeaderValue, GetAll};
 use axum::http::request::Parts;
 use axum::extract::FromRequestParts;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{DateTime, Utc};
 use deadpool_postgres::{Pool, GenericClient};
 
 use crate::net::error;
 use crate::sec::state;
+use crate::sec::audit;
+use crate::sec::authz::Ability;
+use crate::sec::authz::group;
+use crate::sec::authz::permission::{self, Permission, PermissionSet};
 use crate::user;
+use crate::bot;
 
-use super::session;
+use super::{session, password, lockout, access, Authenticate, Verify};
 
 // not sure what to call this
 pub enum Mechanism {
     Session(session::Session),
+    Bot(bot::token::Token),
+    /// username and password provided with the request
+    Basic,
+    /// short lived bearer token minted from a session
+    Access,
+}
+
+impl Mechanism {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mechanism::Session(_) => "session",
+            Mechanism::Bot(_) => "bot",
+            Mechanism::Basic => "basic",
+            Mechanism::Access => "access",
+        }
+    }
 }
 
 pub struct Initiator {
     user: user::User,
-    bot: Option<()>,
-    mechanism: Mechanism
+    bot: Option<bot::Bot>,
+    mechanism: Mechanism,
+    permissions: PermissionSet,
+    groups: Vec<rfs_lib::ids::GroupId>,
 }
 
 impl Initiator {
@@ -30,13 +55,67 @@ impl Initiator {
         &self.user
     }
 
-    pub fn bot(&self) -> Option<&()> {
+    pub fn bot(&self) -> Option<&bot::Bot> {
         self.bot.as_ref()
     }
 
     pub fn mechanism(&self) -> &Mechanism {
         &self.mechanism
     }
+
+    pub fn into_mechanism(self) -> Mechanism {
+        self.mechanism
+    }
+
+    /// the effective permissions of the user from all assigned roles
+    pub fn permissions(&self) -> &PermissionSet {
+        &self.permissions
+    }
+
+    pub fn has_permission(&self, permission: &Permission) -> bool {
+        self.permissions.contains(permission)
+    }
+
+    /// the groups that the user is a member of
+    pub fn groups(&self) -> &[rfs_lib::ids::GroupId] {
+        self.groups.as_slice()
+    }
+
+    /// limits the ability of the user to what the mechanism allows
+    pub fn restrict_ability(
+        &self,
+        storage_id: &rfs_lib::ids::StorageId,
+        ability: Option<Ability>
+    ) -> Option<Ability> {
+        match &self.mechanism {
+            Mechanism::Session(_) |
+            Mechanism::Basic |
+            Mechanism::Access => ability,
+            Mechanism::Bot(token) => token.restrict(storage_id, ability),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Initiator {
+    /// creates an initiator without looking anything up
+    pub fn unchecked(user: user::User, bot: Option<bot::Bot>, mechanism: Mechanism) -> Self {
+        Initiator {
+            user,
+            bot,
+            mechanism,
+            permissions: PermissionSet::new(),
+            groups: Vec::new(),
+        }
+    }
+
+    pub fn with_permissions<P>(mut self, permissions: P) -> Self
+    where
+        P: IntoIterator<Item = Permission>
+    {
+        self.permissions.extend(permissions);
+        self
+    }
 }
 
 pub enum LookupError {
@@ -44,16 +123,32 @@ pub enum LookupError {
     InvalidLength,
     InvalidHash,
     SessionNotFound,
-    SessionExpired(session::Session),
-    SessionUnauthenticated(session::Session),
-    SessionUnverified(session::Session),
+    SessionExpired(Box<session::Session>),
+    SessionUnauthenticated(Box<session::Session>),
+    SessionUnverified(Box<session::Session>),
+
+    InvalidToken,
+    TokenNotFound,
+    TokenExpired,
+    BotNotFound,
+
+    AccessTokenExpired,
+
+    BasicDisabled,
+    InvalidBasic,
+    BasicNotAllowed,
+    InvalidCredentials,
+    LockedOut(DateTime<Utc>),
+    SecretNotFound,
 
-    UserNotFound(Mechanism),
+    UserNotFound(Box<Mechanism>),
 
     MechanismNotFound,
+    InvalidAuthorization,
 
     Database(tokio_postgres::Error),
     HeaderToStr(axum::http::header::ToStrError),
+    Password(password::PasswordError),
 }
 
 impl From<tokio_postgres::Error> for LookupError {
@@ -68,6 +163,12 @@ impl From<axum::http::header::ToStrError> for LookupError {
     }
 }
 
+impl From<password::PasswordError> for LookupError {
+    fn from(e: password::PasswordError) -> Self {
+        LookupError::Password(e)
+    }
+}
+
 impl From<LookupError> for error::Error {
     fn from(e: LookupError) -> Self {
         match e {
@@ -94,6 +195,48 @@ impl From<LookupError> for error::Error {
                 .kind("SessionUnverified")
                 .message("session has not been verified"),
 
+            LookupError::InvalidToken => error::Error::new()
+                .status(StatusCode::UNAUTHORIZED)
+                .kind("InvalidToken")
+                .message("bearer token is invalid"),
+            LookupError::TokenNotFound => error::Error::new()
+                .status(StatusCode::UNAUTHORIZED)
+                .kind("TokenNotFound")
+                .message("bearer token was not found"),
+            LookupError::TokenExpired => error::Error::new()
+                .status(StatusCode::UNAUTHORIZED)
+                .kind("TokenExpired")
+                .message("bearer token has expired"),
+            LookupError::BotNotFound => error::Error::new()
+                .status(StatusCode::NOT_FOUND)
+                .kind("BotNotFound")
+                .message("bearer token bot was not found"),
+
+            LookupError::AccessTokenExpired => error::Error::new()
+                .status(StatusCode::UNAUTHORIZED)
+                .kind("TokenExpired")
+                .message("access token has expired"),
+
+            LookupError::BasicDisabled => error::Error::new()
+                .status(StatusCode::UNAUTHORIZED)
+                .kind("BasicDisabled")
+                .message("basic authorization is not enabled"),
+            LookupError::InvalidBasic => error::Error::new()
+                .status(StatusCode::UNAUTHORIZED)
+                .kind("InvalidBasic")
+                .message("basic authorization is invalid"),
+            LookupError::BasicNotAllowed => error::Error::new()
+                .status(StatusCode::UNAUTHORIZED)
+                .kind("BasicNotAllowed")
+                .message("user is not able to use basic authorization. log in with a session"),
+            LookupError::InvalidCredentials => error::Error::new()
+                .status(StatusCode::UNAUTHORIZED)
+                .kind("InvalidCredentials")
+                .message("username or password is invalid"),
+            LookupError::LockedOut(until) => lockout::locked_out(&until),
+            LookupError::SecretNotFound => error::Error::new()
+                .source("password secret version not found. unable verify user password"),
+
             LookupError::UserNotFound(authorization) => error::Error::new()
                 .status(StatusCode::NOT_FOUND)
                 .kind("UserNotFound")
@@ -103,9 +246,14 @@ impl From<LookupError> for error::Error {
                 .status(StatusCode::UNAUTHORIZED)
                 .kind("MechanismNotFound")
                 .message("authorization not provided"),
+            LookupError::InvalidAuthorization => error::Error::new()
+                .status(StatusCode::UNAUTHORIZED)
+                .kind("InvalidAuthorization")
+                .message("authorization header is invalid or uses an unsupported scheme"),
 
             LookupError::Database(e) => e.into(),
             LookupError::HeaderToStr(e) => e.into(),
+            LookupError::Password(e) => e.into(),
         }
     }
 }
@@ -129,42 +277,262 @@ where
         }
     };
 
-    if let Some(session) = session::Session::retrieve_token(conn, &token).await? {
+    if let Some(mut session) = session::Session::retrieve_token(conn, &token).await? {
         let now = chrono::Utc::now();
 
         if session.dropped || session.expires < now {
-            return Err(LookupError::SessionExpired(session));
+            return Err(LookupError::SessionExpired(Box::new(session)));
         }
 
         if !session.authenticated {
-            return Err(LookupError::SessionUnauthenticated(session));
+            return Err(LookupError::SessionUnauthenticated(Box::new(session)));
         }
 
         if !session.verified {
-            return Err(LookupError::SessionUnverified(session));
+            return Err(LookupError::SessionUnverified(Box::new(session)));
         }
 
+        session.renew(conn, auth.session_info().lifetimes(), now).await?;
+
         if let Some(user) = user::User::query_with_id(conn, &session.user_id).await? {
+            let permissions = permission::user_permissions(conn, user.id()).await?;
+            let groups = group::user_groups(conn, user.id()).await?;
+
             Ok(Initiator {
                 user,
                 bot: None,
                 mechanism: Mechanism::Session(session),
+                permissions,
+                groups,
             })
         } else {
-            Err(LookupError::UserNotFound(Mechanism::Session(session)))
+            Err(LookupError::UserNotFound(Box::new(Mechanism::Session(session))))
         }
     } else {
         Err(LookupError::SessionNotFound)
     }
 }
 
+pub async fn lookup_bot_token(
+    conn: &impl GenericClient,
+    given: &str,
+) -> Result<Initiator, LookupError> {
+    let Some((token_id, secret)) = bot::token::split_token(given) else {
+        return Err(LookupError::InvalidToken);
+    };
+
+    let Some(mut token) = bot::token::Token::retrieve(conn, token_id).await? else {
+        return Err(LookupError::TokenNotFound);
+    };
+
+    if !token.verify(&secret) {
+        return Err(LookupError::TokenNotFound);
+    }
+
+    if token.is_expired() {
+        return Err(LookupError::TokenExpired);
+    }
+
+    let Some(bot) = bot::Bot::retrieve(conn, &token.bot_id).await? else {
+        return Err(LookupError::BotNotFound);
+    };
+
+    token.update_last_used(conn).await?;
+
+    if let Some(user) = user::User::query_with_id(conn, &bot.user_id).await? {
+        let groups = group::user_groups(conn, user.id()).await?;
+
+        // bots only act on items through the abilities of the owner and do
+        // not inherit any permissions from their roles
+        Ok(Initiator {
+            user,
+            bot: Some(bot),
+            mechanism: Mechanism::Bot(token),
+            permissions: PermissionSet::new(),
+            groups,
+        })
+    } else {
+        Err(LookupError::UserNotFound(Box::new(Mechanism::Bot(token))))
+    }
+}
+
+pub async fn lookup_access_token(
+    conn: &impl GenericClient,
+    given: &str,
+) -> Result<Initiator, LookupError> {
+    let Some((token_id, secret)) = access::split_token(given) else {
+        return Err(LookupError::InvalidToken);
+    };
+
+    let Some(token) = access::AccessToken::retrieve(conn, token_id).await? else {
+        return Err(LookupError::TokenNotFound);
+    };
+
+    if !token.verify(&secret) {
+        return Err(LookupError::TokenNotFound);
+    }
+
+    if token.is_expired() {
+        return Err(LookupError::AccessTokenExpired);
+    }
+
+    if let Some(user) = user::User::query_with_id(conn, &token.user_id).await? {
+        let permissions = permission::user_permissions(conn, user.id()).await?;
+        let groups = group::user_groups(conn, user.id()).await?;
+
+        Ok(Initiator {
+            user,
+            bot: None,
+            mechanism: Mechanism::Access,
+            permissions,
+            groups,
+        })
+    } else {
+        Err(LookupError::UserNotFound(Box::new(Mechanism::Access)))
+    }
+}
+
+/// authenticates a user with the base64 "{username}:{password}" value of a
+/// basic authorization header
+///
+/// only users that log in with a password and have no verify method are
+/// able to use this. since every request is a login attempt, failures are
+/// tracked against the ip with a lower threshold than session logins
+pub async fn lookup_basic(
+    auth: &state::Sec,
+    conn: &impl GenericClient,
+    given: &str,
+    context: &audit::Context,
+) -> Result<Initiator, LookupError> {
+    if !*auth.basic_auth() {
+        return Err(LookupError::BasicDisabled);
+    }
+
+    let decoded = STANDARD.decode(given)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .ok_or(LookupError::InvalidBasic)?;
+
+    let Some((username, given_password)) = decoded.split_once(':') else {
+        return Err(LookupError::InvalidBasic);
+    };
+
+    let mut subjects = Vec::with_capacity(2);
+
+    if let Some(ip) = context.ip() {
+        subjects.push(lockout::Subject::Basic(ip));
+    }
+
+    if let Some(until) = lockout::locked_until(conn, &subjects).await? {
+        return Err(LookupError::LockedOut(until));
+    }
+
+    let Some(user) = user::User::query_with_username(conn, &username.to_owned()).await? else {
+        lockout::record_failure(conn, auth.lockout(), &subjects).await?;
+
+        context.entry(audit::Event::LoginFailed)
+            .with_mechanism("basic")
+            .with_details(serde_json::json!({"reason": "user not found"}))
+            .record(conn)
+            .await?;
+
+        return Err(LookupError::InvalidCredentials);
+    };
+
+    subjects.push(lockout::Subject::User(user.id().clone()));
+
+    if let Some(until) = lockout::locked_until(conn, &subjects).await? {
+        return Err(LookupError::LockedOut(until));
+    }
+
+    let Some(Authenticate::Password(user_password)) = Authenticate::retrieve_primary(
+        conn,
+        user.id()
+    ).await? else {
+        return Err(LookupError::BasicNotAllowed);
+    };
+
+    if Verify::retrieve_primary(conn, user.id()).await?.is_some() {
+        return Err(LookupError::BasicNotAllowed);
+    }
+
+    let Some(secret) = auth.secrets().get(user_password.version()) else {
+        return Err(LookupError::SecretNotFound);
+    };
+
+    if !user_password.verify(given_password, secret)? {
+        lockout::record_failure(conn, auth.lockout(), &subjects).await?;
+
+        context.entry(audit::Event::LoginFailed)
+            .with_user_id(user.id().clone())
+            .with_mechanism("basic")
+            .with_details(serde_json::json!({"reason": "invalid password"}))
+            .record(conn)
+            .await?;
+
+        return Err(LookupError::InvalidCredentials);
+    }
+
+    let permissions = permission::user_permissions(conn, user.id()).await?;
+    let groups = group::user_groups(conn, user.id()).await?;
+
+    Ok(Initiator {
+        user,
+        bot: None,
+        mechanism: Mechanism::Basic,
+        permissions,
+        groups,
+    })
+}
+
+enum Authorization<'a> {
+    Bearer(&'a str),
+    Basic(&'a str),
+}
+
+/// an authorization header that is not understood is an error instead of
+/// being ignored so that the request does not fall back to the session
+/// cookie
+fn find_authorization(headers: &HeaderMap) -> Result<Option<Authorization<'_>>, LookupError> {
+    let Some(value) = headers.get("authorization") else {
+        return Ok(None);
+    };
+
+    let value_str = value.to_str()?;
+
+    let Some((scheme, token)) = value_str.split_once(' ') else {
+        return Err(LookupError::InvalidAuthorization);
+    };
+
+    let token = token.trim();
+
+    if token.is_empty() {
+        return Err(LookupError::InvalidAuthorization);
+    }
+
+    if scheme.eq_ignore_ascii_case("bearer") {
+        Ok(Some(Authorization::Bearer(token)))
+    } else if scheme.eq_ignore_ascii_case("basic") {
+        Ok(Some(Authorization::Basic(token)))
+    } else {
+        Err(LookupError::InvalidAuthorization)
+    }
+}
+
+/// checks if the request has a bearer token. browsers will not attach one
+/// to a request on their own unlike cookies and basic credentials
+pub fn has_bearer_authorization(headers: &HeaderMap) -> bool {
+    matches!(find_authorization(headers), Ok(Some(Authorization::Bearer(_))))
+}
+
 fn find_session_id<'a>(cookies: GetAll<'a, HeaderValue>) -> Result<Option<&'a str>, LookupError> {
+    // clients send every cookie in a single header separated by ";"
     for value in cookies {
-        let value_str = value.to_str()?;
-
-        if let Some((name, value)) = value_str.split_once('=') {
-            if name == "session_id" {
-                return Ok(Some(value));
+        for pair in value.to_str()?.split(';') {
+            if let Some((name, value)) = pair.trim().split_once('=') {
+                if name == "session_id" {
+                    return Ok(Some(value));
+                }
             }
         }
     }
@@ -172,11 +540,29 @@ fn find_session_id<'a>(cookies: GetAll<'a, HeaderValue>) -> Result<Option<&'a st
     Ok(None)
 }
 
+/// finds the initiator of a request from the authorization or cookie
+/// headers. the context of the request is used to limit and record basic
+/// authorization attempts
 pub async fn lookup_header_map(
     auth: &state::Sec,
     conn: &impl GenericClient,
-    headers: &HeaderMap
+    headers: &HeaderMap,
+    context: &audit::Context,
 ) -> Result<Initiator, LookupError> {
+    match find_authorization(headers)? {
+        Some(Authorization::Bearer(found)) => {
+            return if found.starts_with(access::TOKEN_PREFIX) {
+                lookup_access_token(conn, found).await
+            } else {
+                lookup_bot_token(conn, found).await
+            };
+        },
+        Some(Authorization::Basic(found)) => {
+            return lookup_basic(auth, conn, found, context).await;
+        },
+        None => {}
+    }
+
     let cookies = headers.get_all("cookie");
 
     if let Some(found) = find_session_id(cookies)? {
@@ -213,8 +599,50 @@ where
             let pool: &Pool = state_deref.as_ref();
             let conn = pool.get().await?;
 
-            Ok(lookup_header_map(auth, &conn, &parts.headers).await?)
+            let context = audit::Context::from_parts(parts);
+
+            Ok(lookup_header_map(auth, &conn, &parts.headers, &context).await?)
         })
     }
 }
 
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn headers(authorization: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", authorization.parse().unwrap());
+        headers.insert("cookie", "session_id=abc".parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn unknown_scheme_does_not_fall_back() {
+        for authorization in ["Custom token", "Bearer", "Basic ", "token"] {
+            assert!(
+                matches!(
+                    find_authorization(&headers(authorization)),
+                    Err(LookupError::InvalidAuthorization)
+                ),
+                "\"{}\" was not rejected",
+                authorization
+            );
+        }
+    }
+
+    #[test]
+    fn supported_schemes() {
+        assert!(matches!(
+            find_authorization(&headers("bearer token")),
+            Ok(Some(Authorization::Bearer("token")))
+        ));
+        assert!(matches!(
+            find_authorization(&headers("Basic dXNlcjpwYXNz")),
+            Ok(Some(Authorization::Basic("dXNlcjpwYXNz")))
+        ));
+        assert!(has_bearer_authorization(&headers("Bearer token")));
+        assert!(!has_bearer_authorization(&headers("Basic dXNlcjpwYXNz")));
+    }
+}
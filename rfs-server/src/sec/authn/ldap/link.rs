@@ -0,0 +1,246 @@
+use chrono::{DateTime, Utc, Duration};
+use rfs_lib::ids;
+use tokio_postgres::Error as PgError;
+use deadpool_postgres::GenericClient;
+
+use crate::net::cookie::SetCookie;
+use crate::sec::state;
+use crate::sec::authn::oidc::random_token;
+use crate::sec::authn::oidc::link::PendingError;
+
+/// the cookie that holds the token of a pending login
+pub const PENDING_COOKIE: &str = "ldap_pending";
+
+/// a login for a username that has no local user. the user is only created
+/// once the password has been checked against the directory
+pub struct PendingLogin {
+    pub token: String,
+    pub username: String,
+    pub expires: DateTime<Utc>,
+}
+
+impl PendingLogin {
+    /// how long the user has to submit their password
+    pub fn lifetime() -> Duration {
+        Duration::minutes(10)
+    }
+
+    pub async fn create(conn: &impl GenericClient, username: String) -> Result<Self, PendingError> {
+        let created = Utc::now();
+        let expires = created + Self::lifetime();
+        let token = random_token()?;
+
+        let _ = conn.execute(
+            "\
+            insert into auth_ldap_pending (token, username, expires, created) values \
+            ($1, $2, $3, $4)",
+            &[&token, &username, &expires, &created]
+        ).await?;
+
+        Ok(PendingLogin {
+            token,
+            username,
+            expires,
+        })
+    }
+
+    /// returns none if the token was not found or is expired
+    pub async fn retrieve(conn: &impl GenericClient, token: &str) -> Result<Option<Self>, PgError> {
+        let Some(row) = conn.query_opt(
+            "\
+            select auth_ldap_pending.username, \
+                   auth_ldap_pending.expires \
+            from auth_ldap_pending \
+            where auth_ldap_pending.token = $1",
+            &[&token]
+        ).await? else {
+            return Ok(None);
+        };
+
+        let found = PendingLogin {
+            token: token.to_owned(),
+            username: row.get(0),
+            expires: row.get(1),
+        };
+
+        if found.expires <= Utc::now() {
+            return Ok(None);
+        }
+
+        Ok(Some(found))
+    }
+
+    pub async fn delete(&self, conn: &impl GenericClient) -> Result<(), PgError> {
+        let _ = conn.execute(
+            "delete from auth_ldap_pending where token = $1",
+            &[&self.token]
+        ).await?;
+
+        Ok(())
+    }
+}
+
+/// removes pending logins that have expired
+pub async fn sweep(conn: &impl GenericClient) -> Result<u64, PgError> {
+    conn.execute(
+        "delete from auth_ldap_pending where expires <= $1",
+        &[&Utc::now()]
+    ).await
+}
+
+pub fn create_pending_cookie(auth: &state::Sec, pending: &PendingLogin) -> SetCookie {
+    let mut cookie = SetCookie::new(PENDING_COOKIE, pending.token.clone())
+        .with_expires(pending.expires)
+        .with_path("/auth")
+        .with_http_only(true)
+        .with_secure(*auth.session_info().secure())
+        .with_same_site(auth.session_info().same_site());
+
+    if let Some(domain) = auth.session_info().domain() {
+        cookie.set_domain(domain);
+    }
+
+    cookie
+}
+
+pub fn expire_pending_cookie(auth: &state::Sec) -> SetCookie {
+    let mut cookie = SetCookie::new(PENDING_COOKIE, "")
+        .with_max_age(std::time::Duration::new(0, 0))
+        .with_path("/auth")
+        .with_http_only(true)
+        .with_secure(*auth.session_info().secure())
+        .with_same_site(auth.session_info().same_site());
+
+    if let Some(domain) = auth.session_info().domain() {
+        cookie.set_domain(domain);
+    }
+
+    cookie
+}
+
+/// marks a local user as authenticating against the directory
+pub struct Link {
+    pub user_id: ids::UserId,
+    pub dn: String,
+    pub synced: DateTime<Utc>,
+}
+
+impl Link {
+    pub async fn create(
+        conn: &impl GenericClient,
+        user_id: ids::UserId,
+        dn: String,
+    ) -> Result<Self, PgError> {
+        let synced = Utc::now();
+
+        let _ = conn.execute(
+            "insert into auth_ldap (user_id, dn, synced) values ($1, $2, $3)",
+            &[&user_id, &dn, &synced]
+        ).await?;
+
+        Ok(Link {
+            user_id,
+            dn,
+            synced,
+        })
+    }
+
+    pub async fn retrieve(
+        conn: &impl GenericClient,
+        user_id: &ids::UserId,
+    ) -> Result<Option<Self>, PgError> {
+        let result = conn.query_opt(
+            "\
+            select auth_ldap.dn, \
+                   auth_ldap.synced \
+            from auth_ldap \
+            where auth_ldap.user_id = $1",
+            &[user_id]
+        ).await?;
+
+        Ok(result.map(|row| Link {
+            user_id: user_id.clone(),
+            dn: row.get(0),
+            synced: row.get(1),
+        }))
+    }
+
+    /// records that the user was just found in the directory
+    pub async fn touch(&mut self, conn: &impl GenericClient) -> Result<(), PgError> {
+        let synced = Utc::now();
+
+        let _ = conn.execute(
+            "update auth_ldap set synced = $2 where user_id = $1",
+            &[&self.user_id, &synced]
+        ).await?;
+
+        self.synced = synced;
+
+        Ok(())
+    }
+}
+
+/// updates the local groups of the user to match the given group names
+///
+/// only memberships that were added by a previous sync are removed so
+/// groups the user was added to locally are left alone. names without a
+/// matching local group are ignored
+pub async fn sync_groups(
+    conn: &impl GenericClient,
+    user_id: &ids::UserId,
+    names: &[String],
+) -> Result<(), PgError> {
+    let now = Utc::now();
+
+    let desired: Vec<i64> = conn.query(
+        "select groups.id from groups where groups.name = any($1)",
+        &[&names]
+    )
+        .await?
+        .into_iter()
+        .map(|row| row.get(0))
+        .collect();
+
+    let _ = conn.execute(
+        "\
+        delete from group_users \
+        using auth_ldap_groups \
+        where group_users.group_id = auth_ldap_groups.group_id and \
+              group_users.user_id = auth_ldap_groups.user_id and \
+              auth_ldap_groups.user_id = $1 and \
+              not (auth_ldap_groups.group_id = any($2))",
+        &[user_id, &desired]
+    ).await?;
+
+    let _ = conn.execute(
+        "\
+        delete from auth_ldap_groups \
+        where user_id = $1 and \
+              not (group_id = any($2))",
+        &[user_id, &desired]
+    ).await?;
+
+    for group_id in desired {
+        let added = conn.execute(
+            "\
+            insert into group_users (group_id, user_id, added) values \
+            ($1, $2, $3) \
+            on conflict on constraint unique_group_id_user_id do nothing",
+            &[&group_id, user_id, &now]
+        ).await?;
+
+        // existing memberships are not tracked since they were not added
+        // by the sync
+        if added == 1 {
+            let _ = conn.execute(
+                "\
+                insert into auth_ldap_groups (user_id, group_id) values \
+                ($1, $2) \
+                on conflict do nothing",
+                &[user_id, &group_id]
+            ).await?;
+        }
+    }
+
+    Ok(())
+}
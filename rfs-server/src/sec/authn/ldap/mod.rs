@@ -0,0 +1,349 @@
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use ldap3::{LdapConnAsync, LdapConnSettings, Ldap, Scope, SearchEntry};
+
+use crate::error;
+use crate::net;
+
+pub mod link;
+
+/// the filter used to find users when none is provided
+pub const DEFAULT_USER_FILTER: &str = "(uid={username})";
+/// the attribute that holds the groups a user is a member of
+pub const DEFAULT_GROUP_ATTR: &str = "memberOf";
+/// the attribute that holds the email of a user
+pub const DEFAULT_EMAIL_ATTR: &str = "mail";
+
+/// the result code returned when a bind fails because of the credentials
+const INVALID_CREDENTIALS: u32 = 49;
+
+pub enum LdapError {
+    Ldap(ldap3::LdapError),
+}
+
+impl From<ldap3::LdapError> for LdapError {
+    fn from(err: ldap3::LdapError) -> Self {
+        LdapError::Ldap(err)
+    }
+}
+
+impl From<LdapError> for net::error::Error {
+    fn from(err: LdapError) -> Self {
+        match err {
+            LdapError::Ldap(err) => net::error::Error::new()
+                .status(StatusCode::BAD_GATEWAY)
+                .kind("LdapError")
+                .message("failed to communicate with the ldap server")
+                .source(err),
+        }
+    }
+}
+
+/// a user found in the directory
+pub struct Entry {
+    pub dn: String,
+    pub email: Option<String>,
+    /// the names of the groups the user is a member of
+    pub groups: Vec<String>,
+}
+
+/// retrieves the value of the first component of a dn. "cn=admins,dc=example"
+/// would give "admins"
+fn dn_name(dn: &str) -> Option<&str> {
+    let first = dn.split(',').next()?;
+    let (_key, value) = first.split_once('=')?;
+    let trimmed = value.trim();
+
+    if trimmed.is_empty() {
+        None
+    } else {
+        Some(trimmed)
+    }
+}
+
+#[derive(Debug)]
+pub struct Builder {
+    url: Option<String>,
+    base_dn: Option<String>,
+    user_filter: Option<String>,
+    bind_dn: Option<String>,
+    bind_password: Option<String>,
+    starttls: bool,
+    no_tls_verify: bool,
+    email_attr: Option<String>,
+    group_attr: Option<String>,
+    sync_groups: bool,
+    provision: bool,
+}
+
+impl Builder {
+    /// url of the ldap server. "ldaps://" urls connect with implicit TLS
+    pub fn set_url(&mut self, url: String) -> &mut Self {
+        self.url = Some(url);
+        self
+    }
+
+    /// where to search for users
+    pub fn set_base_dn(&mut self, base_dn: String) -> &mut Self {
+        self.base_dn = Some(base_dn);
+        self
+    }
+
+    /// the filter to find a user with. "{username}" is replaced with the
+    /// escaped username
+    pub fn set_user_filter(&mut self, filter: String) -> &mut Self {
+        self.user_filter = Some(filter);
+        self
+    }
+
+    /// the account to search with. searches are anonymous if not provided
+    pub fn set_bind_dn(&mut self, bind_dn: String) -> &mut Self {
+        self.bind_dn = Some(bind_dn);
+        self
+    }
+
+    pub fn set_bind_password(&mut self, bind_password: String) -> &mut Self {
+        self.bind_password = Some(bind_password);
+        self
+    }
+
+    /// upgrade plain connections with STARTTLS
+    pub fn set_starttls(&mut self, starttls: bool) -> &mut Self {
+        self.starttls = starttls;
+        self
+    }
+
+    /// skips verifying the certificate of the server. only intended for
+    /// testing against local servers
+    pub fn set_no_tls_verify(&mut self, no_tls_verify: bool) -> &mut Self {
+        self.no_tls_verify = no_tls_verify;
+        self
+    }
+
+    pub fn set_email_attr(&mut self, attr: String) -> &mut Self {
+        self.email_attr = Some(attr);
+        self
+    }
+
+    pub fn set_group_attr(&mut self, attr: String) -> &mut Self {
+        self.group_attr = Some(attr);
+        self
+    }
+
+    /// updates local group membership to match the directory on login
+    pub fn set_sync_groups(&mut self, sync_groups: bool) -> &mut Self {
+        self.sync_groups = sync_groups;
+        self
+    }
+
+    /// creates local users for directory accounts that have not logged in
+    /// before
+    pub fn set_provision(&mut self, provision: bool) -> &mut Self {
+        self.provision = provision;
+        self
+    }
+
+    /// creates the directory if a url was provided
+    pub fn build(self) -> error::Result<Option<Directory>> {
+        let Some(url) = self.url else {
+            return Ok(None);
+        };
+
+        url::Url::parse(&url).map_err(|err| error::Error::new()
+            .kind("InvalidLdapConfig")
+            .message("the ldap url is not a valid url")
+            .source(err))?;
+
+        let Some(base_dn) = self.base_dn else {
+            return Err(error::Error::new()
+                .kind("InvalidLdapConfig")
+                .message("a base dn is required when an ldap url is provided"));
+        };
+
+        let user_filter = self.user_filter
+            .unwrap_or(String::from(DEFAULT_USER_FILTER));
+
+        if !user_filter.contains("{username}") {
+            return Err(error::Error::new()
+                .kind("InvalidLdapConfig")
+                .message("the ldap user filter must contain \"{username}\""));
+        }
+
+        if self.bind_dn.is_some() != self.bind_password.is_some() {
+            return Err(error::Error::new()
+                .kind("InvalidLdapConfig")
+                .message("the ldap bind dn and bind password must be provided together"));
+        }
+
+        Ok(Some(Directory {
+            url,
+            base_dn,
+            user_filter,
+            bind_dn: self.bind_dn,
+            bind_password: self.bind_password,
+            starttls: self.starttls,
+            no_tls_verify: self.no_tls_verify,
+            email_attr: self.email_attr
+                .unwrap_or(String::from(DEFAULT_EMAIL_ATTR)),
+            group_attr: self.group_attr
+                .unwrap_or(String::from(DEFAULT_GROUP_ATTR)),
+            sync_groups: self.sync_groups,
+            provision: self.provision,
+        }))
+    }
+}
+
+/// an ldap server that users can authenticate against
+pub struct Directory {
+    url: String,
+    base_dn: String,
+    user_filter: String,
+    bind_dn: Option<String>,
+    bind_password: Option<String>,
+    starttls: bool,
+    no_tls_verify: bool,
+    email_attr: String,
+    group_attr: String,
+    sync_groups: bool,
+    provision: bool,
+}
+
+impl Directory {
+    pub fn builder() -> Builder {
+        Builder {
+            url: None,
+            base_dn: None,
+            user_filter: None,
+            bind_dn: None,
+            bind_password: None,
+            starttls: false,
+            no_tls_verify: false,
+            email_attr: None,
+            group_attr: None,
+            sync_groups: false,
+            provision: false,
+        }
+    }
+
+    pub fn sync_groups(&self) -> &bool {
+        &self.sync_groups
+    }
+
+    pub fn provision(&self) -> &bool {
+        &self.provision
+    }
+
+    /// opens a new connection to the server
+    ///
+    /// a connection is made for each login since they are infrequent and
+    /// the bound user changes
+    async fn connect(&self) -> Result<Ldap, LdapError> {
+        let settings = LdapConnSettings::new()
+            .set_conn_timeout(Duration::from_secs(10))
+            .set_starttls(self.starttls)
+            .set_no_tls_verify(self.no_tls_verify);
+
+        let (conn, ldap) = LdapConnAsync::with_settings(settings, &self.url).await?;
+
+        ldap3::drive!(conn);
+
+        Ok(ldap)
+    }
+
+    /// binds with the search account if one was provided
+    async fn search_bind(&self, ldap: &mut Ldap) -> Result<(), LdapError> {
+        if let (Some(dn), Some(password)) = (&self.bind_dn, &self.bind_password) {
+            ldap.simple_bind(dn, password).await?.success()?;
+        }
+
+        Ok(())
+    }
+
+    /// searches for the user with the given username
+    ///
+    /// returns none if no user or more than one user matched the filter
+    async fn search(&self, ldap: &mut Ldap, username: &str) -> Result<Option<Entry>, LdapError> {
+        let filter = self.user_filter.replace("{username}", &ldap3::ldap_escape(username));
+        let attrs = [self.email_attr.as_str(), self.group_attr.as_str()];
+
+        let (mut entries, _result) = ldap.search(
+            &self.base_dn,
+            Scope::Subtree,
+            &filter,
+            attrs
+        ).await?.success()?;
+
+        if entries.len() != 1 {
+            return Ok(None);
+        }
+
+        let found = SearchEntry::construct(entries.remove(0));
+
+        let email = found.attrs.get(&self.email_attr)
+            .and_then(|values| values.first())
+            .cloned();
+        let groups = found.attrs.get(&self.group_attr)
+            .map(|values| values.iter()
+                .filter_map(|dn| dn_name(dn))
+                .map(|name| name.to_owned())
+                .collect())
+            .unwrap_or_default();
+
+        Ok(Some(Entry {
+            dn: found.dn,
+            email,
+            groups,
+        }))
+    }
+
+    /// checks the password by binding as the user
+    ///
+    /// returns none if the user was not found or the password is invalid
+    pub async fn authenticate(&self, username: &str, password: &str) -> Result<Option<Entry>, LdapError> {
+        // an empty password would be an unauthenticated bind which most
+        // servers will accept
+        if password.is_empty() {
+            return Ok(None);
+        }
+
+        let mut ldap = self.connect().await?;
+
+        self.search_bind(&mut ldap).await?;
+
+        let Some(entry) = self.search(&mut ldap, username).await? else {
+            let _ = ldap.unbind().await;
+
+            return Ok(None);
+        };
+
+        let result = ldap.simple_bind(&entry.dn, password).await?;
+
+        let _ = ldap.unbind().await;
+
+        if result.rc == INVALID_CREDENTIALS {
+            return Ok(None);
+        }
+
+        result.success()?;
+
+        Ok(Some(entry))
+    }
+}
+
+impl std::fmt::Debug for Directory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Directory")
+            .field("url", &self.url)
+            .field("base_dn", &self.base_dn)
+            .field("user_filter", &self.user_filter)
+            .field("bind_dn", &self.bind_dn)
+            .field("starttls", &self.starttls)
+            .field("no_tls_verify", &self.no_tls_verify)
+            .field("email_attr", &self.email_attr)
+            .field("group_attr", &self.group_attr)
+            .field("sync_groups", &self.sync_groups)
+            .field("provision", &self.provision)
+            .finish()
+    }
+}
@@ -0,0 +1,283 @@
+use std::net::IpAddr;
+
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use rfs_lib::ids;
+use tokio_postgres::Error as PgError;
+use deadpool_postgres::GenericClient;
+
+use crate::net::error;
+use crate::sec::state::LockoutConfig;
+
+/// what failed attempts are tracked against
+pub enum Subject {
+    User(ids::UserId),
+    Ip(IpAddr),
+    /// basic authorization attempts from an ip
+    Basic(IpAddr),
+    /// share link password attempts from an ip for the given share token
+    Share(String, IpAddr),
+}
+
+impl Subject {
+    fn key(&self) -> String {
+        match self {
+            Subject::User(user_id) => format!("user:{}", user_id.id()),
+            Subject::Ip(ip) => format!("ip:{}", ip),
+            Subject::Basic(ip) => format!("basic:{}", ip),
+            Subject::Share(token, ip) => format!("share:{}:{}", token, ip),
+        }
+    }
+
+    fn threshold(&self, config: &LockoutConfig) -> u32 {
+        match self {
+            Subject::User(_) => config.user_threshold,
+            Subject::Ip(_) => config.ip_threshold,
+            Subject::Basic(_) => config.basic_threshold,
+            Subject::Share(..) => config.user_threshold,
+        }
+    }
+}
+
+/// the error returned when a subject is locked out
+pub fn locked_out(until: &DateTime<Utc>) -> error::Error {
+    let seconds = (*until - Utc::now()).num_seconds().max(1);
+
+    error::Error::new()
+        .status(StatusCode::TOO_MANY_REQUESTS)
+        .kind("TooManyAttempts")
+        .message(format!("too many failed attempts. try again in {} seconds", seconds))
+}
+
+/// finds the latest lockout for any of the given subjects
+pub async fn locked_until(
+    conn: &impl GenericClient,
+    subjects: &[Subject],
+) -> Result<Option<DateTime<Utc>>, PgError> {
+    let now = Utc::now();
+    let keys: Vec<String> = subjects.iter()
+        .map(|subject| subject.key())
+        .collect();
+
+    let row = conn.query_one(
+        "\
+        select max(auth_lockout.locked_until) \
+        from auth_lockout \
+        where auth_lockout.subject = any($1) and \
+              auth_lockout.locked_until > $2",
+        &[&keys, &now]
+    ).await?;
+
+    Ok(row.get(0))
+}
+
+/// returns an error if any of the subjects are currently locked out
+pub async fn check(
+    conn: &impl GenericClient,
+    subjects: &[Subject],
+) -> error::Result<()> {
+    if let Some(until) = locked_until(conn, subjects).await? {
+        return Err(locked_out(&until));
+    }
+
+    Ok(())
+}
+
+/// records a failed attempt for each subject and locks out any that have
+/// gone over their threshold
+///
+/// this should be called outside of a transaction so that the attempt is
+/// recorded even if the request fails
+pub async fn record_failure(
+    conn: &impl GenericClient,
+    config: &LockoutConfig,
+    subjects: &[Subject],
+) -> Result<(), PgError> {
+    let now = Utc::now();
+    let reset = now - config.reset;
+
+    for subject in subjects {
+        let key = subject.key();
+
+        let row = conn.query_one(
+            "\
+            insert into auth_lockout (subject, failures, last_failure) values \
+            ($1, 1, $2) \
+            on conflict (subject) do update set \
+                failures = case when auth_lockout.last_failure < $3 \
+                    then 1 \
+                    else auth_lockout.failures + 1 \
+                end, \
+                last_failure = EXCLUDED.last_failure \
+            returning failures",
+            &[&key, &now, &reset]
+        ).await?;
+
+        let failures: i32 = row.get(0);
+
+        if let Some(duration) = config.lockout_for(failures as u32, subject.threshold(config)) {
+            let locked_until = now + duration;
+
+            let _ = conn.execute(
+                "update auth_lockout set locked_until = $2 where subject = $1",
+                &[&key, &locked_until]
+            ).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// removes any failed attempts and lockout for the subject
+pub async fn clear(
+    conn: &impl GenericClient,
+    subject: &Subject,
+) -> Result<bool, PgError> {
+    let deleted = conn.execute(
+        "delete from auth_lockout where subject = $1",
+        &[&subject.key()]
+    ).await?;
+
+    Ok(deleted == 1)
+}
+
+/// removes entries that are no longer locked out and have not failed within
+/// the reset window
+pub async fn sweep(
+    conn: &impl GenericClient,
+    config: &LockoutConfig,
+) -> Result<u64, PgError> {
+    let now = Utc::now();
+    let reset = now - config.reset;
+
+    conn.execute(
+        "\
+        delete from auth_lockout \
+        where last_failure < $2 and \
+              (locked_until is null or locked_until <= $1)",
+        &[&now, &reset]
+    ).await
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::Duration;
+
+    use crate::testing::database;
+
+    use super::*;
+
+    fn config() -> LockoutConfig {
+        LockoutConfig {
+            user_threshold: 3,
+            ip_threshold: 5,
+            basic_threshold: 2,
+            delay: Duration::seconds(10),
+            max: Duration::minutes(1),
+            reset: Duration::hours(1),
+        }
+    }
+
+    fn ip() -> IpAddr {
+        IpAddr::from([127, 0, 0, 1])
+    }
+
+    async fn failures(conn: &impl GenericClient, subject: &Subject) -> Option<i32> {
+        conn.query_opt("select failures from auth_lockout where subject = $1", &[&subject.key()])
+            .await
+            .unwrap()
+            .map(|row| row.get(0))
+    }
+
+    #[test]
+    fn lockout_doubles_until_max() {
+        let config = config();
+
+        assert_eq!(config.lockout_for(2, 3), None);
+        assert_eq!(config.lockout_for(3, 3), Some(Duration::seconds(10)));
+        assert_eq!(config.lockout_for(4, 3), Some(Duration::seconds(20)));
+        assert_eq!(config.lockout_for(5, 3), Some(Duration::seconds(40)));
+        assert_eq!(config.lockout_for(6, 3), Some(Duration::minutes(1)));
+        assert_eq!(config.lockout_for(u32::MAX, 3), Some(Duration::minutes(1)));
+    }
+
+    #[test]
+    fn subjects_use_their_threshold() {
+        let config = config();
+        let user_id = ids::user_id_from_pg(1);
+
+        assert_eq!(Subject::User(user_id).threshold(&config), 3);
+        assert_eq!(Subject::Ip(ip()).threshold(&config), 5);
+        assert_eq!(Subject::Basic(ip()).threshold(&config), 2);
+        assert_eq!(Subject::Share("token".into(), ip()).threshold(&config), 3);
+        assert_ne!(
+            Subject::Ip(ip()).key(),
+            Subject::Basic(ip()).key(),
+            "basic attempts are counted with logins"
+        );
+    }
+
+    #[tokio::test]
+    async fn locks_out_at_threshold() {
+        let Some(state) = database().await else {
+            return;
+        };
+        let conn = state.pool().get().await.unwrap();
+        let config = config();
+        let subjects = [Subject::Basic(ip())];
+
+        record_failure(&conn, &config, &subjects).await.unwrap();
+
+        assert!(check(&conn, &subjects).await.is_ok(), "locked out before threshold");
+
+        record_failure(&conn, &config, &subjects).await.unwrap();
+
+        let Err(err) = check(&conn, &subjects).await else {
+            panic!("not locked out at threshold");
+        };
+
+        assert_eq!(
+            axum::response::IntoResponse::into_response(err).status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        assert!(
+            check(&conn, &[Subject::Ip(ip())]).await.is_ok(),
+            "other subjects were locked out"
+        );
+
+        assert!(clear(&conn, &subjects[0]).await.unwrap());
+        assert!(check(&conn, &subjects).await.is_ok(), "lockout was not cleared");
+    }
+
+    #[tokio::test]
+    async fn failures_reset_after_window() {
+        let Some(state) = database().await else {
+            return;
+        };
+        let conn = state.pool().get().await.unwrap();
+        let config = config();
+        let subjects = [Subject::Ip(ip())];
+
+        record_failure(&conn, &config, &subjects).await.unwrap();
+        record_failure(&conn, &config, &subjects).await.unwrap();
+
+        assert_eq!(failures(&conn, &subjects[0]).await, Some(2));
+
+        conn.execute(
+            "update auth_lockout set last_failure = $2 where subject = $1",
+            &[&subjects[0].key(), &(Utc::now() - Duration::hours(2))]
+        ).await.unwrap();
+
+        record_failure(&conn, &config, &subjects).await.unwrap();
+
+        assert_eq!(failures(&conn, &subjects[0]).await, Some(1), "count was not reset");
+
+        conn.execute(
+            "update auth_lockout set last_failure = $2 where subject = $1",
+            &[&subjects[0].key(), &(Utc::now() - Duration::hours(2))]
+        ).await.unwrap();
+
+        assert_eq!(sweep(&conn, &config).await.unwrap(), 1);
+        assert_eq!(failures(&conn, &subjects[0]).await, None);
+    }
+}
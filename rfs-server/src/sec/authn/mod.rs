@@ -6,6 +6,11 @@ pub mod session;
 pub mod password;
 pub mod totp;
 pub mod initiator;
+pub mod signed;
+pub mod lockout;
+pub mod oidc;
+pub mod ldap;
+pub mod access;
 
 pub enum Verify {
     Totp(totp::Totp)
@@ -16,7 +21,8 @@ impl Verify {
         conn: &impl GenericClient,
         id: &ids::UserId,
     ) -> Result<Option<Verify>, PgError> {
-        if let Some(totp) = totp::Totp::retrieve(conn, id).await? {
+        // pending totps are not used until they are verified
+        if let Some(totp) = totp::Totp::retrieve(conn, id).await?.filter(|totp| *totp.verified()) {
             Ok(Some(Verify::Totp(totp)))
         } else {
             Ok(None)
@@ -25,7 +31,9 @@ impl Verify {
 }
 
 pub enum Authenticate {
-    Password(password::Password)
+    Password(password::Password),
+    Ldap,
+    Oidc,
 }
 
 impl Authenticate {
@@ -35,8 +43,80 @@ impl Authenticate {
     ) -> Result<Option<Self>, PgError> {
         if let Some(password) = password::Password::retrieve(conn, id).await? {
             Ok(Some(Authenticate::Password(password)))
+        } else if ldap::link::Link::retrieve(conn, id).await?.is_some() {
+            Ok(Some(Authenticate::Ldap))
+        } else if oidc::link::Link::exists(conn, id).await? {
+            Ok(Some(Authenticate::Oidc))
         } else {
             Ok(None)
         }
     }
 }
+
+/// periodically removes expired sessions, access tokens, lockouts, old totp
+/// attempts, pending oidc and ldap logins and audit log entries past their
+/// retention from the database
+pub fn start_sweeper(
+    pool: deadpool_postgres::Pool,
+    lockout_config: crate::sec::state::LockoutConfig,
+    audit_retention: Option<chrono::Duration>,
+    period: std::time::Duration,
+    shutdown: tokio_util::sync::CancellationToken,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+
+        loop {
+            // a sweep that has started is allowed to finish
+            tokio::select! {
+                _ = interval.tick() => {},
+                _ = shutdown.cancelled() => break,
+            }
+
+            let conn = match pool.get().await {
+                Ok(conn) => conn,
+                Err(err) => {
+                    tracing::error!("failed to get connection for sweeper: {}", err);
+                    continue;
+                }
+            };
+
+            match session::sweep(&conn).await {
+                Ok(count) => tracing::debug!(count = count, "swept sessions"),
+                Err(err) => tracing::error!("failed to sweep sessions: {}", err),
+            }
+
+            match access::sweep(&conn).await {
+                Ok(count) => tracing::debug!(count = count, "swept access tokens"),
+                Err(err) => tracing::error!("failed to sweep access tokens: {}", err),
+            }
+
+            match lockout::sweep(&conn, &lockout_config).await {
+                Ok(count) => tracing::debug!(count = count, "swept lockouts"),
+                Err(err) => tracing::error!("failed to sweep lockouts: {}", err),
+            }
+
+            match totp::sweep_attempts(&conn, &lockout_config).await {
+                Ok(count) => tracing::debug!(count = count, "swept totp attempts"),
+                Err(err) => tracing::error!("failed to sweep totp attempts: {}", err),
+            }
+
+            match oidc::link::sweep(&conn).await {
+                Ok(count) => tracing::debug!(count = count, "swept pending oidc logins"),
+                Err(err) => tracing::error!("failed to sweep pending oidc logins: {}", err),
+            }
+
+            match ldap::link::sweep(&conn).await {
+                Ok(count) => tracing::debug!(count = count, "swept pending ldap logins"),
+                Err(err) => tracing::error!("failed to sweep pending ldap logins: {}", err),
+            }
+
+            if let Some(retention) = &audit_retention {
+                match crate::sec::audit::sweep(&conn, retention).await {
+                    Ok(count) => tracing::debug!(count = count, "swept audit log"),
+                    Err(err) => tracing::error!("failed to sweep audit log: {}", err),
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,221 @@
+use chrono::{DateTime, Utc, Duration};
+use rfs_lib::ids;
+use tokio_postgres::Error as PgError;
+use deadpool_postgres::GenericClient;
+
+use crate::net;
+use crate::net::cookie::{SetCookie, SameSite};
+use crate::sec::state;
+
+use super::random_token;
+
+/// the cookie that ties a pending login to the browser that started it
+pub const PENDING_COOKIE: &str = "oidc_pending";
+
+pub enum PendingError {
+    Rand(rand::Error),
+    Pg(PgError),
+}
+
+impl From<rand::Error> for PendingError {
+    fn from(err: rand::Error) -> Self {
+        PendingError::Rand(err)
+    }
+}
+
+impl From<PgError> for PendingError {
+    fn from(err: PgError) -> Self {
+        PendingError::Pg(err)
+    }
+}
+
+impl From<PendingError> for net::error::Error {
+    fn from(err: PendingError) -> net::error::Error {
+        match err {
+            PendingError::Rand(err) => err.into(),
+            PendingError::Pg(err) => err.into(),
+        }
+    }
+}
+
+/// a login that has been sent to the provider and is waiting on the
+/// callback
+pub struct PendingLogin {
+    pub state: String,
+    pub nonce: String,
+    /// the pkce code verifier
+    pub verifier: String,
+    pub expires: DateTime<Utc>,
+}
+
+impl PendingLogin {
+    /// how long the user has to log in with the provider
+    pub fn lifetime() -> Duration {
+        Duration::minutes(10)
+    }
+
+    pub async fn create(conn: &impl GenericClient) -> Result<Self, PendingError> {
+        let created = Utc::now();
+        let expires = created + Self::lifetime();
+        let state = random_token()?;
+        let nonce = random_token()?;
+        let verifier = random_token()?;
+
+        let _ = conn.execute(
+            "\
+            insert into auth_oidc_pending (state, nonce, verifier, expires, created) values \
+            ($1, $2, $3, $4, $5)",
+            &[&state, &nonce, &verifier, &expires, &created]
+        ).await?;
+
+        Ok(PendingLogin {
+            state,
+            nonce,
+            verifier,
+            expires,
+        })
+    }
+
+    /// consumes the pending login for the given state
+    ///
+    /// returns none if the state was not found or is expired
+    pub async fn take(
+        conn: &impl GenericClient,
+        state: &str,
+    ) -> Result<Option<Self>, PgError> {
+        let Some(row) = conn.query_opt(
+            "\
+            delete from auth_oidc_pending \
+            where state = $1 \
+            returning nonce, \
+                      verifier, \
+                      expires",
+            &[&state]
+        ).await? else {
+            return Ok(None);
+        };
+
+        let found = PendingLogin {
+            state: state.to_owned(),
+            nonce: row.get(0),
+            verifier: row.get(1),
+            expires: row.get(2),
+        };
+
+        if found.expires <= Utc::now() {
+            return Ok(None);
+        }
+
+        Ok(Some(found))
+    }
+
+    /// checks that the pending cookie sent with the callback is the one
+    /// given when the login was started
+    pub fn matches_cookie(&self, cookie: &str) -> bool {
+        // comparing the hashes keeps the check constant time
+        blake3::hash(self.state.as_bytes()) == blake3::hash(cookie.as_bytes())
+    }
+}
+
+/// the cookie is lax since the browser is sent back to the callback by a
+/// top level navigation from the provider
+pub fn create_pending_cookie(auth: &state::Sec, pending: &PendingLogin) -> SetCookie {
+    let mut cookie = SetCookie::new(PENDING_COOKIE, pending.state.clone())
+        .with_expires(pending.expires)
+        .with_path("/auth/oidc")
+        .with_http_only(true)
+        .with_secure(*auth.session_info().secure())
+        .with_same_site(SameSite::Lax);
+
+    if let Some(domain) = auth.session_info().domain() {
+        cookie.set_domain(domain);
+    }
+
+    cookie
+}
+
+pub fn expire_pending_cookie(auth: &state::Sec) -> SetCookie {
+    let mut cookie = SetCookie::new(PENDING_COOKIE, "")
+        .with_max_age(std::time::Duration::new(0, 0))
+        .with_path("/auth/oidc")
+        .with_http_only(true)
+        .with_secure(*auth.session_info().secure())
+        .with_same_site(SameSite::Lax);
+
+    if let Some(domain) = auth.session_info().domain() {
+        cookie.set_domain(domain);
+    }
+
+    cookie
+}
+
+/// removes pending logins that have expired
+pub async fn sweep(conn: &impl GenericClient) -> Result<u64, PgError> {
+    conn.execute(
+        "delete from auth_oidc_pending where expires <= $1",
+        &[&Utc::now()]
+    ).await
+}
+
+/// connects an account from the provider to a local user
+pub struct Link {
+    pub user_id: ids::UserId,
+}
+
+impl Link {
+    pub async fn create(
+        conn: &impl GenericClient,
+        issuer: String,
+        subject: String,
+        user_id: ids::UserId,
+    ) -> Result<Self, PgError> {
+        let created = Utc::now();
+
+        let _ = conn.execute(
+            "\
+            insert into auth_oidc_link (issuer, subject, user_id, created) values \
+            ($1, $2, $3, $4)",
+            &[&issuer, &subject, &user_id, &created]
+        ).await?;
+
+        Ok(Link {
+            user_id,
+        })
+    }
+
+    pub async fn retrieve(
+        conn: &impl GenericClient,
+        issuer: &str,
+        subject: &str,
+    ) -> Result<Option<Self>, PgError> {
+        let result = conn.query_opt(
+            "\
+            select auth_oidc_link.user_id \
+            from auth_oidc_link \
+            where auth_oidc_link.issuer = $1 and \
+                  auth_oidc_link.subject = $2",
+            &[&issuer, &subject]
+        ).await?;
+
+        Ok(result.map(|row| Link {
+            user_id: ids::user_id_from_pg(row.get(0)),
+        }))
+    }
+
+    /// checks if the user has any accounts from the provider linked to it
+    pub async fn exists(
+        conn: &impl GenericClient,
+        user_id: &ids::UserId,
+    ) -> Result<bool, PgError> {
+        let result = conn.query_opt(
+            "\
+            select 1 \
+            from auth_oidc_link \
+            where auth_oidc_link.user_id = $1 \
+            limit 1",
+            &[user_id]
+        ).await?;
+
+        Ok(result.is_some())
+    }
+}
@@ -0,0 +1,432 @@
+use std::collections::HashMap;
+
+use axum::http::StatusCode;
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use rand::RngCore;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::error;
+use crate::net;
+
+pub mod link;
+
+/// the scopes requested when none are provided
+pub const DEFAULT_SCOPES: [&str; 3] = ["openid", "email", "profile"];
+/// the claim used for the username of provisioned users when none is provided
+pub const DEFAULT_USERNAME_CLAIM: &str = "preferred_username";
+
+pub enum OidcError {
+    Http(reqwest::Error),
+    Jwt(jsonwebtoken::errors::Error),
+    Url(url::ParseError),
+    InvalidProvider(&'static str),
+    InvalidToken(&'static str),
+}
+
+impl From<reqwest::Error> for OidcError {
+    fn from(err: reqwest::Error) -> Self {
+        OidcError::Http(err)
+    }
+}
+
+impl From<jsonwebtoken::errors::Error> for OidcError {
+    fn from(err: jsonwebtoken::errors::Error) -> Self {
+        OidcError::Jwt(err)
+    }
+}
+
+impl From<url::ParseError> for OidcError {
+    fn from(err: url::ParseError) -> Self {
+        OidcError::Url(err)
+    }
+}
+
+impl From<OidcError> for net::error::Error {
+    fn from(err: OidcError) -> Self {
+        match err {
+            OidcError::Http(err) => net::error::Error::new()
+                .status(StatusCode::BAD_GATEWAY)
+                .kind("OidcProviderError")
+                .message("failed to communicate with the identity provider")
+                .source(err),
+            OidcError::Jwt(err) => net::error::Error::new()
+                .status(StatusCode::UNAUTHORIZED)
+                .kind("InvalidIdToken")
+                .message("the id token from the identity provider is invalid")
+                .source(err),
+            OidcError::Url(err) => net::error::Error::new()
+                .status(StatusCode::BAD_GATEWAY)
+                .kind("OidcProviderError")
+                .message("the identity provider returned an invalid url")
+                .source(err),
+            OidcError::InvalidProvider(msg) => net::error::Error::new()
+                .status(StatusCode::BAD_GATEWAY)
+                .kind("OidcProviderError")
+                .message("the identity provider returned an invalid response")
+                .source(msg),
+            OidcError::InvalidToken(msg) => net::error::Error::new()
+                .status(StatusCode::UNAUTHORIZED)
+                .kind("InvalidIdToken")
+                .message("the id token from the identity provider is invalid")
+                .source(msg),
+        }
+    }
+}
+
+/// creates a random url safe string
+pub fn random_token() -> Result<String, rand::Error> {
+    let mut bytes = [0u8; 32];
+
+    rand::thread_rng().try_fill_bytes(&mut bytes)?;
+
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// the S256 code challenge for a pkce verifier
+pub fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// the parts of the provider discovery document that are used
+#[derive(Debug, Deserialize)]
+pub struct Metadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+/// claims from a validated id token
+#[derive(Debug, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub nonce: Option<String>,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+    #[serde(flatten)]
+    pub other: HashMap<String, serde_json::Value>,
+}
+
+impl Claims {
+    /// retrieves a string claim by name
+    pub fn get_str(&self, claim: &str) -> Option<&str> {
+        match claim {
+            "sub" => Some(self.sub.as_str()),
+            "email" => self.email.as_deref(),
+            _ => self.other.get(claim).and_then(|v| v.as_str()),
+        }
+    }
+
+    /// the email if the provider has verified it
+    pub fn verified_email(&self) -> Option<&str> {
+        if self.email_verified {
+            self.email.as_deref()
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Builder {
+    issuer: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    redirect_uri: Option<String>,
+    scopes: Vec<String>,
+    username_claim: Option<String>,
+    provision: bool,
+    link_email: bool,
+}
+
+impl Builder {
+    pub fn set_issuer(&mut self, issuer: String) -> &mut Self {
+        self.issuer = Some(issuer);
+        self
+    }
+
+    pub fn set_client_id(&mut self, client_id: String) -> &mut Self {
+        self.client_id = Some(client_id);
+        self
+    }
+
+    pub fn set_client_secret(&mut self, client_secret: String) -> &mut Self {
+        self.client_secret = Some(client_secret);
+        self
+    }
+
+    /// the callback url registered with the provider
+    pub fn set_redirect_uri(&mut self, redirect_uri: String) -> &mut Self {
+        self.redirect_uri = Some(redirect_uri);
+        self
+    }
+
+    /// replaces any default scopes
+    pub fn set_scopes(&mut self, scopes: Vec<String>) -> &mut Self {
+        self.scopes = scopes;
+        self
+    }
+
+    /// the claim to use as the username of provisioned users
+    pub fn set_username_claim(&mut self, claim: String) -> &mut Self {
+        self.username_claim = Some(claim);
+        self
+    }
+
+    /// creates local users for provider accounts that are not linked
+    pub fn set_provision(&mut self, provision: bool) -> &mut Self {
+        self.provision = provision;
+        self
+    }
+
+    /// links provider accounts to local users with the same verified email
+    pub fn set_link_email(&mut self, link_email: bool) -> &mut Self {
+        self.link_email = link_email;
+        self
+    }
+
+    /// creates the provider if an issuer was provided
+    pub fn build(self) -> error::Result<Option<Provider>> {
+        let Some(issuer) = self.issuer else {
+            return Ok(None);
+        };
+
+        let issuer = issuer.trim_end_matches('/').to_owned();
+
+        url::Url::parse(&issuer).map_err(|err| error::Error::new()
+            .kind("InvalidOidcConfig")
+            .message("the oidc issuer is not a valid url")
+            .source(err))?;
+
+        let Some(client_id) = self.client_id else {
+            return Err(error::Error::new()
+                .kind("InvalidOidcConfig")
+                .message("a client id is required when an oidc issuer is provided"));
+        };
+
+        let Some(redirect_uri) = self.redirect_uri else {
+            return Err(error::Error::new()
+                .kind("InvalidOidcConfig")
+                .message("a redirect uri is required when an oidc issuer is provided"));
+        };
+
+        let redirect_uri = url::Url::parse(&redirect_uri).map_err(|err| error::Error::new()
+            .kind("InvalidOidcConfig")
+            .message("the oidc redirect uri is not a valid url")
+            .source(err))?;
+
+        let mut scopes = if self.scopes.is_empty() {
+            DEFAULT_SCOPES.iter().map(|v| v.to_string()).collect()
+        } else {
+            self.scopes
+        };
+
+        if !scopes.iter().any(|scope| scope == "openid") {
+            scopes.insert(0, String::from("openid"));
+        }
+
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .build()
+            .map_err(|err| error::Error::new()
+                .kind("InvalidOidcConfig")
+                .message("failed to create http client for the oidc provider")
+                .source(err))?;
+
+        Ok(Some(Provider {
+            issuer,
+            client_id,
+            client_secret: self.client_secret,
+            redirect_uri,
+            scopes,
+            username_claim: self.username_claim
+                .unwrap_or(String::from(DEFAULT_USERNAME_CLAIM)),
+            provision: self.provision,
+            link_email: self.link_email,
+            client,
+        }))
+    }
+}
+
+/// an openid connect provider that users can log in with using the
+/// authorization code flow
+pub struct Provider {
+    issuer: String,
+    client_id: String,
+    client_secret: Option<String>,
+    redirect_uri: url::Url,
+    scopes: Vec<String>,
+    username_claim: String,
+    provision: bool,
+    link_email: bool,
+    client: reqwest::Client,
+}
+
+impl Provider {
+    pub fn builder() -> Builder {
+        Builder {
+            issuer: None,
+            client_id: None,
+            client_secret: None,
+            redirect_uri: None,
+            scopes: Vec::new(),
+            username_claim: None,
+            provision: false,
+            link_email: false,
+        }
+    }
+
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    pub fn username_claim(&self) -> &str {
+        &self.username_claim
+    }
+
+    pub fn provision(&self) -> &bool {
+        &self.provision
+    }
+
+    pub fn link_email(&self) -> &bool {
+        &self.link_email
+    }
+
+    /// retrieves the discovery document of the provider
+    ///
+    /// this is done for each login so that changes to the provider are
+    /// picked up without a restart
+    pub async fn metadata(&self) -> Result<Metadata, OidcError> {
+        let url = format!("{}/.well-known/openid-configuration", self.issuer);
+
+        let metadata = self.client.get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Metadata>()
+            .await?;
+
+        if metadata.issuer.trim_end_matches('/') != self.issuer {
+            return Err(OidcError::InvalidProvider("discovered issuer does not match the configured issuer"));
+        }
+
+        Ok(metadata)
+    }
+
+    /// the url to send the user to for logging in with the provider
+    pub async fn authorize_url(&self, pending: &link::PendingLogin) -> Result<String, OidcError> {
+        let metadata = self.metadata().await?;
+        let mut url = url::Url::parse(&metadata.authorization_endpoint)?;
+
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.client_id)
+            .append_pair("redirect_uri", self.redirect_uri.as_str())
+            .append_pair("scope", &self.scopes.join(" "))
+            .append_pair("state", &pending.state)
+            .append_pair("nonce", &pending.nonce)
+            .append_pair("code_challenge", &pkce_challenge(&pending.verifier))
+            .append_pair("code_challenge_method", "S256");
+
+        Ok(url.into())
+    }
+
+    /// exchanges the authorization code for an id token and returns its
+    /// validated claims
+    pub async fn exchange(&self, code: &str, pending: &link::PendingLogin) -> Result<Claims, OidcError> {
+        let metadata = self.metadata().await?;
+
+        let params = [
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.redirect_uri.as_str()),
+            ("client_id", self.client_id.as_str()),
+            ("code_verifier", pending.verifier.as_str()),
+        ];
+
+        let mut request = self.client.post(&metadata.token_endpoint)
+            .form(&params);
+
+        if let Some(secret) = &self.client_secret {
+            request = request.basic_auth(&self.client_id, Some(secret));
+        }
+
+        let token = request.send()
+            .await?
+            .error_for_status()?
+            .json::<TokenResponse>()
+            .await?;
+
+        let claims = self.validate(&metadata, &token.id_token).await?;
+
+        if claims.nonce.as_deref() != Some(pending.nonce.as_str()) {
+            return Err(OidcError::InvalidToken("id token nonce does not match the login request"));
+        }
+
+        Ok(claims)
+    }
+
+    /// checks the signature, issuer, audience and expiration of an id token
+    async fn validate(&self, metadata: &Metadata, id_token: &str) -> Result<Claims, OidcError> {
+        use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+        use jsonwebtoken::jwk::JwkSet;
+
+        let header = jsonwebtoken::decode_header(id_token)?;
+
+        // symmetric algorithms would allow anyone with the client secret to
+        // create tokens
+        if matches!(header.alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+            return Err(OidcError::InvalidToken("id token uses a symmetric algorithm"));
+        }
+
+        let jwks = self.client.get(&metadata.jwks_uri)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<JwkSet>()
+            .await?;
+
+        let jwk = if let Some(kid) = &header.kid {
+            jwks.find(kid)
+        } else if jwks.keys.len() == 1 {
+            jwks.keys.first()
+        } else {
+            None
+        };
+
+        let Some(jwk) = jwk else {
+            return Err(OidcError::InvalidToken("id token signing key was not found"));
+        };
+
+        let key = DecodingKey::from_jwk(jwk)?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_audience(&[&self.client_id]);
+        validation.set_issuer(&[&metadata.issuer]);
+
+        let data = jsonwebtoken::decode::<Claims>(id_token, &key, &validation)?;
+
+        Ok(data.claims)
+    }
+}
+
+impl std::fmt::Debug for Provider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Provider")
+            .field("issuer", &self.issuer)
+            .field("client_id", &self.client_id)
+            .field("redirect_uri", &self.redirect_uri)
+            .field("scopes", &self.scopes)
+            .field("username_claim", &self.username_claim)
+            .field("provision", &self.provision)
+            .field("link_email", &self.link_email)
+            .finish()
+    }
+}
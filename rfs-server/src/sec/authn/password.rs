@@ -39,8 +39,63 @@ impl From<PasswordError> for net::error::Error {
     }
 }
 
+/// hashes the given password with argon2id using the provided secret
+pub fn hash_with_secret<P>(
+    password: P,
+    salt_len: usize,
+    secret: &Secret
+) -> Result<String, PasswordError>
+where
+    P: AsRef<[u8]>
+{
+    use argon2::{Variant};
+    use rand::RngCore;
+
+    let config = argon2::Config {
+        mem_cost: 19456,
+        secret: secret.as_slice(),
+        variant: Variant::Argon2id,
+        ..argon2::Config::default()
+    };
+
+    let mut salt = vec![0u8; salt_len];
+
+    rand::thread_rng()
+        .try_fill_bytes(salt.as_mut_slice())?;
+
+    let hash = argon2::hash_encoded(
+        password.as_ref(),
+        salt.as_slice(),
+        &config
+    )?;
+
+    Ok(hash)
+}
+
+/// verifies the given password against an argon2 encoded hash
+pub fn verify_with_secret<C>(
+    hash: &str,
+    check: C,
+    secret: &Secret
+) -> Result<bool, PasswordError>
+where
+    C: AsRef<[u8]>
+{
+    let ad = [0u8; 0];
+
+    let result = argon2::verify_encoded_ext(
+        hash,
+        check.as_ref(),
+        secret.as_slice(),
+        &ad
+    )?;
+
+    Ok(result)
+}
+
 pub struct PasswordBuilder<'a> {
     user_id: ids::UserId,
+    password: String,
     salt_len: usize,
     secret: Option<&'a Secret>
 }
@@ -51,45 +106,28 @@ impl<'a> PasswordBuilder<'a> {
         self
     }
 
-    pub fn with_secret<'b>(mut self, secret: &'b Secret) -> PasswordBuilder<'b> {
+    pub fn with_secret<'b>(self, secret: &'b Secret) -> PasswordBuilder<'b> {
         PasswordBuilder {
             user_id: self.user_id,
+            password: self.password,
             salt_len: self.salt_len,
             secret: Some(secret)
         }
     }
 
     pub async fn build(self, conn: &impl GenericClient) -> Result<Password, PasswordError> {
-        use argon2::{Variant};
-        use rand::RngCore;
-
         let secret = self.secret.unwrap_or(&EMPTY_SECRET);
         let version = *secret.version();
 
-        let mut config = argon2::Config::default();
-        config.mem_cost = 19456;
-        config.secret = secret.as_slice();
-        config.variant = Variant::Argon2id;
-
-        let mut salt = Vec::with_capacity(self.salt_len);
-
-        for _ in 0..salt.len() {
-            salt.push(0);
-        }
-
-        rand::thread_rng()
-            .try_fill_bytes(salt.as_mut_slice())?;
-
-        let hash = argon2::hash_encoded(
-            secret.as_slice(),
-            &salt.as_slice(),
-            &config
-        )?;
+        let hash = hash_with_secret(&self.password, self.salt_len, secret)?;
 
         let _ = conn.execute(
             "\
-            insert into auth_password (user_id, version, hash) values
-            ($1, $2, $3)",
+            insert into auth_password (user_id, version, hash) values \
+            ($1, $2, $3) \
+            on conflict (user_id) do update set \
+                version = EXCLUDED.version, \
+                hash = EXCLUDED.hash",
             &[
                 &self.user_id,
                 &(version as i32),
@@ -112,9 +150,10 @@ pub struct Password {
 }
 
 impl Password {
-    pub fn builder(user_id: ids::UserId) -> PasswordBuilder<'static> {
+    pub fn builder(user_id: ids::UserId, password: String) -> PasswordBuilder<'static> {
         PasswordBuilder {
             user_id,
+            password,
             salt_len: 32,
             secret: None,
         }
@@ -133,9 +172,11 @@ impl Password {
             where auth_password.user_id = $1",
             &[user_id]
         ).await? {
+            let version: i32 = row.get(1);
+
             Ok(Some(Password {
                 user_id: row.get(0),
-                version: row.get(1),
+                version: version as u32,
                 hash: row.get(2)
             }))
         } else {
@@ -151,16 +192,29 @@ impl Password {
     where
         C: AsRef<[u8]>,
     {
-        let ad = [0u8; 0];
+        verify_with_secret(self.hash.as_str(), check, secret)
+    }
+
+    /// the number of passwords hashed with each secret version
+    pub async fn version_counts(conn: &impl GenericClient) -> Result<Vec<(u32, u64)>, PgError> {
+        let rows = conn.query(
+            "\
+            select auth_password.version, \
+                   count(auth_password.user_id) \
+            from auth_password \
+            group by auth_password.version \
+            order by auth_password.version",
+            &[]
+        ).await?;
 
-        let result = argon2::verify_encoded_ext(
-            &self.hash.as_str(),
-            check.as_ref(),
-            secret.as_slice(),
-            &ad
-        )?;
+        Ok(rows.into_iter()
+            .map(|row| {
+                let version: i32 = row.get(0);
+                let count: i64 = row.get(1);
 
-        Ok(result)
+                (version as u32, count as u64)
+            })
+            .collect())
     }
 
     pub async fn delete(&self, conn: &impl GenericClient) -> Result<bool, PgError> {
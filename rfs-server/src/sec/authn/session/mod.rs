@@ -1,20 +1,25 @@
+use std::net::IpAddr;
+
 use rfs_lib::ids;
+use rfs_lib::schema;
 use axum::http::StatusCode;
 use chrono::{DateTime, Utc};
-use base64::{Engine, engine::general_purpose::URL_SAFE};
+use base64::{Engine, engine::general_purpose::{URL_SAFE, URL_SAFE_NO_PAD}};
 use tokio_postgres::{Error as PgError};
 use deadpool_postgres::GenericClient;
 use hmac::{Hmac, Mac, digest::CtOutput};
 
 use crate::sec::state;
 use crate::net::error::Error as NetError;
-use crate::net::cookie::{SameSite, SetCookie};
+use crate::net::cookie::SetCookie;
 
 pub mod token;
 
 pub enum AuthMethod {
     None,
-    Password
+    Password,
+    Oidc,
+    Ldap,
 }
 
 impl AuthMethod {
@@ -22,6 +27,8 @@ impl AuthMethod {
         match v {
             0 => Some(AuthMethod::None),
             1 => Some(AuthMethod::Password),
+            2 => Some(AuthMethod::Oidc),
+            3 => Some(AuthMethod::Ldap),
             _ => None
         }
     }
@@ -30,6 +37,8 @@ impl AuthMethod {
         match self {
             AuthMethod::None => 0,
             AuthMethod::Password => 1,
+            AuthMethod::Oidc => 2,
+            AuthMethod::Ldap => 3,
         }
     }
 }
@@ -109,7 +118,10 @@ pub struct SessionBuilder {
     authenticated: Option<bool>,
     verified: Option<bool>,
     auth_method: Option<AuthMethod>,
-    verify_method: Option<VerifyMethod>
+    verify_method: Option<VerifyMethod>,
+    user_agent: Option<String>,
+    ip: Option<IpAddr>,
+    lifetimes: Option<state::SessionLifetimes>,
 }
 
 impl SessionBuilder {
@@ -148,6 +160,22 @@ impl SessionBuilder {
         self
     }
 
+    pub fn user_agent(&mut self, user_agent: String) -> &mut Self {
+        self.user_agent = Some(user_agent);
+        self
+    }
+
+    pub fn ip(&mut self, ip: IpAddr) -> &mut Self {
+        self.ip = Some(ip);
+        self
+    }
+
+    /// used to calculate the expiration if one is not provided
+    pub fn lifetimes(&mut self, lifetimes: state::SessionLifetimes) -> &mut Self {
+        self.lifetimes = Some(lifetimes);
+        self
+    }
+
     pub async fn build(self, conn: &impl GenericClient) -> Result<Session, BuilderError> {
         let user_id = self.user_id;
         let dropped = false;
@@ -161,22 +189,6 @@ impl SessionBuilder {
         } else {
             token::SessionToken::unique(conn).await?.unwrap()
         };
-        let expires = if let Some(expires) = self.expires {
-            if expires <= issued_on {
-                return Err(BuilderError::InvalidExpires);
-            }
-
-            expires
-        } else {
-            let duration = chrono::Duration::days(7);
-
-            let Some(expires) = issued_on.clone().checked_add_signed(duration) else {
-                return Err(BuilderError::UtcOverflow);
-            };
-
-            expires
-        };
-
         let authenticated_calc;
         let verified_calc;
         let auth_method = if let Some(method) = self.auth_method {
@@ -211,6 +223,29 @@ impl SessionBuilder {
         };
         let authenticated = self.authenticated.unwrap_or(authenticated_calc);
         let verified = self.verified.unwrap_or(verified_calc);
+        let expires = if let Some(expires) = self.expires {
+            if expires <= issued_on {
+                return Err(BuilderError::InvalidExpires);
+            }
+
+            expires
+        } else {
+            let lifetimes = self.lifetimes.unwrap_or_default();
+
+            // sessions that still need to be authenticated or verified are
+            // only given a short amount of time to do so
+            let duration = if authenticated && verified {
+                std::cmp::min(lifetimes.lifetime, lifetimes.idle.unwrap_or(lifetimes.lifetime))
+            } else {
+                lifetimes.pending
+            };
+
+            let Some(expires) = issued_on.checked_add_signed(duration) else {
+                return Err(BuilderError::UtcOverflow);
+            };
+
+            expires
+        };
 
         {
             let auth_method_int = auth_method.as_i16();
@@ -227,9 +262,11 @@ impl SessionBuilder {
                     authenticated, \
                     verified, \
                     auth_method, \
-                    verify_method\
+                    verify_method, \
+                    user_agent, \
+                    ip\
                 ) values \
-                ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+                ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
                 &[
                     &token.as_slice(),
                     &user_id,
@@ -240,6 +277,8 @@ impl SessionBuilder {
                     &verified,
                     &auth_method_int,
                     &verify_method_int,
+                    &self.user_agent,
+                    &self.ip,
                 ]
             ).await?;
         }
@@ -253,7 +292,9 @@ impl SessionBuilder {
             authenticated,
             verified,
             auth_method,
-            verify_method
+            verify_method,
+            user_agent: self.user_agent,
+            ip: self.ip,
         })
     }
 }
@@ -269,6 +310,8 @@ pub struct Session {
     pub verified: bool,
     pub auth_method: AuthMethod,
     pub verify_method: VerifyMethod,
+    pub user_agent: Option<String>,
+    pub ip: Option<IpAddr>,
 }
 
 impl Session {
@@ -282,6 +325,27 @@ impl Session {
             verified: None,
             auth_method: None,
             verify_method: None,
+            user_agent: None,
+            ip: None,
+            lifetimes: None,
+        }
+    }
+
+    fn from_row(row: tokio_postgres::Row) -> Session {
+        Session {
+            token: token::SessionToken::from_vec(row.get(0)),
+            user_id: ids::user_id_from_pg(row.get(1)),
+            dropped: row.get(2),
+            issued_on: row.get(3),
+            expires: row.get(4),
+            authenticated: row.get(5),
+            verified: row.get(6),
+            auth_method: AuthMethod::from_i16(row.get(7))
+                .expect("invalid auth method returned from database for session"),
+            verify_method: VerifyMethod::from_i16(row.get(8))
+                .expect("invalid verify method returned from database for session"),
+            user_agent: row.get(9),
+            ip: row.get(10),
         }
     }
 
@@ -289,7 +353,7 @@ impl Session {
         conn: &impl GenericClient, 
         token: &token::SessionToken
     ) -> Result<Option<Session>, PgError> {
-        if let Some(row) = conn.query_opt(
+        let result = conn.query_opt(
             "\
             select auth_session.token, \
                    auth_session.user_id, \
@@ -299,27 +363,49 @@ impl Session {
                    auth_session.authenticated, \
                    auth_session.verified, \
                    auth_session.auth_method, \
-                   auth_session.verify_method \
+                   auth_session.verify_method, \
+                   auth_session.user_agent, \
+                   auth_session.ip \
             from auth_session \
             where auth_session.token = $1",
             &[&token.as_slice()]
-        ).await? {
-            Ok(Some(Session {
-                token: token::SessionToken::from_vec(row.get(0)),
-                user_id: row.get(1),
-                dropped: row.get(2),
-                issued_on: row.get(3),
-                expires: row.get(4),
-                authenticated: row.get(5),
-                verified: row.get(6),
-                auth_method: AuthMethod::from_i16(row.get(7))
-                    .expect("invalid auth method returned from database for session"),
-                verify_method: VerifyMethod::from_i16(row.get(8))
-                    .expect("invalid verify method returned from database for session"),
-            }))
-        } else {
-            Ok(None)
-        }
+        ).await?;
+
+        Ok(result.map(Self::from_row))
+    }
+
+    /// retrieves all sessions for a user that have not been dropped or
+    /// expired
+    pub async fn retrieve_user(
+        conn: &impl GenericClient,
+        user_id: &ids::UserId,
+    ) -> Result<Vec<Session>, PgError> {
+        let now = Utc::now();
+
+        let rows = conn.query(
+            "\
+            select auth_session.token, \
+                   auth_session.user_id, \
+                   auth_session.dropped, \
+                   auth_session.issued_on, \
+                   auth_session.expires, \
+                   auth_session.authenticated, \
+                   auth_session.verified, \
+                   auth_session.auth_method, \
+                   auth_session.verify_method, \
+                   auth_session.user_agent, \
+                   auth_session.ip \
+            from auth_session \
+            where auth_session.user_id = $1 and \
+                  auth_session.dropped = false and \
+                  auth_session.expires > $2 \
+            order by auth_session.issued_on",
+            &[user_id, &now]
+        ).await?;
+
+        Ok(rows.into_iter()
+            .map(Self::from_row)
+            .collect())
     }
 
     pub async fn create(&self, conn: &impl GenericClient) -> Result<(), PgError> {
@@ -337,9 +423,11 @@ impl Session {
                 authenticated, \
                 verified, \
                 auth_method, \
-                verify_method\
+                verify_method, \
+                user_agent, \
+                ip\
             ) values \
-            ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+            ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
             &[
                 &self.token.as_slice(),
                 &self.user_id,
@@ -350,6 +438,8 @@ impl Session {
                 &self.verified,
                 &auth_method,
                 &verify_method,
+                &self.user_agent,
+                &self.ip,
             ]
         ).await?;
 
@@ -396,6 +486,69 @@ impl Session {
 
         Ok(())
     }
+
+    /// marks the session as active at the given time
+    ///
+    /// the new expiration is only written if it has moved forward by at
+    /// least the renew interval so that every request does not cause an
+    /// update. returns true if the session was updated
+    pub async fn renew(
+        &mut self,
+        conn: &impl GenericClient,
+        lifetimes: &state::SessionLifetimes,
+        active: DateTime<Utc>,
+    ) -> Result<bool, PgError> {
+        let expires = lifetimes.active_expires(&self.issued_on, &active);
+
+        if expires <= self.expires || expires - self.expires < lifetimes.renew {
+            return Ok(false);
+        }
+
+        let _ = conn.execute(
+            "update auth_session set expires = $2 where token = $1",
+            &[&self.token.as_slice(), &expires]
+        ).await?;
+
+        self.expires = expires;
+
+        Ok(true)
+    }
+
+    /// removes all sessions for the user except for this one
+    pub async fn delete_others(&self, conn: &impl GenericClient) -> Result<u64, PgError> {
+        conn.execute(
+            "delete from auth_session where user_id = $1 and token != $2",
+            &[&self.user_id, &self.token.as_slice()]
+        ).await
+    }
+
+    /// an identifier that can be given out without exposing the token
+    pub fn public_id(&self) -> String {
+        URL_SAFE_NO_PAD.encode(blake3::hash(self.token.as_slice()).as_bytes())
+    }
+
+    pub fn into_schema(self, current: bool) -> schema::auth::Session {
+        schema::auth::Session {
+            id: self.public_id(),
+            current,
+            issued_on: self.issued_on,
+            expires: self.expires,
+            authenticated: self.authenticated,
+            verified: self.verified,
+            user_agent: self.user_agent,
+            ip: self.ip.map(|ip| ip.to_string()),
+        }
+    }
+}
+
+/// removes sessions that have been dropped or are expired
+pub async fn sweep(conn: &impl GenericClient) -> Result<u64, PgError> {
+    let now = Utc::now();
+
+    conn.execute(
+        "delete from auth_session where dropped = true or expires <= $1",
+        &[&now]
+    ).await
 }
 
 type HS256 = Hmac<sha3::Sha3_256>;
@@ -587,12 +740,19 @@ pub fn create_session_cookie(auth: &state::Sec, session: &Session) -> SetCookie
     let hash = create_hash(auth, &session.token);
     let encoded_token = encode_base64(&session.token, hash);
 
+    // the cookie is given the absolute lifetime of the session since the
+    // expiration in the database moves with activity
+    let expires = std::cmp::max(
+        session.expires,
+        session.issued_on + auth.session_info().lifetimes().lifetime
+    );
+
     let mut cookie = SetCookie::new("session_id", encoded_token)
-        .with_expires(session.expires.clone())
+        .with_expires(expires)
         .with_path("/")
         .with_http_only(true)
         .with_secure(*auth.session_info().secure())
-        .with_same_site(SameSite::Strict);
+        .with_same_site(auth.session_info().same_site());
 
     if let Some(domain) = auth.session_info().domain() {
         cookie.set_domain(domain);
@@ -607,7 +767,7 @@ pub fn expire_session_cookie(auth: &state::Sec) -> SetCookie {
         .with_path("/")
         .with_http_only(true)
         .with_secure(*auth.session_info().secure())
-        .with_same_site(SameSite::Strict);
+        .with_same_site(auth.session_info().same_site());
 
     if let Some(domain) = auth.session_info().domain() {
         cookie.set_domain(domain);
@@ -619,7 +779,7 @@ pub fn expire_session_cookie(auth: &state::Sec) -> SetCookie {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::auth::state;
+    use crate::sec::state;
 
     fn check_encode_decode(auth: state::Sec) {
         let token = token::SessionToken::new().expect("failed to generate token");
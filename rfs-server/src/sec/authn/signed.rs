@@ -0,0 +1,241 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc, TimeZone};
+use hmac::{Hmac, Mac};
+use rfs_lib::ids;
+use rfs_lib::schema;
+
+use axum::http::StatusCode;
+
+use crate::net;
+use crate::sec::state;
+
+type HS256 = Hmac<sha3::Sha3_256>;
+type HS384 = Hmac<sha3::Sha3_384>;
+type HS512 = Hmac<sha3::Sha3_512>;
+
+/// the default number of seconds a signed url is valid for
+pub const DEFAULT_EXPIRES_IN: u64 = 60 * 60;
+/// the max number of seconds a signed url can be valid for
+pub const MAX_EXPIRES_IN: u64 = 60 * 60 * 24 * 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Download,
+    Upload,
+}
+
+impl Operation {
+    pub fn as_method(&self) -> &'static str {
+        match self {
+            Operation::Download => "GET",
+            Operation::Upload => "PUT",
+        }
+    }
+
+    pub fn into_schema(self) -> schema::fs::SignedOperation {
+        match self {
+            Operation::Download => schema::fs::SignedOperation::Download,
+            Operation::Upload => schema::fs::SignedOperation::Upload,
+        }
+    }
+}
+
+impl From<schema::fs::SignedOperation> for Operation {
+    fn from(v: schema::fs::SignedOperation) -> Self {
+        match v {
+            schema::fs::SignedOperation::Download => Operation::Download,
+            schema::fs::SignedOperation::Upload => Operation::Upload,
+        }
+    }
+}
+
+/// the data that is signed for a url
+///
+/// the user is included so that the request can be checked against the
+/// current access of the user that created the url
+pub struct Claims<'a> {
+    pub operation: Operation,
+    pub fs_id: &'a ids::FSId,
+    pub user_id: &'a ids::UserId,
+    pub expires: i64,
+}
+
+impl<'a> Claims<'a> {
+    fn message(&self) -> String {
+        format!(
+            "rfs signed url\n{}\n{}\n{}\n{}",
+            self.operation.as_method(),
+            self.fs_id.id(),
+            self.user_id.id(),
+            self.expires
+        )
+    }
+
+    pub fn expires_datetime(&self) -> Option<DateTime<Utc>> {
+        Utc.timestamp_opt(self.expires, 0).single()
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires <= Utc::now().timestamp()
+    }
+}
+
+/// retrieves the key for signing urls
+///
+/// signed urls are only available when a signing secret has been configured
+pub fn signing_key(auth: &state::Sec) -> Result<&state::SessionKey, net::error::Error> {
+    auth.signing_key().ok_or_else(|| net::error::Error::new()
+        .status(StatusCode::NOT_FOUND)
+        .kind("SignedUrlsDisabled")
+        .message("signed urls are not enabled on this server"))
+}
+
+/// creates the url safe signature for the given claims
+pub fn sign(key: &state::SessionKey, claims: &Claims<'_>) -> String {
+    let message = claims.message();
+
+    let bytes = match key {
+        state::SessionKey::Blake3(key) => {
+            blake3::keyed_hash(key, message.as_bytes())
+                .as_bytes()
+                .to_vec()
+        },
+        state::SessionKey::HS256(key) => {
+            let mut mac = HS256::new_from_slice(key).unwrap();
+            mac.update(message.as_bytes());
+
+            mac.finalize().into_bytes().to_vec()
+        },
+        state::SessionKey::HS384(key) => {
+            let mut mac = HS384::new_from_slice(key).unwrap();
+            mac.update(message.as_bytes());
+
+            mac.finalize().into_bytes().to_vec()
+        },
+        state::SessionKey::HS512(key) => {
+            let mut mac = HS512::new_from_slice(key).unwrap();
+            mac.update(message.as_bytes());
+
+            mac.finalize().into_bytes().to_vec()
+        }
+    };
+
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// verifies the given signature against the claims
+pub fn verify<S>(key: &state::SessionKey, claims: &Claims<'_>, signature: S) -> bool
+where
+    S: AsRef<[u8]>
+{
+    let Ok(given) = URL_SAFE_NO_PAD.decode(signature) else {
+        return false;
+    };
+
+    let message = claims.message();
+
+    match key {
+        state::SessionKey::Blake3(key) => {
+            let Ok(array) = <[u8; blake3::OUT_LEN]>::try_from(given.as_slice()) else {
+                return false;
+            };
+
+            // comparison of blake3::Hash is constant time
+            blake3::Hash::from(array) == blake3::keyed_hash(key, message.as_bytes())
+        },
+        state::SessionKey::HS256(key) => {
+            let mut mac = HS256::new_from_slice(key).unwrap();
+            mac.update(message.as_bytes());
+
+            mac.verify_slice(&given).is_ok()
+        },
+        state::SessionKey::HS384(key) => {
+            let mut mac = HS384::new_from_slice(key).unwrap();
+            mac.update(message.as_bytes());
+
+            mac.verify_slice(&given).is_ok()
+        },
+        state::SessionKey::HS512(key) => {
+            let mut mac = HS512::new_from_slice(key).unwrap();
+            mac.update(message.as_bytes());
+
+            mac.verify_slice(&given).is_ok()
+        }
+    }
+}
+
+/// creates the path and query string for a signed url
+pub fn create_url(key: &state::SessionKey, claims: &Claims<'_>) -> String {
+    let signature = sign(key, claims);
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("user_id", &claims.user_id.id().to_string())
+        .append_pair("expires", &claims.expires.to_string())
+        .append_pair("signature", &signature)
+        .finish();
+
+    format!("/signed/{}?{}", claims.fs_id.id(), query)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SIGNING_SECRET: &str = "a signing secret that is long enough";
+
+    fn claims<'a>(fs_id: &'a ids::FSId, user_id: &'a ids::UserId) -> Claims<'a> {
+        Claims {
+            operation: Operation::Download,
+            fs_id,
+            user_id,
+            expires: Utc::now().timestamp() + 60,
+        }
+    }
+
+    #[test]
+    fn signed_urls_require_signing_secret() {
+        let auth = state::Sec::builder()
+            .build()
+            .expect("failed to build sec state");
+
+        assert!(signing_key(&auth).is_err(), "signed urls enabled without a secret");
+
+        let mut builder = state::Sec::builder();
+        builder.set_signing_secret("short".into());
+
+        assert!(builder.build().is_err(), "accepted a short signing secret");
+
+        let mut builder = state::Sec::builder();
+        builder.set_session_secret(SIGNING_SECRET.into())
+            .set_signing_secret(SIGNING_SECRET.into());
+
+        assert!(builder.build().is_err(), "accepted the session secret for signing");
+    }
+
+    #[test]
+    fn signature_uses_signing_key() {
+        let fs_id = ids::fs_id_from_pg(10);
+        let user_id = ids::user_id_from_pg(1);
+        let claims = claims(&fs_id, &user_id);
+
+        let mut builder = state::Sec::builder();
+        builder.set_signing_secret(SIGNING_SECRET.into());
+
+        let auth = builder.build().expect("failed to build sec state");
+        let key = signing_key(&auth).expect("signed urls are disabled");
+        let signature = sign(key, &claims);
+
+        assert!(verify(key, &claims, &signature), "valid signature rejected");
+        assert!(
+            !verify(auth.session_info().key(), &claims, &signature),
+            "signature accepted with the session key"
+        );
+
+        let other_fs_id = ids::fs_id_from_pg(11);
+        let other = Claims {
+            fs_id: &other_fs_id,
+            ..claims
+        };
+
+        assert!(!verify(key, &other, &signature), "signature accepted for another item");
+    }
+}
@@ -1,13 +1,16 @@
 use std::str::FromStr;
+use std::net::IpAddr;
 
 use rfs_lib::ids;
 use axum::http::StatusCode;
+use chrono::Utc;
 use tokio_postgres::{Error as PgError};
 use deadpool_postgres::GenericClient;
 use rand::RngCore;
 
 use crate::net;
 use crate::util::HistoryField;
+use crate::sec::state::{TotpWindow, LockoutConfig};
 
 #[derive(Clone)]
 pub enum Algo {
@@ -33,6 +36,14 @@ impl Algo {
             Algo::SHA512 => 2,
         }
     }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Algo::SHA1 => "SHA1",
+            Algo::SHA256 => "SHA256",
+            Algo::SHA512 => "SHA512",
+        }
+    }
 }
 
 pub struct FromIntError;
@@ -174,7 +185,10 @@ impl TotpBuilder {
         true
     }
 
+    /// creates the totp in a pending state. it must be verified before it
+    /// can be used to verify sessions
     pub async fn build(self, conn: &impl GenericClient) -> Result<Totp, TotpBuilderError> {
+        let verified = false;
         let algo = self.algo.unwrap_or(Algo::SHA512);
         let digits = self.digits.unwrap_or(8);
         let step = self.step.unwrap_or(30);
@@ -192,14 +206,15 @@ impl TotpBuilder {
         let algo_int = algo.as_i16();
         let _ = conn.execute(
             "\
-            insert into auth_totp (user_id, algo, secret, digits, step) values \
-            ($1, $2, $3, $4, $5)",
+            insert into auth_totp (user_id, algo, secret, digits, step, verified) values \
+            ($1, $2, $3, $4, $5, $6)",
             &[
                 &self.user_id,
                 &algo_int,
                 &secret,
                 &(digits as i32),
-                &(step as i32)
+                &(step as i32),
+                &verified,
             ]
         ).await?;
 
@@ -208,7 +223,9 @@ impl TotpBuilder {
             algo,
             secret,
             digits,
-            step
+            step,
+            verified,
+            last_step: None,
         })
     }
 }
@@ -219,6 +236,18 @@ pub struct Totp {
     secret: Vec<u8>,
     digits: u32,
     step: u64,
+    verified: bool,
+    last_step: Option<u64>,
+}
+
+/// the result of checking a code against a totp
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TotpCheck {
+    /// the code matched the given time step
+    Valid(u64),
+    /// the code matched a time step that has already been used
+    Replayed(u64),
+    Invalid,
 }
 
 impl Totp {
@@ -258,19 +287,24 @@ impl Totp {
                    auth_totp.secret, \
                    auth_totp.digits, \
                    auth_totp.step, \
+                   auth_totp.verified, \
+                   auth_totp.last_step \
             from auth_totp \
             where auth_totp.user_id = $1",
             &[id]
         ).await? {
             Ok(Some(Totp {
                 user_id: id.clone(),
-                algo: Algo::from_i16(row.get(1))
+                algo: Algo::from_i16(row.get(0))
                     .expect("unexpected value from database for totp algo"),
-                secret: row.get(2),
-                digits: Self::digits_from_db(row.get(3))
+                secret: row.get(1),
+                digits: Self::digits_from_db(row.get(2))
                     .expect("unexpected value from database for totp digits"),
-                step: Self::step_from_db(row.get(4))
+                step: Self::step_from_db(row.get(3))
                     .expect("unexpected value from database for totp step"),
+                verified: row.get(4),
+                last_step: row.get::<usize, Option<i64>>(5)
+                    .map(|v| v as u64),
             }))
         } else {
             Ok(None)
@@ -293,21 +327,120 @@ impl Totp {
         &self.step
     }
 
-    pub fn verify<C>(&self, code: C) -> rust_otp::error::Result<rust_otp::VerifyResult>
+    /// if the user has proven that they have the secret
+    pub fn verified(&self) -> &bool {
+        &self.verified
+    }
+
+    /// the secret encoded for authenticator apps
+    pub fn secret_base32(&self) -> String {
+        data_encoding::BASE32_NOPAD.encode(&self.secret)
+    }
+
+    /// creates an otpauth uri that authenticator apps can import
+    pub fn provisioning_uri(&self, issuer: &str, account: &str) -> String {
+        let mut uri = url::Url::parse("otpauth://totp/")
+            .expect("invalid otpauth base uri");
+
+        uri.set_path(&format!("{}:{}", issuer, account));
+        uri.query_pairs_mut()
+            .append_pair("secret", &self.secret_base32())
+            .append_pair("issuer", issuer)
+            .append_pair("algorithm", self.algo.as_str())
+            .append_pair("digits", &self.digits.to_string())
+            .append_pair("period", &self.step.to_string());
+
+        uri.into()
+    }
+
+    pub async fn set_verified(&mut self, conn: &impl GenericClient) -> Result<(), PgError> {
+        let _ = conn.execute(
+            "update auth_totp set verified = true where user_id = $1",
+            &[&self.user_id]
+        ).await?;
+
+        self.verified = true;
+
+        Ok(())
+    }
+
+    /// checks the code against each time step in the window
+    ///
+    /// codes that match a step at or before the last accepted step are
+    /// reported as replayed. this does not record the step, use
+    /// [`Totp::accept_step`] once the code should be consumed
+    pub fn verify<C>(&self, code: C, window: &TotpWindow) -> rust_otp::error::Result<TotpCheck>
     where
         C: AsRef<str>
     {
-        let settings = rust_otp::TotpSettings {
+        let now = Utc::now().timestamp().max(0) as u64;
+        let current = now / self.step;
+        let first = current.saturating_sub(window.before);
+        let last = current.saturating_add(window.after);
+
+        let mut settings = rust_otp::TotpSettings {
             algo: self.algo.clone().into(),
             secret: self.secret.clone(),
             digits: self.digits,
             step: self.step,
-            window_before: 1,
-            window_after: 1,
+            window_before: 0,
+            window_after: 0,
             now: None,
         };
+        let mut replayed = None;
+
+        for time_step in first..=last {
+            settings.now = Some(time_step * self.step);
+
+            if !matches!(
+                rust_otp::verify_totp_code(&settings, code.as_ref())?,
+                rust_otp::VerifyResult::Valid
+            ) {
+                continue;
+            }
+
+            match self.last_step {
+                Some(last_step) if time_step <= last_step => {
+                    replayed = Some(time_step);
+                },
+                _ => {
+                    return Ok(TotpCheck::Valid(time_step));
+                }
+            }
+        }
+
+        if let Some(time_step) = replayed {
+            Ok(TotpCheck::Replayed(time_step))
+        } else {
+            Ok(TotpCheck::Invalid)
+        }
+    }
+
+    /// records the time step as used so that codes at or before it are
+    /// rejected
+    ///
+    /// returns false if another request has already accepted the same or a
+    /// later step
+    pub async fn accept_step(
+        &mut self,
+        conn: &impl GenericClient,
+        time_step: u64,
+    ) -> Result<bool, PgError> {
+        let updated = conn.execute(
+            "\
+            update auth_totp set last_step = $2 \
+            where user_id = $1 and \
+                  (last_step is null or last_step < $2)",
+            &[&self.user_id, &(time_step as i64)]
+        ).await?;
+
+        if updated == 1 {
+            self.last_step = Some(time_step);
 
-        rust_otp::verify_totp_code(&settings, code)
+            Ok(true)
+        } else {
+            Ok(false)
+        }
     }
 
     pub async fn delete(&self, conn: &impl GenericClient) -> Result<(), PgError> {
@@ -320,6 +453,73 @@ impl Totp {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttemptResult {
+    Accepted,
+    Invalid,
+    Replayed,
+}
+
+impl AttemptResult {
+    pub fn as_i16(&self) -> i16 {
+        match self {
+            AttemptResult::Accepted => 0,
+            AttemptResult::Invalid => 1,
+            AttemptResult::Replayed => 2,
+        }
+    }
+}
+
+impl From<&TotpCheck> for AttemptResult {
+    fn from(check: &TotpCheck) -> Self {
+        match check {
+            TotpCheck::Valid(_) => AttemptResult::Accepted,
+            TotpCheck::Replayed(_) => AttemptResult::Replayed,
+            TotpCheck::Invalid => AttemptResult::Invalid,
+        }
+    }
+}
+
+/// records a totp verification for auditing
+///
+/// this should be called outside of a transaction so that rejected attempts
+/// are recorded even if the request fails
+pub async fn record_attempt(
+    conn: &impl GenericClient,
+    user_id: &ids::UserId,
+    time_step: Option<u64>,
+    result: AttemptResult,
+    ip: Option<IpAddr>,
+) -> Result<(), PgError> {
+    let _ = conn.execute(
+        "\
+        insert into auth_totp_attempts (user_id, step, result, ip, created) values \
+        ($1, $2, $3, $4, $5)",
+        &[
+            user_id,
+            &time_step.map(|v| v as i64),
+            &result.as_i16(),
+            &ip,
+            &Utc::now(),
+        ]
+    ).await?;
+
+    Ok(())
+}
+
+/// removes recorded attempts that are older than the lockout reset window
+pub async fn sweep_attempts(
+    conn: &impl GenericClient,
+    config: &LockoutConfig,
+) -> Result<u64, PgError> {
+    let reset = Utc::now() - config.reset;
+
+    conn.execute(
+        "delete from auth_totp_attempts where created < $1",
+        &[&reset]
+    ).await
+}
+
 pub enum TotpHashBuilderError {
     KeyExists,
     Rand(rand::Error),
@@ -351,16 +551,37 @@ impl From<TotpHashBuilderError> for net::error::Error {
     }
 }
 
-pub fn create_totp_hash(len: usize) -> Result<String, rand::Error> {
-    let mut bytes = Vec::with_capacity(len);
+/// the number of recovery codes created when none are specified
+pub const DEFAULT_RECOVERY_CODES: usize = 10;
+/// the most recovery codes that can be created at once
+pub const MAX_RECOVERY_CODES: usize = 50;
+/// unused recovery codes at or below this amount will warn the user
+pub const LOW_RECOVERY_CODES: u64 = 3;
 
-    for _ in 0..bytes.len() {
-        bytes.push(0);
-    }
+pub fn create_totp_hash(len: usize) -> Result<String, rand::Error> {
+    let mut bytes = vec![0u8; len];
 
     rand::thread_rng().try_fill_bytes(&mut bytes)?;
 
-    Ok(data_encoding::BASE32.encode(&bytes))
+    Ok(data_encoding::BASE32_NOPAD.encode(&bytes))
+}
+
+/// only the hash of a code is stored so the code itself is only known when
+/// it is created
+fn hash_totp_code(code: &str) -> String {
+    blake3::hash(code.as_bytes()).to_hex().to_string()
+}
+
+/// the message to give when a user is running out of recovery codes
+pub fn low_recovery_warning(remaining: u64) -> Option<String> {
+    if remaining > LOW_RECOVERY_CODES {
+        return None;
+    }
+
+    Some(format!(
+        "only {} unused recovery codes remain. regenerate them to avoid being locked out",
+        remaining
+    ))
 }
 
 pub struct TotpHashBuilder {
@@ -374,9 +595,12 @@ impl TotpHashBuilder {
         self.hash_len = Some(hash_len);
     }
 
-    pub async fn build(self, conn: &impl GenericClient) -> Result<TotpHash, TotpHashBuilderError> {
+    /// creates the totp hash and returns it along with the code. the code
+    /// cannot be retrieved again after this
+    pub async fn build(self, conn: &impl GenericClient) -> Result<(TotpHash, String), TotpHashBuilderError> {
         let used = false;
-        let hash = create_totp_hash(self.hash_len.unwrap_or(25))?;
+        let code = create_totp_hash(self.hash_len.unwrap_or(10))?;
+        let hash = hash_totp_code(&code);
 
         let check = conn.execute(
             "\
@@ -403,12 +627,12 @@ impl TotpHashBuilder {
             ]
         ).await?;
 
-        Ok(TotpHash {
+        Ok((TotpHash {
             user_id: self.user_id,
             key: HistoryField::new(self.key),
             hash: HistoryField::new(hash),
             used,
-        })
+        }, code))
     }
 }
 
@@ -431,6 +655,54 @@ impl TotpHash {
         }
     }
 
+    /// creates a batch of recovery codes for the user
+    ///
+    /// all previous totp hashes for the user are removed so only the codes
+    /// returned here will be valid
+    pub async fn generate_batch(
+        conn: &impl GenericClient,
+        user_id: &ids::UserId,
+        count: usize,
+    ) -> Result<Vec<(Self, String)>, TotpHashBuilderError> {
+        let _ = conn.execute(
+            "delete from auth_totp_hash where user_id = $1",
+            &[user_id]
+        ).await?;
+
+        let mut rtn = Vec::with_capacity(count);
+
+        for index in 1..=count {
+            let created = Self::builder(user_id.clone(), format!("recovery-{}", index))
+                .build(conn)
+                .await?;
+
+            rtn.push(created);
+        }
+
+        Ok(rtn)
+    }
+
+    /// the total and unused number of totp hashes for the user
+    pub async fn count(
+        conn: &impl GenericClient,
+        user_id: &ids::UserId,
+    ) -> Result<(u64, u64), PgError> {
+        let row = conn.query_one(
+            "\
+            select count(*), \
+                   count(*) filter (where not auth_totp_hash.used) \
+            from auth_totp_hash \
+            where auth_totp_hash.user_id = $1",
+            &[user_id]
+        ).await?;
+
+        let total: i64 = row.get(0);
+        let unused: i64 = row.get(1);
+
+        Ok((total as u64, unused as u64))
+    }
+
+    /// finds the totp hash for the given code
     pub async fn retrieve_hash<H>(
         conn: &impl GenericClient,
         user_id: &ids::UserId,
@@ -439,6 +711,8 @@ impl TotpHash {
     where
         H: AsRef<str>
     {
+        let hashed = hash_totp_code(hash.as_ref());
+
         if let Some(row) = conn.query_opt(
             "\
             select auth_totp_hash.user_id, \
@@ -450,7 +724,7 @@ impl TotpHash {
                   auth_totp_hash.hash = $2",
             &[
                 user_id,
-                &hash.as_ref()
+                &hashed
             ]
         ).await? {
             Ok(Some(TotpHash {
@@ -556,18 +830,21 @@ impl TotpHash {
         }
     }
 
-    pub fn regen_hash(&mut self, len: Option<usize>) -> Result<(), rand::Error> {
-        self.hash.set(create_totp_hash(len.unwrap_or(25))?);
+    /// creates a new code for the totp hash and returns it
+    pub fn regen_hash(&mut self, len: Option<usize>) -> Result<String, rand::Error> {
+        let code = create_totp_hash(len.unwrap_or(10))?;
+
+        self.hash.set(hash_totp_code(&code));
         self.used = false;
 
-        Ok(())
+        Ok(code)
     }
 
     pub fn verify<V>(&self, verify: V) -> bool
     where
         V: AsRef<str>
     {
-        self.hash.get() == verify.as_ref()
+        *self.hash.get() == hash_totp_code(verify.as_ref())
     }
 
     pub async fn update(&mut self, conn: &impl GenericClient) -> Result<bool, PgError> {
@@ -617,3 +894,122 @@ impl TotpHash {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use hmac::{Hmac, Mac};
+
+    use crate::testing::database;
+    use crate::user;
+
+    use super::*;
+
+    /// a long step keeps the current step from changing during a test
+    const STEP: u64 = 3600;
+
+    fn totp(last_step: Option<u64>) -> Totp {
+        Totp {
+            user_id: ids::user_id_from_pg(1),
+            algo: Algo::SHA1,
+            secret: b"12345678901234567890".to_vec(),
+            digits: 6,
+            step: STEP,
+            verified: true,
+            last_step,
+        }
+    }
+
+    fn current_step() -> u64 {
+        Utc::now().timestamp() as u64 / STEP
+    }
+
+    /// the code for the time step as given by rfc 6238
+    fn code_at(totp: &Totp, time_step: u64) -> String {
+        let mut mac = Hmac::<sha1::Sha1>::new_from_slice(&totp.secret).unwrap();
+        mac.update(&time_step.to_be_bytes());
+
+        let hash = mac.finalize().into_bytes();
+        let offset = (hash[hash.len() - 1] & 0xf) as usize;
+        let value = u32::from_be_bytes([
+            hash[offset] & 0x7f,
+            hash[offset + 1],
+            hash[offset + 2],
+            hash[offset + 3],
+        ]);
+
+        format!("{:0width$}", value % 10u32.pow(totp.digits), width = totp.digits as usize)
+    }
+
+    fn check(totp: &Totp, time_step: u64, window: TotpWindow) -> TotpCheck {
+        let Ok(result) = totp.verify(code_at(totp, time_step), &window) else {
+            panic!("failed to verify totp code");
+        };
+
+        result
+    }
+
+    #[test]
+    fn default_window_allows_adjacent_steps() {
+        let totp = totp(None);
+        let window = TotpWindow::default();
+        let current = current_step();
+
+        assert_eq!(check(&totp, current, window), TotpCheck::Valid(current));
+        assert_eq!(check(&totp, current - 1, window), TotpCheck::Valid(current - 1));
+        assert_eq!(check(&totp, current + 1, window), TotpCheck::Valid(current + 1));
+        assert_eq!(check(&totp, current - 2, window), TotpCheck::Invalid);
+        assert_eq!(check(&totp, current + 2, window), TotpCheck::Invalid);
+    }
+
+    #[test]
+    fn window_is_configurable() {
+        let totp = totp(None);
+        let current = current_step();
+        let exact = TotpWindow { before: 0, after: 0 };
+        let behind = TotpWindow { before: 2, after: 0 };
+
+        assert_eq!(check(&totp, current, exact), TotpCheck::Valid(current));
+        assert_eq!(check(&totp, current - 1, exact), TotpCheck::Invalid);
+        assert_eq!(check(&totp, current + 1, exact), TotpCheck::Invalid);
+        assert_eq!(check(&totp, current - 2, behind), TotpCheck::Valid(current - 2));
+        assert_eq!(check(&totp, current + 1, behind), TotpCheck::Invalid);
+    }
+
+    #[test]
+    fn used_steps_are_replayed() {
+        let current = current_step();
+        let totp = totp(Some(current));
+        let window = TotpWindow::default();
+
+        assert_eq!(check(&totp, current, window), TotpCheck::Replayed(current));
+        assert_eq!(check(&totp, current - 1, window), TotpCheck::Replayed(current - 1));
+        assert_eq!(check(&totp, current + 1, window), TotpCheck::Valid(current + 1));
+    }
+
+    #[tokio::test]
+    async fn accepted_steps_only_move_forward() {
+        let Some(state) = database().await else {
+            return;
+        };
+        let conn = state.pool().get().await.unwrap();
+
+        let user = user::User::builder(state.ids().wait_user_id().unwrap(), "tester".into())
+            .build(&conn)
+            .await
+            .unwrap();
+        let Ok(mut totp) = Totp::builder(user.id().clone()).build(&conn).await else {
+            panic!("failed to create totp");
+        };
+
+        assert!(totp.accept_step(&conn, 10).await.unwrap());
+        assert!(!totp.accept_step(&conn, 10).await.unwrap(), "step was accepted twice");
+        assert!(!totp.accept_step(&conn, 9).await.unwrap(), "earlier step was accepted");
+        assert!(totp.accept_step(&conn, 11).await.unwrap());
+
+        let Some(found) = Totp::retrieve(&conn, user.id()).await.unwrap() else {
+            panic!("totp was not found");
+        };
+
+        assert_eq!(found.last_step, Some(11));
+    }
+}
@@ -0,0 +1,222 @@
+use rfs_lib::ids;
+use rfs_lib::schema;
+use chrono::{DateTime, Utc};
+use tokio_postgres::Error as PgError;
+use tokio_postgres::types::ToSql;
+use deadpool_postgres::GenericClient;
+
+use super::Ability;
+
+pub struct Access {
+    pub user_id: ids::UserId,
+    pub ability: Ability,
+    pub created: DateTime<Utc>,
+    pub updated: Option<DateTime<Utc>>,
+}
+
+impl Access {
+    pub fn into_schema(self) -> schema::access::AccessItem {
+        schema::access::AccessItem {
+            user_id: self.user_id,
+            ability: self.ability.into_schema(),
+            created: self.created,
+            updated: self.updated,
+        }
+    }
+}
+
+fn access_from_row(row: tokio_postgres::Row) -> Access {
+    Access {
+        user_id: row.get(0),
+        ability: Ability::from_i16(row.get(1))
+            .expect("invalid ability returned from database for access"),
+        created: row.get(2),
+        updated: row.get(3),
+    }
+}
+
+pub async fn get_access<I>(
+    conn: &impl GenericClient,
+    table: &str,
+    id_field: &str,
+    id: &I,
+) -> Result<Vec<Access>, PgError>
+where
+    I: ToSql + Sync
+{
+    let query = format!(
+        "\
+        select {table}.user_id, \
+               {table}.ability, \
+               {table}.created, \
+               {table}.updated \
+        from {table} \
+        where {table}.{id_field} = $1 \
+        order by {table}.created"
+    );
+
+    let rows = conn.query(query.as_str(), &[id]).await?;
+
+    Ok(rows.into_iter()
+        .map(access_from_row)
+        .collect())
+}
+
+/// creates or updates the access for a user
+pub async fn set_access<I>(
+    conn: &impl GenericClient,
+    table: &str,
+    id_field: &str,
+    id: &I,
+    user_id: &ids::UserId,
+    ability: &Ability,
+) -> Result<Access, PgError>
+where
+    I: ToSql + Sync
+{
+    let now = Utc::now();
+    let ability_int = ability.as_i16();
+    let query = format!(
+        "\
+        insert into {table} ({id_field}, user_id, ability, created) values \
+        ($1, $2, $3, $4) \
+        on conflict ({id_field}, user_id) do update set \
+            ability = EXCLUDED.ability, \
+            updated = EXCLUDED.created \
+        returning user_id, \
+                  ability, \
+                  created, \
+                  updated"
+    );
+
+    let row = conn.query_one(
+        query.as_str(),
+        &[id, user_id, &ability_int, &now]
+    ).await?;
+
+    Ok(access_from_row(row))
+}
+
+pub async fn drop_access<I>(
+    conn: &impl GenericClient,
+    table: &str,
+    id_field: &str,
+    id: &I,
+    user_id: &ids::UserId,
+) -> Result<bool, PgError>
+where
+    I: ToSql + Sync
+{
+    let query = format!("delete from {table} where {id_field} = $1 and user_id = $2");
+
+    let deleted = conn.execute(query.as_str(), &[id, user_id]).await?;
+
+    Ok(deleted == 1)
+}
+
+pub struct GroupAccess {
+    pub group_id: ids::GroupId,
+    pub ability: Ability,
+    pub created: DateTime<Utc>,
+    pub updated: Option<DateTime<Utc>>,
+}
+
+impl GroupAccess {
+    pub fn into_schema(self) -> schema::access::GroupAccessItem {
+        schema::access::GroupAccessItem {
+            group_id: self.group_id,
+            ability: self.ability.into_schema(),
+            created: self.created,
+            updated: self.updated,
+        }
+    }
+}
+
+fn group_access_from_row(row: tokio_postgres::Row) -> GroupAccess {
+    GroupAccess {
+        group_id: row.get(0),
+        ability: Ability::from_i16(row.get(1))
+            .expect("invalid ability returned from database for group access"),
+        created: row.get(2),
+        updated: row.get(3),
+    }
+}
+
+pub async fn get_group_access<I>(
+    conn: &impl GenericClient,
+    table: &str,
+    id_field: &str,
+    id: &I,
+) -> Result<Vec<GroupAccess>, PgError>
+where
+    I: ToSql + Sync
+{
+    let query = format!(
+        "\
+        select {table}.group_id, \
+               {table}.ability, \
+               {table}.created, \
+               {table}.updated \
+        from {table} \
+        where {table}.{id_field} = $1 \
+        order by {table}.created"
+    );
+
+    let rows = conn.query(query.as_str(), &[id]).await?;
+
+    Ok(rows.into_iter()
+        .map(group_access_from_row)
+        .collect())
+}
+
+/// creates or updates the access for a group
+pub async fn set_group_access<I>(
+    conn: &impl GenericClient,
+    table: &str,
+    id_field: &str,
+    id: &I,
+    group_id: &ids::GroupId,
+    ability: &Ability,
+) -> Result<GroupAccess, PgError>
+where
+    I: ToSql + Sync
+{
+    let now = Utc::now();
+    let ability_int = ability.as_i16();
+    let query = format!(
+        "\
+        insert into {table} ({id_field}, group_id, ability, created) values \
+        ($1, $2, $3, $4) \
+        on conflict ({id_field}, group_id) do update set \
+            ability = EXCLUDED.ability, \
+            updated = EXCLUDED.created \
+        returning group_id, \
+                  ability, \
+                  created, \
+                  updated"
+    );
+
+    let row = conn.query_one(
+        query.as_str(),
+        &[id, group_id, &ability_int, &now]
+    ).await?;
+
+    Ok(group_access_from_row(row))
+}
+
+pub async fn drop_group_access<I>(
+    conn: &impl GenericClient,
+    table: &str,
+    id_field: &str,
+    id: &I,
+    group_id: &ids::GroupId,
+) -> Result<bool, PgError>
+where
+    I: ToSql + Sync
+{
+    let query = format!("delete from {table} where {id_field} = $1 and group_id = $2");
+
+    let deleted = conn.execute(query.as_str(), &[id, group_id]).await?;
+
+    Ok(deleted == 1)
+}
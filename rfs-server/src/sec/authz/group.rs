@@ -0,0 +1,245 @@
+use chrono::{DateTime, Utc};
+use rfs_lib::ids;
+use rfs_lib::schema;
+use tokio_postgres::Error as PgError;
+use deadpool_postgres::GenericClient;
+
+pub async fn name_check(
+    conn: &impl GenericClient,
+    name: &str,
+) -> Result<Option<ids::GroupId>, PgError> {
+    let check = conn.query_opt(
+        "select id from groups where name = $1",
+        &[&name]
+    ).await?;
+
+    Ok(check.map(|row| ids::group_id_from_pg(row.get(0))))
+}
+
+/// retrieves the ids of all groups that the user is a member of
+pub async fn user_groups(
+    conn: &impl GenericClient,
+    user_id: &ids::UserId,
+) -> Result<Vec<ids::GroupId>, PgError> {
+    let rows = conn.query(
+        "select group_users.group_id from group_users where group_users.user_id = $1",
+        &[user_id]
+    ).await?;
+
+    Ok(rows.into_iter()
+        .map(|row| ids::group_id_from_pg(row.get(0)))
+        .collect())
+}
+
+/// converts a list of group ids into something that can be passed to
+/// postgres as an array
+pub fn pg_group_list(groups: &[ids::GroupId]) -> Vec<i64> {
+    groups.iter()
+        .map(|id| *id.id())
+        .collect()
+}
+
+pub struct Member {
+    pub user_id: ids::UserId,
+    pub username: String,
+    pub admin: bool,
+    pub added: DateTime<Utc>,
+}
+
+impl Member {
+    fn from_row(row: tokio_postgres::Row) -> Self {
+        Member {
+            user_id: ids::user_id_from_pg(row.get(0)),
+            username: row.get(1),
+            admin: row.get(2),
+            added: row.get(3),
+        }
+    }
+
+    pub async fn retrieve(
+        conn: &impl GenericClient,
+        group_id: &ids::GroupId,
+        user_id: &ids::UserId,
+    ) -> Result<Option<Self>, PgError> {
+        let result = conn.query_opt(
+            "\
+            select group_users.user_id, \
+                   users.username, \
+                   group_users.admin, \
+                   group_users.added \
+            from group_users \
+                join users on \
+                    group_users.user_id = users.id \
+            where group_users.group_id = $1 and \
+                  group_users.user_id = $2",
+            &[group_id, user_id]
+        ).await?;
+
+        Ok(result.map(Self::from_row))
+    }
+
+    pub async fn retrieve_group(
+        conn: &impl GenericClient,
+        group_id: &ids::GroupId,
+    ) -> Result<Vec<Self>, PgError> {
+        let rows = conn.query(
+            "\
+            select group_users.user_id, \
+                   users.username, \
+                   group_users.admin, \
+                   group_users.added \
+            from group_users \
+                join users on \
+                    group_users.user_id = users.id \
+            where group_users.group_id = $1 and \
+                  users.deleted is null \
+            order by group_users.added",
+            &[group_id]
+        ).await?;
+
+        Ok(rows.into_iter()
+            .map(Self::from_row)
+            .collect())
+    }
+
+    /// adds the user to the group or updates the admin flag if they are
+    /// already a member
+    pub async fn set(
+        conn: &impl GenericClient,
+        group_id: &ids::GroupId,
+        user_id: &ids::UserId,
+        admin: bool,
+    ) -> Result<(), PgError> {
+        let now = Utc::now();
+
+        let _ = conn.execute(
+            "\
+            insert into group_users (group_id, user_id, admin, added) values \
+            ($1, $2, $3, $4) \
+            on conflict (group_id, user_id) do update set \
+                admin = EXCLUDED.admin",
+            &[group_id, user_id, &admin, &now]
+        ).await?;
+
+        Ok(())
+    }
+
+    pub async fn drop(
+        conn: &impl GenericClient,
+        group_id: &ids::GroupId,
+        user_id: &ids::UserId,
+    ) -> Result<bool, PgError> {
+        let _ = conn.execute(
+            "delete from auth_ldap_groups where group_id = $1 and user_id = $2",
+            &[group_id, user_id]
+        ).await?;
+        let deleted = conn.execute(
+            "delete from group_users where group_id = $1 and user_id = $2",
+            &[group_id, user_id]
+        ).await?;
+
+        Ok(deleted == 1)
+    }
+
+    pub fn into_schema(self) -> schema::group::Member {
+        schema::group::Member {
+            user_id: self.user_id,
+            username: self.username,
+            admin: self.admin,
+            added: self.added,
+        }
+    }
+}
+
+pub struct GroupBuilder {
+    id: ids::GroupId,
+    name: String,
+}
+
+impl GroupBuilder {
+    pub async fn build(self, conn: &impl GenericClient) -> Result<Group, PgError> {
+        let created = Utc::now();
+
+        let _ = conn.execute(
+            "insert into groups (id, name, created) values ($1, $2, $3)",
+            &[&self.id, &self.name, &created]
+        ).await?;
+
+        Ok(Group {
+            id: self.id,
+            name: self.name,
+            created,
+        })
+    }
+}
+
+pub struct Group {
+    pub id: ids::GroupId,
+    pub name: String,
+    pub created: DateTime<Utc>,
+}
+
+impl Group {
+    pub fn builder(id: ids::GroupId, name: String) -> GroupBuilder {
+        GroupBuilder {
+            id,
+            name,
+        }
+    }
+
+    pub async fn retrieve(
+        conn: &impl GenericClient,
+        id: &ids::GroupId,
+    ) -> Result<Option<Self>, PgError> {
+        let result = conn.query_opt(
+            "\
+            select groups.id, \
+                   groups.name, \
+                   groups.created \
+            from groups \
+            where groups.id = $1",
+            &[id]
+        ).await?;
+
+        Ok(result.map(|row| Group {
+            id: ids::group_id_from_pg(row.get(0)),
+            name: row.get(1),
+            created: row.get(2),
+        }))
+    }
+
+    /// removes the group along with its members and any access given to it
+    pub async fn delete(&self, conn: &impl GenericClient) -> Result<bool, PgError> {
+        let _ = conn.execute(
+            "delete from storage_group_access where group_id = $1",
+            &[&self.id]
+        ).await?;
+        let _ = conn.execute(
+            "delete from fs_group_access where group_id = $1",
+            &[&self.id]
+        ).await?;
+        let _ = conn.execute(
+            "delete from auth_ldap_groups where group_id = $1",
+            &[&self.id]
+        ).await?;
+        let _ = conn.execute(
+            "delete from group_users where group_id = $1",
+            &[&self.id]
+        ).await?;
+
+        let deleted = conn.execute(
+            "delete from groups where id = $1",
+            &[&self.id]
+        ).await?;
+
+        Ok(deleted == 1)
+    }
+
+    pub fn into_schema(self) -> schema::group::Group {
+        schema::group::Group {
+            id: self.id,
+            name: self.name,
+            created: self.created,
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use rfs_lib::ids;
+use rfs_lib::schema;
+use axum::http::StatusCode;
+use tokio_postgres::Error as PgError;
+use deadpool_postgres::GenericClient;
+
+use crate::net;
+use crate::fs;
+use crate::storage;
+use crate::sec::authn::initiator;
+
+pub mod access;
+pub mod permission;
+pub mod role;
+pub mod group;
+
+pub use permission::Permission;
+
+/// the level of access a user has to a storage medium or fs item
+///
+/// each ability includes the abilities below it. write implies read and
+/// manage implies write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Ability {
+    Read,
+    Write,
+    Manage,
+}
+
+impl Ability {
+    pub fn from_i16(v: i16) -> Option<Self> {
+        match v {
+            0 => Some(Ability::Read),
+            1 => Some(Ability::Write),
+            2 => Some(Ability::Manage),
+            _ => None
+        }
+    }
+
+    pub fn as_i16(&self) -> i16 {
+        match self {
+            Ability::Read => 0,
+            Ability::Write => 1,
+            Ability::Manage => 2,
+        }
+    }
+
+    pub fn into_schema(self) -> schema::access::Ability {
+        match self {
+            Ability::Read => schema::access::Ability::Read,
+            Ability::Write => schema::access::Ability::Write,
+            Ability::Manage => schema::access::Ability::Manage,
+        }
+    }
+}
+
+impl From<schema::access::Ability> for Ability {
+    fn from(v: schema::access::Ability) -> Self {
+        match v {
+            schema::access::Ability::Read => Ability::Read,
+            schema::access::Ability::Write => Ability::Write,
+            schema::access::Ability::Manage => Ability::Manage,
+        }
+    }
+}
+
+/// checks if the given ability satisfies the required ability
+pub fn has_ability(given: &Option<Ability>, required: Ability) -> bool {
+    if let Some(ability) = given {
+        *ability >= required
+    } else {
+        false
+    }
+}
+
+pub fn permission_denied() -> net::error::Error {
+    net::error::Error::new()
+        .status(StatusCode::FORBIDDEN)
+        .kind("PermissionDenied")
+        .message("you do not have permission to perform the requested action")
+}
+
+pub fn session_required() -> net::error::Error {
+    net::error::Error::new()
+        .status(StatusCode::FORBIDDEN)
+        .kind("InvalidMechanism")
+        .message("the requested action can only be performed with a session")
+}
+
+/// checks that the initiator authenticated with a session
+///
+/// credentials, sessions and access tokens are only managed with a session.
+/// bot tokens, basic authorization and access tokens are rejected
+pub fn require_session(
+    initiator: &initiator::Initiator,
+) -> Result<(), net::error::Error> {
+    match initiator.mechanism() {
+        initiator::Mechanism::Session(_) => Ok(()),
+        _ => Err(session_required()),
+    }
+}
+
+/// checks that the initiator has the given permission
+pub fn require_permission(
+    initiator: &initiator::Initiator,
+    permission: Permission
+) -> Result<(), net::error::Error> {
+    if initiator.has_permission(&permission) {
+        Ok(())
+    } else {
+        Err(permission_denied())
+    }
+}
+
+/// checks that the initiator is allowed to grant the given permissions
+///
+/// permissions can only be handed out by someone that already holds all of
+/// them so that managing roles cannot be used to gain more access
+pub fn require_grantable(
+    initiator: &initiator::Initiator,
+    permissions: &permission::PermissionSet,
+) -> Result<(), net::error::Error> {
+    require_permission(initiator, Permission::RoleManage)?;
+
+    if permissions.is_subset(initiator.permissions()) {
+        Ok(())
+    } else {
+        Err(permission_denied())
+    }
+}
+
+/// checks that the initiator is allowed to assign the given roles to a user
+///
+/// requires role management and every permission granted by the roles
+pub async fn require_assignable(
+    conn: &impl GenericClient,
+    initiator: &initiator::Initiator,
+    roles: &[ids::RoleId],
+) -> Result<(), net::error::Error> {
+    let permissions = permission::roles_permissions(conn, roles).await?;
+
+    require_grantable(initiator, &permissions)
+}
+
+fn highest_ability(rows: Vec<tokio_postgres::Row>) -> Option<Ability> {
+    let mut rtn: Option<Ability> = None;
+
+    for row in rows {
+        let Some(ability) = Ability::from_i16(row.get(0)) else {
+            continue;
+        };
+
+        if let Some(current) = &rtn {
+            if ability > *current {
+                rtn = Some(ability);
+            }
+        } else {
+            rtn = Some(ability);
+        }
+    }
+
+    rtn
+}
+
+/// retrieves the ability a user has for the given storage medium
+///
+/// access given to any of the groups the user is a member of applies as
+/// well. the owner of the medium will always have manage
+pub async fn storage_ability(
+    conn: &impl GenericClient,
+    user_id: &ids::UserId,
+    groups: &[ids::GroupId],
+    medium: &storage::Medium,
+) -> Result<Option<Ability>, PgError> {
+    if medium.user_id == *user_id {
+        return Ok(Some(Ability::Manage));
+    }
+
+    let group_list = group::pg_group_list(groups);
+
+    let rows = conn.query(
+        "\
+        select storage_access.ability \
+        from storage_access \
+        where storage_access.storage_id = $1 and \
+              storage_access.user_id = $2 \
+        union all \
+        select storage_group_access.ability \
+        from storage_group_access \
+        where storage_group_access.storage_id = $1 and \
+              storage_group_access.group_id = any($3)",
+        &[&medium.id, user_id, &group_list]
+    ).await?;
+
+    Ok(highest_ability(rows))
+}
+
+/// retrieves the ability of the initiator for the given storage medium
+///
+/// the ability of the user is limited by the mechanism used to authenticate
+pub async fn initiator_storage_ability(
+    conn: &impl GenericClient,
+    initiator: &initiator::Initiator,
+    medium: &storage::Medium,
+) -> Result<Option<Ability>, PgError> {
+    let ability = storage_ability(
+        conn,
+        initiator.user().id(),
+        initiator.groups(),
+        medium
+    ).await?;
+
+    Ok(initiator.restrict_ability(&medium.id, ability))
+}
+
+/// retrieves the ability a user has for the given fs item
+///
+/// walks up the parents of the item so that access granted to a directory
+/// will apply to everything under it. access granted to the storage medium
+/// of the item and to any groups the user is a member of applies as well.
+/// the owner of the storage medium will always have manage. the user that
+/// created the item has no implicit ability and relies on the access they
+/// were granted
+pub async fn fs_ability(
+    conn: &impl GenericClient,
+    user_id: &ids::UserId,
+    groups: &[ids::GroupId],
+    item: &fs::Item,
+) -> Result<Option<Ability>, PgError> {
+    let manage = Ability::Manage.as_i16();
+    let group_list = group::pg_group_list(groups);
+
+    let rows = conn.query(
+        "\
+        with recursive fs_tree as (\
+            select fs.id, \
+                   fs.parent \
+            from fs \
+            where fs.id = $1 \
+            union \
+            select fs.id, \
+                   fs.parent \
+            from fs \
+                join fs_tree on \
+                    fs_tree.parent = fs.id\
+        ) \
+        select fs_access.ability \
+        from fs_access \
+            join fs_tree on \
+                fs_access.fs_id = fs_tree.id \
+        where fs_access.user_id = $2 \
+        union all \
+        select fs_group_access.ability \
+        from fs_group_access \
+            join fs_tree on \
+                fs_group_access.fs_id = fs_tree.id \
+        where fs_group_access.group_id = any($5) \
+        union all \
+        select storage_access.ability \
+        from storage_access \
+        where storage_access.storage_id = $3 and \
+              storage_access.user_id = $2 \
+        union all \
+        select storage_group_access.ability \
+        from storage_group_access \
+        where storage_group_access.storage_id = $3 and \
+              storage_group_access.group_id = any($5) \
+        union all \
+        select $4::smallint \
+        from storage \
+        where storage.id = $3 and \
+              storage.user_id = $2",
+        &[item.id(), user_id, item.storage_id(), &manage, &group_list]
+    ).await?;
+
+    Ok(highest_ability(rows))
+}
+
+/// retrieves the ability of the initiator for the given fs item
+///
+/// the ability of the user is limited by the mechanism used to authenticate
+pub async fn initiator_fs_ability(
+    conn: &impl GenericClient,
+    initiator: &initiator::Initiator,
+    item: &fs::Item,
+) -> Result<Option<Ability>, PgError> {
+    let ability = fs_ability(
+        conn,
+        initiator.user().id(),
+        initiator.groups(),
+        item
+    ).await?;
+
+    Ok(initiator.restrict_ability(item.storage_id(), ability))
+}
@@ -0,0 +1,116 @@
+use std::collections::HashSet;
+
+use rfs_lib::ids;
+use rfs_lib::schema;
+use tokio_postgres::Error as PgError;
+use deadpool_postgres::GenericClient;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Permission {
+    StorageCreate,
+    StorageLocalAny,
+    UserManage,
+    RoleManage,
+    AuditRead,
+}
+
+pub type PermissionSet = HashSet<Permission>;
+
+impl Permission {
+    pub fn from_i16(v: i16) -> Option<Self> {
+        match v {
+            0 => Some(Permission::StorageCreate),
+            1 => Some(Permission::StorageLocalAny),
+            2 => Some(Permission::UserManage),
+            3 => Some(Permission::RoleManage),
+            4 => Some(Permission::AuditRead),
+            _ => None
+        }
+    }
+
+    pub fn as_i16(&self) -> i16 {
+        match self {
+            Permission::StorageCreate => 0,
+            Permission::StorageLocalAny => 1,
+            Permission::UserManage => 2,
+            Permission::RoleManage => 3,
+            Permission::AuditRead => 4,
+        }
+    }
+
+    pub fn into_schema(self) -> schema::role::Permission {
+        match self {
+            Permission::StorageCreate => schema::role::Permission::StorageCreate,
+            Permission::StorageLocalAny => schema::role::Permission::StorageLocalAny,
+            Permission::UserManage => schema::role::Permission::UserManage,
+            Permission::RoleManage => schema::role::Permission::RoleManage,
+            Permission::AuditRead => schema::role::Permission::AuditRead,
+        }
+    }
+}
+
+impl From<schema::role::Permission> for Permission {
+    fn from(v: schema::role::Permission) -> Self {
+        match v {
+            schema::role::Permission::StorageCreate => Permission::StorageCreate,
+            schema::role::Permission::StorageLocalAny => Permission::StorageLocalAny,
+            schema::role::Permission::UserManage => Permission::UserManage,
+            schema::role::Permission::RoleManage => Permission::RoleManage,
+            schema::role::Permission::AuditRead => Permission::AuditRead,
+        }
+    }
+}
+
+/// retrieves the effective permissions of a user from all of their roles
+pub async fn user_permissions(
+    conn: &impl GenericClient,
+    user_id: &ids::UserId,
+) -> Result<PermissionSet, PgError> {
+    let rows = conn.query(
+        "\
+        select distinct authz_permissions.permission \
+        from authz_permissions \
+            join authz_user_roles on \
+                authz_permissions.role_id = authz_user_roles.role_id \
+        where authz_user_roles.user_id = $1",
+        &[user_id]
+    ).await?;
+
+    let mut rtn = PermissionSet::with_capacity(rows.len());
+
+    for row in rows {
+        if let Some(permission) = Permission::from_i16(row.get(0)) {
+            rtn.insert(permission);
+        }
+    }
+
+    Ok(rtn)
+}
+
+/// retrieves the combined permissions granted by the given roles
+pub async fn roles_permissions(
+    conn: &impl GenericClient,
+    roles: &[ids::RoleId],
+) -> Result<PermissionSet, PgError> {
+    let role_list: Vec<i64> = roles.iter()
+        .map(|id| *id.id())
+        .collect();
+
+    let rows = conn.query(
+        "\
+        select distinct authz_permissions.permission \
+        from authz_permissions \
+        where authz_permissions.role_id = any($1)",
+        &[&role_list]
+    ).await?;
+
+    let mut rtn = PermissionSet::with_capacity(rows.len());
+
+    for row in rows {
+        if let Some(permission) = Permission::from_i16(row.get(0)) {
+            rtn.insert(permission);
+        }
+    }
+
+    Ok(rtn)
+}
@@ -0,0 +1,229 @@
+use rfs_lib::ids;
+use rfs_lib::schema;
+use tokio_postgres::Error as PgError;
+use tokio_postgres::types::ToSql;
+use deadpool_postgres::GenericClient;
+
+use super::permission::{Permission, PermissionSet};
+
+pub async fn name_check(
+    conn: &impl GenericClient,
+    name: &str,
+) -> Result<Option<ids::RoleId>, PgError> {
+    let check = conn.query_opt(
+        "select id from authz_roles where name = $1",
+        &[&name]
+    ).await?;
+
+    Ok(check.map(|row| ids::role_id_from_pg(row.get(0))))
+}
+
+/// finds the first role in the given list that does not exist
+pub async fn missing_check(
+    conn: &impl GenericClient,
+    roles: &[ids::RoleId],
+) -> Result<Option<ids::RoleId>, PgError> {
+    for role_id in roles {
+        let found = conn.query_opt(
+            "select id from authz_roles where id = $1",
+            &[role_id]
+        ).await?;
+
+        if found.is_none() {
+            return Ok(Some(role_id.clone()));
+        }
+    }
+
+    Ok(None)
+}
+
+/// retrieves the roles assigned to a user
+pub async fn user_roles(
+    conn: &impl GenericClient,
+    user_id: &ids::UserId,
+) -> Result<Vec<schema::role::ListItem>, PgError> {
+    let rows = conn.query(
+        "\
+        select authz_roles.id, \
+               authz_roles.name \
+        from authz_roles \
+            join authz_user_roles on \
+                authz_roles.id = authz_user_roles.role_id \
+        where authz_user_roles.user_id = $1 \
+        order by authz_roles.id",
+        &[user_id]
+    ).await?;
+
+    Ok(rows.into_iter()
+        .map(|row| schema::role::ListItem {
+            id: ids::role_id_from_pg(row.get(0)),
+            name: row.get(1),
+        })
+        .collect())
+}
+
+/// replaces the current permissions of a role with the given permissions
+pub async fn set_permissions(
+    conn: &impl GenericClient,
+    role_id: &ids::RoleId,
+    permissions: &PermissionSet,
+) -> Result<(), PgError> {
+    let _ = conn.execute(
+        "delete from authz_permissions where role_id = $1",
+        &[role_id]
+    ).await?;
+
+    for permission in permissions {
+        let value = permission.as_i16();
+
+        let _ = conn.execute(
+            "\
+            insert into authz_permissions (role_id, permission) values \
+            ($1, $2)",
+            &[role_id, &value]
+        ).await?;
+    }
+
+    Ok(())
+}
+
+/// replaces the current roles of a user with the given roles
+pub async fn set_user_roles(
+    conn: &impl GenericClient,
+    user_id: &ids::UserId,
+    roles: &[ids::RoleId],
+) -> Result<(), PgError> {
+    let _ = conn.execute(
+        "delete from authz_user_roles where user_id = $1",
+        &[user_id]
+    ).await?;
+
+    for role_id in roles {
+        let _ = conn.execute(
+            "\
+            insert into authz_user_roles (user_id, role_id) values \
+            ($1, $2) \
+            on conflict do nothing",
+            &[user_id, role_id]
+        ).await?;
+    }
+
+    Ok(())
+}
+
+pub struct RoleBuilder {
+    id: ids::RoleId,
+    name: String,
+    permissions: PermissionSet,
+}
+
+impl RoleBuilder {
+    pub fn add_permission(&mut self, permission: Permission) {
+        self.permissions.insert(permission);
+    }
+
+    pub async fn build(self, conn: &impl GenericClient) -> Result<Role, PgError> {
+        let _ = conn.execute(
+            "insert into authz_roles (id, name) values ($1, $2)",
+            &[&self.id, &self.name]
+        ).await?;
+
+        set_permissions(conn, &self.id, &self.permissions).await?;
+
+        Ok(Role {
+            id: self.id,
+            name: self.name,
+            permissions: self.permissions,
+        })
+    }
+}
+
+pub struct Role {
+    pub id: ids::RoleId,
+    pub name: String,
+    pub permissions: PermissionSet,
+}
+
+impl Role {
+    pub fn builder(id: ids::RoleId, name: String) -> RoleBuilder {
+        RoleBuilder {
+            id,
+            name,
+            permissions: PermissionSet::new(),
+        }
+    }
+
+    pub async fn retrieve(
+        conn: &impl GenericClient,
+        id: &ids::RoleId,
+    ) -> Result<Option<Self>, PgError> {
+        let params: [&(dyn ToSql + Sync); 1] = [id];
+        let record_query = conn.query_opt(
+            "\
+            select authz_roles.id, \
+                   authz_roles.name \
+            from authz_roles \
+            where authz_roles.id = $1",
+            &params
+        );
+        let permissions_query = conn.query(
+            "\
+            select authz_permissions.permission \
+            from authz_permissions \
+            where authz_permissions.role_id = $1",
+            &params
+        );
+
+        match tokio::try_join!(record_query, permissions_query) {
+            Ok((Some(row), permission_rows)) => {
+                let mut permissions = PermissionSet::with_capacity(permission_rows.len());
+
+                for permission_row in permission_rows {
+                    if let Some(permission) = Permission::from_i16(permission_row.get(0)) {
+                        permissions.insert(permission);
+                    }
+                }
+
+                Ok(Some(Role {
+                    id: ids::role_id_from_pg(row.get(0)),
+                    name: row.get(1),
+                    permissions,
+                }))
+            },
+            Ok((None, _)) => Ok(None),
+            Err(err) => Err(err)
+        }
+    }
+
+    pub async fn delete(&self, conn: &impl GenericClient) -> Result<bool, PgError> {
+        let _ = conn.execute(
+            "delete from authz_user_roles where role_id = $1",
+            &[&self.id]
+        ).await?;
+        let _ = conn.execute(
+            "delete from authz_permissions where role_id = $1",
+            &[&self.id]
+        ).await?;
+
+        let deleted = conn.execute(
+            "delete from authz_roles where id = $1",
+            &[&self.id]
+        ).await?;
+
+        Ok(deleted == 1)
+    }
+
+    pub fn into_schema(self) -> schema::role::Role {
+        let mut permissions: Vec<schema::role::Permission> = self.permissions.into_iter()
+            .map(|permission| permission.into_schema())
+            .collect();
+
+        permissions.sort_by_key(|permission| Permission::from(*permission).as_i16());
+
+        schema::role::Role {
+            id: self.id,
+            name: self.name,
+            permissions,
+        }
+    }
+}
@@ -2,3 +2,5 @@ pub mod secret;
 pub mod state;
 
 pub mod authn;
+pub mod authz;
+pub mod audit;
@@ -1,7 +1,11 @@
 use std::collections::HashMap;
+use std::path::Path;
 
+use base64::{Engine, engine::general_purpose::STANDARD};
 use lazy_static::lazy_static;
 
+use crate::error;
+
 lazy_static! {
     pub static ref EMPTY_SECRET: Secret = Secret::empty();
 }
@@ -77,4 +81,90 @@ impl Manager {
     pub fn get(&self, version: &u32) -> Option<&Secret> {
         self.map.get(version)
     }
+
+    /// all available versions from oldest to newest
+    pub fn versions(&self) -> &[u32] {
+        self.ordering.as_slice()
+    }
+}
+
+fn parse_version(value: &str, path: &Path) -> error::Result<u32> {
+    let Ok(version) = value.parse::<u32>() else {
+        return Err(error::Error::new()
+            .kind("InvalidSecret")
+            .message(format!("invalid secret version \"{}\". path: {}", value, path.display())));
+    };
+
+    if version == 0 {
+        return Err(error::Error::new()
+            .kind("InvalidSecret")
+            .message(format!("secret version 0 is reserved for the empty secret. path: {}", path.display())));
+    }
+
+    Ok(version)
+}
+
+/// loads secrets from a key file
+///
+/// each line of the file is "{version} {secret}" with the secret being
+/// standard base64. empty lines and lines starting with "#" are ignored
+pub fn load_file(path: &Path) -> error::Result<Vec<Secret>> {
+    let contents = std::fs::read_to_string(path)?;
+    let mut rtn = Vec::new();
+
+    for line in contents.lines() {
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let Some((version, encoded)) = trimmed.split_once(char::is_whitespace) else {
+            return Err(error::Error::new()
+                .kind("InvalidSecret")
+                .message(format!("secret line is missing a version or value. path: {}", path.display())));
+        };
+
+        let version = parse_version(version, path)?;
+        let bytes = STANDARD.decode(encoded.trim())
+            .map_err(|err| error::Error::new()
+                .kind("InvalidSecret")
+                .message(format!("secret version {} is not valid base64. path: {}", version, path.display()))
+                .source(err))?;
+
+        rtn.push(Secret::new(version, bytes));
+    }
+
+    Ok(rtn)
+}
+
+/// loads secrets from a directory
+///
+/// each file is named after the version of the secret and contains the raw
+/// bytes of the secret
+pub fn load_dir(path: &Path) -> error::Result<Vec<Secret>> {
+    let mut rtn = Vec::new();
+
+    for item in std::fs::read_dir(path)? {
+        let entry = item?;
+
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+
+        let entry_path = entry.path();
+        let file_name = entry.file_name();
+        let Some(name) = file_name.to_str() else {
+            return Err(error::Error::new()
+                .kind("InvalidSecret")
+                .message(format!("secret file name contains invalid UTF-8 characters. path: {}", entry_path.display())));
+        };
+
+        let version = parse_version(name, &entry_path)?;
+        let bytes = std::fs::read(&entry_path)?;
+
+        rtn.push(Secret::new(version, bytes));
+    }
+
+    Ok(rtn)
 }
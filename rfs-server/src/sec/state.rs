@@ -1,8 +1,19 @@
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc, Duration};
+
 use crate::error;
+use crate::net::cookie::SameSite;
 
 use super::secret;
+use super::authn::{oidc, ldap};
 
 const BLAKE3_CONTEXT: &str = "rust-file-server 2023-05-12 12:35:00 session tokens";
+const BLAKE3_SIGNING_CONTEXT: &str = "rust-file-server 2023-05-12 12:35:00 signed urls";
+const HKDF_SIGNING_INFO: &[u8] = b"rust-file-server signed urls";
+
+/// the shortest secret that will be accepted for signing urls
+pub const MIN_SIGNING_SECRET: usize = 32;
 
 #[derive(clap::ValueEnum, Clone, Debug)]
 pub enum SessionHash {
@@ -15,16 +26,148 @@ pub enum SessionHash {
     HS512,
 }
 
+/// the same site policy of the session cookie. "None" is not available
+/// since it would send the cookie with cross site requests
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum SessionSameSite {
+    Strict,
+    Lax,
+}
+
+/// how long sessions are valid for
+#[derive(Debug, Clone, Copy)]
+pub struct SessionLifetimes {
+    /// the absolute lifetime of a session from when it was issued
+    pub lifetime: Duration,
+    /// how long a session can go without activity before it expires
+    pub idle: Option<Duration>,
+    /// the lifetime of a session that has not finished authentication or
+    /// verification
+    pub pending: Duration,
+    /// the minimum amount of time between updates to the expiration of an
+    /// active session
+    pub renew: Duration,
+}
+
+impl SessionLifetimes {
+    /// calculates when a session should expire if it was active at the given
+    /// time
+    pub fn active_expires(&self, issued_on: &DateTime<Utc>, active: &DateTime<Utc>) -> DateTime<Utc> {
+        let max = *issued_on + self.lifetime;
+
+        if let Some(idle) = &self.idle {
+            std::cmp::min(*active + *idle, max)
+        } else {
+            max
+        }
+    }
+}
+
+impl Default for SessionLifetimes {
+    fn default() -> Self {
+        SessionLifetimes {
+            lifetime: Duration::days(7),
+            idle: None,
+            pending: Duration::minutes(10),
+            renew: Duration::minutes(5),
+        }
+    }
+}
+
+/// limits on failed login attempts
+#[derive(Debug, Clone, Copy)]
+pub struct LockoutConfig {
+    /// failed attempts for a user before they are locked out
+    pub user_threshold: u32,
+    /// failed attempts from an ip before it is locked out
+    pub ip_threshold: u32,
+    /// failed basic authorization attempts from an ip before it is locked
+    /// out. kept low since every request is a login attempt
+    pub basic_threshold: u32,
+    /// the lockout for the first attempt over a threshold. doubles for each
+    /// attempt after
+    pub delay: Duration,
+    /// the longest that a lockout can be
+    pub max: Duration,
+    /// how long without a failed attempt before the count is reset
+    pub reset: Duration,
+}
+
+impl LockoutConfig {
+    /// calculates how long to lock out for the number of failures
+    pub fn lockout_for(&self, failures: u32, threshold: u32) -> Option<Duration> {
+        if failures < threshold {
+            return None;
+        }
+
+        // keeps the multiplication from overflowing
+        let exp = std::cmp::min(failures - threshold, 20);
+        let delay = self.delay * 2i32.pow(exp);
+
+        Some(std::cmp::min(delay, self.max))
+    }
+}
+
+impl Default for LockoutConfig {
+    fn default() -> Self {
+        LockoutConfig {
+            user_threshold: 5,
+            ip_threshold: 20,
+            basic_threshold: 3,
+            delay: Duration::seconds(1),
+            max: Duration::minutes(15),
+            reset: Duration::hours(1),
+        }
+    }
+}
+
+/// the number of steps around the current one that totp codes are accepted
+/// for to allow for clock drift
+#[derive(Debug, Clone, Copy)]
+pub struct TotpWindow {
+    pub before: u64,
+    pub after: u64,
+}
+
+impl Default for TotpWindow {
+    fn default() -> Self {
+        TotpWindow {
+            before: 1,
+            after: 1,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Builder {
     session_hash: Option<SessionHash>,
     session_secret: Option<String>,
+    signing_secret: Option<String>,
     session_domain: Option<String>,
     session_secure: bool,
+    session_same_site: Option<SessionSameSite>,
+    session_lifetimes: SessionLifetimes,
+    lockout: LockoutConfig,
+    totp_window: TotpWindow,
+    basic_auth: bool,
+    access_lifetime: Duration,
+    audit_retention: Option<Duration>,
+    secrets_file: Option<PathBuf>,
+    secrets_dir: Option<PathBuf>,
     secret_manager: secret::Manager,
+    oidc: oidc::Builder,
+    ldap: ldap::Builder,
 }
 
 impl Builder {
+    pub fn oidc(&mut self) -> &mut oidc::Builder {
+        &mut self.oidc
+    }
+
+    pub fn ldap(&mut self) -> &mut ldap::Builder {
+        &mut self.ldap
+    }
+
     pub fn set_session_secret(&mut self, key: String) -> &mut Self {
         self.session_secret = Some(key);
         self
@@ -35,6 +178,13 @@ impl Builder {
         self
     }
 
+    /// the secret used to sign urls. signed urls are disabled if not
+    /// provided
+    pub fn set_signing_secret(&mut self, secret: String) -> &mut Self {
+        self.signing_secret = Some(secret);
+        self
+    }
+
     pub fn set_session_hash(&mut self, hash: SessionHash) -> &mut Self {
         self.session_hash = Some(hash);
         self
@@ -45,6 +195,12 @@ impl Builder {
         self
     }
 
+    /// the domain attribute of the session cookie
+    pub fn set_session_domain(&mut self, domain: String) -> &mut Self {
+        self.session_domain = Some(domain);
+        self
+    }
+
     pub fn set_session_secure(&mut self, secure: bool) -> &mut Self {
         self.session_secure = secure;
         self
@@ -55,45 +211,209 @@ impl Builder {
         self
     }
 
+    pub fn set_session_same_site(&mut self, same_site: SessionSameSite) -> &mut Self {
+        self.session_same_site = Some(same_site);
+        self
+    }
+
+    /// the absolute lifetime of a session in seconds
+    pub fn set_session_lifetime(&mut self, seconds: u32) -> &mut Self {
+        self.session_lifetimes.lifetime = Duration::seconds(seconds as i64);
+        self
+    }
+
+    /// the idle lifetime of a session in seconds
+    pub fn set_session_idle(&mut self, seconds: u32) -> &mut Self {
+        self.session_lifetimes.idle = Some(Duration::seconds(seconds as i64));
+        self
+    }
+
+    /// the lifetime in seconds of a session that is pending authentication
+    /// or verification
+    pub fn set_session_pending(&mut self, seconds: u32) -> &mut Self {
+        self.session_lifetimes.pending = Duration::seconds(seconds as i64);
+        self
+    }
+
+    /// the minimum number of seconds between session expiration updates
+    pub fn set_session_renew(&mut self, seconds: u32) -> &mut Self {
+        self.session_lifetimes.renew = Duration::seconds(seconds as i64);
+        self
+    }
+
+    /// failed attempts for a user before they are locked out
+    pub fn set_lockout_user_threshold(&mut self, threshold: u32) -> &mut Self {
+        self.lockout.user_threshold = threshold;
+        self
+    }
+
+    /// failed attempts from an ip before it is locked out
+    pub fn set_lockout_ip_threshold(&mut self, threshold: u32) -> &mut Self {
+        self.lockout.ip_threshold = threshold;
+        self
+    }
+
+    /// failed basic authorization attempts from an ip before it is locked
+    /// out
+    pub fn set_lockout_basic_threshold(&mut self, threshold: u32) -> &mut Self {
+        self.lockout.basic_threshold = threshold;
+        self
+    }
+
+    /// the initial lockout in seconds once a threshold is reached
+    pub fn set_lockout_delay(&mut self, seconds: u32) -> &mut Self {
+        self.lockout.delay = Duration::seconds(seconds as i64);
+        self
+    }
+
+    /// the longest lockout in seconds
+    pub fn set_lockout_max(&mut self, seconds: u32) -> &mut Self {
+        self.lockout.max = Duration::seconds(seconds as i64);
+        self
+    }
+
+    /// seconds without a failure before the failure count is reset
+    pub fn set_lockout_reset(&mut self, seconds: u32) -> &mut Self {
+        self.lockout.reset = Duration::seconds(seconds as i64);
+        self
+    }
+
+    /// steps before the current one that totp codes are accepted for
+    pub fn set_totp_window_before(&mut self, steps: u64) -> &mut Self {
+        self.totp_window.before = steps;
+        self
+    }
+
+    /// steps after the current one that totp codes are accepted for
+    pub fn set_totp_window_after(&mut self, steps: u64) -> &mut Self {
+        self.totp_window.after = steps;
+        self
+    }
+
+    /// allows users without a verify method to authenticate each request
+    /// with a username and password
+    pub fn set_basic_auth(&mut self, basic_auth: bool) -> &mut Self {
+        self.basic_auth = basic_auth;
+        self
+    }
+
+    /// the lifetime in seconds of access tokens created from a session
+    pub fn set_access_token_lifetime(&mut self, seconds: u32) -> &mut Self {
+        self.access_lifetime = Duration::seconds(seconds as i64);
+        self
+    }
+
+    /// the number of days to keep audit log entries for. entries are kept
+    /// forever if not provided
+    pub fn set_audit_retention(&mut self, days: u32) -> &mut Self {
+        self.audit_retention = Some(Duration::days(days as i64));
+        self
+    }
+
+    /// file to load password secrets from
+    pub fn set_secrets_file(&mut self, path: PathBuf) -> &mut Self {
+        self.secrets_file = Some(path);
+        self
+    }
+
+    /// directory to load password secrets from
+    pub fn set_secrets_dir(&mut self, path: PathBuf) -> &mut Self {
+        self.secrets_dir = Some(path);
+        self
+    }
+
     pub fn add_secret(&mut self, version: u32, bytes: Vec<u8>) -> bool {
         self.secret_manager.add(secret::Secret::new(version, bytes))
     }
 
-    pub fn build(self) -> error::Result<Sec> {
-        let session_secret = self.session_secret.unwrap_or(String::from("secret"));
+    pub fn build(mut self) -> error::Result<Sec> {
+        let mut loaded = Vec::new();
 
-        let session_key = match self.session_hash.unwrap_or(SessionHash::Blake3) {
-            SessionHash::Blake3 => {
+        if let Some(path) = &self.secrets_file {
+            loaded.extend(secret::load_file(path)?);
+        }
 
-                SessionKey::Blake3(blake3::derive_key(
-                    BLAKE3_CONTEXT,
-                    session_secret.as_bytes()
-                ))
-            },
-            SessionHash::HS256 => {
-                let hk = hkdf::Hkdf::<sha3::Sha3_256>::new(None, session_secret.as_bytes());
-                let mut bytes = [0u8; 64];
+        if let Some(path) = &self.secrets_dir {
+            loaded.extend(secret::load_dir(path)?);
+        }
 
-                hk.expand(&[], &mut bytes)?;
+        for secret in loaded {
+            let version = *secret.version();
 
-                SessionKey::HS256(bytes)
-            },
-            SessionHash::HS384 => {
-                let hk = hkdf::Hkdf::<sha3::Sha3_384>::new(None, session_secret.as_bytes());
-                let mut bytes = [0u8; 64];
+            if !self.secret_manager.add(secret) {
+                return Err(error::Error::new()
+                    .kind("InvalidSecret")
+                    .message(format!("secret version {} was provided more than once", version)));
+            }
+        }
 
-                hk.expand(&[], &mut bytes)?;
+        let lifetimes = self.session_lifetimes;
 
-                SessionKey::HS384(bytes)
-            },
-            SessionHash::HS512 => {
-                let hk = hkdf::Hkdf::<sha3::Sha3_512>::new(None, session_secret.as_bytes());
-                let mut bytes = [0u8; 64];
+        if lifetimes.lifetime <= Duration::zero() ||
+            lifetimes.pending <= Duration::zero() ||
+            lifetimes.idle.map(|idle| idle <= Duration::zero()).unwrap_or(false) {
+            return Err(error::Error::new()
+                .kind("InvalidSessionLifetime")
+                .message("session lifetimes must be greater than 0"));
+        }
 
-                hk.expand(&[], &mut bytes)?;
+        if self.lockout.user_threshold == 0 ||
+            self.lockout.ip_threshold == 0 ||
+            self.lockout.basic_threshold == 0 {
+            return Err(error::Error::new()
+                .kind("InvalidLockout")
+                .message("lockout thresholds must be greater than 0"));
+        }
+
+        if self.totp_window.before > 10 || self.totp_window.after > 10 {
+            return Err(error::Error::new()
+                .kind("InvalidTotpWindow")
+                .message("totp window cannot be more than 10 steps in either direction"));
+        }
+
+        if self.access_lifetime <= Duration::zero() {
+            return Err(error::Error::new()
+                .kind("InvalidAccessTokenLifetime")
+                .message("access token lifetime must be greater than 0"));
+        }
+
+        if self.audit_retention.map(|retention| retention <= Duration::zero()).unwrap_or(false) {
+            return Err(error::Error::new()
+                .kind("InvalidAuditRetention")
+                .message("audit retention must be greater than 0"));
+        }
 
-                SessionKey::HS512(bytes)
+        let session_hash = self.session_hash.unwrap_or(SessionHash::Blake3);
+        let session_secret = self.session_secret.unwrap_or(String::from("secret"));
+
+        let session_key = derive_key(
+            &session_hash,
+            session_secret.as_bytes(),
+            BLAKE3_CONTEXT,
+            &[]
+        )?;
+
+        let signing_key = if let Some(signing_secret) = &self.signing_secret {
+            if signing_secret.len() < MIN_SIGNING_SECRET {
+                return Err(error::Error::new()
+                    .kind("InvalidSigningSecret")
+                    .message(format!("the signing secret must be at least {} bytes", MIN_SIGNING_SECRET)));
+            }
+
+            if *signing_secret == session_secret {
+                return Err(error::Error::new()
+                    .kind("InvalidSigningSecret")
+                    .message("the signing secret cannot be the same as the session secret"));
             }
+
+            Some(derive_key(
+                &session_hash,
+                signing_secret.as_bytes(),
+                BLAKE3_SIGNING_CONTEXT,
+                HKDF_SIGNING_INFO
+            )?)
+        } else {
+            None
         };
 
         Ok(Sec {
@@ -101,12 +421,62 @@ impl Builder {
                 key: session_key,
                 domain: self.session_domain,
                 secure: self.session_secure,
+                same_site: self.session_same_site.unwrap_or(SessionSameSite::Strict),
+                lifetimes,
             },
+            signing_key,
+            lockout: self.lockout,
+            totp_window: self.totp_window,
+            basic_auth: self.basic_auth,
+            access_lifetime: self.access_lifetime,
+            audit_retention: self.audit_retention,
             secrets: self.secret_manager,
+            oidc: self.oidc.build()?,
+            ldap: self.ldap.build()?,
         })
     }
 }
 
+/// derives a key for the given hash from a secret
+fn derive_key(
+    hash: &SessionHash,
+    secret: &[u8],
+    blake3_context: &str,
+    hkdf_info: &[u8],
+) -> error::Result<SessionKey> {
+    let key = match hash {
+        SessionHash::Blake3 => {
+            SessionKey::Blake3(blake3::derive_key(blake3_context, secret))
+        },
+        SessionHash::HS256 => {
+            let hk = hkdf::Hkdf::<sha3::Sha3_256>::new(None, secret);
+            let mut bytes = [0u8; 64];
+
+            hk.expand(hkdf_info, &mut bytes)?;
+
+            SessionKey::HS256(bytes)
+        },
+        SessionHash::HS384 => {
+            let hk = hkdf::Hkdf::<sha3::Sha3_384>::new(None, secret);
+            let mut bytes = [0u8; 64];
+
+            hk.expand(hkdf_info, &mut bytes)?;
+
+            SessionKey::HS384(bytes)
+        },
+        SessionHash::HS512 => {
+            let hk = hkdf::Hkdf::<sha3::Sha3_512>::new(None, secret);
+            let mut bytes = [0u8; 64];
+
+            hk.expand(hkdf_info, &mut bytes)?;
+
+            SessionKey::HS512(bytes)
+        }
+    };
+
+    Ok(key)
+}
+
 #[derive(Debug)]
 pub enum SessionKey {
     Blake3([u8; 32]),
@@ -120,6 +490,8 @@ pub struct SessionInfo {
     key: SessionKey,
     domain: Option<String>,
     secure: bool,
+    same_site: SessionSameSite,
+    lifetimes: SessionLifetimes,
 }
 
 impl SessionInfo {
@@ -134,12 +506,31 @@ impl SessionInfo {
     pub fn secure(&self) -> &bool {
         &self.secure
     }
+
+    pub fn same_site(&self) -> SameSite {
+        match self.same_site {
+            SessionSameSite::Strict => SameSite::Strict,
+            SessionSameSite::Lax => SameSite::Lax,
+        }
+    }
+
+    pub fn lifetimes(&self) -> &SessionLifetimes {
+        &self.lifetimes
+    }
 }
 
 #[derive(Debug)]
 pub struct Sec {
     session_info: SessionInfo,
+    signing_key: Option<SessionKey>,
+    lockout: LockoutConfig,
+    totp_window: TotpWindow,
+    basic_auth: bool,
+    access_lifetime: Duration,
+    audit_retention: Option<Duration>,
     secrets: secret::Manager,
+    oidc: Option<oidc::Provider>,
+    ldap: Option<ldap::Directory>,
 }
 
 impl Sec {
@@ -147,9 +538,21 @@ impl Sec {
         Builder {
             session_hash: None,
             session_secret: None,
+            signing_secret: None,
             session_domain: None,
             session_secure: false,
+            session_same_site: None,
+            session_lifetimes: SessionLifetimes::default(),
+            lockout: LockoutConfig::default(),
+            totp_window: TotpWindow::default(),
+            basic_auth: false,
+            access_lifetime: Duration::minutes(15),
+            audit_retention: None,
+            secrets_file: None,
+            secrets_dir: None,
             secret_manager: secret::Manager::new(),
+            oidc: oidc::Provider::builder(),
+            ldap: ldap::Directory::builder(),
         }
     }
 
@@ -157,7 +560,45 @@ impl Sec {
         &self.session_info
     }
 
+    /// the key used to sign urls. none if signed urls are disabled
+    pub fn signing_key(&self) -> Option<&SessionKey> {
+        self.signing_key.as_ref()
+    }
+
+    pub fn lockout(&self) -> &LockoutConfig {
+        &self.lockout
+    }
+
+    pub fn totp_window(&self) -> &TotpWindow {
+        &self.totp_window
+    }
+
+    /// if basic authorization is accepted
+    pub fn basic_auth(&self) -> &bool {
+        &self.basic_auth
+    }
+
+    /// how long access tokens are valid for
+    pub fn access_lifetime(&self) -> &Duration {
+        &self.access_lifetime
+    }
+
+    /// how long audit log entries are kept for
+    pub fn audit_retention(&self) -> Option<&Duration> {
+        self.audit_retention.as_ref()
+    }
+
     pub fn secrets(&self) -> &secret::Manager {
         &self.secrets
     }
+
+    /// the oidc provider if one was configured
+    pub fn oidc(&self) -> Option<&oidc::Provider> {
+        self.oidc.as_ref()
+    }
+
+    /// the ldap directory if one was configured
+    pub fn ldap(&self) -> Option<&ldap::Directory> {
+        self.ldap.as_ref()
+    }
 }
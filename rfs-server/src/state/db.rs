@@ -1,28 +1,127 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
 use tokio_postgres::{Config, NoTls};
-use deadpool_postgres::{Manager, ManagerConfig, Pool, RecyclingMethod};
+use tokio_postgres::config::SslMode;
+use tokio_postgres_rustls::MakeRustlsConnect;
+use deadpool_postgres::{Manager, ManagerConfig, Pool, RecyclingMethod, Runtime};
+use rustls::{ClientConfig, RootCertStore};
+
+use crate::error;
+
+/// creates the tls connector for postgres. the certificate and hostname of
+/// the server are always verified
+fn make_tls(ca_file: Option<&PathBuf>) -> error::Result<MakeRustlsConnect> {
+    let mut roots = RootCertStore::empty();
+
+    if let Some(path) = ca_file {
+        let file = std::fs::File::open(path)
+            .map_err(|err| error::Error::new()
+                .kind("InvalidPgTls")
+                .message(format!("failed to open postgres ca file. path: {}", path.display()))
+                .source(err))?;
+        let mut reader = std::io::BufReader::new(file);
+
+        for cert in rustls_pemfile::certs(&mut reader) {
+            let cert = cert.map_err(|err| error::Error::new()
+                .kind("InvalidPgTls")
+                .message(format!("failed to read postgres ca file. path: {}", path.display()))
+                .source(err))?;
+
+            roots.add(cert).map_err(|err| error::Error::new()
+                .kind("InvalidPgTls")
+                .message(format!("invalid certificate in postgres ca file. path: {}", path.display()))
+                .source(err))?;
+        }
+
+        if roots.is_empty() {
+            return Err(error::Error::new()
+                .kind("InvalidPgTls")
+                .message(format!("no certificates found in postgres ca file. path: {}", path.display())));
+        }
+    } else {
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    }
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|err| error::Error::new()
+            .kind("InvalidPgTls")
+            .message("failed to create postgres tls config")
+            .source(err))?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    Ok(MakeRustlsConnect::new(config))
+}
 
 #[derive(Debug)]
 pub struct Builder {
     with_tls: bool,
+    ca_file: Option<PathBuf>,
+    url: Option<String>,
     user: Option<String>,
     password: Option<String>,
     host: Option<String>,
     port: Option<u16>,
     dbname: Option<String>,
+    max_size: Option<usize>,
+    wait_timeout: Option<Duration>,
+    create_timeout: Option<Duration>,
+    recycle_timeout: Option<Duration>,
+    statement_timeout: Option<u64>,
 }
 
 impl Builder {
     pub fn new() -> Self {
         Builder {
             with_tls: false,
+            ca_file: None,
+            url: None,
             user: None,
             password: None,
             host: None,
             port: None,
             dbname: None,
+            max_size: None,
+            wait_timeout: None,
+            create_timeout: None,
+            recycle_timeout: None,
+            statement_timeout: None,
         }
     }
 
+    /// connects to postgres with tls. the server certificate and hostname
+    /// are verified
+    pub fn set_with_tls(&mut self, with_tls: bool) -> &mut Self {
+        self.with_tls = with_tls;
+        self
+    }
+
+    /// pem file of the certificate authorities to verify the server with.
+    /// the webpki roots are used if not provided
+    pub fn set_ca_file<P>(&mut self, path: P) -> &mut Self
+    where
+        P: Into<PathBuf>
+    {
+        self.ca_file = Some(path.into());
+        self
+    }
+
+    /// a postgres connection string or url. the user, password, dbname and
+    /// statement timeout that are set individually take precedence over the
+    /// string
+    pub fn set_url<U>(&mut self, url: U) -> &mut Self
+    where
+        U: Into<String>
+    {
+        self.url = Some(url.into());
+        self
+    }
+
     pub fn set_user<U>(&mut self, user: U) -> &mut Self
     where
         U: Into<String>
@@ -63,8 +162,55 @@ impl Builder {
         self
     }
 
-    pub fn build(self) -> Result<Pool, deadpool_postgres::BuildError> {
-        let mut config = Config::new();
+    /// the most connections the pool will open
+    pub fn set_max_size(&mut self, max_size: usize) -> &mut Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// seconds to wait for a connection to be available in the pool
+    pub fn set_wait_timeout(&mut self, seconds: u64) -> &mut Self {
+        self.wait_timeout = Some(Duration::from_secs(seconds));
+        self
+    }
+
+    /// seconds to wait for a new connection to be created
+    pub fn set_create_timeout(&mut self, seconds: u64) -> &mut Self {
+        self.create_timeout = Some(Duration::from_secs(seconds));
+        self
+    }
+
+    /// seconds to wait for an existing connection to be checked before it
+    /// is reused
+    pub fn set_recycle_timeout(&mut self, seconds: u64) -> &mut Self {
+        self.recycle_timeout = Some(Duration::from_secs(seconds));
+        self
+    }
+
+    /// milliseconds a statement can run before postgres cancels it
+    pub fn set_statement_timeout(&mut self, milliseconds: u64) -> &mut Self {
+        self.statement_timeout = Some(milliseconds);
+        self
+    }
+
+    pub fn build(self) -> error::Result<Pool> {
+        let max_size = self.max_size.unwrap_or(4);
+
+        if max_size == 0 {
+            return Err(error::Error::new()
+                .kind("InvalidPgPool")
+                .message("postgres pool max size must be greater than 0"));
+        }
+
+        let mut config = if let Some(url) = &self.url {
+            Config::from_str(url.as_str())
+                .map_err(|err| error::Error::new()
+                    .kind("InvalidPgUrl")
+                    .message("invalid postgres connection string")
+                    .source(err))?
+        } else {
+            Config::new()
+        };
 
         if let Some(user) = self.user {
             config.user(user.as_str());
@@ -74,11 +220,25 @@ impl Builder {
             config.password(password.as_str());
         }
 
+        // hosts and ports are added to the list in the config instead of
+        // replacing it
         if let Some(host) = self.host {
+            if !config.get_hosts().is_empty() {
+                return Err(error::Error::new()
+                    .kind("InvalidPgUrl")
+                    .message("postgres host cannot be set when the connection string has a host"));
+            }
+
             config.host(host.as_str());
         }
 
         if let Some(port) = self.port {
+            if !config.get_ports().is_empty() {
+                return Err(error::Error::new()
+                    .kind("InvalidPgUrl")
+                    .message("postgres port cannot be set when the connection string has a port"));
+            }
+
             config.port(port);
         }
 
@@ -86,14 +246,37 @@ impl Builder {
             config.dbname(dbname.as_str());
         }
 
+        if let Some(statement_timeout) = self.statement_timeout {
+            let mut options = config.get_options()
+                .map(|options| format!("{} ", options))
+                .unwrap_or_default();
+            options.push_str(&format!("-c statement_timeout={}", statement_timeout));
+
+            config.options(options.as_str());
+        }
+
         let manager_config = ManagerConfig {
             recycling_method: RecyclingMethod::Fast,
         };
 
-        let manager = Manager::from_config(config, NoTls, manager_config);
+        // a connection string that requires tls will not connect without it
+        let with_tls = self.with_tls ||
+            matches!(config.get_ssl_mode(), SslMode::Require);
+
+        let manager = if with_tls {
+            config.ssl_mode(SslMode::Require);
+
+            Manager::from_config(config, make_tls(self.ca_file.as_ref())?, manager_config)
+        } else {
+            Manager::from_config(config, NoTls, manager_config)
+        };
 
-        Pool::builder(manager)
-            .max_size(4)
-            .build()
+        Ok(Pool::builder(manager)
+            .max_size(max_size)
+            .runtime(Runtime::Tokio1)
+            .wait_timeout(self.wait_timeout)
+            .create_timeout(self.create_timeout)
+            .recycle_timeout(self.recycle_timeout)
+            .build()?)
     }
 }
@@ -10,11 +10,17 @@ pub const START_TIME: u64 = 1685862000000;
 pub type UserIdGenerator = MutexGenerator<ids::UserId>;
 pub type StorageIdGenerator = MutexGenerator<ids::StorageId>;
 pub type FSIdGenerator = MutexGenerator<ids::FSId>;
+pub type RoleIdGenerator = MutexGenerator<ids::RoleId>;
+pub type BotIdGenerator = MutexGenerator<ids::BotId>;
+pub type GroupIdGenerator = MutexGenerator<ids::GroupId>;
 
 pub struct Ids {
     user: UserIdGenerator,
     storage: StorageIdGenerator,
     fs: FSIdGenerator,
+    role: RoleIdGenerator,
+    bot: BotIdGenerator,
+    group: GroupIdGenerator,
 }
 
 impl Ids {
@@ -22,7 +28,10 @@ impl Ids {
         Ok(Ids {
             user: MutexGenerator::new(START_TIME, primary)?,
             storage: MutexGenerator::new(START_TIME, primary)?,
-            fs: MutexGenerator::new(START_TIME, primary)?
+            fs: MutexGenerator::new(START_TIME, primary)?,
+            role: MutexGenerator::new(START_TIME, primary)?,
+            bot: MutexGenerator::new(START_TIME, primary)?,
+            group: MutexGenerator::new(START_TIME, primary)?,
         })
     }
 
@@ -64,6 +73,33 @@ impl Ids {
 
         id.map_err(Into::into)
     }
+
+    pub fn wait_role_id(&self) -> NetResult<ids::RoleId> {
+        let Some(id) = blocking_next_id(&self.role, 5) else {
+            return Err(NetError::new()
+                .source("failed to generate role id. no more attempts"));
+        };
+
+        id.map_err(Into::into)
+    }
+
+    pub fn wait_bot_id(&self) -> NetResult<ids::BotId> {
+        let Some(id) = blocking_next_id(&self.bot, 5) else {
+            return Err(NetError::new()
+                .source("failed to generate bot id. no more attempts"));
+        };
+
+        id.map_err(Into::into)
+    }
+
+    pub fn wait_group_id(&self) -> NetResult<ids::GroupId> {
+        let Some(id) = blocking_next_id(&self.group, 5) else {
+            return Err(NetError::new()
+                .source("failed to generate group id. no more attempts"));
+        };
+
+        id.map_err(Into::into)
+    }
 }
 
 impl std::fmt::Debug for Ids {
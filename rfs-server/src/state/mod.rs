@@ -2,24 +2,50 @@ use std::path::PathBuf;
 use std::sync::Arc;
 
 use deadpool_postgres::Pool;
+use rfs_lib::schema;
 
 use crate::error;
 use crate::template;
 use crate::fs;
 use crate::sec;
+use crate::email;
 
 pub mod ids;
 pub mod db;
 
+/// how new users are able to create accounts
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Registration {
+    /// only admins can create users
+    Disabled,
+    /// anyone can create an account
+    Open,
+    /// an invite code is required to create an account
+    Invite,
+}
+
+impl Registration {
+    pub fn into_schema(self) -> schema::user::RegistrationMode {
+        match self {
+            Registration::Disabled => schema::user::RegistrationMode::Disabled,
+            Registration::Open => schema::user::RegistrationMode::Open,
+            Registration::Invite => schema::user::RegistrationMode::Invite,
+        }
+    }
+}
+
 /// builder for creating the [`Shared`] struct
 #[derive(Debug)]
 pub struct SharedBuilder {
     primary_id: Option<i64>,
     assets: Option<PathBuf>,
     pages: Option<PathBuf>,
+    storage_root: Option<PathBuf>,
+    registration: Registration,
     pg_options: db::Builder,
     templates: template::state::Builder,
     sec: sec::state::Builder,
+    email: email::Builder,
 }
 
 impl SharedBuilder {
@@ -35,6 +61,10 @@ impl SharedBuilder {
         &mut self.pg_options
     }
 
+    pub fn email(&mut self) -> &mut email::Builder {
+        &mut self.email
+    }
+
     /// assigns a new directory for assets lookup
     pub fn set_assets<P>(&mut self, path: P) -> &mut Self 
     where
@@ -53,6 +83,21 @@ impl SharedBuilder {
         self
     }
 
+    /// assigns a directory that local storage mediums must be created in
+    /// unless the user is allowed to use any path
+    pub fn set_storage_root<P>(&mut self, path: P) -> &mut Self
+    where
+        P: Into<PathBuf>
+    {
+        self.storage_root = Some(path.into());
+        self
+    }
+
+    pub fn set_registration(&mut self, registration: Registration) -> &mut Self {
+        self.registration = registration;
+        self
+    }
+
     pub fn set_primary_id(&mut self, primary: i64) -> &mut Self {
         self.primary_id = Some(primary);
         self
@@ -73,15 +118,25 @@ impl SharedBuilder {
             self.pages.unwrap_or("pages".into())
         )?;
 
+        let storage_root = if let Some(path) = self.storage_root {
+            Some(fs::validate_dir("storage root", &cwd, path)?)
+        } else {
+            None
+        };
+
         let primary_id = self.primary_id.unwrap_or(1);
 
         Ok(Shared {
             assets,
             pages,
+            storage_root,
+            registration: self.registration,
             pool: self.pg_options.build()?,
             templates: self.templates.build()?,
             sec: self.sec.build()?,
+            email: self.email.build()?,
             ids: ids::Ids::new(primary_id)?,
+            uploads: fs::upload::Uploads::new(),
         })
     }
 }
@@ -90,10 +145,14 @@ impl SharedBuilder {
 pub struct Shared {
     assets: PathBuf,
     pages: PathBuf,
+    storage_root: Option<PathBuf>,
+    registration: Registration,
     pool: Pool,
     templates: template::state::Templates,
     sec: sec::state::Sec,
+    email: Option<email::Mailer>,
     ids: ids::Ids,
+    uploads: fs::upload::Uploads,
 }
 
 pub type ArcShared = Arc<Shared>;
@@ -104,9 +163,12 @@ impl Shared {
             primary_id: None,
             assets: None,
             pages: None,
+            storage_root: None,
+            registration: Registration::Disabled,
             pg_options: db::Builder::new(),
             templates: template::state::Templates::builder(),
             sec: sec::state::Sec::builder(),
+            email: email::Mailer::builder(),
         }
     }
 
@@ -118,6 +180,14 @@ impl Shared {
         &self.pages
     }
 
+    pub fn storage_root(&self) -> Option<&PathBuf> {
+        self.storage_root.as_ref()
+    }
+
+    pub fn registration(&self) -> &Registration {
+        &self.registration
+    }
+
     pub fn pool(&self) -> &Pool {
         &self.pool
     }
@@ -134,9 +204,19 @@ impl Shared {
         &self.sec
     }
 
+    /// the mailer if smtp was configured
+    pub fn email(&self) -> Option<&email::Mailer> {
+        self.email.as_ref()
+    }
+
     pub fn ids(&self) -> &ids::Ids {
         &self.ids
     }
+
+    /// uploads that are currently being written
+    pub fn uploads(&self) -> &fs::upload::Uploads {
+        &self.uploads
+    }
 }
 
 impl AsRef<Pool> for Shared {
@@ -11,6 +11,8 @@ pub struct Local {
 }
 
 impl Local {
+    /// the path is canonicalized once it exists so that the medium always
+    /// refers to the directory that was checked and not a symlink to it
     pub async fn build(path: PathBuf) -> Result<Self, BuilderError> {
         if path.try_exists()? {
             if !path.is_dir() {
@@ -20,9 +22,69 @@ impl Local {
             tokio::fs::create_dir_all(&path).await?;
         }
 
+        let path = tokio::fs::canonicalize(&path).await?;
+
         Ok(Local { path })
     }
 
+    /// resolves the given path against the root directory. relative paths are
+    /// joined to the root and any parent components are normalized. the parts
+    /// of the result that exist are canonicalized so that symlinks are
+    /// followed before the result is compared to the canonical root. returns
+    /// None if the result is outside of the root.
+    pub async fn resolve_within(root: &Path, path: &Path) -> std::io::Result<Option<PathBuf>> {
+        use std::path::Component;
+
+        let root = tokio::fs::canonicalize(root).await?;
+        let mut lexical = PathBuf::new();
+
+        for comp in root.join(path).components() {
+            match comp {
+                Component::ParentDir => {
+                    if !lexical.pop() {
+                        return Ok(None);
+                    }
+                },
+                Component::CurDir => {},
+                _ => lexical.push(comp)
+            }
+        }
+
+        if !lexical.starts_with(&root) {
+            return Ok(None);
+        }
+
+        // the path may not exist yet so the closest ancestor that does is
+        // canonicalized and the missing components are added back to it
+        let mut existing = lexical.as_path();
+        let mut missing = Vec::new();
+
+        let mut rtn = loop {
+            match tokio::fs::canonicalize(existing).await {
+                Ok(canonical) => break canonical,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                    let (Some(parent), Some(name)) = (existing.parent(), existing.file_name()) else {
+                        return Ok(None);
+                    };
+
+                    missing.push(name);
+                    existing = parent;
+                },
+                Err(err) => return Err(err),
+            }
+        };
+
+        for name in missing.into_iter().rev() {
+            rtn.push(name);
+        }
+
+        if rtn.starts_with(&root) {
+            Ok(Some(rtn))
+        } else {
+            Ok(None)
+        }
+    }
+
     pub fn into_schema(self) -> StorageLocal {
         StorageLocal {
             path: self.path
@@ -49,3 +111,92 @@ impl From<Local> for Type {
         Type::Local(local)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use super::Local;
+
+    /// a directory that is removed once the test is done
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let path = std::env::temp_dir()
+                .join(format!("rfs_test_{:016x}", rand::random::<u64>()));
+
+            std::fs::create_dir_all(&path).unwrap();
+
+            TempDir(std::fs::canonicalize(path).unwrap())
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn resolve_within_root() {
+        let root = TempDir::new();
+
+        std::fs::create_dir(root.0.join("existing")).unwrap();
+
+        assert_eq!(
+            Local::resolve_within(&root.0, Path::new("existing/new/dir")).await.unwrap(),
+            Some(root.0.join("existing/new/dir"))
+        );
+        assert_eq!(
+            Local::resolve_within(&root.0, Path::new("new/../other")).await.unwrap(),
+            Some(root.0.join("other"))
+        );
+        assert_eq!(
+            Local::resolve_within(&root.0, Path::new("../outside")).await.unwrap(),
+            None
+        );
+        assert_eq!(
+            Local::resolve_within(&root.0, Path::new("/etc")).await.unwrap(),
+            None
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn resolve_within_follows_symlinks() {
+        let root = TempDir::new();
+        let outside = TempDir::new();
+
+        std::os::unix::fs::symlink(&outside.0, root.0.join("escape")).unwrap();
+
+        assert_eq!(
+            Local::resolve_within(&root.0, Path::new("escape")).await.unwrap(),
+            None,
+            "symlink to outside of the root was allowed"
+        );
+        assert_eq!(
+            Local::resolve_within(&root.0, Path::new("escape/new")).await.unwrap(),
+            None,
+            "path under a symlink to outside of the root was allowed"
+        );
+
+        std::os::unix::fs::symlink(&root.0, outside.0.join("back")).unwrap();
+
+        // the root itself being reached through a symlink is still inside
+        assert_eq!(
+            Local::resolve_within(&outside.0.join("back"), Path::new("dir")).await.unwrap(),
+            Some(root.0.join("dir"))
+        );
+    }
+
+    #[tokio::test]
+    async fn build_canonicalizes() {
+        let root = TempDir::new();
+        let Ok(local) = Local::build(root.0.join("a/../b")).await else {
+            panic!("failed to build local storage");
+        };
+
+        assert_eq!(local.path, root.0.join("b"));
+    }
+}
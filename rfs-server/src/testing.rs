@@ -0,0 +1,233 @@
+//! helpers shared by the tests of the server
+
+use std::sync::{Arc, Mutex};
+
+use axum::http::{Request, StatusCode};
+use axum::response::IntoResponse;
+use rfs_lib::ids;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+use crate::email;
+use crate::net::error;
+use crate::sec::audit;
+use crate::sec::authn::initiator::{Initiator, Mechanism};
+use crate::sec::authz::Permission;
+use crate::state::{ArcShared, Shared, SharedBuilder};
+use crate::user;
+
+/// creates the shared state with a pool that is never connected to. the
+/// handlers being tested are expected to reject the request before a
+/// connection is needed
+pub fn shared() -> ArcShared {
+    let manifest = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+    let tmp = std::env::temp_dir();
+
+    let mut builder = Shared::builder();
+    builder.set_assets(&tmp)
+        .set_pages(&tmp);
+    builder.templates()
+        .set_templates(manifest.join("../templates"));
+
+    Arc::new(builder.build().expect("failed to create shared state"))
+}
+
+/// creates a fresh database with the schema loaded and the shared state
+/// connected to it. the server to use is read from `RFS_TEST_PG_URL` and
+/// nothing is returned if it is not set so that the calling test can be
+/// skipped
+pub async fn database() -> Option<ArcShared> {
+    database_with(|_| {}).await
+}
+
+/// same as [`database`] with the chance to change the shared state before
+/// it is built
+pub async fn database_with<F>(configure: F) -> Option<ArcShared>
+where
+    F: FnOnce(&mut SharedBuilder)
+{
+    let Ok(url) = std::env::var("RFS_TEST_PG_URL") else {
+        return None;
+    };
+    let dbname = format!("rfs_test_{:016x}", rand::random::<u64>());
+
+    {
+        let (client, conn) = tokio_postgres::connect(url.as_str(), tokio_postgres::NoTls)
+            .await
+            .expect("failed to connect to test postgres server");
+
+        tokio::spawn(conn);
+
+        client.batch_execute(&format!("create database {dbname}"))
+            .await
+            .expect("failed to create test database");
+    }
+
+    let manifest = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+    let tmp = std::env::temp_dir();
+
+    let mut builder = Shared::builder();
+    builder.set_assets(&tmp)
+        .set_pages(&tmp);
+    builder.templates()
+        .set_templates(manifest.join("../templates"));
+    builder.pg_options()
+        .set_url(url)
+        .set_dbname(dbname);
+
+    configure(&mut builder);
+
+    let state = Arc::new(builder.build().expect("failed to create shared state"));
+
+    {
+        // roles are shared by every database on the server so the setups
+        // cannot run at the same time
+        static SETUP: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+        let _setup = SETUP.lock().await;
+        let mut conn = state.pool().get()
+            .await
+            .expect("failed to connect to test database");
+        let transaction = conn.transaction()
+            .await
+            .expect("failed to start transaction");
+
+        transaction.batch_execute(include_str!("../../rfs-db/setup/postgres/init.sql"))
+            .await
+            .expect("failed to setup test database");
+        transaction.commit()
+            .await
+            .expect("failed to commit test database setup");
+    }
+
+    Some(state)
+}
+
+/// an initiator for a user that authenticated with basic authorization
+/// and has the given permissions
+pub fn initiator<P>(user_id: ids::UserId, permissions: P) -> Initiator
+where
+    P: IntoIterator<Item = Permission>
+{
+    Initiator::unchecked(
+        user::User::unsaved(user_id, "tester".into()),
+        None,
+        Mechanism::Basic,
+    ).with_permissions(permissions)
+}
+
+/// the audit context of a request that has no id or address
+pub fn audit_context() -> audit::Context {
+    let (parts, _) = Request::new(()).into_parts();
+
+    audit::Context::from_parts(&parts)
+}
+
+/// the status code of the response that a handler produced
+pub fn status<T>(result: error::Result<T>) -> StatusCode
+where
+    T: IntoResponse
+{
+    match result {
+        Ok(res) => res.into_response().status(),
+        Err(err) => err.into_response().status(),
+    }
+}
+
+/// an smtp server that accepts every message it is sent and keeps them so
+/// that tests can read what was delivered
+#[derive(Clone)]
+pub struct SmtpSink {
+    port: u16,
+    messages: Arc<Mutex<Vec<String>>>,
+}
+
+impl SmtpSink {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let sink = SmtpSink {
+            port: listener.local_addr().unwrap().port(),
+            messages: Arc::new(Mutex::new(Vec::new())),
+        };
+
+        let messages = sink.messages.clone();
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let messages = messages.clone();
+
+                tokio::spawn(async move {
+                    let (reader, mut writer) = stream.into_split();
+                    let mut lines = BufReader::new(reader).lines();
+                    let mut data: Option<String> = None;
+
+                    if writer.write_all(b"220 sink\r\n").await.is_err() {
+                        return;
+                    }
+
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        let reply: &[u8] = if let Some(mut message) = data.take() {
+                            if line != "." {
+                                message.push_str(&line);
+                                message.push('\n');
+                                data = Some(message);
+
+                                continue;
+                            }
+
+                            messages.lock().unwrap().push(message);
+
+                            b"250 queued\r\n"
+                        } else {
+                            let command = line.get(..4).unwrap_or_default().to_ascii_uppercase();
+
+                            match command.as_str() {
+                                "DATA" => {
+                                    data = Some(String::new());
+
+                                    b"354 send data\r\n"
+                                },
+                                "QUIT" => b"221 bye\r\n",
+                                _ => b"250 ok\r\n",
+                            }
+                        };
+
+                        if writer.write_all(reply).await.is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+
+        sink
+    }
+
+    /// sends the emails of the shared state to the sink
+    pub fn configure(&self, builder: &mut SharedBuilder) {
+        builder.email()
+            .set_host(String::from("127.0.0.1"))
+            .set_port(self.port)
+            .set_from(String::from("rfs@example.com"))
+            .set_security(email::SmtpSecurity::None);
+    }
+
+    /// the messages that have been delivered in the order they were sent
+    pub fn messages(&self) -> Vec<String> {
+        self.messages.lock().unwrap().clone()
+    }
+
+    /// finds the email token in the last message that was delivered
+    pub fn last_token(&self) -> Option<String> {
+        let messages = self.messages.lock().unwrap();
+
+        // tokens are the only lines made of the 43 url safe characters that
+        // an encoded token takes up
+        messages.last()?
+            .lines()
+            .map(|line| line.trim())
+            .find(|line| line.len() == 43 && line.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+            .map(|line| line.to_owned())
+    }
+}
@@ -0,0 +1,197 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use rfs_lib::ids;
+use rfs_lib::schema;
+use tokio_postgres::Error as PgError;
+use deadpool_postgres::GenericClient;
+
+use crate::net;
+
+pub const INVITE_CODE_BYTES: usize = 16;
+
+pub enum InviteError {
+    Rand(rand::Error),
+    Pg(PgError),
+}
+
+impl From<rand::Error> for InviteError {
+    fn from(err: rand::Error) -> Self {
+        InviteError::Rand(err)
+    }
+}
+
+impl From<PgError> for InviteError {
+    fn from(err: PgError) -> Self {
+        InviteError::Pg(err)
+    }
+}
+
+impl From<InviteError> for net::error::Error {
+    fn from(err: InviteError) -> net::error::Error {
+        match err {
+            InviteError::Rand(err) => err.into(),
+            InviteError::Pg(err) => err.into(),
+        }
+    }
+}
+
+/// generates a new url safe invite code
+pub fn gen_code() -> Result<String, rand::Error> {
+    let mut bytes = [0u8; INVITE_CODE_BYTES];
+
+    rand::thread_rng().try_fill_bytes(&mut bytes)?;
+
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+pub struct InviteBuilder {
+    user_id: ids::UserId,
+    max_uses: Option<u32>,
+    expires: Option<DateTime<Utc>>,
+}
+
+impl InviteBuilder {
+    pub fn set_max_uses(&mut self, max: u32) {
+        self.max_uses = Some(max);
+    }
+
+    pub fn set_expires(&mut self, expires: DateTime<Utc>) {
+        self.expires = Some(expires);
+    }
+
+    pub async fn build(self, conn: &impl GenericClient) -> Result<Invite, InviteError> {
+        let created = Utc::now();
+        let code = gen_code()?;
+        let max_uses = self.max_uses.map(|v| v as i32);
+
+        let _ = conn.execute(
+            "\
+            insert into user_invites (code, user_id, max_uses, expires, created) values \
+            ($1, $2, $3, $4, $5)",
+            &[&code, &self.user_id, &max_uses, &self.expires, &created]
+        ).await?;
+
+        Ok(Invite {
+            code,
+            user_id: self.user_id,
+            max_uses: self.max_uses,
+            uses: 0,
+            expires: self.expires,
+            created,
+        })
+    }
+}
+
+pub struct Invite {
+    pub code: String,
+    pub user_id: ids::UserId,
+    pub max_uses: Option<u32>,
+    pub uses: u32,
+    pub expires: Option<DateTime<Utc>>,
+    pub created: DateTime<Utc>,
+}
+
+impl Invite {
+    pub fn builder(user_id: ids::UserId) -> InviteBuilder {
+        InviteBuilder {
+            user_id,
+            max_uses: None,
+            expires: None,
+        }
+    }
+
+    fn from_row(row: tokio_postgres::Row) -> Self {
+        let max_uses: Option<i32> = row.get(2);
+        let uses: i32 = row.get(3);
+
+        Invite {
+            code: row.get(0),
+            user_id: ids::user_id_from_pg(row.get(1)),
+            max_uses: max_uses.map(|v| v as u32),
+            uses: uses as u32,
+            expires: row.get(4),
+            created: row.get(5),
+        }
+    }
+
+    pub async fn retrieve(
+        conn: &impl GenericClient,
+        code: &str,
+    ) -> Result<Option<Self>, PgError> {
+        let result = conn.query_opt(
+            "\
+            select user_invites.code, \
+                   user_invites.user_id, \
+                   user_invites.max_uses, \
+                   user_invites.uses, \
+                   user_invites.expires, \
+                   user_invites.created \
+            from user_invites \
+            where user_invites.code = $1",
+            &[&code]
+        ).await?;
+
+        Ok(result.map(Self::from_row))
+    }
+
+    pub async fn retrieve_all(conn: &impl GenericClient) -> Result<Vec<Self>, PgError> {
+        let rows = conn.query(
+            "\
+            select user_invites.code, \
+                   user_invites.user_id, \
+                   user_invites.max_uses, \
+                   user_invites.uses, \
+                   user_invites.expires, \
+                   user_invites.created \
+            from user_invites \
+            order by user_invites.created",
+            &[]
+        ).await?;
+
+        Ok(rows.into_iter()
+            .map(Self::from_row)
+            .collect())
+    }
+
+    /// attempts to use the invite
+    ///
+    /// the check and increment happen in a single statement so that
+    /// concurrent registrations cannot go over the max uses. returns false if
+    /// the invite was not found, is expired or has no uses left
+    pub async fn use_code(conn: &impl GenericClient, code: &str) -> Result<bool, PgError> {
+        let now = Utc::now();
+
+        let updated = conn.execute(
+            "\
+            update user_invites \
+            set uses = uses + 1 \
+            where code = $1 and \
+                  (max_uses is null or uses < max_uses) and \
+                  (expires is null or expires > $2)",
+            &[&code, &now]
+        ).await?;
+
+        Ok(updated == 1)
+    }
+
+    pub async fn delete(&self, conn: &impl GenericClient) -> Result<bool, PgError> {
+        let deleted = conn.execute(
+            "delete from user_invites where code = $1",
+            &[&self.code]
+        ).await?;
+
+        Ok(deleted == 1)
+    }
+
+    pub fn into_schema(self) -> schema::user::Invite {
+        schema::user::Invite {
+            code: self.code,
+            user_id: self.user_id,
+            max_uses: self.max_uses,
+            uses: self.uses,
+            expires: self.expires,
+            created: self.created,
+        }
+    }
+}
@@ -1,12 +1,86 @@
 use rfs_lib::ids;
+use rfs_lib::schema;
 use tokio_postgres::{Error as PgError};
 use deadpool_postgres::GenericClient;
 
+pub mod invite;
+
+pub async fn username_check(
+    conn: &impl GenericClient,
+    username: &str,
+) -> Result<Option<ids::UserId>, PgError> {
+    let check = conn.query_opt(
+        "select id from users where username = $1",
+        &[&username]
+    ).await?;
+
+    Ok(check.map(|row| ids::user_id_from_pg(row.get(0))))
+}
+
+pub async fn email_check(
+    conn: &impl GenericClient,
+    email: &str,
+) -> Result<Option<ids::UserId>, PgError> {
+    let check = conn.query_opt(
+        "select id from users where email = $1",
+        &[&email]
+    ).await?;
+
+    Ok(check.map(|row| ids::user_id_from_pg(row.get(0))))
+}
+
 pub struct UserEmail {
     email: String,
     verified: bool
 }
 
+impl UserEmail {
+    pub fn email(&self) -> &String {
+        &self.email
+    }
+
+    pub fn verified(&self) -> &bool {
+        &self.verified
+    }
+}
+
+pub struct UserBuilder {
+    id: ids::UserId,
+    username: String,
+    email: Option<String>,
+    email_verified: bool,
+}
+
+impl UserBuilder {
+    pub fn set_email(&mut self, email: String) {
+        self.email = Some(email);
+    }
+
+    /// marks the email as verified. used when the email was verified by
+    /// another party
+    pub fn set_email_verified(&mut self, verified: bool) {
+        self.email_verified = verified;
+    }
+
+    pub async fn build(self, conn: &impl GenericClient) -> Result<User, PgError> {
+        let _ = conn.execute(
+            "\
+            insert into users (id, username, email, email_verified) values \
+            ($1, $2, $3, $4)",
+            &[&self.id, &self.username, &self.email, &self.email_verified]
+        ).await?;
+
+        Ok(User {
+            id: self.id,
+            username: self.username,
+            email: self.email.map(|email| UserEmail {
+                email,
+                verified: self.email_verified,
+            }),
+        })
+    }
+}
+
 pub struct User {
     id: ids::UserId,
     username: String,
@@ -14,6 +88,15 @@ pub struct User {
 }
 
 impl User {
+    pub fn builder(id: ids::UserId, username: String) -> UserBuilder {
+        UserBuilder {
+            id,
+            username,
+            email: None,
+            email_verified: false,
+        }
+    }
+
     pub fn id(&self) -> &ids::UserId {
         &self.id
     }
@@ -25,66 +108,90 @@ impl User {
     pub fn email(&self) -> Option<&UserEmail> {
         self.email.as_ref()
     }
+
+    pub fn into_schema(self) -> schema::user::User {
+        schema::user::User {
+            id: self.id,
+            username: self.username,
+            email: self.email.map(|email| schema::user::UserEmail {
+                email: email.email,
+                verified: email.verified,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+impl User {
+    /// creates a user without storing it
+    pub fn unsaved(id: ids::UserId, username: String) -> User {
+        User {
+            id,
+            username,
+            email: None,
+        }
+    }
 }
 
 impl User {
+    fn from_row(row: tokio_postgres::Row) -> User {
+        let email = row.get::<usize, Option<String>>(2).map(|email| UserEmail {
+            email,
+            verified: row.get(3),
+        });
+
+        User {
+            id: ids::user_id_from_pg(row.get(0)),
+            username: row.get(1),
+            email,
+        }
+    }
+
     pub async fn query_with_id(conn: &impl GenericClient, id: &ids::UserId) -> Result<Option<User>, PgError> {
-        if let Some(row) = conn.query_opt(
+        let result = conn.query_opt(
             "\
             select users.id, \
                    users.username, \
                    users.email, \
                    users.email_verified \
             from users \
-            where users.id = $1",
+            where users.id = $1 and \
+                  users.deleted is null",
             &[id]
-        ).await? {
-            let email = if let Some(email) = row.get(2) {
-                Some(UserEmail {
-                    email,
-                    verified: row.get(3),
-                })
-            } else {
-                None
-            };
-
-            Ok(Some(User {
-                id: ids::user_id_from_pg(row.get(0)),
-                username: row.get(1),
-                email,
-            }))
-        } else {
-            Ok(None)
-        }
+        ).await?;
+
+        Ok(result.map(Self::from_row))
     }
 
     pub async fn query_with_username(conn: &impl GenericClient, username: &String) -> Result<Option<User>, PgError> {
-        if let Some(row) = conn.query_opt(
+        let result = conn.query_opt(
             "\
             select users.id, \
                    users.username, \
                    users.email, \
                    users.email_verified \
             from users \
-            where users.username = $1",
+            where users.username = $1 and \
+                  users.deleted is null",
             &[username]
-        ).await? {
-            let email = if let Some(email) = row.get(2) {
-                Some(UserEmail {
-                    email,
-                    verified: row.get(3)
-                })
-            } else {
-                None
-            };
-
-            Ok(Some(User {
-                id: ids::user_id_from_pg(row.get(0)),
-                username: row.get(1),
-                email
-            }))
-        } else {
-            Ok(None)
-        }
+        ).await?;
+
+        Ok(result.map(Self::from_row))
+    }
+
+    pub async fn query_with_email(conn: &impl GenericClient, email: &str) -> Result<Option<User>, PgError> {
+        let result = conn.query_opt(
+            "\
+            select users.id, \
+                   users.username, \
+                   users.email, \
+                   users.email_verified \
+            from users \
+            where users.email = $1 and \
+                  users.deleted is null",
+            &[&email]
+        ).await?;
+
+        Ok(result.map(Self::from_row))
     }
 }